@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// 任意文本作为配置内容：只能得到配置或错误，不能 panic
+fuzz_target!(|data: &str| {
+    let _ = ztr_lib::config::Config::from_toml_str(data);
+    let _ = ztr_lib::config::parse_size(data);
+});
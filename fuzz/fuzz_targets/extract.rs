@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ztr_lib::extractor::{self, ExtractOptions};
+use ztr_lib::format::Format;
+
+// 任意字节作为压缩包解压到临时目录：只能得到错误，不能 panic，也不能写到目标目录之外
+fuzz_target!(|data: &[u8]| {
+    let Ok(dir) = tempfile::tempdir() else {
+        return;
+    };
+    for format in Format::ALL {
+        let archive = dir.path().join(format!("fuzz.{}", format.extension()));
+        if std::fs::write(&archive, data).is_err() {
+            return;
+        }
+        let target = dir.path().join("out");
+        let _ = extractor::extract_archive(&archive, &target, &ExtractOptions::default());
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::path::Path;
+use ztr_lib::ignore_rules::IgnoreRules;
+
+// 每一行作为一条忽略规则，再用同样的行作为路径进行匹配
+fuzz_target!(|data: &str| {
+    let base_dir = Path::new("/fuzz");
+    let rules: Vec<String> = data.lines().map(str::to_string).collect();
+    if let Ok(ignore_rules) = IgnoreRules::new(&rules, base_dir) {
+        for rule in &rules {
+            let path = base_dir.join(rule);
+            let _ = ignore_rules.should_ignore(&path, false);
+            let _ = ignore_rules.should_ignore(&path, true);
+        }
+    }
+});
@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use ztr_lib::format::Format;
+use ztr_lib::{reader, verifier};
+
+// 任意字节分别作为三种格式的压缩包读取与校验
+fuzz_target!(|data: &[u8]| {
+    for format in Format::ALL {
+        let _ = reader::for_each_entry_in(Cursor::new(data), format, |_, entry| {
+            std::io::copy(entry, &mut std::io::sink())?;
+            Ok(())
+        });
+    }
+    let _ = verifier::verify_zip(Cursor::new(data));
+    let _ = verifier::verify_tar_gz(Cursor::new(data));
+    let _ = verifier::verify_7z(Cursor::new(data));
+});
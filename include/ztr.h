@@ -0,0 +1,37 @@
+/*
+ * ztr 的 C 接口，对应启用 `ztr-ffi` 特性构建的 ztr_lib 库。
+ *
+ *   cargo rustc --release --lib --features ztr-ffi --crate-type cdylib
+ *
+ * 所有函数在出错时返回 -1，错误信息通过 ztr_last_error() 获取（按线程保存）。
+ */
+#ifndef ZTR_H
+#define ZTR_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * 压缩 base_dir，应用配置中的忽略规则、源路径与过滤条件。
+ *
+ * config_json: JSON 格式的配置，字段与 ztr.toml 相同，如
+ *              {"format": "tar.gz", "ignore": ["*.log"]}；NULL 或空字符串表示默认配置。
+ * base_dir:    要压缩的目录，不能为 NULL。
+ * out_path:    压缩包的输出路径；NULL 表示按配置中的 output_dir 与 output_name 决定。
+ *
+ * 成功时返回 0，失败时返回 -1。字符串参数均为 UTF-8 编码。
+ */
+int ztr_compress(const char *config_json, const char *base_dir, const char *out_path);
+
+/*
+ * 当前线程最近一次失败的调用的错误信息，没有错误时返回 NULL。
+ * 字符串由 ztr 持有，在当前线程下一次调用 ztr 函数之前有效，不要释放它。
+ */
+const char *ztr_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ZTR_H */
@@ -0,0 +1,307 @@
+use crate::checksum;
+use crate::collector;
+use crate::config::Config;
+use crate::reader;
+use crate::verifier::EntryError;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// 内嵌清单在压缩包中的条目名称。
+pub const ENTRY_NAME: &str = "ZTR_MANIFEST.json";
+
+/// 写入压缩包内部的清单 (`ZTR_MANIFEST.json`)，使压缩包本身即可用于校验与审计。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// 生成压缩包的 ztr 版本
+    pub ztr_version: String,
+    /// 生成时间 (Unix 时间戳)；可复现模式下为 `SOURCE_DATE_EPOCH`
+    pub created: u64,
+    /// 生成时使用的配置；上传与通知配置可能包含凭据，不会写入
+    pub config: serde_json::Value,
+    /// 压缩包中的文件，顺序与写入顺序一致
+    pub files: Vec<ManifestFile>,
+}
+
+/// 内嵌清单中的一个文件记录。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// 条目名称 (使用 `/` 分隔)
+    pub path: String,
+    /// 写入压缩包的字节数
+    pub size: u64,
+    /// 写入内容的 SHA-256 摘要；符号链接条目为 `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// 符号链接的目标；普通文件为 `None`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl ArchiveManifest {
+    /// 为将要写入压缩包的文件生成清单。
+    ///
+    /// # 参数
+    /// - `config`: 压缩配置。
+    /// - `base_dir`: 基础目录，用于计算条目名称。
+    /// - `files`: 要写入的文件，顺序与写入顺序一致。
+    /// - `links`: 作为符号链接条目写入的文件。
+    /// - `max_entry_bytes`: 每个条目最多写入的字节数 (采样模式)。
+    /// - `created`: 生成时间 (Unix 时间戳)。
+    ///
+    /// # 返回
+    /// `Result<ArchiveManifest>`: 文件无法读取时返回错误。
+    pub fn build(
+        config: &Config,
+        base_dir: &Path,
+        files: &[PathBuf],
+        links: impl Fn(&Path) -> bool,
+        max_entry_bytes: Option<u64>,
+        created: u64,
+    ) -> Result<Self> {
+        let mounts = collector::mounts(config, base_dir)?;
+        let mut entries = Vec::with_capacity(files.len());
+        for file_path in files {
+            let path = collector::entry_path(base_dir, &mounts, file_path)
+                .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?
+                .to_string_lossy()
+                .replace("\\", "/");
+            if links(file_path) {
+                let target = std::fs::read_link(file_path)
+                    .with_context(|| format!("读取符号链接失败: {}", file_path.display()))?;
+                entries.push(ManifestFile {
+                    path,
+                    size: 0,
+                    sha256: None,
+                    link: Some(target.to_string_lossy().replace("\\", "/")),
+                });
+                continue;
+            }
+            let file = std::fs::File::open(file_path)
+                .with_context(|| format!("打开文件失败: {}", file_path.display()))?;
+            let mut counter = CountingRead::new(file.take(max_entry_bytes.unwrap_or(u64::MAX)));
+            let sha256 = checksum::sha256_reader(&mut counter)
+                .with_context(|| format!("读取文件失败: {}", file_path.display()))?;
+            entries.push(ManifestFile {
+                path,
+                size: counter.count,
+                sha256: Some(sha256),
+                link: None,
+            });
+        }
+
+        let mut config = config.clone();
+        config.upload = None;
+        config.notify = None;
+        Ok(Self {
+            ztr_version: env!("CARGO_PKG_VERSION").to_string(),
+            created,
+            config: serde_json::to_value(&config).context("序列化配置失败")?,
+            files: entries,
+        })
+    }
+
+    /// 序列化为格式化的 JSON。
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).context("序列化内嵌清单失败")
+    }
+}
+
+/// 统计读取字节数的读取器。
+pub(crate) struct CountingRead<R> {
+    inner: R,
+    /// 已读取的字节数
+    pub(crate) count: u64,
+}
+
+impl<R> CountingRead<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<R: Read> Read for CountingRead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+/// 读取压缩包中的内嵌清单。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，可以是分卷压缩包。
+///
+/// # 返回
+/// `Result<Option<ArchiveManifest>>`: 压缩包不包含内嵌清单时返回 `None`；清单格式错误时返回错误。
+pub fn read_embedded(archive_path: &Path) -> Result<Option<ArchiveManifest>> {
+    let mut content = None;
+    reader::for_each_entry(archive_path, |name, entry| {
+        if name == ENTRY_NAME {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).context("读取内嵌清单失败")?;
+            content = Some(data);
+        }
+        Ok(())
+    })?;
+    content
+        .map(|data| serde_json::from_slice(&data).context("解析内嵌清单失败"))
+        .transpose()
+}
+
+/// 根据内嵌清单校验压缩包中每个文件的大小与 SHA-256 摘要。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，可以是分卷压缩包。
+///
+/// # 返回
+/// `Result<Option<Vec<EntryError>>>`: 与清单不一致的条目（缺失、多余、大小或摘要不同）；
+/// 压缩包不包含内嵌清单时返回 `None`。
+pub fn verify_embedded(archive_path: &Path) -> Result<Option<Vec<EntryError>>> {
+    let Some(manifest) = read_embedded(archive_path)? else {
+        return Ok(None);
+    };
+    let mut actual: HashMap<String, (u64, String)> = HashMap::new();
+    reader::for_each_entry(archive_path, |name, entry| {
+        if name != ENTRY_NAME {
+            let mut counter = CountingRead::new(entry);
+            let sha256 = checksum::sha256_reader(&mut counter)
+                .with_context(|| format!("读取条目失败: {}", name))?;
+            actual.insert(name.to_string(), (counter.count, sha256));
+        }
+        Ok(())
+    })?;
+
+    let mut errors = Vec::new();
+    let error = |name: &str, message: String| EntryError {
+        name: name.to_string(),
+        message,
+    };
+    // 符号链接在各格式中的存储方式不同，只检查文件条目
+    for file in &manifest.files {
+        let Some(expected) = &file.sha256 else {
+            continue;
+        };
+        match actual.remove(&file.path) {
+            None => errors.push(error(&file.path, "压缩包中缺少该文件".to_string())),
+            Some((size, _)) if size != file.size => errors.push(error(
+                &file.path,
+                format!(
+                    "大小不一致: 清单为 {} 字节, 实际为 {} 字节",
+                    file.size, size
+                ),
+            )),
+            Some((_, sha256)) if &sha256 != expected => {
+                errors.push(error(&file.path, "SHA-256 摘要不一致".to_string()))
+            }
+            Some(_) => {}
+        }
+    }
+    let links: Vec<&str> = manifest
+        .files
+        .iter()
+        .filter(|f| f.link.is_some())
+        .map(|f| f.path.as_str())
+        .collect();
+    let mut extra: Vec<String> = actual
+        .into_keys()
+        .filter(|name| !links.contains(&name.as_str()))
+        .collect();
+    extra.sort();
+    for name in extra {
+        errors.push(error(&name, "清单中没有该文件".to_string()));
+    }
+    Ok(Some(errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor;
+    use crate::format::Format;
+    use crate::progress::NoopReporter;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_embed_and_verify() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir_all(base_dir.join("src"))?;
+        std::fs::write(base_dir.join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(base_dir.join("README.md"), "# app")?;
+
+        for format in Format::ALL {
+            let config = Config {
+                format,
+                output_name: Some(format!("app-{}", format.extension())),
+                embed_manifest: Some(true),
+                ..Default::default()
+            };
+            let files = vec![base_dir.join("README.md"), base_dir.join("src/main.rs")];
+            let report = compressor::compress_directory(&config, base_dir, files, &NoopReporter)?;
+
+            let manifest = read_embedded(&report.output_path)?.expect("缺少内嵌清单");
+            assert_eq!(manifest.ztr_version, env!("CARGO_PKG_VERSION"));
+            assert_eq!(manifest.config["format"], format.to_string());
+            assert_eq!(manifest.files.len(), 2);
+            assert_eq!(manifest.files[1].path, "src/main.rs");
+            assert_eq!(manifest.files[1].size, 12);
+            assert_eq!(
+                manifest.files[1].sha256.as_deref(),
+                Some(checksum::sha256_reader("fn main() {}".as_bytes())?.as_str())
+            );
+            assert_eq!(verify_embedded(&report.output_path)?, Some(Vec::new()));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_mismatch() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("tampered.zip");
+        let manifest = ArchiveManifest {
+            ztr_version: "0.0.0".to_string(),
+            created: 0,
+            config: serde_json::Value::Null,
+            files: vec![
+                ManifestFile {
+                    path: "a.txt".to_string(),
+                    size: 1,
+                    sha256: Some(checksum::sha256_reader("a".as_bytes())?),
+                    link: None,
+                },
+                ManifestFile {
+                    path: "missing.txt".to_string(),
+                    size: 0,
+                    sha256: Some(String::new()),
+                    link: None,
+                },
+            ],
+        };
+        let entries = vec![
+            ("a.txt".to_string(), Cursor::new(b"b".to_vec())),
+            ("extra.txt".to_string(), Cursor::new(Vec::new())),
+            (ENTRY_NAME.to_string(), Cursor::new(manifest.to_json()?)),
+        ];
+        let out = compressor::compress_entries(Format::Zip, entries, Cursor::new(Vec::new()))?;
+        std::fs::write(&archive, out.into_inner())?;
+
+        let errors = verify_embedded(&archive)?.unwrap();
+        let names: Vec<&str> = errors.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.txt", "missing.txt", "extra.txt"]);
+
+        // 没有内嵌清单的压缩包
+        let plain = compressor::compress_entries(
+            Format::Zip,
+            vec![("a.txt".to_string(), Cursor::new(b"a".to_vec()))],
+            Cursor::new(Vec::new()),
+        )?;
+        std::fs::write(&archive, plain.into_inner())?;
+        assert_eq!(verify_embedded(&archive)?, None);
+        Ok(())
+    }
+}
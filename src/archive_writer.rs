@@ -0,0 +1,1187 @@
+//! 可扩展的压缩包写入接口与格式注册表。
+//!
+//! [`ArchiveWriter`] 以 `start_entry` → `write` → … → `finish` 的顺序逐个写入条目，
+//! [`FormatRegistry`] 按名称查找创建写入器的工厂函数。内置格式 (`zip`、`tar.gz`、`tar.zst`、`7z`)
+//! 已注册，`compressor`、`update`、`convert` 等模块的所有写入都通过注册表完成；
+//! 下游 crate 可以注册自己的格式（例如内部使用的容器格式），再通过
+//! [`compress_files_with`] 用同样的文件列表与配置生成压缩包，而不必修改 `compressor` 模块。
+//!
+//! # 示例
+//!
+//! ```
+//! use anyhow::Context;
+//! use std::io::{Cursor, Write};
+//! use ztr_lib::archive_writer::{ArchiveOutput, ArchiveWriter, EntryHeader, FormatRegistry};
+//! use ztr_lib::compressor::EntryOptions;
+//!
+//! /// 每个条目写成 `名称\n大小\n内容` 的简单容器格式。
+//! struct Plain<'a>(Box<dyn Write + 'a>);
+//!
+//! impl Write for Plain<'_> {
+//!     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+//!         self.0.write(buf)
+//!     }
+//!     fn flush(&mut self) -> std::io::Result<()> {
+//!         self.0.flush()
+//!     }
+//! }
+//!
+//! impl ArchiveWriter for Plain<'_> {
+//!     fn start_entry(&mut self, header: &EntryHeader) -> anyhow::Result<()> {
+//!         let size = header.size.context("plain 格式需要预先知道条目大小")?;
+//!         write!(self.0, "{}\n{}\n", header.name, size)?;
+//!         Ok(())
+//!     }
+//!     fn finish(self: Box<Self>) -> anyhow::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let mut registry = FormatRegistry::default();
+//!     registry.register("plain", |out, _options| Ok(Box::new(Plain(out.into_write()))));
+//!
+//!     let mut buf = Cursor::new(Vec::new());
+//!     let options = EntryOptions::default();
+//!     let mut writer = registry.create("plain", ArchiveOutput::seekable(&mut buf), &options)?;
+//!     writer.start_entry(&EntryHeader::new("a.txt", 5))?;
+//!     writer.write_all(b"hello")?;
+//!     writer.finish()?;
+//!     assert_eq!(buf.into_inner(), b"a.txt\n5\nhello");
+//!     Ok(())
+//! }
+//! ```
+
+use crate::compressor::{self, EntryOptions, virtual_entry_name};
+use crate::config::{ByteSize, Config, GzipHeader, SevenZConfig};
+use crate::extractor::UNIX_EXTENSION;
+use crate::format::Format;
+use crate::metadata::{self, EntryMetadata};
+use crate::progress::NoopReporter;
+use crate::rules::{CompressionRule, ZipMethod};
+use anyhow::{Context, Result};
+use sevenz_rust::{SeqReader, SevenZArchiveEntry, SevenZWriter, SourceReader};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// 压缩包的写入目标，即任意 `Write + Seek`（文件、`Cursor<Vec<u8>>`、`&mut` 引用等）。
+pub trait ArchiveSink: Write + Seek {}
+
+impl<T: Write + Seek> ArchiveSink for T {}
+
+/// 创建写入器时的输出目标。
+pub enum ArchiveOutput<'a> {
+    /// 可随机访问的目标，如文件或内存缓冲区
+    Seekable(Box<dyn ArchiveSink + 'a>),
+    /// 只能顺序写入的数据流，如标准输出或网络连接；ZIP 条目的大小与 CRC 写在数据描述符中，7z 不支持
+    Stream(Box<dyn Write + 'a>),
+}
+
+impl<'a> ArchiveOutput<'a> {
+    /// 可随机访问的目标。
+    pub fn seekable<W: Write + Seek + 'a>(out: W) -> Self {
+        Self::Seekable(Box::new(out))
+    }
+
+    /// 只能顺序写入的目标。
+    pub fn stream<W: Write + 'a>(out: W) -> Self {
+        Self::Stream(Box::new(out))
+    }
+
+    /// 作为顺序写入的目标使用，适用于不需要回写的格式（如 TAR）。
+    pub fn into_write(self) -> Box<dyn Write + 'a> {
+        match self {
+            Self::Seekable(out) => out,
+            Self::Stream(out) => out,
+        }
+    }
+}
+
+/// 压缩包中一个条目的元数据。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryHeader {
+    /// 条目名称，使用 `/` 分隔的相对路径
+    pub name: String,
+    /// 内容的字节数，之后通过 `write` 写入的内容必须恰好为这么多字节；
+    /// `None` 表示大小未知 (ZIP 启用 ZIP64 扩展，TAR 先缓存内容再写入头部)
+    pub size: Option<u64>,
+    /// 修改时间 (Unix 时间戳)
+    pub mtime: u64,
+    /// Unix 权限位
+    pub mode: u32,
+    /// 所有者的 uid (只写入 TAR)
+    pub uid: u64,
+    /// 所属组的 gid (只写入 TAR)
+    pub gid: u64,
+    /// 设置时条目为指向该目标的符号链接，没有内容
+    pub link_target: Option<String>,
+    /// 压缩参数，未指定的部分使用写入器的默认值 (配置中的 `zip_method`、`[sevenz]` 等)
+    pub compression: CompressionRule,
+    /// ZIP 条目注释或 TAR 的 PAX 扩展头
+    pub metadata: EntryMetadata,
+}
+
+impl EntryHeader {
+    /// 创建普通文件条目的元数据，修改时间为 0，权限为 644，其余为默认值。
+    pub fn new<S: Into<String>>(name: S, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            size: Some(size),
+            mtime: 0,
+            mode: 0o644,
+            uid: 0,
+            gid: 0,
+            link_target: None,
+            compression: CompressionRule::default(),
+            metadata: EntryMetadata::default(),
+        }
+    }
+}
+
+/// 逐个条目写入压缩包。
+///
+/// 调用顺序为 `start_entry`，随后通过 [`Write`] 写入该条目的全部内容，再开始下一个条目；
+/// 所有条目写完后调用 `finish` 完成压缩包（写入目录、结束压缩数据流并刷新输出目标等）。
+/// 不调用 `finish` 得到的压缩包是不完整的。
+pub trait ArchiveWriter: Write {
+    /// 开始一个新的条目，上一个条目的内容随之结束。
+    fn start_entry(&mut self, header: &EntryHeader) -> Result<()>;
+
+    /// 结束最后一个条目并完成压缩包。
+    fn finish(self: Box<Self>) -> Result<()>;
+
+    /// 不解压、不重新压缩地复制 ZIP 压缩包中的条目，用于更新已有的压缩包。
+    ///
+    /// # 返回
+    /// `Result<bool>`: 写入器不支持时返回 `false` (默认实现)，调用者应改为重新写入该条目的内容。
+    fn copy_zip_entry(&mut self, entry: zip::read::ZipFile<'_, BufReader<File>>) -> Result<bool> {
+        let _ = entry;
+        Ok(false)
+    }
+}
+
+/// 创建写入器的工厂函数，参数为压缩包的输出目标与条目选项 (默认压缩方法、gzip 头部、`[sevenz]` 等)。
+pub type WriterFactory = dyn for<'a> Fn(ArchiveOutput<'a>, &EntryOptions) -> Result<Box<dyn ArchiveWriter + 'a>>
+    + Send
+    + Sync;
+
+/// 按名称查找压缩格式的注册表，[`Default`] 包含所有内置格式。
+#[derive(Clone)]
+pub struct FormatRegistry {
+    formats: BTreeMap<String, Arc<WriterFactory>>,
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(Format::Zip.extension(), zip_writer);
+        registry.register(Format::TarGz.extension(), tar_gz_writer);
+        registry.register(Format::TarZst.extension(), tar_zst_writer);
+        registry.register(Format::SevenZ.extension(), sevenz_writer);
+        registry
+    }
+}
+
+impl std::fmt::Debug for FormatRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.formats.keys()).finish()
+    }
+}
+
+impl FormatRegistry {
+    /// 不包含任何格式的注册表。
+    pub fn empty() -> Self {
+        Self {
+            formats: BTreeMap::new(),
+        }
+    }
+
+    /// 注册格式，同名的格式（包括内置格式）会被替换。
+    ///
+    /// # 参数
+    /// - `name`: 格式名称，通常为扩展名，如 `"tar.gz"`。
+    /// - `factory`: 根据输出目标与条目选项创建写入器的函数。
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: for<'a> Fn(ArchiveOutput<'a>, &EntryOptions) -> Result<Box<dyn ArchiveWriter + 'a>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.formats.insert(name.into(), Arc::new(factory));
+    }
+
+    /// 已注册的格式名称，按字典序排列。
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.formats.keys().map(String::as_str)
+    }
+
+    /// 是否注册了该格式。
+    pub fn contains(&self, name: &str) -> bool {
+        self.formats.contains_key(name)
+    }
+
+    /// 创建写入 `out` 的写入器。
+    ///
+    /// # 返回
+    /// `Result<Box<dyn ArchiveWriter>>`: 格式未注册或创建失败时返回错误。
+    pub fn create<'a>(
+        &self,
+        name: &str,
+        out: ArchiveOutput<'a>,
+        options: &EntryOptions,
+    ) -> Result<Box<dyn ArchiveWriter + 'a>> {
+        let factory = self.formats.get(name).with_context(|| {
+            format!(
+                "未注册的压缩格式: {}，已注册的格式: {}",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        factory(out, options)
+    }
+}
+
+/// 按配置用注册表中的格式将文件列表压缩写入 `out`。
+///
+/// 与 [`compress_files`](crate::compressor::compress_files) 使用同样的条目选项：`store_only`、
+/// `[rules]`、`[metadata]`、可复现模式、gzip 头部与级别、符号链接策略等都会生效，
+/// 内置格式得到的压缩包与其完全相同。忽略规则等过滤应在调用前完成（例如使用 `FilterPipeline`）。
+///
+/// # 参数
+/// - `registry`: 格式注册表。
+/// - `format`: 格式名称。
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录。
+/// - `files`: 要压缩的文件路径列表，必须位于 `base_dir` 或配置的源路径中。
+/// - `out`: 压缩包的写入目标。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`。
+pub fn compress_files_with<W: Write + Seek>(
+    registry: &FormatRegistry,
+    format: &str,
+    config: &Config,
+    base_dir: &Path,
+    files: &[PathBuf],
+    mut out: W,
+) -> Result<W> {
+    let mut files = files.to_vec();
+    if config.reproducible.unwrap_or(false) {
+        // 条目顺序与遍历顺序无关
+        files.sort();
+    }
+    let options = EntryOptions::from_config(config, base_dir, &files)?;
+    compressor::write_archive(
+        registry,
+        format,
+        ArchiveOutput::seekable(&mut out),
+        &files,
+        base_dir,
+        &options,
+        &NoopReporter,
+    )?;
+    Ok(out)
+}
+
+/// ZIP 条目大小达到此值 (4 GiB - 1) 时需要使用 ZIP64 扩展。
+pub(crate) const ZIP64_SIZE_THRESHOLD: u64 = u32::MAX as u64;
+
+/// 普通文件的 Unix 文件类型 (S_IFREG)。
+const UNIX_REGULAR: u32 = 0o100000;
+/// 符号链接的 Unix 文件类型 (S_IFLNK)。
+const UNIX_SYMLINK: u32 = 0o120000;
+
+/// 正在写入的条目：名称、声明的大小与已写入的字节数。
+struct OpenEntry {
+    name: String,
+    size: Option<u64>,
+    written: u64,
+}
+
+impl OpenEntry {
+    fn new(name: &str, size: Option<u64>) -> Self {
+        Self {
+            name: name.to_string(),
+            size,
+            written: 0,
+        }
+    }
+
+    /// 记录即将写入的字节数，超出声明的大小时返回错误。
+    fn advance(&mut self, len: usize) -> io::Result<()> {
+        self.written += len as u64;
+        if let Some(size) = self.size
+            && self.written > size
+        {
+            return Err(io::Error::other(format!(
+                "条目 {} 的内容超出声明的大小 {} 字节",
+                self.name, size
+            )));
+        }
+        Ok(())
+    }
+
+    /// 条目结束时检查写入的内容是否与声明的大小一致。
+    fn close(self) -> Result<()> {
+        if let Some(size) = self.size
+            && self.written != size
+        {
+            anyhow::bail!(
+                "条目 {} 的内容为 {} 字节，与声明的大小 {} 字节不符",
+                self.name,
+                self.written,
+                size
+            );
+        }
+        Ok(())
+    }
+}
+
+fn no_entry() -> io::Error {
+    io::Error::other("写入内容前需要先调用 start_entry")
+}
+
+fn zip_writer<'a>(
+    out: ArchiveOutput<'a>,
+    options: &EntryOptions,
+) -> Result<Box<dyn ArchiveWriter + 'a>> {
+    let method = options.zip_method;
+    Ok(match out {
+        ArchiveOutput::Seekable(out) => {
+            Box::new(ZipArchiveWriter::new(zip::ZipWriter::new(out), method))
+        }
+        ArchiveOutput::Stream(out) => Box::new(ZipArchiveWriter::new(
+            zip::ZipWriter::new_stream(out),
+            method,
+        )),
+    })
+}
+
+/// ZIP 写入器，未指定压缩方法的条目使用 `method`。
+struct ZipArchiveWriter<W: Write + Seek> {
+    zip: zip::ZipWriter<W>,
+    method: ZipMethod,
+    entry: Option<OpenEntry>,
+}
+
+impl<W: Write + Seek> ZipArchiveWriter<W> {
+    fn new(zip: zip::ZipWriter<W>, method: ZipMethod) -> Self {
+        Self {
+            zip,
+            method,
+            entry: None,
+        }
+    }
+
+    fn close_entry(&mut self) -> Result<()> {
+        match self.entry.take() {
+            Some(entry) => entry.close(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Write + Seek> Write for ZipArchiveWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.entry
+            .as_mut()
+            .ok_or_else(no_entry)?
+            .advance(buf.len())?;
+        self.zip.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.zip.flush()
+    }
+}
+
+impl<W: Write + Seek> ArchiveWriter for ZipArchiveWriter<W> {
+    fn start_entry(&mut self, header: &EntryHeader) -> Result<()> {
+        self.close_entry()?;
+        let name = virtual_entry_name(&header.name)?;
+        let method = header.compression.method.unwrap_or(self.method);
+        let mut options = zip::write::FullFileOptions::default()
+            .compression_method(method.to_zip())
+            .compression_level(
+                header
+                    .compression
+                    .level
+                    .and_then(|level| method.clamp_level(level)),
+            )
+            .unix_permissions(header.mode)
+            .last_modified_time(compressor::zip_datetime(header.mtime))
+            // 大小未知或达到 4 GiB 的条目需要 ZIP64 扩展；条目数超过 65535 时 ZIP64 目录由 zip 库自动写入
+            .large_file(header.size.is_none_or(|size| size >= ZIP64_SIZE_THRESHOLD));
+        if let Some(comment) = &header.metadata.comment {
+            options = options.with_file_comment(comment.as_str());
+        }
+        let size = match &header.link_target {
+            Some(target) => {
+                self.zip
+                    .add_symlink(&name, target, options)
+                    .with_context(|| format!("添加符号链接到ZIP失败: {}", name))?;
+                Some(0)
+            }
+            None => {
+                self.zip
+                    .start_file(&name, options)
+                    .with_context(|| format!("添加文件到ZIP失败: {}", name))?;
+                header.size
+            }
+        };
+        self.entry = Some(OpenEntry::new(&name, size));
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.close_entry()?;
+        self.zip
+            .finish()
+            .and_then(|mut out| Ok(out.flush()?))
+            .context("完成ZIP写入失败")
+    }
+
+    fn copy_zip_entry(&mut self, entry: zip::read::ZipFile<'_, BufReader<File>>) -> Result<bool> {
+        self.close_entry()?;
+        let name = entry.name().to_string();
+        self.zip
+            .raw_copy_file(entry)
+            .with_context(|| format!("复制ZIP条目失败: {}", name))?;
+        Ok(true)
+    }
+}
+
+fn tar_gz_writer<'a>(
+    out: ArchiveOutput<'a>,
+    options: &EntryOptions,
+) -> Result<Box<dyn ArchiveWriter + 'a>> {
+    let encoder = gzip_encoder(out.into_write(), &options.gzip_header, options.gzip_level);
+    Ok(Box::new(TarArchiveWriter::new(encoder, |encoder| {
+        encoder
+            .finish()
+            .and_then(|mut out| out.flush())
+            .context("完成TAR.GZ写入失败")
+    })))
+}
+
+#[cfg(feature = "tar-zst")]
+fn tar_zst_writer<'a>(
+    out: ArchiveOutput<'a>,
+    _options: &EntryOptions,
+) -> Result<Box<dyn ArchiveWriter + 'a>> {
+    let encoder = zstd_encoder(out.into_write())?;
+    Ok(Box::new(TarArchiveWriter::new(encoder, |encoder| {
+        encoder
+            .finish()
+            .and_then(|mut out| out.flush())
+            .context("完成TAR.ZST写入失败")
+    })))
+}
+
+/// 未启用 `tar-zst` 特性时没有 zstd 编码器。
+#[cfg(not(feature = "tar-zst"))]
+fn tar_zst_writer<'a>(
+    _out: ArchiveOutput<'a>,
+    _options: &EntryOptions,
+) -> Result<Box<dyn ArchiveWriter + 'a>> {
+    anyhow::bail!("TAR.ZST 格式需要启用 tar-zst 特性")
+}
+
+/// 按 gzip 头部配置与压缩级别创建 gzip 压缩流。
+fn gzip_encoder<W: Write>(
+    out: W,
+    gzip_header: &GzipHeader,
+    level: flate2::Compression,
+) -> flate2::write::GzEncoder<W> {
+    let mut gz_builder = flate2::GzBuilder::new()
+        .mtime(gzip_header.mtime.unwrap_or(0))
+        .operating_system(gzip_header.os.unwrap_or(255));
+    if let Some(filename) = &gzip_header.filename {
+        gz_builder = gz_builder.filename(filename.as_bytes());
+    }
+    gz_builder.write(out, level)
+}
+
+/// 创建 zstd 压缩流，写入帧校验和，使 `ztr verify` 能发现数据损坏。
+#[cfg(feature = "tar-zst")]
+fn zstd_encoder<W: Write>(out: W) -> Result<zstd::Encoder<'static, W>> {
+    let mut encoder = zstd::Encoder::new(out, 0).context("创建TAR.ZST数据流失败")?;
+    encoder
+        .include_checksum(true)
+        .context("创建TAR.ZST数据流失败")?;
+    Ok(encoder)
+}
+
+/// TAR 写入器，`W` 为外层的压缩数据流，`finish_stream` 负责结束该数据流。
+struct TarArchiveWriter<W: Write> {
+    tar: tar::Builder<W>,
+    finish_stream: fn(W) -> Result<()>,
+    entry: Option<OpenEntry>,
+    /// 大小未知的条目：TAR 头需要预先知道大小，内容先缓存，条目结束时再写入头部与内容
+    spooled: Option<(tar::Header, String, Spool)>,
+}
+
+impl<W: Write> TarArchiveWriter<W> {
+    fn new(out: W, finish_stream: fn(W) -> Result<()>) -> Self {
+        Self {
+            tar: tar::Builder::new(out),
+            finish_stream,
+            entry: None,
+            spooled: None,
+        }
+    }
+
+    /// 结束当前条目：检查大小，写入缓存的内容或补齐到 512 字节的块。
+    fn close_entry(&mut self) -> Result<()> {
+        let Some(entry) = self.entry.take() else {
+            return Ok(());
+        };
+        let written = entry.written;
+        entry.close()?;
+        match self.spooled.take() {
+            Some((mut header, name, mut content)) => {
+                content.rewind().context("读取缓存的条目内容失败")?;
+                header.set_size(written);
+                self.tar
+                    .append_data(&mut header, &name, content)
+                    .with_context(|| format!("添加条目到TAR失败: {}", name))?;
+            }
+            None => {
+                let padding = (512 - written % 512) % 512;
+                self.tar
+                    .get_mut()
+                    .write_all(&[0; 512][..padding as usize])
+                    .context("写入TAR失败")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 条目有 PAX 记录时，写入其 PAX 扩展头（作用于紧随其后的条目）。
+    fn append_pax_header(&mut self, metadata: &EntryMetadata) -> io::Result<()> {
+        let pax_records = metadata.pax_records();
+        if pax_records.is_empty() {
+            return Ok(());
+        }
+        let data = metadata::encode_pax_records(&pax_records);
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::XHeader);
+        header.set_path("././@PaxHeader")?;
+        header.set_mode(0o644);
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        self.tar.append(&header, data.as_slice())
+    }
+}
+
+impl<W: Write> Write for TarArchiveWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.entry
+            .as_mut()
+            .ok_or_else(no_entry)?
+            .advance(buf.len())?;
+        match &mut self.spooled {
+            Some((_, _, content)) => content.write_all(buf)?,
+            None => self.tar.get_mut().write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tar.get_mut().flush()
+    }
+}
+
+impl<W: Write> ArchiveWriter for TarArchiveWriter<W> {
+    fn start_entry(&mut self, header: &EntryHeader) -> Result<()> {
+        self.close_entry()?;
+        let name = virtual_entry_name(&header.name)?;
+        self.append_pax_header(&header.metadata)
+            .with_context(|| format!("写入PAX扩展头失败: {}", name))?;
+        let mut tar_header = tar::Header::new_gnu();
+        tar_header.set_mode(header.mode);
+        tar_header.set_uid(header.uid);
+        tar_header.set_gid(header.gid);
+        tar_header.set_mtime(header.mtime);
+
+        if let Some(target) = &header.link_target {
+            tar_header.set_entry_type(tar::EntryType::Symlink);
+            tar_header.set_size(0);
+            self.tar
+                .append_link(&mut tar_header, &name, target)
+                .with_context(|| format!("添加符号链接到TAR失败: {}", name))?;
+            self.entry = Some(OpenEntry::new(&name, Some(0)));
+            return Ok(());
+        }
+
+        tar_header.set_entry_type(tar::EntryType::Regular);
+        match header.size {
+            Some(size) => {
+                tar_header.set_size(size);
+                // 传入空的内容只写出头部（包括过长名称所需的 GNU 扩展头），内容随后直接写入数据流
+                self.tar
+                    .append_data(&mut tar_header, &name, io::empty())
+                    .with_context(|| format!("添加条目到TAR失败: {}", name))?;
+            }
+            None => self.spooled = Some((tar_header, name.clone(), Spool::new(SPOOL_MEMORY_LIMIT))),
+        }
+        self.entry = Some(OpenEntry::new(&name, header.size));
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.close_entry()?;
+        let finish_stream = self.finish_stream;
+        let out = self.tar.into_inner().context("完成TAR写入失败")?;
+        finish_stream(out)
+    }
+}
+
+fn sevenz_writer<'a>(
+    out: ArchiveOutput<'a>,
+    options: &EntryOptions,
+) -> Result<Box<dyn ArchiveWriter + 'a>> {
+    let ArchiveOutput::Seekable(out) = out else {
+        anyhow::bail!("7z 格式需要可随机访问的输出，不能写入数据流");
+    };
+    let mut writer = SevenZWriter::new(out).context("创建7Z文件失败")?;
+    let solid = options.sevenz.solid.unwrap_or(false);
+    if solid {
+        writer.set_content_methods(vec![lzma2_options(&options.sevenz, None).into()]);
+    }
+    Ok(Box::new(SevenZArchiveWriter {
+        writer,
+        sevenz: options.sevenz.clone(),
+        content: Spool::new(SPOOL_MEMORY_LIMIT),
+        solid: solid.then(Vec::new),
+        entry: None,
+    }))
+}
+
+/// 7Z 写入器。sevenz-rust 一次读取整个条目，因此条目内容先缓存，条目结束时再压缩；
+/// `[sevenz]` 中启用 `solid` 时所有条目缓存在一起，完成时写入同一个固实数据块。
+/// 符号链接按 p7zip 的约定写为带 Unix 链接属性、内容为链接目标的条目。
+struct SevenZArchiveWriter<'a> {
+    writer: SevenZWriter<Box<dyn ArchiveSink + 'a>>,
+    sevenz: SevenZConfig,
+    /// 缓存的内容：非固实压缩时只有当前条目，固实压缩时为所有条目依次拼接
+    content: Spool,
+    /// 固实压缩时已结束、等待写入数据块的条目；非固实压缩时为 `None`
+    solid: Option<Vec<SevenZArchiveEntry>>,
+    entry: Option<(OpenEntry, SevenZArchiveEntry)>,
+}
+
+impl SevenZArchiveWriter<'_> {
+    fn close_entry(&mut self) -> Result<()> {
+        let Some((entry, mut archive_entry)) = self.entry.take() else {
+            return Ok(());
+        };
+        archive_entry.size += entry.written;
+        entry.close()?;
+        match &mut self.solid {
+            Some(entries) => entries.push(archive_entry),
+            None => {
+                let mut content =
+                    std::mem::replace(&mut self.content, Spool::new(SPOOL_MEMORY_LIMIT));
+                content.rewind().context("读取缓存的条目内容失败")?;
+                let name = archive_entry.name.clone();
+                self.writer
+                    .push_archive_entry(archive_entry, Some(content))
+                    .with_context(|| format!("添加条目到7Z失败: {}", name))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for SevenZArchiveWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (entry, _) = self.entry.as_mut().ok_or_else(no_entry)?;
+        entry.advance(buf.len())?;
+        self.content.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ArchiveWriter for SevenZArchiveWriter<'_> {
+    fn start_entry(&mut self, header: &EntryHeader) -> Result<()> {
+        self.close_entry()?;
+        let mut archive_entry = SevenZArchiveEntry::default();
+        archive_entry.name = virtual_entry_name(&header.name)?;
+        archive_entry.has_stream = true;
+        let file_type = match header.link_target {
+            Some(_) => UNIX_SYMLINK,
+            None => UNIX_REGULAR,
+        };
+        archive_entry.has_windows_attributes = true;
+        archive_entry.windows_attributes =
+            UNIX_EXTENSION | ((file_type | (header.mode & 0o7777)) << 16);
+        if let Ok(date) = (SystemTime::UNIX_EPOCH + Duration::from_secs(header.mtime)).try_into() {
+            archive_entry.last_modified_date = date;
+            archive_entry.has_last_modified_date = true;
+        }
+        if self.solid.is_none() {
+            self.writer
+                .set_content_methods(vec![sevenz_method(&self.sevenz, &header.compression)]);
+        }
+
+        let size = match &header.link_target {
+            Some(target) => {
+                self.content
+                    .write_all(target.as_bytes())
+                    .context("缓存条目内容失败")?;
+                archive_entry.size = target.len() as u64;
+                Some(0)
+            }
+            None => header.size,
+        };
+        self.entry = Some((OpenEntry::new(&archive_entry.name, size), archive_entry));
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.close_entry()?;
+        let Self {
+            mut writer,
+            mut content,
+            solid,
+            ..
+        } = *self;
+        if let Some(entries) = solid
+            && !entries.is_empty()
+        {
+            content.rewind().context("读取缓存的条目内容失败")?;
+            let content = RefCell::new(content);
+            let sources: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    SourceReader::new(SpoolSlice {
+                        spool: &content,
+                        remaining: entry.size,
+                    })
+                })
+                .collect();
+            writer
+                .push_archive_entries(entries, SeqReader::new(sources))
+                // 保留读取时的 IO 错误，sevenz-rust 的错误类型不提供错误来源
+                .map_err(|e| match e {
+                    sevenz_rust::Error::Io(e, _) => anyhow::Error::new(e),
+                    e => anyhow::Error::msg(format!("{:?}", e)),
+                })
+                .context("写入7Z固实数据块失败")?;
+        }
+        writer
+            .finish()
+            .and_then(|mut out| out.flush())
+            .context("完成7Z写入失败")
+    }
+}
+
+/// 固实压缩时共享缓存中一个条目的内容，各条目按顺序读取。
+struct SpoolSlice<'s> {
+    spool: &'s RefCell<Spool>,
+    remaining: u64,
+}
+
+impl Read for SpoolSlice<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let read = self.spool.borrow_mut().read(&mut buf[..len])?;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// 条目写入 7Z 时使用的压缩方法：`stored` 使用最快的 LZMA2 预设
+/// (sevenz-rust 不支持写入 COPY 方法)，指定了级别时使用对应的 LZMA2 预设。
+fn sevenz_method(
+    sevenz: &SevenZConfig,
+    rule: &CompressionRule,
+) -> sevenz_rust::SevenZMethodConfiguration {
+    match rule.method {
+        Some(ZipMethod::Stored) => sevenz_rust::lzma::LZMA2Options::with_preset(0).into(),
+        _ => lzma2_options(sevenz, rule.level).into(),
+    }
+}
+
+/// 按 `[sevenz]` 构造 LZMA2 参数，`level` 优先于 `[sevenz]` 中的级别。
+fn lzma2_options(sevenz: &SevenZConfig, level: Option<u32>) -> sevenz_rust::lzma::LZMA2Options {
+    let preset = level.or(sevenz.level).unwrap_or(6);
+    let mut lzma2 = sevenz_rust::lzma::LZMA2Options::with_preset(preset);
+    if let Some(ByteSize(dict_size)) = sevenz.dict_size {
+        lzma2.dict_size = lzma2_dict_size(dict_size as u32);
+    }
+    lzma2
+}
+
+/// 将字典大小向上取整为 7Z 头部能够表示的值 (2^n 或 3·2^(n-1))，
+/// 否则头部记录的字典会小于编码时实际使用的字典，导致无法解压。
+fn lzma2_dict_size(size: u32) -> u32 {
+    let power = size.next_power_of_two();
+    let three_quarters = power / 4 * 3;
+    if size <= three_quarters {
+        three_quarters
+    } else {
+        power
+    }
+}
+
+/// 条目内容在内存中缓存的上限 (字节)，更大的内容转存到临时文件。
+const SPOOL_MEMORY_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// 缓存的条目内容：不超过上限时在内存中，超过后转存到系统临时目录中的临时文件
+/// (关闭 `native` 特性时全部缓存在内存中)。写完后调用 `rewind` 再从头读取。
+#[cfg_attr(not(feature = "native"), allow(dead_code))]
+enum Spool {
+    Memory {
+        buffer: io::Cursor<Vec<u8>>,
+        limit: u64,
+    },
+    #[cfg(feature = "native")]
+    File(TempFile),
+}
+
+impl Spool {
+    fn new(limit: u64) -> Self {
+        Self::Memory {
+            buffer: io::Cursor::new(Vec::new()),
+            limit,
+        }
+    }
+
+    fn rewind(&mut self) -> io::Result<()> {
+        match self {
+            Self::Memory { buffer, .. } => buffer.set_position(0),
+            #[cfg(feature = "native")]
+            Self::File(temp) => temp.file.rewind()?,
+        }
+        Ok(())
+    }
+}
+
+impl Write for Spool {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "native")]
+        if let Self::Memory { buffer, limit } = self
+            && (buffer.get_ref().len() + buf.len()) as u64 > *limit
+        {
+            let mut temp = TempFile::create()?;
+            temp.file.write_all(buffer.get_ref())?;
+            *self = Self::File(temp);
+        }
+        match self {
+            Self::Memory { buffer, .. } => buffer.write(buf),
+            #[cfg(feature = "native")]
+            Self::File(temp) => temp.file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Spool {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Memory { buffer, .. } => buffer.read(buf),
+            #[cfg(feature = "native")]
+            Self::File(temp) => temp.file.read(buf),
+        }
+    }
+}
+
+/// 系统临时目录中的临时文件，丢弃时删除。
+#[cfg(feature = "native")]
+struct TempFile {
+    file: File,
+    path: PathBuf,
+}
+
+#[cfg(feature = "native")]
+impl TempFile {
+    fn create() -> io::Result<Self> {
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        loop {
+            let path = std::env::temp_dir().join(format!(
+                "ztr-entry-{}-{}.tmp",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            match std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(file) => return Ok(Self { file, path }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::compress_files;
+    use crate::progress::NoopReporter;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    fn read_entries(data: Vec<u8>, format: Format) -> Result<Vec<(String, String)>> {
+        let mut read = Vec::new();
+        crate::reader::for_each_entry_in(Cursor::new(data), format, |name, r| {
+            let mut content = String::new();
+            r.read_to_string(&mut content)?;
+            read.push((name.to_string(), content));
+            Ok(())
+        })?;
+        Ok(read)
+    }
+
+    #[test]
+    fn test_builtin_writers() -> Result<()> {
+        let registry = FormatRegistry::default();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["7z", "tar.gz", "tar.zst", "zip"]
+        );
+        let long_name = format!("{}/file.txt", "d".repeat(120));
+        let options = EntryOptions::default();
+        for format in Format::ALL {
+            let mut buf = Cursor::new(Vec::new());
+            let mut writer = registry.create(
+                format.extension(),
+                ArchiveOutput::seekable(&mut buf),
+                &options,
+            )?;
+            writer.start_entry(&EntryHeader::new("a.txt", 5))?;
+            writer.write_all(b"hel")?;
+            writer.write_all(b"lo")?;
+            writer.start_entry(&EntryHeader::new(long_name.as_str(), 0))?;
+            // 大小未知的条目
+            writer.start_entry(&EntryHeader {
+                size: None,
+                ..EntryHeader::new("sub/b.txt", 0)
+            })?;
+            writer.write_all(&[b'x'; 600])?;
+            writer.finish()?;
+
+            assert_eq!(
+                read_entries(buf.into_inner(), format)?,
+                [
+                    ("a.txt".to_string(), "hello".to_string()),
+                    (long_name.clone(), String::new()),
+                    ("sub/b.txt".to_string(), "x".repeat(600)),
+                ],
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_mismatch_and_unknown_format() -> Result<()> {
+        let registry = FormatRegistry::default();
+        let options = EntryOptions::default();
+        let mut writer = registry.create(
+            "tar.gz",
+            ArchiveOutput::seekable(Cursor::new(Vec::new())),
+            &options,
+        )?;
+        assert!(writer.write_all(b"x").is_err());
+        writer.start_entry(&EntryHeader::new("a.txt", 2))?;
+        assert!(writer.write_all(b"abc").is_err());
+
+        let mut writer = registry.create(
+            "zip",
+            ArchiveOutput::seekable(Cursor::new(Vec::new())),
+            &options,
+        )?;
+        writer.start_entry(&EntryHeader::new("a.txt", 5))?;
+        writer.write_all(b"hi")?;
+        assert!(writer.finish().is_err());
+
+        // 7z 不能写入只能顺序写入的数据流
+        assert!(
+            registry
+                .create("7z", ArchiveOutput::stream(Vec::new()), &options)
+                .is_err()
+        );
+
+        let error = registry
+            .create(
+                "rar",
+                ArchiveOutput::seekable(Cursor::new(Vec::new())),
+                &options,
+            )
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("rar") && error.contains("tar.gz"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_files_with_custom_format() -> Result<()> {
+        struct Names<'a>(Box<dyn Write + 'a>);
+
+        impl Write for Names<'_> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl ArchiveWriter for Names<'_> {
+            fn start_entry(&mut self, header: &EntryHeader) -> Result<()> {
+                writeln!(self.0, "{} {:?}", header.name, header.size)?;
+                Ok(())
+            }
+            fn finish(self: Box<Self>) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let dir = tempdir()?;
+        std::fs::create_dir(dir.path().join("sub"))?;
+        std::fs::write(dir.path().join("a.txt"), "hello")?;
+        std::fs::write(dir.path().join("sub/b.txt"), "hi")?;
+        let files = vec![dir.path().join("a.txt"), dir.path().join("sub/b.txt")];
+
+        let mut registry = FormatRegistry::default();
+        registry.register("names", |out, _options| {
+            Ok(Box::new(Names(out.into_write())))
+        });
+        let out = compress_files_with(
+            &registry,
+            "names",
+            &Config::default(),
+            dir.path(),
+            &files,
+            Cursor::new(Vec::new()),
+        )?;
+        assert_eq!(out.into_inner(), b"a.txt Some(5)\nsub/b.txt Some(2)\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_files_with_matches_compress_files() -> Result<()> {
+        use std::collections::BTreeMap;
+
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("src");
+        std::fs::create_dir_all(base_dir.join("sub"))?;
+        std::fs::write(base_dir.join("a.txt"), "hello ".repeat(100))?;
+        std::fs::write(base_dir.join("sub/b.log"), "log ".repeat(100))?;
+        std::fs::write(base_dir.join("sub/c.bin"), [7u8; 300])?;
+        let files = vec![
+            base_dir.join("sub/c.bin"),
+            base_dir.join("a.txt"),
+            base_dir.join("sub/b.log"),
+        ];
+
+        for format in Format::ALL {
+            let config = Config {
+                format,
+                reproducible: Some(true),
+                store_only: Some(vec!["*.bin".to_string()]),
+                rules: Some(BTreeMap::from([(
+                    "*.log".to_string(),
+                    CompressionRule {
+                        method: None,
+                        level: Some(1),
+                    },
+                )])),
+                metadata: Some(BTreeMap::from([(
+                    "a.txt".to_string(),
+                    EntryMetadata {
+                        comment: Some("greeting".to_string()),
+                        ..Default::default()
+                    },
+                )])),
+                gzip_header: Some(GzipHeader {
+                    mtime: Some(1),
+                    os: Some(3),
+                    filename: Some("src.tar".to_string()),
+                }),
+                gzip_level: Some(9),
+                embed_manifest: Some(true),
+                ..Default::default()
+            };
+            let output = dir.path().join(format!("out.{}", format.extension()));
+            compress_files(&config, &base_dir, files.clone(), &output, &NoopReporter)?;
+
+            let out = compress_files_with(
+                &FormatRegistry::default(),
+                format.extension(),
+                &config,
+                &base_dir,
+                &files,
+                Cursor::new(Vec::new()),
+            )?;
+            assert_eq!(out.into_inner(), std::fs::read(&output)?, "{}", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_lzma2_dict_size() {
+        assert_eq!(lzma2_dict_size(4096), 4096);
+        assert_eq!(lzma2_dict_size(100 * 1024), 128 * 1024);
+        assert_eq!(lzma2_dict_size(3 << 20), 3 << 20);
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_spool_large_entry_to_temp_file() -> Result<()> {
+        let content: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        let mut spool = Spool::new(100);
+        for chunk in content.chunks(64) {
+            spool.write_all(chunk)?;
+        }
+        let Spool::File(temp) = &spool else {
+            panic!("超过内存上限的内容应转存到临时文件");
+        };
+        let path = temp.path.clone();
+        assert!(path.exists());
+        spool.rewind()?;
+        let mut read = Vec::new();
+        spool.read_to_end(&mut read)?;
+        assert_eq!(read, content);
+        drop(spool);
+        assert!(!path.exists());
+
+        let mut spool = Spool::new(1000);
+        spool.write_all(&content)?;
+        assert!(matches!(spool, Spool::Memory { .. }));
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! 异步 API，需要启用 `tokio` 特性。
+//!
+//! 压缩与上传都是 CPU 或阻塞 IO 密集的操作，这里的函数在 tokio 的阻塞线程池
+//! (`spawn_blocking`) 中执行对应的同步函数，调用者可以在异步服务中直接 `await`，
+//! 不会阻塞运行时的工作线程。必须在 tokio 运行时中调用。
+
+use crate::compressor;
+use crate::config::Config;
+use crate::progress::ProgressReporter;
+use crate::report::CompressionReport;
+use crate::uploader::{self, UploadReport};
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// [`compressor::compress_directory`] 的异步版本。
+///
+/// # 示例
+///
+/// ```no_run
+/// use ztr_lib::async_api::compress_directory_async;
+/// use ztr_lib::collector::{self, SymlinkPolicy};
+/// use ztr_lib::config::Config;
+/// use ztr_lib::progress::NoopReporter;
+///
+/// async fn handle() -> anyhow::Result<()> {
+///     let base_dir = std::path::PathBuf::from("./reports");
+///     let files = collector::collect_all_files(&base_dir, SymlinkPolicy::Follow)?;
+///     let report = compress_directory_async(Config::default(), base_dir, files, NoopReporter).await?;
+///     println!("压缩文件已创建: {}", report.output_path.display());
+///     Ok(())
+/// }
+/// ```
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录。
+/// - `files_to_compress`: 要压缩的文件路径列表。
+/// - `reporter`: 进度与日志回调，在阻塞线程池中被调用。
+///
+/// # 返回
+/// `Result<CompressionReport>`: 与同步版本相同；压缩任务 panic 时也返回错误。
+pub async fn compress_directory_async<R>(
+    config: Config,
+    base_dir: PathBuf,
+    files_to_compress: Vec<PathBuf>,
+    reporter: R,
+) -> Result<CompressionReport>
+where
+    R: ProgressReporter + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        compressor::compress_directory(&config, &base_dir, files_to_compress, &reporter)
+    })
+    .await
+    .context("压缩任务异常结束")?
+}
+
+/// [`uploader::upload`] 的异步版本，将压缩包上传到配置中的所有上传目标。
+///
+/// # 参数
+/// - `config`: 包含 `[upload]` 的配置。
+/// - `archive`: 要上传的压缩包，分卷压缩包会上传所有分卷。
+/// - `reporter`: 上传进度回调，在阻塞线程池中被调用。
+///
+/// # 返回
+/// `Result<Vec<UploadReport>>`: 与同步版本相同；上传任务 panic 时也返回错误。
+pub async fn upload_async<R>(
+    config: Config,
+    archive: PathBuf,
+    reporter: R,
+) -> Result<Vec<UploadReport>>
+where
+    R: ProgressReporter + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || uploader::upload(&config, &archive, &reporter))
+        .await
+        .context("上传任务异常结束")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compress_directory_async() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "hello")?;
+        let config = Config {
+            format: Format::TarGz,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let files = vec![dir.path().join("a.txt")];
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        let report = runtime.block_on(compress_directory_async(
+            config.clone(),
+            dir.path().to_path_buf(),
+            files,
+            NoopReporter,
+        ))?;
+        assert_eq!(report.output_path, dir.path().join("out.tar.gz"));
+        assert!(report.output_path.is_file());
+
+        // 没有上传目标时返回同步版本的错误
+        let result = runtime.block_on(upload_async(config, report.output_path, NoopReporter));
+        assert!(result.is_err());
+        Ok(())
+    }
+}
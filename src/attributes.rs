@@ -0,0 +1,289 @@
+//! Windows 文件属性（只读/隐藏/系统）与 ACL 的保存和恢复。
+//!
+//! 压缩包格式本身无法可靠地保存这些属性，因此它们被写入压缩包旁的附属文件：
+//! - `<压缩包>.attrs`: 每行一个文件，格式为 `<属性标记>\t<相对路径>`，属性标记由 `R`/`H`/`S` 组成。
+//! - `<压缩包>.acl`: 由 `icacls /save` 生成的 ACL 数据（仅 Windows）。
+//!
+//! 在非 Windows 平台上采集为空操作；恢复时仅能还原只读属性。
+use crate::collector::Mount;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// 单个文件的 Windows 属性。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+    /// 只读
+    pub readonly: bool,
+    /// 隐藏
+    pub hidden: bool,
+    /// 系统
+    pub system: bool,
+}
+
+impl FileAttributes {
+    /// 从 Windows 的属性位掩码构造。
+    pub fn from_bits(bits: u32) -> Self {
+        Self {
+            readonly: bits & 0x1 != 0,
+            hidden: bits & 0x2 != 0,
+            system: bits & 0x4 != 0,
+        }
+    }
+
+    /// 是否没有任何需要保存的属性。
+    pub fn is_plain(&self) -> bool {
+        !(self.readonly || self.hidden || self.system)
+    }
+
+    /// 转换为附属文件中使用的属性标记，例如 `RH`。
+    pub fn to_flags(&self) -> String {
+        let mut flags = String::new();
+        if self.readonly {
+            flags.push('R');
+        }
+        if self.hidden {
+            flags.push('H');
+        }
+        if self.system {
+            flags.push('S');
+        }
+        flags
+    }
+
+    /// 解析属性标记。
+    pub fn from_flags(flags: &str) -> Result<Self> {
+        let mut attrs = Self::default();
+        for c in flags.chars() {
+            match c {
+                'R' => attrs.readonly = true,
+                'H' => attrs.hidden = true,
+                'S' => attrs.system = true,
+                _ => anyhow::bail!("无效的属性标记: {}", flags),
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+/// 返回属性附属文件的路径 (`<压缩包>.attrs`)。
+pub fn sidecar_path(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(".attrs");
+    PathBuf::from(name)
+}
+
+/// 返回 ACL 附属文件的路径 (`<压缩包>.acl`)。
+pub fn acl_sidecar_path(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(".acl");
+    PathBuf::from(name)
+}
+
+/// 采集文件的属性，仅返回带有属性的文件。
+///
+/// # 参数
+/// - `files`: 要采集的文件路径列表。
+/// - `base_dir`: 基础目录，用于计算相对路径。
+/// - `mounts`: 映射到压缩包中指定前缀的源路径。
+///
+/// # 返回
+/// `Result<Vec<(String, FileAttributes)>>`: 相对路径与属性的列表。非 Windows 平台始终为空。
+pub fn capture(
+    files: &[PathBuf],
+    base_dir: &Path,
+    mounts: &[Mount],
+) -> Result<Vec<(String, FileAttributes)>> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        let mut entries = Vec::new();
+        for file_path in files {
+            let metadata = std::fs::metadata(file_path)
+                .with_context(|| format!("读取文件属性失败: {}", file_path.display()))?;
+            let attrs = FileAttributes::from_bits(metadata.file_attributes());
+            if attrs.is_plain() {
+                continue;
+            }
+            let relative_path = crate::collector::entry_path(base_dir, mounts, file_path)
+                .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?;
+            entries.push((relative_path.to_string_lossy().replace("\\", "/"), attrs));
+        }
+        Ok(entries)
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = (files, base_dir, mounts);
+        Ok(Vec::new())
+    }
+}
+
+/// 将属性列表序列化为附属文件内容。
+pub fn to_sidecar_string(entries: &[(String, FileAttributes)]) -> String {
+    let mut content = String::new();
+    for (path, attrs) in entries {
+        content.push_str(&attrs.to_flags());
+        content.push('\t');
+        content.push_str(path);
+        content.push('\n');
+    }
+    content
+}
+
+/// 解析附属文件内容。
+///
+/// # 参数
+/// - `content`: 附属文件的文本内容。
+///
+/// # 返回
+/// `Result<Vec<(String, FileAttributes)>>`: 相对路径与属性的列表。
+pub fn parse_sidecar(content: &str) -> Result<Vec<(String, FileAttributes)>> {
+    let mut entries = Vec::new();
+    for line in content.lines().filter(|l| !l.is_empty()) {
+        let (flags, path) = line
+            .split_once('\t')
+            .with_context(|| format!("无效的属性记录: {}", line))?;
+        entries.push((path.to_string(), FileAttributes::from_flags(flags)?));
+    }
+    Ok(entries)
+}
+
+/// 为压缩包写入属性附属文件（Windows 上还会通过 `icacls` 保存 ACL）。
+///
+/// 如果没有任何文件带有需要保存的属性，则不会写入 `.attrs` 文件。ACL 只保存基础目录中的文件。
+pub fn write_sidecars(
+    files: &[PathBuf],
+    base_dir: &Path,
+    mounts: &[Mount],
+    archive_path: &Path,
+) -> Result<()> {
+    let entries = capture(files, base_dir, mounts)?;
+    if !entries.is_empty() {
+        let path = sidecar_path(archive_path);
+        std::fs::write(&path, to_sidecar_string(&entries))
+            .with_context(|| format!("写入属性文件失败: {}", path.display()))?;
+    }
+
+    #[cfg(windows)]
+    {
+        let acl_path = acl_sidecar_path(archive_path);
+        let status = std::process::Command::new("icacls")
+            .arg(base_dir.join("*"))
+            .arg("/save")
+            .arg(&acl_path)
+            .args(["/T", "/C", "/Q"])
+            .status()
+            .context("执行 icacls 失败")?;
+        if !status.success() {
+            anyhow::bail!("保存 ACL 失败: {}", acl_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// 将属性恢复到解压目录中的文件。
+///
+/// 非 Windows 平台仅恢复只读属性。
+///
+/// # 参数
+/// - `entries`: 由 [`parse_sidecar`] 得到的属性列表。
+/// - `target_dir`: 解压目标目录。
+pub fn restore(entries: &[(String, FileAttributes)], target_dir: &Path) -> Result<()> {
+    for (relative_path, attrs) in entries {
+        let path = target_dir.join(relative_path);
+        if !path.exists() {
+            continue;
+        }
+
+        #[cfg(windows)]
+        if attrs.hidden || attrs.system {
+            let mut command = std::process::Command::new("attrib");
+            if attrs.hidden {
+                command.arg("+H");
+            }
+            if attrs.system {
+                command.arg("+S");
+            }
+            command
+                .arg(&path)
+                .status()
+                .with_context(|| format!("恢复文件属性失败: {}", path.display()))?;
+        }
+
+        if attrs.readonly {
+            let mut permissions = std::fs::metadata(&path)
+                .with_context(|| format!("读取文件属性失败: {}", path.display()))?
+                .permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(&path, permissions)
+                .with_context(|| format!("恢复只读属性失败: {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// 通过 `icacls /restore` 将 ACL 附属文件恢复到解压目录（仅 Windows，其他平台为空操作）。
+pub fn restore_acl(acl_path: &Path, target_dir: &Path) -> Result<()> {
+    #[cfg(windows)]
+    {
+        let status = std::process::Command::new("icacls")
+            .arg(target_dir)
+            .arg("/restore")
+            .arg(acl_path)
+            .args(["/C", "/Q"])
+            .status()
+            .context("执行 icacls 失败")?;
+        if !status.success() {
+            anyhow::bail!("恢复 ACL 失败: {}", acl_path.display());
+        }
+    }
+
+    #[cfg(not(windows))]
+    let _ = (acl_path, target_dir);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_flags_roundtrip() -> Result<()> {
+        let attrs = FileAttributes::from_bits(0x1 | 0x4);
+        assert_eq!(attrs.to_flags(), "RS");
+        assert_eq!(FileAttributes::from_flags("RS")?, attrs);
+        assert!(FileAttributes::from_flags("X").is_err());
+        assert!(FileAttributes::default().is_plain());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sidecar_roundtrip() -> Result<()> {
+        let entries = vec![
+            ("a.txt".to_string(), FileAttributes::from_bits(0x2)),
+            ("dir/b.dat".to_string(), FileAttributes::from_bits(0x1)),
+        ];
+        let content = to_sidecar_string(&entries);
+        assert_eq!(content, "H\ta.txt\nR\tdir/b.dat\n");
+        assert_eq!(parse_sidecar(&content)?, entries);
+        assert!(parse_sidecar("no-tab").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_readonly() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "content")?;
+
+        let entries = vec![("a.txt".to_string(), FileAttributes::from_bits(0x1))];
+        restore(&entries, dir.path())?;
+
+        let metadata = std::fs::metadata(dir.path().join("a.txt"))?;
+        assert!(metadata.permissions().readonly());
+        Ok(())
+    }
+}
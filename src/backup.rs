@@ -0,0 +1,326 @@
+use crate::compressor::Compressor;
+use crate::config::Config;
+use crate::extractor::{self, ExtractOptions, ExtractReport};
+use crate::progress::ProgressReporter;
+use crate::template;
+use crate::validate::ConflictPolicy;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 快照目录文件名，位于备份目录中。
+pub const CATALOG_FILE_NAME: &str = "catalog.toml";
+
+/// 未配置 `backup_dir` 时，备份目录相对于基础目录的默认位置。
+pub const DEFAULT_BACKUP_DIR: &str = ".ztr-backups";
+
+/// 一个备份快照。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// 快照 ID，为创建时的本地时间 `YYYYMMDD-HHMMSS`（同一秒内的多个快照追加 `-1`、`-2`……）
+    pub id: String,
+    /// 快照压缩包的文件名，位于备份目录中
+    pub archive: String,
+    /// 创建时间 (Unix 时间戳，秒)
+    pub created: u64,
+    /// 被备份的目录
+    pub source: String,
+    /// 快照中的文件数
+    pub files: usize,
+    /// 快照中文件的总大小 (字节)
+    pub bytes: u64,
+    /// 快照压缩包的大小 (字节)
+    pub archive_bytes: u64,
+}
+
+/// 快照目录：按创建顺序索引备份目录中的所有快照，保存为 `catalog.toml`。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Catalog {
+    /// 所有快照，按创建顺序排列
+    #[serde(default, rename = "snapshot")]
+    pub snapshots: Vec<Snapshot>,
+}
+
+impl Catalog {
+    /// 返回备份目录中快照目录文件的路径。
+    pub fn path(backup_dir: &Path) -> PathBuf {
+        backup_dir.join(CATALOG_FILE_NAME)
+    }
+
+    /// 读取备份目录中的快照目录，文件不存在时返回空目录。
+    pub fn load(backup_dir: &Path) -> Result<Self> {
+        let path = Self::path(backup_dir);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("读取快照目录失败: {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("解析快照目录失败: {}", path.display()))
+    }
+
+    /// 将快照目录写入备份目录。先写入临时文件再重命名，中断时不会留下损坏的目录文件。
+    pub fn save(&self, backup_dir: &Path) -> Result<()> {
+        let path = Self::path(backup_dir);
+        let content = toml::to_string(self).context("序列化快照目录失败")?;
+        let temp = backup_dir.join(format!("{}.tmp", CATALOG_FILE_NAME));
+        std::fs::write(&temp, content)
+            .with_context(|| format!("写入快照目录失败: {}", temp.display()))?;
+        std::fs::rename(&temp, &path)
+            .with_context(|| format!("写入快照目录失败: {}", path.display()))
+    }
+
+    /// 查找快照：`latest` 表示最新的快照，否则按 ID 精确匹配，再按唯一的 ID 前缀匹配（如 `20261017`）。
+    pub fn find(&self, id: &str) -> Result<&Snapshot> {
+        if id == "latest" {
+            return self.snapshots.last().context("备份目录中还没有任何快照");
+        }
+        if let Some(snapshot) = self.snapshots.iter().find(|s| s.id == id) {
+            return Ok(snapshot);
+        }
+        let matches: Vec<&Snapshot> = self
+            .snapshots
+            .iter()
+            .filter(|s| s.id.starts_with(id))
+            .collect();
+        match matches.as_slice() {
+            [snapshot] => Ok(snapshot),
+            [] => anyhow::bail!("快照不存在: {}（使用 ztr backup --list 查看所有快照）", id),
+            _ => anyhow::bail!(
+                "快照 ID 前缀 {} 匹配多个快照: {}",
+                id,
+                matches
+                    .iter()
+                    .map(|s| s.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// 返回不与已有快照重复的 ID。
+    fn unique_id(&self, id: String) -> String {
+        let taken = |candidate: &str| self.snapshots.iter().any(|s| s.id == candidate);
+        if !taken(&id) {
+            return id;
+        }
+        (1..)
+            .map(|n| format!("{}-{}", id, n))
+            .find(|candidate| !taken(candidate))
+            .unwrap_or(id)
+    }
+}
+
+/// 根据时间生成快照 ID `YYYYMMDD-HHMMSS`。
+///
+/// # 参数
+/// - `timestamp`: Unix 时间戳 (秒)。
+/// - `utc_offset`: 本地时间相对 UTC 的偏移 (秒)。
+pub fn snapshot_id(timestamp: u64, utc_offset: i64) -> String {
+    let local = timestamp as i64 + utc_offset;
+    let (year, month, day) = template::civil_from_days(local.div_euclid(86_400));
+    let rem = local.rem_euclid(86_400);
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+/// 返回备份目录：配置中的 `backup_dir`，未配置时为基础目录下的 `.ztr-backups`。
+pub fn backup_dir(config: &Config, base_dir: &Path) -> PathBuf {
+    match &config.backup_dir {
+        Some(dir) => config.resolve_path(dir, base_dir),
+        None => base_dir.join(DEFAULT_BACKUP_DIR),
+    }
+}
+
+/// 为基础目录创建一个带时间戳的快照，并记录到备份目录的快照目录中。
+///
+/// 文件的收集与过滤与 `ztr compress` 相同；备份目录位于基础目录中时，其中的文件不会被备份。
+///
+/// # 参数
+/// - `config`: 压缩配置，快照使用其中的格式、忽略规则等选项。
+/// - `base_dir`: 要备份的目录。
+/// - `reporter`: 进度与日志回调。
+///
+/// # 返回
+/// `Result<Snapshot>`: 新建的快照；没有需要备份的文件时返回错误。
+pub fn create_snapshot(
+    config: &Config,
+    base_dir: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<Snapshot> {
+    let backup_dir = backup_dir(config, base_dir);
+    std::fs::create_dir_all(&backup_dir)
+        .with_context(|| format!("创建备份目录失败: {}", backup_dir.display()))?;
+    let mut catalog = Catalog::load(&backup_dir)?;
+
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let id = catalog.unique_id(snapshot_id(created, template::local_utc_offset(created)));
+    let archive = format!("{}.{}", id, config.format.extension());
+
+    let mut config = config.clone();
+    config.incremental = None;
+    config.on_conflict = Some(ConflictPolicy::Error);
+    let source = base_dir
+        .canonicalize()
+        .with_context(|| format!("要备份的目录不存在: {}", base_dir.display()))?;
+    if let Ok(relative) = backup_dir.canonicalize()?.strip_prefix(&source)
+        && !relative.as_os_str().is_empty()
+    {
+        config
+            .extra_ignore
+            .get_or_insert_with(Vec::new)
+            .push(format!(
+                "/{}/",
+                relative.to_string_lossy().replace('\\', "/")
+            ));
+    }
+
+    let report = Compressor::new(base_dir)
+        .config(config)
+        .output(backup_dir.join(&archive))
+        .run_with_progress(reporter)?;
+    if report.files_count == 0 {
+        anyhow::bail!("没有需要备份的文件: {}", base_dir.display());
+    }
+
+    let snapshot = Snapshot {
+        id,
+        archive,
+        created,
+        source: source.display().to_string(),
+        files: report.files_count,
+        bytes: report.input_bytes,
+        archive_bytes: report.output_bytes,
+    };
+    catalog.snapshots.push(snapshot.clone());
+    catalog.save(&backup_dir)?;
+    Ok(snapshot)
+}
+
+/// 将快照解压到目标目录。
+///
+/// # 参数
+/// - `backup_dir`: 备份目录。
+/// - `id`: 快照 ID、唯一的 ID 前缀或 `latest`。
+/// - `target_dir`: 解压目标目录。
+/// - `options`: 解压选项，`paths` 不为空时只恢复其中的路径。
+///
+/// # 返回
+/// `Result<(Snapshot, ExtractReport)>`: 被恢复的快照与解压统计。
+pub fn restore_snapshot(
+    backup_dir: &Path,
+    id: &str,
+    target_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<(Snapshot, ExtractReport)> {
+    let catalog = Catalog::load(backup_dir)?;
+    let snapshot = catalog.find(id)?.clone();
+    let archive = backup_dir.join(&snapshot.archive);
+    if !archive.is_file() {
+        anyhow::bail!("快照 {} 的压缩包不存在: {}", snapshot.id, archive.display());
+    }
+    let report = extractor::extract_archive(&archive, target_dir, options)?;
+    Ok((snapshot, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    fn snapshot(id: &str) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            archive: format!("{}.zip", id),
+            created: 0,
+            source: "/src".to_string(),
+            files: 1,
+            bytes: 1,
+            archive_bytes: 1,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_id() {
+        assert_eq!(snapshot_id(1_700_000_000, 0), "20231114-221320");
+        assert_eq!(snapshot_id(1_700_000_000, 8 * 3_600), "20231115-061320");
+    }
+
+    #[test]
+    fn test_catalog_find() -> Result<()> {
+        let dir = tempdir()?;
+        let catalog = Catalog {
+            snapshots: vec![
+                snapshot("20261016-020000"),
+                snapshot("20261017-020000"),
+                snapshot("20261017-020000-1"),
+            ],
+        };
+        catalog.save(dir.path())?;
+        let catalog = Catalog::load(dir.path())?;
+        assert_eq!(catalog.find("latest")?.id, "20261017-020000-1");
+        assert_eq!(catalog.find("20261017-020000")?.id, "20261017-020000");
+        assert_eq!(catalog.find("20261016")?.id, "20261016-020000");
+        assert!(catalog.find("20261017").is_err());
+        assert!(catalog.find("2025").is_err());
+        assert_eq!(
+            catalog.unique_id("20261017-020000".to_string()),
+            "20261017-020000-2"
+        );
+        assert!(Catalog::default().find("latest").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_and_restore() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("project");
+        std::fs::create_dir_all(base_dir.join("src"))?;
+        std::fs::write(base_dir.join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(base_dir.join("notes.txt"), "v1")?;
+        let config = Config {
+            format: Format::Zip,
+            ..Default::default()
+        };
+
+        let first = create_snapshot(&config, &base_dir, &NoopReporter)?;
+        assert_eq!(first.files, 2);
+        std::fs::write(base_dir.join("notes.txt"), "v2")?;
+        // 备份目录位于项目中，第二个快照不包含第一个快照
+        let second = create_snapshot(&config, &base_dir, &NoopReporter)?;
+        assert_eq!(second.files, 2);
+        assert_ne!(first.id, second.id);
+
+        let backup_dir = base_dir.join(DEFAULT_BACKUP_DIR);
+        assert_eq!(Catalog::load(&backup_dir)?.snapshots.len(), 2);
+
+        let target = dir.path().join("restored");
+        let (restored, report) =
+            restore_snapshot(&backup_dir, &first.id, &target, &ExtractOptions::default())?;
+        assert_eq!(restored, first);
+        assert_eq!(report.entries, 2);
+        assert_eq!(std::fs::read_to_string(target.join("notes.txt"))?, "v1");
+
+        let partial = dir.path().join("partial");
+        let options = ExtractOptions {
+            paths: vec!["src".to_string()],
+            ..Default::default()
+        };
+        restore_snapshot(&backup_dir, "latest", &partial, &options)?;
+        assert!(partial.join("src/main.rs").exists());
+        assert!(!partial.join("notes.txt").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use crate::reader;
+use crate::split;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// 压缩包校验和附属文件使用的摘要算法。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgorithm {
+    /// SHA-256，附属文件为 `<压缩包>.sha256`，可用 `sha256sum -c` 校验
+    Sha256,
+    /// BLAKE3，附属文件为 `<压缩包>.blake3`，可用 `b3sum -c` 校验
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    /// 所有支持的算法。
+    pub const ALL: [ChecksumAlgorithm; 2] = [ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Blake3];
+
+    /// 附属文件的扩展名（不含点）。
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// 计算读取器中全部数据的摘要，返回小写十六进制字符串。
+    pub fn digest_reader<R: Read>(&self, reader: R) -> std::io::Result<String> {
+        match self {
+            ChecksumAlgorithm::Sha256 => sha256_reader(reader),
+            ChecksumAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update_reader(reader)?;
+                Ok(hasher.finalize().to_hex().to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// 计算读取器中全部数据的 SHA-256 摘要。
+///
+/// # 参数
+/// - `reader`: 数据来源。
+///
+/// # 返回
+/// `std::io::Result<String>`: 小写十六进制格式的摘要。
+pub fn sha256_reader<R: Read>(mut reader: R) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// 计算文件的 SHA-256 摘要。
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("打开文件失败: {}", path.display()))?;
+    sha256_reader(file).with_context(|| format!("读取文件失败: {}", path.display()))
+}
+
+/// 计算压缩包中每个文件条目的 SHA-256 摘要。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径。
+///
+/// # 返回
+/// `Result<HashMap<String, String>>`: 条目名称到摘要的映射。
+pub fn archive_digests(archive_path: &Path) -> Result<HashMap<String, String>> {
+    let mut digests = HashMap::new();
+    reader::for_each_entry(archive_path, |name, entry| {
+        let digest = sha256_reader(entry).with_context(|| format!("读取条目失败: {}", name))?;
+        digests.insert(name.to_string(), digest);
+        Ok(())
+    })?;
+    Ok(digests)
+}
+
+/// 返回压缩包校验和附属文件的路径 (`<压缩包>.sha256`、`<压缩包>.blake3`)。
+///
+/// 分卷压缩包的第一个分卷 (`.001`) 对应合并后压缩包的附属文件。
+pub fn sidecar_path(archive_path: &Path, algorithm: ChecksumAlgorithm) -> PathBuf {
+    let archive_path =
+        split::archive_of_first_volume(archive_path).unwrap_or_else(|| archive_path.to_path_buf());
+    let mut name = archive_path.into_os_string();
+    name.push(format!(".{}", algorithm.extension()));
+    PathBuf::from(name)
+}
+
+/// 计算压缩包的摘要；分卷压缩包按拼接后的数据计算。
+pub fn archive_digest(archive_path: &Path, algorithm: ChecksumAlgorithm) -> Result<String> {
+    let volumes =
+        split::find_volumes(archive_path).unwrap_or_else(|| vec![archive_path.to_path_buf()]);
+    let reader = split::VolumeReader::open(&volumes)?;
+    algorithm
+        .digest_reader(reader)
+        .with_context(|| format!("读取压缩包失败: {}", archive_path.display()))
+}
+
+/// 计算压缩包的摘要并写入校验和附属文件，格式与 `sha256sum`/`b3sum` 相同：`<摘要>  <文件名>`。
+///
+/// # 返回
+/// `Result<String>`: 压缩包的摘要。
+pub fn write_sidecar(archive_path: &Path, algorithm: ChecksumAlgorithm) -> Result<String> {
+    let digest = archive_digest(archive_path, algorithm)?;
+    let sidecar = sidecar_path(archive_path, algorithm);
+    let file_name = sidecar
+        .file_stem()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    std::fs::write(&sidecar, format!("{}  {}\n", digest, file_name))
+        .with_context(|| format!("写入校验和文件失败: {}", sidecar.display()))?;
+    Ok(digest)
+}
+
+/// 一个校验和附属文件的校验结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumCheck {
+    /// 摘要算法
+    pub algorithm: ChecksumAlgorithm,
+    /// 附属文件中记录的摘要
+    pub expected: String,
+    /// 实际计算出的摘要
+    pub actual: String,
+}
+
+impl ChecksumCheck {
+    /// 摘要是否一致。
+    pub fn is_ok(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// 根据压缩包旁的所有校验和附属文件校验压缩包。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，可以是分卷压缩包。
+///
+/// # 返回
+/// `Result<Vec<ChecksumCheck>>`: 每个附属文件的校验结果；没有任何附属文件或附属文件格式错误时返回错误。
+pub fn verify_sidecars(archive_path: &Path) -> Result<Vec<ChecksumCheck>> {
+    let mut checks = Vec::new();
+    for algorithm in ChecksumAlgorithm::ALL {
+        let sidecar = sidecar_path(archive_path, algorithm);
+        if !sidecar.is_file() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&sidecar)
+            .with_context(|| format!("读取校验和文件失败: {}", sidecar.display()))?;
+        let expected = content
+            .split_whitespace()
+            .next()
+            .filter(|digest| digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()))
+            .with_context(|| format!("校验和文件格式错误: {}", sidecar.display()))?
+            .to_ascii_lowercase();
+        checks.push(ChecksumCheck {
+            algorithm,
+            expected,
+            actual: archive_digest(archive_path, algorithm)?,
+        });
+    }
+    if checks.is_empty() {
+        anyhow::bail!(
+            "找不到校验和文件: {}",
+            sidecar_path(archive_path, ChecksumAlgorithm::Sha256).display()
+        );
+    }
+    Ok(checks)
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_sha256_reader() -> Result<()> {
+        assert_eq!(
+            sha256_reader("abc".as_bytes())?,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_blake3_digest() -> Result<()> {
+        assert_eq!(
+            ChecksumAlgorithm::Blake3.digest_reader("abc".as_bytes())?,
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_and_verify_sidecars() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("app.zip");
+        assert!(verify_sidecars(&archive).is_err());
+        std::fs::write(&archive, "abc")?;
+
+        let digest = write_sidecar(&archive, ChecksumAlgorithm::Sha256)?;
+        write_sidecar(&archive, ChecksumAlgorithm::Blake3)?;
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("app.zip.sha256"))?,
+            format!("{}  app.zip\n", digest)
+        );
+        let checks = verify_sidecars(&archive)?;
+        assert_eq!(checks.len(), 2);
+        assert!(checks.iter().all(ChecksumCheck::is_ok));
+
+        // 分卷压缩包按拼接后的数据校验
+        split::split_archive(&archive, 2)?;
+        let first = split::volume_path(&archive, 1);
+        assert_eq!(
+            sidecar_path(&first, ChecksumAlgorithm::Blake3),
+            dir.path().join("app.zip.blake3")
+        );
+        assert!(verify_sidecars(&first)?.iter().all(ChecksumCheck::is_ok));
+
+        std::fs::write(split::volume_path(&archive, 2), "x")?;
+        let checks = verify_sidecars(&archive)?;
+        assert!(checks.iter().all(|check| !check.is_ok()));
+        Ok(())
+    }
+}
@@ -0,0 +1,848 @@
+use crate::config::Config;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 符号链接的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    /// 将符号链接本身作为链接条目写入压缩包
+    Preserve,
+    /// 跟随符号链接，压缩其指向的文件或目录 (默认)
+    #[default]
+    Follow,
+    /// 忽略所有符号链接
+    Skip,
+}
+
+/// 文件列表的来源。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// 遍历目录树 (默认)
+    #[default]
+    Walk,
+    /// 只收集 git 跟踪的文件 (`git ls-files`)，自动排除未跟踪的文件
+    Git,
+}
+
+/// `sources` 中的一项。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SourceEntry {
+    /// 相对于基础目录的 glob 模式，如 `"packages/*/dist"`，匹配的文件在压缩包中保持原有的相对路径
+    Pattern(String),
+    /// 放到压缩包中指定前缀下的目录或文件，如 `{ path = "../docs", prefix = "docs" }`
+    Mapped {
+        /// 目录或文件的路径，相对于基础目录，可以位于基础目录之外
+        path: String,
+        /// 在压缩包中的前缀，默认为 `path` 的最后一级名称，`""` 表示压缩包的根目录
+        prefix: Option<String>,
+    },
+}
+
+impl From<String> for SourceEntry {
+    fn from(pattern: String) -> Self {
+        SourceEntry::Pattern(pattern)
+    }
+}
+
+impl From<&str> for SourceEntry {
+    fn from(pattern: &str) -> Self {
+        SourceEntry::Pattern(pattern.to_string())
+    }
+}
+
+/// `path_rewrites` 中的一项：把条目名称中的路径前缀 `from` 替换为 `to`。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRewrite {
+    /// 要替换的路径前缀 (按路径的层级匹配)，如 `"build/output/"`
+    pub from: String,
+    /// 替换后的前缀，`""` 表示压缩包的根目录
+    pub to: String,
+}
+
+/// 映射到压缩包中指定前缀的源路径，由 `sources` 中的 [`SourceEntry::Mapped`] 或 `path_rewrites` 得到。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// 源目录或文件，即基础目录与 `path` 拼接得到的路径
+    pub root: PathBuf,
+    /// 在压缩包中的前缀
+    pub prefix: PathBuf,
+}
+
+/// 计算文件在压缩包中的映射：`sources` 中映射的源路径与 `path_rewrites` 中的改写规则，
+/// 配置了 `wrap_root` 时所有条目 (包括基础目录本身) 都放在与压缩包同名的顶层目录下。更深的路径排在前面。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录。
+///
+/// # 返回
+/// `Result<Vec<Mount>>`: 映射列表；前缀不是相对路径或包含 `..` 时返回错误。
+pub fn mounts(config: &Config, base_dir: &Path) -> Result<Vec<Mount>> {
+    let mapped = mapped_mounts(config, base_dir)?;
+    let mut mounts = mapped.clone();
+    for rewrite in config.path_rewrites.iter().flatten() {
+        let from = relative_prefix(&rewrite.from)
+            .with_context(|| format!("路径改写规则的 from {} 无效", rewrite.from))?;
+        if from.as_os_str().is_empty() {
+            anyhow::bail!("路径改写规则的 from 不能为空");
+        }
+        // `from` 是条目名称的前缀，先找到它所在的源路径，再换算成文件系统中的路径
+        let root = mapped
+            .iter()
+            .filter(|mount| !mount.prefix.as_os_str().is_empty())
+            .find_map(|mount| {
+                let rest = from.strip_prefix(&mount.prefix).ok()?;
+                Some(if rest.as_os_str().is_empty() {
+                    mount.root.clone()
+                } else {
+                    mount.root.join(rest)
+                })
+            })
+            .unwrap_or_else(|| base_dir.join(&from));
+        mounts.push(new_mount(root, &rewrite.to, &rewrite.from)?);
+    }
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.root.components().count()));
+    if config.wrap_root.unwrap_or(false) {
+        let root = PathBuf::from(config.get_output_name(base_dir));
+        for mount in &mut mounts {
+            mount.prefix = root.join(&mount.prefix);
+        }
+        // 基础目录最浅，排在最后
+        mounts.push(Mount {
+            root: base_dir.to_path_buf(),
+            prefix: root,
+        });
+    }
+    Ok(mounts)
+}
+
+/// 只根据配置中的 `sources` 计算映射的源路径，不包含 `path_rewrites` 与 `wrap_root` 的顶层目录。
+///
+/// 忽略规则按这些映射得到的路径匹配，与条目名称如何改写无关。
+pub fn mapped_mounts(config: &Config, base_dir: &Path) -> Result<Vec<Mount>> {
+    let mut mounts = Vec::new();
+    for source in config.sources.iter().flatten() {
+        let SourceEntry::Mapped { path, prefix } = source else {
+            continue;
+        };
+        let root = base_dir.join(path);
+        let prefix = match prefix {
+            Some(prefix) => prefix.clone(),
+            None => match normalize(&root).file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => anyhow::bail!("无法确定源路径 {} 在压缩包中的前缀，请指定 prefix", path),
+            },
+        };
+        mounts.push(new_mount(root, &prefix, path)?);
+    }
+    mounts.sort_by_key(|mount| std::cmp::Reverse(mount.root.components().count()));
+    Ok(mounts)
+}
+
+/// 创建一个映射，`source` 只用于错误信息。前缀为空的单个文件放在压缩包根目录，保留原文件名。
+fn new_mount(root: PathBuf, prefix: &str, source: &str) -> Result<Mount> {
+    let mut prefix = relative_prefix(prefix)
+        .with_context(|| format!("源路径 {} 的前缀 {} 无效", source, prefix))?;
+    if prefix.as_os_str().is_empty()
+        && root.is_file()
+        && let Some(name) = normalize(&root).file_name()
+    {
+        prefix = PathBuf::from(name);
+    }
+    Ok(Mount { root, prefix })
+}
+
+/// 解析压缩包中的路径前缀，去掉其中的 `.`；不是相对路径或包含 `..` 时返回 `None`。
+fn relative_prefix(prefix: &str) -> Option<PathBuf> {
+    let path = Path::new(prefix);
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    Some(
+        path.components()
+            .filter(|c| *c != Component::CurDir)
+            .collect(),
+    )
+}
+
+/// 文件在压缩包中的相对路径：位于映射的源路径中的文件为 `前缀/相对路径`，
+/// 其余文件为相对于基础目录的路径，两者都不是时返回 `None`。
+pub fn entry_path(base_dir: &Path, mounts: &[Mount], file: &Path) -> Option<PathBuf> {
+    for mount in mounts {
+        let Ok(rest) = file.strip_prefix(&mount.root) else {
+            continue;
+        };
+        // 映射的是单个文件时 `rest` 为空，条目名称即为前缀
+        return Some(if rest.as_os_str().is_empty() {
+            mount.prefix.clone()
+        } else {
+            mount.prefix.join(rest)
+        });
+    }
+    file.strip_prefix(base_dir).ok().map(Path::to_path_buf)
+}
+
+/// 文件在文件系统中的唯一标识。
+///
+/// Unix 上为 (设备号, inode)，其他平台退化为规范化后的路径。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum FileId {
+    #[cfg(unix)]
+    Inode(u64, u64),
+    #[cfg(not(unix))]
+    Path(PathBuf),
+}
+
+impl FileId {
+    /// 获取路径（跟随符号链接）指向的文件的标识，无法读取时返回 `None`。
+    fn of(path: &Path) -> Option<FileId> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let metadata = std::fs::metadata(path).ok()?;
+            Some(FileId::Inode(metadata.dev(), metadata.ino()))
+        }
+        #[cfg(not(unix))]
+        {
+            std::fs::canonicalize(path).ok().map(FileId::Path)
+        }
+    }
+}
+
+/// 已访问过的文件与目录。
+///
+/// 用于避免硬链接、指向树内的符号链接以及绑定挂载导致同一内容被多次收集和压缩。
+#[derive(Debug, Default)]
+struct Visited {
+    ids: HashSet<FileId>,
+}
+
+impl Visited {
+    /// 记录路径，返回此前是否未访问过。无法获取标识的路径总是视为未访问。
+    fn insert(&mut self, path: &Path) -> bool {
+        match FileId::of(path) {
+            Some(id) => self.ids.insert(id),
+            None => true,
+        }
+    }
+}
+
+/// 递归地收集指定目录中所有文件的路径。
+///
+/// 内容相同的文件（硬链接、符号链接指向的同一文件）和重复出现的目录（绑定挂载）只会收集一次，
+/// 以先遍历到的路径为准。
+///
+/// # 参数
+/// - `dir`: 要遍历的目录。
+/// - `symlinks`: 符号链接的处理方式。`Preserve` 时返回的列表中包含链接本身的路径。
+///
+/// # 返回
+/// `Result<Vec<PathBuf>>`: 目录中所有文件的路径。
+pub fn collect_all_files(dir: &Path, symlinks: SymlinkPolicy) -> Result<Vec<PathBuf>> {
+    let mut visited = Visited::default();
+    Ok(walk(dir, symlinks, &mut visited))
+}
+
+/// 遍历目录，跳过 `visited` 中已出现过的文件和目录。
+fn walk(dir: &Path, symlinks: SymlinkPolicy, visited: &mut Visited) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut it = WalkDir::new(dir)
+        .follow_links(symlinks == SymlinkPolicy::Follow)
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = it.next() {
+        let Ok(entry) = entry else { continue };
+        let path = entry.path();
+        if entry.depth() > 0 && entry.path_is_symlink() {
+            match symlinks {
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Preserve => {
+                    files.push(path.to_path_buf());
+                    continue;
+                }
+                SymlinkPolicy::Follow => {}
+            }
+        }
+        if entry.file_type().is_dir() {
+            if !visited.insert(path) {
+                it.skip_current_dir();
+            }
+        } else if entry.file_type().is_file() && visited.insert(path) {
+            files.push(path.to_path_buf());
+        }
+    }
+    files
+}
+
+/// 收集基础目录下由 `sources` 指定的文件。
+///
+/// `sources` 中的模式都是相对于 `base_dir` 的 glob 模式（例如 `packages/*/dist`），
+/// 在运行时展开：匹配到的目录会被递归遍历，匹配到的文件会被直接加入。
+/// 映射的源路径（`{ path, prefix }`）不展开 glob，可以位于基础目录之外，其中的文件以
+/// `base_dir.join(path)` 为前缀，在压缩包中的名称见 [`entry_path`]。
+/// 如果 `sources` 为 `None`，则收集整个基础目录。
+///
+/// # 参数
+/// - `base_dir`: 基础目录。
+/// - `sources`: 源路径列表 (可选)。
+/// - `symlinks`: 符号链接的处理方式。
+///
+/// # 返回
+/// `Result<Vec<PathBuf>>`: 去重后的文件路径列表。映射的源路径不存在时返回错误。
+pub fn collect_files(
+    base_dir: &Path,
+    sources: Option<&[SourceEntry]>,
+    symlinks: SymlinkPolicy,
+) -> Result<Vec<PathBuf>> {
+    let Some(sources) = sources else {
+        return collect_all_files(base_dir, symlinks);
+    };
+
+    let mut visited = Visited::default();
+    let mut files = Vec::new();
+    for source in sources {
+        let pattern = match source {
+            SourceEntry::Pattern(pattern) => pattern,
+            SourceEntry::Mapped { path, .. } => {
+                let root = base_dir.join(path);
+                if !root.exists() && !root.is_symlink() {
+                    anyhow::bail!("源路径不存在: {}", root.display());
+                }
+                collect_root(root, symlinks, &mut visited, &mut files);
+                continue;
+            }
+        };
+        let full_pattern = base_dir.join(pattern);
+        let matches = glob::glob(&full_pattern.to_string_lossy())
+            .with_context(|| format!("无效的源路径模式: {}", pattern))?;
+
+        for root in matches.filter_map(|m| m.ok()) {
+            collect_root(root, symlinks, &mut visited, &mut files);
+        }
+    }
+    Ok(files)
+}
+
+/// 收集一个源路径：目录被递归遍历，文件被直接加入。
+fn collect_root(
+    root: PathBuf,
+    symlinks: SymlinkPolicy,
+    visited: &mut Visited,
+    files: &mut Vec<PathBuf>,
+) {
+    if root.is_symlink() {
+        match symlinks {
+            SymlinkPolicy::Skip => return,
+            SymlinkPolicy::Preserve => {
+                files.push(root);
+                return;
+            }
+            SymlinkPolicy::Follow => {}
+        }
+    }
+    if root.is_dir() {
+        files.extend(walk(&root, symlinks, visited));
+    } else if root.is_file() && visited.insert(&root) {
+        files.push(root);
+    }
+}
+
+/// 按文件列表收集文件，代替遍历基础目录 (类似 `tar -T`)。
+///
+/// 列表中的路径以 NUL 分隔 (内容包含 NUL 时，如 `git ls-files -z`、`find -print0` 的输出)
+/// 或每行一个，空行被忽略。相对路径相对于 `base_dir`，列出的目录会被递归遍历。
+///
+/// # 参数
+/// - `base_dir`: 基础目录，列出的路径必须位于其中。
+/// - `list`: 文件列表的内容。
+/// - `symlinks`: 符号链接的处理方式。
+///
+/// # 返回
+/// `Result<Vec<PathBuf>>`: 去重后的文件路径列表，路径均以 `base_dir` 为前缀。
+/// 列出的路径不存在或位于基础目录之外时返回错误。
+pub fn collect_listed(
+    base_dir: &Path,
+    list: &str,
+    symlinks: SymlinkPolicy,
+) -> Result<Vec<PathBuf>> {
+    let names: Vec<&str> = if list.contains('\0') {
+        list.split('\0').collect()
+    } else {
+        list.lines().collect()
+    };
+
+    let mut visited = Visited::default();
+    let mut files = Vec::new();
+    for name in names.into_iter().filter(|name| !name.is_empty()) {
+        let path = Path::new(name);
+        let relative = if path.is_absolute() {
+            path.strip_prefix(base_dir)
+                .with_context(|| format!("文件列表中的路径不在要压缩的目录中: {}", name))?
+        } else {
+            path
+        };
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            anyhow::bail!("文件列表中的路径不在要压缩的目录中: {}", name);
+        }
+        let relative: PathBuf = relative
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+        let root = base_dir.join(relative);
+        if !root.exists() && !root.is_symlink() {
+            anyhow::bail!("文件列表中的路径不存在: {}", name);
+        }
+        collect_root(root, symlinks, &mut visited, &mut files);
+    }
+    Ok(files)
+}
+
+/// 将命令行中的路径或 glob 模式转换为 `sources` 中相对于基础目录的模式。
+///
+/// 相对路径相对于 `cwd`；实际存在的路径按字面匹配 (其中的 `[`、`*` 等不作为通配符)。
+///
+/// # 参数
+/// - `paths`: 命令行中的文件、目录或 glob 模式，如 `src/`、`assets/**/*.svg`。
+/// - `cwd`: 当前目录。
+/// - `base_dir`: 要压缩的目录。
+///
+/// # 返回
+/// `Result<Vec<String>>`: 对应的 `sources` 模式。路径位于基础目录之外或没有匹配任何文件时返回错误。
+pub fn sources_from_paths(paths: &[String], cwd: &Path, base_dir: &Path) -> Result<Vec<String>> {
+    let base_dir = normalize(&cwd.join(base_dir));
+    let mut sources = Vec::new();
+    for path in paths {
+        let absolute = normalize(&cwd.join(path));
+        let Ok(relative) = absolute.strip_prefix(&base_dir) else {
+            anyhow::bail!(
+                "路径 {} 不在要压缩的目录 {} 中，请使用 --path 指定包含它的目录",
+                path,
+                base_dir.display()
+            );
+        };
+        let relative = relative.to_string_lossy().replace("\\", "/");
+        let pattern = if absolute.exists() || absolute.is_symlink() {
+            glob::Pattern::escape(&relative)
+        } else {
+            relative
+        };
+        let mut matches = glob::glob(&base_dir.join(&pattern).to_string_lossy())
+            .with_context(|| format!("无效的路径模式: {}", path))?;
+        if matches.next().is_none() {
+            anyhow::bail!("没有匹配的文件: {}", path);
+        }
+        sources.push(if pattern.is_empty() {
+            ".".to_string()
+        } else {
+            pattern
+        });
+    }
+    Ok(sources)
+}
+
+/// 按字面规范化路径：去掉 `.`，`..` 与前一级抵消 (不访问文件系统)。
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// 读取 `files_from` 指定的文件列表，`"-"` 表示标准输入，相对路径相对于 `base_dir`。
+fn read_file_list(files_from: &str, base_dir: &Path) -> Result<String> {
+    let mut content = Vec::new();
+    if files_from == "-" {
+        std::io::stdin()
+            .read_to_end(&mut content)
+            .context("从标准输入读取文件列表失败")?;
+    } else {
+        let path = base_dir.join(files_from);
+        content = std::fs::read(&path)
+            .with_context(|| format!("读取文件列表失败: {}", path.display()))?;
+    }
+    String::from_utf8(content).context("文件列表包含无效的 UTF-8")
+}
+
+/// 按配置收集基础目录中的文件。
+///
+/// `source = "git"` 时只保留 git 跟踪的文件；如果同时配置了 `sources`，结果为两者的交集
+/// (映射的源路径中的文件不受限制)。配置了 `files_from` 时只收集列表中的文件，`source` 与 `sources` 不再生效。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录。
+///
+/// # 返回
+/// `Result<Vec<PathBuf>>`: 文件路径列表，路径均以 `base_dir` 为前缀。
+pub fn collect_for_config(config: &Config, base_dir: &Path) -> Result<Vec<PathBuf>> {
+    let symlinks = config.symlinks.unwrap_or_default();
+    if let Some(files_from) = &config.files_from {
+        let list = read_file_list(files_from, base_dir)?;
+        return collect_listed(base_dir, &list, symlinks);
+    }
+    match config.source.unwrap_or_default() {
+        Source::Walk => collect_files(base_dir, config.sources.as_deref(), symlinks),
+        Source::Git => {
+            let tracked = collect_git_tracked(base_dir)?;
+            if config.sources.is_none() {
+                return Ok(tracked);
+            }
+            let tracked: HashSet<PathBuf> = tracked.into_iter().collect();
+            let mounts = mounts(config, base_dir)?;
+            let files = collect_files(base_dir, config.sources.as_deref(), symlinks)?;
+            Ok(files
+                .into_iter()
+                .filter(|f| tracked.contains(f) || mounts.iter().any(|m| f.starts_with(&m.root)))
+                .collect())
+        }
+    }
+}
+
+/// 通过 `git ls-files` 收集基础目录下被 git 跟踪的文件。
+///
+/// 已从工作区删除的文件和子模块会被跳过。
+///
+/// # 参数
+/// - `base_dir`: 基础目录，必须位于 git 仓库中。
+///
+/// # 返回
+/// `Result<Vec<PathBuf>>`: 文件路径列表，路径均以 `base_dir` 为前缀。
+pub fn collect_git_tracked(base_dir: &Path) -> Result<Vec<PathBuf>> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(base_dir)
+        .args(["ls-files", "-z", "--cached"])
+        .output()
+        .context("执行 git ls-files 失败，请确认已安装 git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git ls-files 执行失败: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let stdout = String::from_utf8(output.stdout).context("git ls-files 输出了无效的 UTF-8")?;
+    Ok(stdout
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(|name| base_dir.join(name))
+        .filter(|path| path.is_file() || path.is_symlink())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_collect_all_files() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("a/b"))?;
+        fs::write(base_dir.join("root.txt"), "content")?;
+        fs::write(base_dir.join("a/b/nested.txt"), "content")?;
+
+        let mut files = collect_all_files(base_dir, SymlinkPolicy::Follow)?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![base_dir.join("a/b/nested.txt"), base_dir.join("root.txt")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_files_with_glob_sources() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("packages/a/dist"))?;
+        fs::create_dir_all(base_dir.join("packages/b/dist"))?;
+        fs::create_dir_all(base_dir.join("packages/b/src"))?;
+        fs::write(base_dir.join("packages/a/dist/a.js"), "content")?;
+        fs::write(base_dir.join("packages/b/dist/b.js"), "content")?;
+        fs::write(base_dir.join("packages/b/src/b.ts"), "content")?;
+        fs::write(base_dir.join("README.md"), "content")?;
+
+        let sources = vec![
+            "packages/*/dist".into(),
+            "README.md".into(),
+            "packages/a/dist".into(), // 重复的源不会导致重复的文件
+        ];
+        let mut files = collect_files(base_dir, Some(&sources), SymlinkPolicy::Follow)?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                base_dir.join("README.md"),
+                base_dir.join("packages/a/dist/a.js"),
+                base_dir.join("packages/b/dist/b.js"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_skips_links_to_same_content() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("a/loop"))?;
+        fs::write(base_dir.join("a/file.txt"), "content")?;
+        fs::hard_link(base_dir.join("a/file.txt"), base_dir.join("b.txt"))?;
+        std::os::unix::fs::symlink(base_dir.join("a/file.txt"), base_dir.join("c.txt"))?;
+        // 指向上级目录的符号链接不会导致无限递归
+        std::os::unix::fs::symlink(base_dir, base_dir.join("a/loop/up"))?;
+
+        let files = collect_all_files(base_dir, SymlinkPolicy::Follow)?;
+        assert_eq!(files, vec![base_dir.join("a/file.txt")]);
+
+        let sources = vec!["b.txt".into(), "a".into()];
+        let files = collect_files(base_dir, Some(&sources), SymlinkPolicy::Follow)?;
+        assert_eq!(files, vec![base_dir.join("b.txt")]);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policies() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("base");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&base_dir)?;
+        fs::create_dir_all(&outside)?;
+        fs::write(outside.join("data.txt"), "content")?;
+        fs::write(base_dir.join("real.txt"), "content")?;
+        std::os::unix::fs::symlink(&outside, base_dir.join("linked_dir"))?;
+        std::os::unix::fs::symlink(outside.join("data.txt"), base_dir.join("linked.txt"))?;
+
+        let follow = collect_all_files(&base_dir, SymlinkPolicy::Follow)?;
+        assert_eq!(
+            follow,
+            vec![base_dir.join("linked.txt"), base_dir.join("real.txt"),]
+        );
+
+        let preserve = collect_all_files(&base_dir, SymlinkPolicy::Preserve)?;
+        assert_eq!(
+            preserve,
+            vec![
+                base_dir.join("linked.txt"),
+                base_dir.join("linked_dir"),
+                base_dir.join("real.txt"),
+            ]
+        );
+
+        let skip = collect_all_files(&base_dir, SymlinkPolicy::Skip)?;
+        assert_eq!(skip, vec![base_dir.join("real.txt")]);
+
+        let sources = vec!["linked_dir".into()];
+        assert_eq!(
+            collect_files(&base_dir, Some(&sources), SymlinkPolicy::Follow)?,
+            vec![base_dir.join("linked_dir/data.txt")]
+        );
+        assert!(collect_files(&base_dir, Some(&sources), SymlinkPolicy::Skip)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_files_without_sources() -> Result<()> {
+        let dir = tempdir()?;
+        fs::write(dir.path().join("a.txt"), "content")?;
+        let files = collect_files(dir.path(), None, SymlinkPolicy::default())?;
+        assert_eq!(files, vec![dir.path().join("a.txt")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_listed() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("src/sub"))?;
+        fs::write(base_dir.join("a.txt"), "a")?;
+        fs::write(base_dir.join("b c.txt"), "b")?;
+        fs::write(base_dir.join("src/sub/lib.rs"), "lib")?;
+        fs::write(base_dir.join("unlisted.txt"), "x")?;
+
+        let list = format!(
+            "a.txt\n./b c.txt\n\nsrc\n{}\n",
+            base_dir.join("a.txt").display()
+        );
+        let files = collect_listed(base_dir, &list, SymlinkPolicy::Follow)?;
+        assert_eq!(
+            files,
+            vec![
+                base_dir.join("a.txt"),
+                base_dir.join("b c.txt"),
+                base_dir.join("src/sub/lib.rs")
+            ]
+        );
+
+        // 内容包含 NUL 时按 NUL 分隔 (git ls-files -z、find -print0)
+        let files = collect_listed(base_dir, "src/sub/lib.rs\0a.txt\0", SymlinkPolicy::Follow)?;
+        assert_eq!(
+            files,
+            vec![base_dir.join("src/sub/lib.rs"), base_dir.join("a.txt")]
+        );
+
+        assert!(collect_listed(base_dir, "missing.txt\n", SymlinkPolicy::Follow).is_err());
+        assert!(collect_listed(base_dir, "../a.txt\n", SymlinkPolicy::Follow).is_err());
+        assert!(collect_listed(base_dir, "/etc/passwd\n", SymlinkPolicy::Follow).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sources_from_paths() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("src"))?;
+        fs::create_dir_all(base_dir.join("assets/icons"))?;
+        fs::write(base_dir.join("Cargo.toml"), "")?;
+        fs::write(base_dir.join("src/main.rs"), "")?;
+        fs::write(base_dir.join("assets/icons/a.svg"), "")?;
+        fs::write(base_dir.join("assets/b.png"), "")?;
+        fs::write(base_dir.join("[x].txt"), "")?;
+
+        let paths = ["src/", "Cargo.toml", "assets/**/*.svg", "[x].txt"].map(String::from);
+        let sources = sources_from_paths(&paths, base_dir, base_dir)?;
+        assert_eq!(
+            sources,
+            ["src", "Cargo.toml", "assets/**/*.svg", "[[]x[]].txt"]
+        );
+        let sources: Vec<SourceEntry> = sources.into_iter().map(SourceEntry::from).collect();
+        let files = collect_files(base_dir, Some(&sources), SymlinkPolicy::Follow)?;
+        assert_eq!(
+            files,
+            vec![
+                base_dir.join("src/main.rs"),
+                base_dir.join("Cargo.toml"),
+                base_dir.join("assets/icons/a.svg"),
+                base_dir.join("[x].txt"),
+            ]
+        );
+
+        // 相对路径相对于当前目录
+        let sources =
+            sources_from_paths(&["../Cargo.toml".into()], &base_dir.join("src"), base_dir)?;
+        assert_eq!(sources, ["Cargo.toml"]);
+
+        assert!(sources_from_paths(&["missing/*.rs".into()], base_dir, base_dir).is_err());
+        assert!(sources_from_paths(&["..".into()], base_dir, base_dir).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_rewrites() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("app");
+        fs::create_dir_all(base_dir.join("build/output/js"))?;
+        fs::create_dir_all(dir.path().join("docs/api"))?;
+        fs::write(base_dir.join("build/output/js/app.js"), "")?;
+        fs::write(base_dir.join("build/output/app.bin"), "")?;
+        fs::write(base_dir.join("build/log.txt"), "")?;
+        fs::write(dir.path().join("docs/api/index.html"), "")?;
+
+        let config: Config = toml::from_str(
+            r#"
+            format = "zip"
+            sources = [".", { path = "../docs", prefix = "docs" }]
+            path_rewrites = [
+                { from = "build/", to = "misc" },
+                { from = "build/output/", to = "" },
+                { from = "./build/output/app.bin", to = "bin/app" },
+                { from = "docs/api", to = "reference" },
+            ]
+            "#,
+        )?;
+        let mounts = mounts(&config, &base_dir)?;
+        let entry = |path: &Path| entry_path(&base_dir, &mounts, path);
+        assert_eq!(
+            entry(&base_dir.join("build/output/js/app.js")),
+            Some(PathBuf::from("js/app.js"))
+        );
+        assert_eq!(
+            entry(&base_dir.join("build/output/app.bin")),
+            Some(PathBuf::from("bin/app"))
+        );
+        assert_eq!(
+            entry(&base_dir.join("build/log.txt")),
+            Some(PathBuf::from("misc/log.txt"))
+        );
+        assert_eq!(
+            entry(&base_dir.join("../docs/api/index.html")),
+            Some(PathBuf::from("reference/index.html"))
+        );
+
+        for rewrite in [
+            r#"{ from = "", to = "x" }"#,
+            r#"{ from = "a", to = "../x" }"#,
+        ] {
+            let config: Config =
+                toml::from_str(&format!("format = \"zip\"\npath_rewrites = [{}]", rewrite))?;
+            assert!(super::mounts(&config, &base_dir).is_err(), "{}", rewrite);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_collect_git_tracked() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(base_dir)
+                .args(args)
+                .output()
+        };
+        if git(&["init", "-q"]).is_err() {
+            // 没有安装 git
+            return Ok(());
+        }
+        fs::create_dir_all(base_dir.join("src"))?;
+        fs::write(base_dir.join("src/main.rs"), "content")?;
+        fs::write(base_dir.join("README.md"), "content")?;
+        fs::write(base_dir.join("deleted.txt"), "content")?;
+        git(&["add", "src/main.rs", "README.md", "deleted.txt"])?;
+        fs::remove_file(base_dir.join("deleted.txt"))?;
+        fs::write(base_dir.join("untracked.log"), "content")?;
+
+        let mut files = collect_git_tracked(base_dir)?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec![base_dir.join("README.md"), base_dir.join("src/main.rs")]
+        );
+
+        let config = Config {
+            source: Some(Source::Git),
+            sources: Some(vec!["src".into()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            collect_for_config(&config, base_dir)?,
+            vec![base_dir.join("src/main.rs")]
+        );
+        Ok(())
+    }
+}
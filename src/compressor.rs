@@ -1,224 +1,2802 @@
-use crate::config::Config;
+use crate::archive_manifest::{self, ArchiveManifest};
+use crate::archive_writer::{ArchiveOutput, ArchiveWriter, EntryHeader, FormatRegistry};
+use crate::attributes;
+use crate::checksum::{self, ChecksumAlgorithm};
+use crate::collector::{self, Mount, SourceEntry, SymlinkPolicy};
+use crate::config::{ByteSize, Config, GzipHeader, SevenZConfig};
+use crate::fault::{self, Stage};
+use crate::filter::FilterPipeline;
+use crate::format::Format;
+use crate::ignore_rules;
+use crate::incremental;
+use crate::listing;
+use crate::metadata::MetadataRules;
+use crate::progress::{Cancelled, NoopReporter, ProgressRead, ProgressReporter};
+use crate::report::CompressionReport;
+use crate::rules::{CompressionRule, CompressionRules, ZipMethod};
+use crate::signing::{self, SignTool};
+use crate::split;
+use crate::update;
+use crate::validate::{self, CollisionPolicy, ConflictPolicy};
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use ignore::gitignore::Gitignore;
+use std::collections::HashSet;
 use std::fs::File;
+use std::io::{self, Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 /// 根据配置压缩指定文件列表。
+///
+/// 配置了 `incremental = true` 时与上一次的清单比较，只压缩新增或修改的文件，
+/// 见 [`incremental::compress_incremental`]；配置了 `update = true` 时更新已有的压缩包，
+/// 见 [`update::compress_update`]。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录，所有文件路径都将相对于此目录进行计算。
+/// - `files_to_compress`: 要压缩的文件路径列表。
+/// - `reporter`: 进度与日志回调，不需要时传入 [`NoopReporter`](crate::progress::NoopReporter)。
+///
+/// # 返回
+/// `Result<CompressionReport>`: 成功时返回包含输出路径与统计信息的报告，失败时返回错误信息。
+pub fn compress_directory(
+    config: &Config,
+    base_dir: &Path,
+    files_to_compress: Vec<PathBuf>,
+    reporter: &dyn ProgressReporter,
+) -> Result<CompressionReport> {
+    let output_path = output_path(config, base_dir);
+    if config.incremental.unwrap_or(false) {
+        return incremental::compress_incremental(
+            config,
+            base_dir,
+            files_to_compress,
+            &output_path,
+            reporter,
+        );
+    }
+    if config.update.unwrap_or(false) {
+        return update::compress_update(
+            config,
+            base_dir,
+            files_to_compress,
+            &output_path,
+            reporter,
+        );
+    }
+    compress_files(config, base_dir, files_to_compress, &output_path, reporter)
+}
 
+/// 根据配置将指定文件列表压缩到给定的输出路径。
+///
+/// 与 [`compress_directory`] 相同，但输出路径由调用者指定，而不是由配置推导。
+///
 /// # 参数
 /// - `config`: 压缩配置。
 /// - `base_dir`: 基础目录，所有文件路径都将相对于此目录进行计算。
 /// - `files_to_compress`: 要压缩的文件路径列表。
+/// - `output_path`: 输出文件的路径。
+/// - `reporter`: 进度与日志回调。
+///
+/// # 返回
+/// `Result<CompressionReport>`: 成功时返回包含输出路径与统计信息的报告，失败时返回错误信息。
+pub fn compress_files(
+    config: &Config,
+    base_dir: &Path,
+    files_to_compress: Vec<PathBuf>,
+    output_path: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<CompressionReport> {
+    let start = Instant::now();
+    let split_size = config.split_size.map(|size| size.0);
+    if split_size.is_some()
+        && let Some(sign) = &config.sign
+        && sign.tool == SignTool::Minisign
+    {
+        anyhow::bail!("minisign 只能为完整的文件签名，分卷压缩包 (split_size) 请使用 gpg 签名");
+    }
+    let output_path = validate::resolve_output_conflict_with(
+        output_path,
+        config.format.extension(),
+        config.on_conflict.unwrap_or_default(),
+        // 分卷压缩时原压缩包不会保留，检查第一个分卷
+        |path| path.exists() || (split_size.is_some() && split::volume_path(path, 1).exists()),
+    )?;
+    reporter.on_message(&format!("正在压缩目录: {}", base_dir.display()));
+    reporter.on_message(&format!("输出文件: {}", output_path.display()));
+    reporter.on_message(&format!("压缩格式: {}", config.format));
+
+    let Prepared {
+        files,
+        mut report,
+        max_entry_bytes,
+        fixed_mtime,
+    } = prepare_files(config, base_dir, files_to_compress, &output_path, reporter)?;
+    if files.is_empty() {
+        reporter.on_message("没有需要压缩的文件。");
+        report.duration = start.elapsed();
+        return Ok(report);
+    }
+
+    reporter.on_message(&format!("找到 {} 个文件要压缩", files.len()));
+
+    check_entries(config, base_dir, &files, reporter)?;
+    check_disk_space(
+        config,
+        &output_path,
+        files.len(),
+        report.input_bytes,
+        reporter,
+    )?;
 
+    let options = entry_options(config, base_dir, &files, max_entry_bytes, fixed_mtime)?;
+
+    if let Some(output_dir) = output_path.parent()
+        && !output_dir.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("创建输出目录失败: {}", output_dir.display()))?;
+    }
+
+    reporter.on_start(files.len(), report.input_bytes);
+
+    let result = match split_size {
+        Some(volume_size) => compress_volumes(
+            config.format,
+            &files,
+            base_dir,
+            &output_path,
+            volume_size,
+            &options,
+            reporter,
+        ),
+        None => write_archive_file(
+            config.format,
+            &files,
+            base_dir,
+            &output_path,
+            &options,
+            reporter,
+        ),
+    };
+
+    reporter.on_done();
+    if let Err(e) = result {
+        // 不留下写了一半的压缩包或分卷（包括被取消的情况）
+        if split_size.is_some() {
+            let _ = split::remove_volumes(&output_path);
+        } else {
+            let _ = std::fs::remove_file(&output_path);
+        }
+        return Err(e);
+    }
+
+    finish_archive(config, base_dir, &files, &mut report, reporter)?;
+    report.duration = start.elapsed();
+    Ok(report)
+}
+
+/// 根据配置将指定文件列表压缩为数据流写入 `out`，例如标准输出或网络连接。
+///
+/// 输出不能随机访问，因此不支持 7z 格式、分卷、增量压缩与更新模式；ZIP 条目的大小与 CRC
+/// 写在条目数据之后的数据描述符中。不会生成清单、属性、校验和与签名等附属文件。
+/// 没有需要压缩的文件时写入一个空的压缩包。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录，所有文件路径都将相对于此目录进行计算。
+/// - `files_to_compress`: 要压缩的文件路径列表。
+/// - `out`: 压缩包数据的写入目标。
+/// - `reporter`: 进度与日志回调。
+///
 /// # 返回
-/// `Result<PathBuf>`: 成功时返回输出文件的路径，失败时返回错误信息。
-pub fn compress_directory(
+/// `Result<CompressionReport>`: 成功时返回统计信息 (`output_path` 为 `-`)，失败时返回错误信息。
+pub fn compress_to_stream<W: Write>(
     config: &Config,
     base_dir: &Path,
     files_to_compress: Vec<PathBuf>,
-) -> Result<PathBuf> {
-    let output_name = config.get_output_name();
-    let output_path = match config.format.as_str() {
-        "zip" => base_dir.join(format!("{}.zip", output_name)),
-        "tar.gz" => base_dir.join(format!("{}.tar.gz", output_name)),
-        "7z" => base_dir.join(format!("{}.7z", output_name)),
-        _ => anyhow::bail!("不支持的压缩格式: {}", config.format),
+    out: W,
+    reporter: &dyn ProgressReporter,
+) -> Result<CompressionReport> {
+    let start = Instant::now();
+    if config.format == Format::SevenZ {
+        anyhow::bail!("7z 格式需要可随机访问的输出，不能写入数据流");
+    }
+    if config.split_size.is_some() {
+        anyhow::bail!("写入数据流时不能切分分卷 (split_size)");
+    }
+    if config.incremental.unwrap_or(false) || config.update.unwrap_or(false) {
+        anyhow::bail!("写入数据流时不能使用增量压缩或更新模式");
+    }
+    let Prepared {
+        files,
+        mut report,
+        max_entry_bytes,
+        fixed_mtime,
+    } = prepare_files(
+        config,
+        base_dir,
+        files_to_compress,
+        &output_path(config, base_dir),
+        reporter,
+    )?;
+    report.output_path = PathBuf::from("-");
+    check_entries(config, base_dir, &files, reporter)?;
+    let options = entry_options(config, base_dir, &files, max_entry_bytes, fixed_mtime)?;
+
+    let mut out = CountingWrite {
+        inner: out,
+        count: 0,
     };
+    reporter.on_start(files.len(), report.input_bytes);
+    let result = write_archive(
+        &FormatRegistry::default(),
+        config.format.extension(),
+        ArchiveOutput::stream(&mut out),
+        &files,
+        base_dir,
+        &options,
+        reporter,
+    );
+    reporter.on_done();
+    result?;
+    out.flush().context("写入压缩包数据失败")?;
 
-    println!("正在压缩目录: {}", base_dir.display());
-    println!("输出文件: {}", output_path.display());
-    println!("压缩格式: {}", config.format);
+    report.output_bytes = out.count;
+    report.duration = start.elapsed();
+    Ok(report)
+}
 
-    let files = files_to_compress;
+/// 将文件压缩到内存中，返回压缩包的全部字节，例如作为 HTTP 响应的附件，不经过磁盘。
+///
+/// 使用默认配置与给定的格式；需要忽略规则、压缩规则等设置时请使用 [`compress_zip_to`] 等函数。
+/// 整个压缩包保存在内存中，不适合很大的目录。
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::path::Path;
+/// use ztr_lib::collector::{self, SymlinkPolicy};
+/// use ztr_lib::compressor::compress_to_vec;
+/// use ztr_lib::format::Format;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let base_dir = Path::new("./reports");
+///     let files = collector::collect_all_files(base_dir, SymlinkPolicy::Follow)?;
+///     let bytes = compress_to_vec(Format::Zip, base_dir, &files)?;
+///     println!("{} 字节", bytes.len());
+///     Ok(())
+/// }
+/// ```
+///
+/// # 参数
+/// - `format`: 压缩格式。
+/// - `base_dir`: 基础目录，条目名称相对于此目录。
+/// - `files`: 要压缩的文件路径列表。
+///
+/// # 返回
+/// `Result<Vec<u8>>`: 压缩包的字节，读取文件失败时返回错误信息。
+pub fn compress_to_vec(format: Format, base_dir: &Path, files: &[PathBuf]) -> Result<Vec<u8>> {
+    let config = Config {
+        format,
+        ..Default::default()
+    };
+    let options = EntryOptions::from_config(&config, base_dir, files)?;
+    let mut out = io::Cursor::new(Vec::new());
+    write_archive(
+        &FormatRegistry::default(),
+        format.extension(),
+        ArchiveOutput::seekable(&mut out),
+        files,
+        base_dir,
+        &options,
+        &NoopReporter,
+    )?;
+    Ok(out.into_inner())
+}
 
-    if files.is_empty() {
-        println!("没有需要压缩的文件。");
-        return Ok(output_path);
+/// 记录写入字节数的写入器。
+struct CountingWrite<W> {
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWrite<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
     }
 
-    println!("找到 {} 个文件要压缩", files.len());
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
 
-    // 创建进度条
-    let pb = ProgressBar::new(files.len() as u64);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-            .unwrap()
-            .progress_chars("#>-"),
-    );
-    pb.set_message("正在压缩...");
-
-    // 根据格式选择压缩方法
-    let result = match config.format.as_str() {
-        "zip" => compress_zip(&files, base_dir, &output_path, &pb),
-        "tar.gz" => compress_tar_gz(&files, base_dir, &output_path, &pb),
-        "7z" => compress_7z(&files, base_dir, &output_path, &pb),
-        _ => anyhow::bail!("不支持的压缩格式: {}", config.format),
+/// [`prepare_files`] 的结果。
+struct Prepared {
+    /// 要写入的文件
+    files: Vec<PathBuf>,
+    /// 初始的报告，尚未填充输出大小与耗时
+    report: CompressionReport,
+    /// 每个条目最多写入的字节数 (采样模式)
+    max_entry_bytes: Option<u64>,
+    /// 可复现模式下所有条目统一使用的修改时间
+    fixed_mtime: Option<u64>,
+}
+
+/// 压缩前的准备：排除压缩包自身与参考压缩包中未变化的文件，按配置排序与采样，并生成初始的报告。
+fn prepare_files(
+    config: &Config,
+    base_dir: &Path,
+    files_to_compress: Vec<PathBuf>,
+    output_path: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<Prepared> {
+    let candidates = files_to_compress.clone();
+    let mut files = exclude_archives(config, base_dir, output_path, files_to_compress);
+    if let Some(reference) = &config.exclude_from_archive {
+        let mounts = collector::mounts(config, base_dir)?;
+        files = exclude_unchanged(base_dir, &mounts, Path::new(reference), files)?;
+    }
+
+    let fixed_mtime = config.reproducible.unwrap_or(false).then(source_date_epoch);
+    if fixed_mtime.is_some() {
+        // 条目顺序与遍历顺序无关
+        files.sort();
+    }
+
+    let max_entry_bytes = match config.sample_bytes {
+        Some(budget) => {
+            let total = files.len();
+            let (sampled, cap) = sample_files(files, budget);
+            reporter.on_message(&format!(
+                "采样模式: 从 {} 个文件中选取 {} 个, 每个文件最多 {} 字节",
+                total,
+                sampled.len(),
+                cap
+            ));
+            files = sampled;
+            Some(cap)
+        }
+        None => None,
     };
 
-    pb.finish_with_message("压缩完成");
-
-    match result {
-        Ok(_) => {
-            println!("✓ 压缩完成: {}", output_path.display());
-
-            // 显示文件大小
-            if let Ok(metadata) = std::fs::metadata(&output_path) {
-                let size = metadata.len();
-                if size > 1024 * 1024 {
-                    println!("文件大小: {:.2} MB", size as f64 / (1024.0 * 1024.0));
-                } else if size > 1024 {
-                    println!("文件大小: {:.2} KB", size as f64 / 1024.0);
-                } else {
-                    println!("文件大小: {} bytes", size);
-                }
+    let symlinks = config.symlinks.unwrap_or_default();
+    let kept: HashSet<&PathBuf> = files.iter().collect();
+    let skipped: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter(|f| !kept.contains(f))
+        .collect();
+    let report = CompressionReport {
+        output_path: output_path.to_path_buf(),
+        files_count: files.len(),
+        input_bytes: files
+            .iter()
+            .filter(|f| !is_preserved_link(f, symlinks))
+            .filter_map(|f| f.metadata().ok())
+            .map(|m| m.len().min(max_entry_bytes.unwrap_or(u64::MAX)))
+            .sum(),
+        output_bytes: 0,
+        duration: Default::default(),
+        skipped,
+        checksums: Vec::new(),
+        signature: None,
+    };
+    Ok(Prepared {
+        files,
+        report,
+        max_entry_bytes,
+        fixed_mtime,
+    })
+}
+
+/// 根据配置构造写入条目时使用的选项，配置了 `embed_manifest` 时生成内嵌清单。
+pub(crate) fn entry_options(
+    config: &Config,
+    base_dir: &Path,
+    files: &[PathBuf],
+    max_entry_bytes: Option<u64>,
+    fixed_mtime: Option<u64>,
+) -> Result<EntryOptions> {
+    let symlinks = config.symlinks.unwrap_or_default();
+    let options = EntryOptions {
+        metadata: match &config.metadata {
+            Some(map) => MetadataRules::new(map)?,
+            None => MetadataRules::default(),
+        },
+        symlinks,
+        max_entry_bytes,
+        fixed_mtime,
+        manifest: None,
+        store_only: match &config.store_only {
+            Some(patterns) => Some(ignore_rules::pattern_matcher(
+                patterns,
+                base_dir,
+                "store_only 规则",
+            )?),
+            None => None,
+        },
+        rules: match &config.rules {
+            Some(map) => CompressionRules::new(map, base_dir)?,
+            None => CompressionRules::default(),
+        },
+        zip_method: match config.zip_method {
+            Some(method) => {
+                method.check_available()?;
+                method
             }
+            None => ZipMethod::default(),
+        },
+        sevenz: match &config.sevenz {
+            Some(sevenz) => {
+                check_sevenz_config(sevenz)?;
+                sevenz.clone()
+            }
+            None => SevenZConfig::default(),
+        },
+        gzip_header: config.gzip_header.clone().unwrap_or_default(),
+        gzip_level: gzip_level(config)?,
+        mounts: collector::mounts(config, base_dir)?,
+        root: config
+            .wrap_root
+            .unwrap_or(false)
+            .then(|| config.get_output_name(base_dir)),
+    };
+    if !config.embed_manifest.unwrap_or(false) {
+        return Ok(options);
+    }
+    if files.iter().any(|f| {
+        collector::entry_path(base_dir, &options.mounts, f)
+            == Some(PathBuf::from(archive_manifest::ENTRY_NAME))
+    }) {
+        anyhow::bail!(
+            "文件 {} 与内嵌清单的条目名称冲突，请将其加入忽略规则或关闭 embed_manifest",
+            archive_manifest::ENTRY_NAME
+        );
+    }
+    let created = fixed_mtime.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    });
+    let manifest = ArchiveManifest::build(
+        config,
+        base_dir,
+        files,
+        |f| is_preserved_link(f, symlinks),
+        max_entry_bytes,
+        created,
+    )?;
+    Ok(EntryOptions {
+        manifest: Some(manifest.to_json()?),
+        ..options
+    })
+}
+
+/// 7z 字典大小的下限 (与 LZMA2 格式一致)。
+const SEVENZ_DICT_SIZE_MIN: u64 = 4 * 1024;
+/// 7z 字典大小的上限 (与 7-Zip 一致)。
+const SEVENZ_DICT_SIZE_MAX: u64 = 1536 * 1024 * 1024;
+
+/// 检查 `[sevenz]` 中的压缩级别与字典大小是否在有效范围内。
+fn check_sevenz_config(sevenz: &SevenZConfig) -> Result<()> {
+    if let Some(level) = sevenz.level
+        && level > CompressionRule::MAX_LEVEL
+    {
+        anyhow::bail!(
+            "[sevenz] 的压缩级别 {} 超出范围 0-{}",
+            level,
+            CompressionRule::MAX_LEVEL
+        );
+    }
+    if let Some(ByteSize(dict_size)) = sevenz.dict_size
+        && !(SEVENZ_DICT_SIZE_MIN..=SEVENZ_DICT_SIZE_MAX).contains(&dict_size)
+    {
+        anyhow::bail!(
+            "[sevenz] 的字典大小 {} 字节超出范围 {}-{} 字节",
+            dict_size,
+            SEVENZ_DICT_SIZE_MIN,
+            SEVENZ_DICT_SIZE_MAX
+        );
+    }
+    Ok(())
+}
+
+/// 将文件压缩后直接写入分卷 `<压缩包>.001`、`.002`……（见 [`split::VolumeWriter`]），
+/// 不会生成完整的压缩包，因此单个文件的大小与需要的磁盘空间都不会超过分卷的总和。
+///
+/// 所有格式（包括 ZIP）都只按字节切分，不生成 ZIP 规范中的跨卷 (spanned) 压缩包。
+fn compress_volumes(
+    format: Format,
+    files: &[PathBuf],
+    base_dir: &Path,
+    output_path: &Path,
+    volume_size: u64,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    let mut out = io::BufWriter::new(split::VolumeWriter::create(output_path, volume_size)?);
+    write_archive(
+        &FormatRegistry::default(),
+        format.extension(),
+        ArchiveOutput::seekable(&mut out),
+        files,
+        base_dir,
+        options,
+        reporter,
+    )?;
+    out.into_inner()
+        .map_err(|e| e.into_error())
+        .context("写入分卷失败")?
+        .finish()?;
+    Ok(())
+}
+
+/// 压缩包写入完成后的步骤：按配置写入清单、属性、校验和与签名附属文件。
+///
+/// `report.output_path` 为写好的压缩包；分卷压缩时更新为第一个分卷，同时填充 `output_bytes`、
+/// `checksums` 与 `signature`。
+pub(crate) fn finish_archive(
+    config: &Config,
+    base_dir: &Path,
+    files: &[PathBuf],
+    report: &mut CompressionReport,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    let output_path = report.output_path.clone();
+    let mounts = collector::mounts(config, base_dir)?;
+    if config.listing.unwrap_or(false) {
+        listing::write_listing(files, base_dir, &mounts, &output_path)?;
+    }
+
+    if config.preserve_attributes.unwrap_or(false) {
+        attributes::write_sidecars(files, base_dir, &mounts, &output_path)?;
+    }
+
+    let volumes = config
+        .split_size
+        .and_then(|_| split::find_volumes(&output_path));
+    report.output_bytes = match &volumes {
+        Some(volumes) => split::VolumeReader::open(volumes)?.len(),
+        None => std::fs::metadata(&output_path)
+            .with_context(|| format!("读取压缩包信息失败: {}", output_path.display()))?
+            .len(),
+    };
+    for &algorithm in config.checksum.iter().flatten() {
+        let digest = checksum::write_sidecar(&output_path, algorithm)?;
+        report.checksums.push((algorithm, digest));
+    }
+    if let Some(sign) = &config.sign {
+        report.signature = Some(signing::sign_archive(sign, &output_path)?);
+    }
+    if let Some(volumes) = volumes {
+        reporter.on_message(&format!("已写入 {} 个分卷", volumes.len()));
+        report.output_path = volumes[0].clone();
+    }
+    Ok(())
+}
+
+/// 以构建器方式配置并执行一次压缩。
+///
+/// 构建器会自行收集基础目录中的文件并应用忽略规则，调用者无需构造完整的 [`Config`]
+/// 或预先收集文件列表。未设置的选项使用 [`Config::default`] 中的值。
+///
+/// # 示例
+///
+/// ```no_run
+/// use ztr_lib::compressor::Compressor;
+/// use ztr_lib::format::Format;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let report = Compressor::new("./my_project")
+///         .format(Format::Zip)
+///         .ignore(["target/", "*.log"])
+///         .output("out.zip")
+///         .run()?;
+///     println!("{}: {} 个文件", report.output_path.display(), report.files_count);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compressor {
+    base_dir: PathBuf,
+    config: Config,
+    output: Option<PathBuf>,
+}
+
+impl Compressor {
+    /// 创建压缩 `base_dir` 的构建器。
+    pub fn new<P: AsRef<Path>>(base_dir: P) -> Self {
+        Self {
+            base_dir: base_dir.as_ref().to_path_buf(),
+            config: Config::default(),
+            output: None,
         }
-        Err(e) => {
-            println!("✗ 压缩失败: {}", e);
-            return Err(e);
-        }
     }
 
-    Ok(output_path)
+    /// 以已有配置为基础（例如从 `ztr.toml` 加载的配置）。
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 设置压缩格式。
+    pub fn format(mut self, format: Format) -> Self {
+        self.config.format = format;
+        self
+    }
+
+    /// 设置输出文件名（不含扩展名），压缩包写入输出目录（默认为基础目录）。
+    pub fn output_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.config.output_name = Some(name.into());
+        self
+    }
+
+    /// 设置输出文件已存在时的处理方式，默认为 [`ConflictPolicy::Error`]。
+    pub fn on_conflict(mut self, policy: ConflictPolicy) -> Self {
+        self.config.on_conflict = Some(policy);
+        self
+    }
+
+    /// 设置单个文件的最大大小 (字节)，更大的文件被跳过，并通过进度报告器的 `on_message` 列出。
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.config.max_file_size = Some(ByteSize(bytes));
+        self
+    }
+
+    /// 只压缩修改时间晚于 `when` 的文件，格式同配置中的 `newer_than`（如 `24h`、`2024-05-01`）。
+    pub fn newer_than<S: Into<String>>(mut self, when: S) -> Self {
+        self.config.newer_than = Some(when.into());
+        self
+    }
+
+    /// 启用增量压缩：只压缩相对上一次清单新增或修改的文件，输出 `<输出名>.incr-<N>.<扩展名>`。
+    pub fn incremental(mut self, enabled: bool) -> Self {
+        self.config.incremental = Some(enabled);
+        self
+    }
+
+    /// 启用更新模式：压缩包已存在时只压缩新增或修改的文件，未变化的条目从原压缩包中复制。
+    pub fn update(mut self, enabled: bool) -> Self {
+        self.config.update = Some(enabled);
+        self
+    }
+
+    /// 将压缩包直接写入不超过 `bytes` 字节的分卷 `<压缩包>.001`、`.002`……。
+    pub fn split_size(mut self, bytes: u64) -> Self {
+        self.config.split_size = Some(ByteSize(bytes));
+        self
+    }
+
+    /// 设置输出目录（相对路径相对于基础目录），不存在时会被创建。
+    pub fn output_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.config.output_dir = Some(dir.as_ref().to_string_lossy().to_string());
+        self
+    }
+
+    /// 设置完整的输出路径，优先于 [`Compressor::output_name`] 与 [`Compressor::output_dir`]。
+    pub fn output<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.output = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// 替换忽略规则（Gitignore 语法）。
+    pub fn ignore<I, S>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.ignore = Some(rules.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// 设置源路径的 glob 模式，只压缩匹配的文件或目录。
+    pub fn sources<I, S>(mut self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<SourceEntry>,
+    {
+        self.config.sources = Some(sources.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// 收集文件、应用忽略规则并执行压缩，不报告进度。
+    ///
+    /// # 返回
+    /// `Result<CompressionReport>`: 成功时返回包含输出路径与统计信息的报告，失败时返回错误信息。
+    pub fn run(self) -> Result<CompressionReport> {
+        self.run_with_progress(&NoopReporter)
+    }
+
+    /// 与 [`Compressor::run`] 相同，但通过 `reporter` 报告进度与日志。
+    pub fn run_with_progress(self, reporter: &dyn ProgressReporter) -> Result<CompressionReport> {
+        let all_files = collector::collect_for_config(&self.config, &self.base_dir)?;
+        let filtered =
+            FilterPipeline::from_config(&self.config, &self.base_dir)?.filter_files(all_files)?;
+        for warning in &filtered.warnings {
+            reporter.on_message(warning);
+        }
+        let files = filtered.kept;
+
+        let output_path = self
+            .output
+            .unwrap_or_else(|| output_path(&self.config, &self.base_dir));
+        if self.config.incremental.unwrap_or(false) {
+            return incremental::compress_incremental(
+                &self.config,
+                &self.base_dir,
+                files,
+                &output_path,
+                reporter,
+            );
+        }
+        if self.config.update.unwrap_or(false) {
+            return update::compress_update(
+                &self.config,
+                &self.base_dir,
+                files,
+                &output_path,
+                reporter,
+            );
+        }
+        compress_files(&self.config, &self.base_dir, files, &output_path, reporter)
+    }
 }
 
-/// 将文件压缩为 ZIP 格式。
+/// 计算压缩包的输出路径。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录，未配置 `output_dir` 时压缩包写入此目录。相对的 `output_dir` 以配置文件所在目录为基准，
+///   配置不来自文件时以此目录为基准。
+///
+/// # 返回
+/// `PathBuf`: 输出文件的路径。
+pub fn output_path(config: &Config, base_dir: &Path) -> PathBuf {
+    let mut output_name = config.get_output_name(base_dir);
+    if config.sample_bytes.is_some() {
+        output_name.push_str("-sample");
+    }
+    let output_dir = match &config.output_dir {
+        Some(dir) => config.resolve_path(dir, base_dir),
+        None => base_dir.to_path_buf(),
+    };
+    output_dir.join(format!("{}.{}", output_name, config.format.extension()))
+}
 
+/// 从待压缩文件中移除输出文件本身，以及（默认启用）以前生成的同名压缩包。
+///
+/// 以前的压缩包指输出目录下名为 `<输出名>.zip`、`<输出名>.tar.gz` 或 `<输出名>.7z` 的文件，
+/// 增量压缩生成的 `<输出名>.incr-<N>.<扩展名>` 和 `<输出名>.manifest`，以及这些压缩包的分卷 `.001`……，
+/// 可通过配置项 `exclude_previous_archives = false` 关闭。
+///
 /// # 参数
-/// - `files`: 要压缩的文件路径列表。
-/// - `base_dir`: 基础目录，用于计算文件中相对路径。
-/// - `output_path`: 输出 ZIP 文件的路径。
-/// - `pb`: 进度条。
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录。
+/// - `output_path`: 本次输出文件的路径。
+/// - `files`: 待压缩的文件路径列表。
+///
+/// # 返回
+/// `Vec<PathBuf>`: 过滤后的文件路径列表。
+pub fn exclude_archives(
+    config: &Config,
+    base_dir: &Path,
+    output_path: &Path,
+    files: Vec<PathBuf>,
+) -> Vec<PathBuf> {
+    let output_name = config.get_output_name(base_dir);
+    let output_dir = output_path.parent().unwrap_or(base_dir);
+    let previous_archives: Vec<PathBuf> = if config.exclude_previous_archives.unwrap_or(true) {
+        Format::ALL
+            .iter()
+            .map(|format| output_dir.join(format!("{}.{}", output_name, format.extension())))
+            .collect()
+    } else {
+        Vec::new()
+    };
 
+    // 增量压缩包与清单每次运行都会变化，增量模式下总是排除
+    let exclude_incremental =
+        config.exclude_previous_archives.unwrap_or(true) || config.incremental.unwrap_or(false);
+    let is_incremental_artifact = |f: &Path| {
+        exclude_incremental
+            && f.parent() == Some(output_dir)
+            && f.file_name().is_some_and(|name| {
+                incremental::is_incremental_artifact(&name.to_string_lossy(), &output_name)
+            })
+    };
+
+    // 分卷压缩生成的 `<压缩包>.001` 等文件
+    let archive_names: Vec<String> = previous_archives
+        .iter()
+        .chain(std::iter::once(&output_path.to_path_buf()))
+        .filter_map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .collect();
+    let is_volume = |f: &Path| {
+        f.parent() == Some(output_dir)
+            && f.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+                archive_names
+                    .iter()
+                    .any(|archive| split::is_volume_of(&name, archive))
+            })
+    };
+    // 压缩包的校验和与签名文件 `<压缩包>.sha256`、`<压缩包>.minisig` 等
+    let is_sidecar = |f: &Path| {
+        f.parent() == Some(output_dir)
+            && archive_names.iter().any(|archive| {
+                let archive = output_dir.join(archive);
+                ChecksumAlgorithm::ALL
+                    .iter()
+                    .any(|&alg| f == checksum::sidecar_path(&archive, alg))
+                    || SignTool::ALL
+                        .iter()
+                        .any(|&tool| f == signing::signature_path(&archive, tool))
+            })
+    };
+
+    files
+        .into_iter()
+        .filter(|f| {
+            f != output_path
+                && !previous_archives.contains(f)
+                && !is_incremental_artifact(f)
+                && !is_volume(f)
+                && !is_sidecar(f)
+        })
+        .collect()
+}
+
+/// 移除在参考压缩包中已存在且内容相同（路径与 SHA-256 均一致）的文件，
+/// 用于生成只包含新增或修改文件的补充压缩包。
+///
+/// # 参数
+/// - `base_dir`: 基础目录，用于计算与条目名称比较的相对路径。
+/// - `mounts`: 映射到压缩包中指定前缀的源路径。
+/// - `reference_archive`: 参考压缩包的路径。
+/// - `files`: 待压缩的文件路径列表。
+///
 /// # 返回
-/// `Result<()>`: 成功时返回 `Ok(())`，失败时返回错误信息。
-pub fn compress_zip(
-    files: &[PathBuf],
+/// `Result<Vec<PathBuf>>`: 过滤后的文件路径列表。
+pub fn exclude_unchanged(
+    base_dir: &Path,
+    mounts: &[Mount],
+    reference_archive: &Path,
+    files: Vec<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let digests = checksum::archive_digests(reference_archive)
+        .with_context(|| format!("读取参考压缩包失败: {}", reference_archive.display()))?;
+
+    let mut kept = Vec::new();
+    for file in files {
+        let relative_path = match collector::entry_path(base_dir, mounts, &file) {
+            Some(p) => p.to_string_lossy().replace("\\", "/"),
+            None => {
+                kept.push(file);
+                continue;
+            }
+        };
+        let unchanged = match digests.get(&relative_path) {
+            Some(digest) => checksum::sha256_file(&file)? == *digest,
+            None => false,
+        };
+        if !unchanged {
+            kept.push(file);
+        }
+    }
+    Ok(kept)
+}
+
+/// 写入条目时使用的选项。
+#[derive(Debug, Default)]
+pub struct EntryOptions {
+    /// 条目元数据规则
+    pub metadata: MetadataRules,
+    /// 符号链接的处理方式
+    pub symlinks: SymlinkPolicy,
+    /// 每个条目最多写入的字节数 (采样模式)，`None` 表示写入完整内容
+    pub max_entry_bytes: Option<u64>,
+    /// 可复现模式下所有条目统一使用的修改时间 (Unix 时间戳)。
+    /// 设置时还会清除 TAR 头中的 uid/gid 并规范化权限位
+    pub fixed_mtime: Option<u64>,
+    /// 在所有文件之后写入的内嵌清单 `ZTR_MANIFEST.json` 的内容，`None` 表示不写入
+    pub manifest: Option<Vec<u8>>,
+    /// 只存储不压缩的条目的匹配规则 (配置中的 `store_only`)
+    pub store_only: Option<Gitignore>,
+    /// 按文件类型调整的压缩参数 (配置中的 `[rules]`)
+    pub rules: CompressionRules,
+    /// ZIP 条目默认的压缩方法 (配置中的 `zip_method`)
+    pub zip_method: ZipMethod,
+    /// 7Z 的压缩参数 (配置中的 `[sevenz]`)
+    pub sevenz: SevenZConfig,
+    /// TAR.GZ 的 gzip 头部字段 (配置中的 `[gzip_header]`)
+    pub gzip_header: GzipHeader,
+    /// TAR.GZ 的 gzip 压缩级别 (配置中的 `gzip_level`)
+    pub gzip_level: flate2::Compression,
+    /// 映射到压缩包中指定前缀的源路径 (配置中 `sources` 的 `{ path, prefix }` 项，以及 `wrap_root` 的顶层目录)
+    pub mounts: Vec<Mount>,
+    /// 配置了 `wrap_root` 时所有条目所在的顶层目录，匹配规则时会去掉这一层
+    pub root: Option<String>,
+}
+
+impl EntryOptions {
+    /// 根据配置构造条目选项，供直接调用 [`compress_zip_to`] 等函数的库用户使用。
+    ///
+    /// # 参数
+    /// - `config`: 压缩配置，使用其中的元数据、压缩规则、源路径映射与可复现等设置。
+    /// - `base_dir`: 基础目录。
+    /// - `files`: 要压缩的文件，配置了 `embed_manifest` 时用于生成内嵌清单。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 配置中的规则无效时返回错误信息。
+    pub fn from_config(config: &Config, base_dir: &Path, files: &[PathBuf]) -> Result<Self> {
+        let fixed_mtime = config.reproducible.unwrap_or(false).then(source_date_epoch);
+        entry_options(config, base_dir, files, None, fixed_mtime)
+    }
+
+    fn entry_limit(&self) -> u64 {
+        self.max_entry_bytes.unwrap_or(u64::MAX)
+    }
+
+    /// 用于匹配 `store_only`、`[rules]` 与 `[metadata]` 的路径，即去掉 `wrap_root` 顶层目录后的条目名称。
+    pub fn rule_path<'a>(&self, name: &'a str) -> &'a str {
+        self.root
+            .as_deref()
+            .and_then(|root| name.strip_prefix(root)?.strip_prefix('/'))
+            .unwrap_or(name)
+    }
+
+    /// 条目是否匹配 `store_only`，应当只存储而不压缩。
+    pub fn is_store_only(&self, name: &str) -> bool {
+        let name = self.rule_path(name);
+        self.store_only
+            .as_ref()
+            .is_some_and(|rules| rules.matched_path_or_any_parents(name, false).is_ignore())
+    }
+
+    /// 条目的压缩参数：`[rules]` 中匹配的规则，匹配 `store_only` 时方法总是 `stored`。
+    pub fn compression_rule(&self, name: &str) -> CompressionRule {
+        let mut rule = self.rules.lookup(self.rule_path(name));
+        if self.is_store_only(name) {
+            rule.method = Some(ZipMethod::Stored);
+        }
+        rule
+    }
+
+    /// 条目在 ZIP 中使用的压缩方法。
+    pub(crate) fn zip_method(&self, name: &str) -> zip::CompressionMethod {
+        self.compression_rule(name)
+            .method
+            .unwrap_or(self.zip_method)
+            .to_zip()
+    }
+}
+
+/// 计算文件在压缩包中的条目名称（相对于基础目录或映射的前缀，使用 `/` 分隔）。
+pub(crate) fn entry_name(base_dir: &Path, mounts: &[Mount], file_path: &Path) -> Result<String> {
+    let relative_path = collector::entry_path(base_dir, mounts, file_path)
+        .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?;
+    Ok(relative_path.to_string_lossy().replace("\\", "/"))
+}
+
+/// 在写入之前检查条目名称冲突与条目数量，按 `on_name_collision` 策略报错或警告。
+pub(crate) fn check_entries(
+    config: &Config,
     base_dir: &Path,
+    files: &[PathBuf],
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    if config.format == Format::Zip && files.len() > validate::ZIP_MAX_ENTRIES_WITHOUT_ZIP64 {
+        reporter.on_message(&format!(
+            "⚠ 条目数 {} 超过 {}，将使用 ZIP64 扩展，部分旧的解压工具可能无法读取",
+            files.len(),
+            validate::ZIP_MAX_ENTRIES_WITHOUT_ZIP64
+        ));
+    }
+
+    let policy = config.on_name_collision.unwrap_or_default();
+    if policy == CollisionPolicy::Ignore {
+        return Ok(());
+    }
+    let mounts = collector::mounts(config, base_dir)?;
+    let names = files
+        .iter()
+        .map(|f| entry_name(base_dir, &mounts, f))
+        .collect::<Result<Vec<_>>>()?;
+    let collisions = validate::find_collisions(names.iter().map(String::as_str));
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    let details: Vec<String> = collisions.iter().map(|c| c.to_string()).collect();
+    if policy == CollisionPolicy::Error {
+        anyhow::bail!(
+            "发现 {} 个条目名称冲突:\n{}",
+            collisions.len(),
+            details.join("\n")
+        );
+    }
+    for detail in details {
+        reporter.on_message(&format!("⚠ {}", detail));
+    }
+    Ok(())
+}
+
+/// 在写入之前检查输出目录所在磁盘的可用空间，按 `on_low_disk_space` 策略报错或警告。
+///
+/// 需要的空间按内容无法压缩的最坏情况估算。
+pub(crate) fn check_disk_space(
+    config: &Config,
     output_path: &Path,
-    pb: &ProgressBar,
+    files: usize,
+    input_bytes: u64,
+    reporter: &dyn ProgressReporter,
 ) -> Result<()> {
-    use std::io::Write;
-    use zip::{ZipWriter, write::FileOptions};
+    let required = validate::worst_case_output_size(files, input_bytes);
+    let policy = config.on_low_disk_space.unwrap_or_default();
+    if let Some(warning) = validate::check_disk_space(output_path, required, policy)? {
+        reporter.on_message(&format!("⚠ {}", warning));
+    }
+    Ok(())
+}
 
-    let file = File::create(output_path).context("创建ZIP文件失败")?;
-    let mut zip = ZipWriter::new(file);
+/// 可复现模式使用的时间戳：环境变量 `SOURCE_DATE_EPOCH`，未设置或无效时为 0。
+pub(crate) fn source_date_epoch() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0)
+}
 
-    for file_path in files {
-        pb.inc(1);
+/// 将 Unix 时间戳转换为 ZIP 的 MS-DOS 时间，超出 1980–2107 年范围时取边界值。
+pub(crate) fn zip_datetime(secs: u64) -> zip::DateTime {
+    // 1980-01-01 与 2107-12-31 23:59:58 对应的 Unix 时间戳
+    let secs = secs.clamp(315_532_800, 4_354_819_198) as i64;
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = crate::template::civil_from_days(days);
 
-        let relative_path = file_path
-            .strip_prefix(base_dir)
-            .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?;
+    zip::DateTime::from_date_and_time(
+        year as u16,
+        month as u8,
+        day as u8,
+        (rem / 3_600) as u8,
+        (rem % 3_600 / 60) as u8,
+        (rem % 60) as u8,
+    )
+    .unwrap_or_default()
+}
 
-        let mut file = File::open(file_path)
-            .with_context(|| format!("打开文件失败: {}", file_path.display()))?;
+/// 采样时每个文件至少保留的字节数，避免文件很多时每个文件只剩几个字节。
+const MIN_SAMPLE_BYTES: u64 = 64 * 1024;
 
-        let relative_path_str = relative_path.to_string_lossy().replace("\\", "/");
-        zip.start_file(&relative_path_str, FileOptions::default())
-            .with_context(|| format!("添加文件到ZIP失败: {}", file_path.display()))?;
+/// 从文件列表中选出一个总大小不超过 `budget` 字节的代表性样本。
+///
+/// 每个文件最多保留前 `cap` 字节；如果截断后的总量仍超出预算，则在整个列表中均匀地抽取文件，
+/// 使样本覆盖所有目录而不是只包含排在前面的文件。
+///
+/// # 返回
+/// `(Vec<PathBuf>, u64)`: 抽取的文件与每个文件保留的最大字节数 `cap`。
+pub fn sample_files(files: Vec<PathBuf>, budget: u64) -> (Vec<PathBuf>, u64) {
+    let cap = (budget / files.len().max(1) as u64)
+        .max(MIN_SAMPLE_BYTES)
+        .min(budget);
+    let sizes: Vec<u64> = files
+        .iter()
+        .map(|f| f.metadata().map(|m| m.len().min(cap)).unwrap_or(0))
+        .collect();
 
-        let mut buffer = Vec::new();
-        std::io::copy(&mut file, &mut buffer)
-            .with_context(|| format!("读取文件失败: {}", file_path.display()))?;
+    let total: u64 = sizes.iter().sum();
+    let step = total.div_ceil(budget.max(1)).max(1) as usize;
 
-        zip.write_all(&buffer)
-            .with_context(|| format!("写入ZIP失败: {}", file_path.display()))?;
+    let mut remaining = budget;
+    let mut sampled = Vec::new();
+    for (file, size) in files.into_iter().zip(sizes).step_by(step) {
+        if size > remaining {
+            break;
+        }
+        remaining -= size;
+        sampled.push(file);
     }
+    (sampled, cap)
+}
 
-    zip.finish().context("完成ZIP写入失败")?;
+/// 路径是否为需要按 [`SymlinkPolicy::Preserve`] 保存为链接条目的符号链接。
+pub(crate) fn is_preserved_link(path: &Path, symlinks: SymlinkPolicy) -> bool {
+    symlinks == SymlinkPolicy::Preserve && path.is_symlink()
+}
 
-    Ok(())
+/// 读取符号链接的目标，统一使用 `/` 分隔。
+fn link_target(path: &Path) -> Result<String> {
+    let target = std::fs::read_link(path)
+        .with_context(|| format!("读取符号链接失败: {}", path.display()))?;
+    Ok(target.to_string_lossy().replace("\\", "/"))
 }
 
-/// 将文件压缩为 TAR.GZ 格式。
+/// 将文件压缩为 ZIP 格式。
+///
+/// # 参数
+/// - `files`: 要压缩的文件路径列表。
+/// - `base_dir`: 基础目录，用于计算文件中相对路径。
+/// - `output_path`: 输出 ZIP 文件的路径。
+/// - `options`: 条目选项。匹配到的元数据注释会写为 ZIP 条目注释，
+///   `Preserve` 策略下的符号链接写为链接条目。
+/// - `reporter`: 进度回调。
+///
+/// # 返回
+/// `Result<()>`: 成功时返回 `Ok(())`，失败时返回错误信息。
+pub fn compress_zip(
+    files: &[PathBuf],
+    base_dir: &Path,
+    output_path: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    write_archive_file(Format::Zip, files, base_dir, output_path, options, reporter)
+}
+
+/// 将文件压缩为 ZIP 格式并写入 `out`，例如内存缓冲区或加密包装的文件。
+///
+/// 参数与 [`compress_zip`] 相同，只是输出为任意 `Write + Seek` 目标；
+/// 只能顺序写入的目标请使用 [`compress_to_stream`]。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`，失败时返回错误信息。
+pub fn compress_zip_to<W: Write + Seek>(
+    mut out: W,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<W> {
+    write_archive(
+        &FormatRegistry::default(),
+        Format::Zip.extension(),
+        ArchiveOutput::seekable(&mut out),
+        files,
+        base_dir,
+        options,
+        reporter,
+    )?;
+    Ok(out)
+}
 
+/// 将文件压缩为 TAR.GZ 格式。
+///
 /// # 参数
 /// - `files`: 要压缩的文件路径列表。
 /// - `base_dir`: 基础目录，用于计算文件中相对路径。
 /// - `output_path`: 输出 TAR.GZ 文件的路径。
-/// - `pb`: 进度条。
-
+/// - `options`: 条目选项。匹配到的元数据会写为条目前的 PAX 扩展头，
+///   `Preserve` 策略下的符号链接写为链接条目；gzip 头部字段与压缩级别取自
+///   `gzip_header` 与 `gzip_level`。
+/// - `reporter`: 进度回调。
+///
 /// # 返回
 /// `Result<()>`: 成功时返回 `Ok(())`，失败时返回错误信息。
 pub fn compress_tar_gz(
     files: &[PathBuf],
     base_dir: &Path,
     output_path: &Path,
-    pb: &ProgressBar,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
 ) -> Result<()> {
-    use flate2::Compression;
-    use flate2::write::GzEncoder;
-    use tar::Builder;
-
-    let file = File::create(output_path).context("创建TAR.GZ文件失败")?;
-    let gz_encoder = GzEncoder::new(file, Compression::default());
-    let mut tar = Builder::new(gz_encoder);
-
-    for file_path in files {
-        pb.inc(1);
+    write_archive_file(
+        Format::TarGz,
+        files,
+        base_dir,
+        output_path,
+        options,
+        reporter,
+    )
+}
 
-        let relative_path = file_path
-            .strip_prefix(base_dir)
-            .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?;
+/// 将文件压缩为 TAR.GZ 格式并写入 `out`，只需要顺序写入，例如管道或网络连接。
+///
+/// 参数与 [`compress_tar_gz`] 相同，只是输出为任意 `Write` 目标。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`，失败时返回错误信息。
+pub fn compress_tar_gz_to<W: Write>(
+    mut out: W,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<W> {
+    write_archive(
+        &FormatRegistry::default(),
+        Format::TarGz.extension(),
+        ArchiveOutput::stream(&mut out),
+        files,
+        base_dir,
+        options,
+        reporter,
+    )?;
+    Ok(out)
+}
 
-        tar.append_path_with_name(file_path, relative_path)
-            .with_context(|| format!("添加文件到TAR失败: {}", file_path.display()))?;
+/// 配置中的 gzip 压缩级别，未设置时为默认级别 6。
+pub(crate) fn gzip_level(config: &Config) -> Result<flate2::Compression> {
+    match config.gzip_level {
+        None => Ok(flate2::Compression::default()),
+        Some(level @ 1..=9) => Ok(flate2::Compression::new(level)),
+        Some(level) => anyhow::bail!("gzip_level {} 超出范围 1-9", level),
     }
+}
 
-    tar.finish().context("完成TAR.GZ写入失败")?;
+/// 将文件压缩为 TAR.ZST 格式。
+///
+/// # 参数
+/// - `files`: 要压缩的文件路径列表。
+/// - `base_dir`: 基础目录，用于计算文件中相对路径。
+/// - `output_path`: 输出 TAR.ZST 文件的路径。
+/// - `options`: 条目选项，与 [`compress_tar_gz`] 相同。
+/// - `reporter`: 进度回调。
+///
+/// # 返回
+/// `Result<()>`: 成功时返回 `Ok(())`，失败时返回错误信息。
+pub fn compress_tar_zst(
+    files: &[PathBuf],
+    base_dir: &Path,
+    output_path: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    write_archive_file(
+        Format::TarZst,
+        files,
+        base_dir,
+        output_path,
+        options,
+        reporter,
+    )
+}
 
-    Ok(())
+/// 将文件压缩为 TAR.ZST 格式并写入 `out`，只需要顺序写入。
+///
+/// 参数与 [`compress_tar_zst`] 相同，只是输出为任意 `Write` 目标。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`，失败时返回错误信息。
+pub fn compress_tar_zst_to<W: Write>(
+    mut out: W,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<W> {
+    write_archive(
+        &FormatRegistry::default(),
+        Format::TarZst.extension(),
+        ArchiveOutput::stream(&mut out),
+        files,
+        base_dir,
+        options,
+        reporter,
+    )?;
+    Ok(out)
 }
 
 /// 将文件压缩为 7Z 格式。
-
+///
 /// # 参数
 /// - `files`: 要压缩的文件路径列表。
 /// - `base_dir`: 基础目录，用于计算文件中相对路径。
 /// - `output_path`: 输出 7Z 文件的路径。
-/// - `pb`: 进度条。
-
+/// - `options`: 条目选项。`Preserve` 策略下的符号链接按 p7zip 的约定写为
+///   带 Unix 链接属性、内容为链接目标的条目；`[sevenz]` 中启用 `solid` 时所有条目
+///   写入同一个固实数据块。
+/// - `reporter`: 进度回调。
+///
 /// # 返回
 /// `Result<()>`: 成功时返回 `Ok(())`，失败时返回错误信息。
 pub fn compress_7z(
     files: &[PathBuf],
     base_dir: &Path,
     output_path: &Path,
-    pb: &ProgressBar,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
 ) -> Result<()> {
-    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
-    use std::io::Read;
+    write_archive_file(
+        Format::SevenZ,
+        files,
+        base_dir,
+        output_path,
+        options,
+        reporter,
+    )
+}
 
-    let mut sz_writer = SevenZWriter::create(output_path).context("创建7Z文件失败")?;
+/// 将文件压缩为 7Z 格式并写入 `out`，例如内存缓冲区。
+///
+/// 参数与 [`compress_7z`] 相同，只是输出为任意 `Write + Seek` 目标 (7z 的头部在最后回写)。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`，失败时返回错误信息。
+pub fn compress_7z_to<W: Write + Seek>(
+    mut out: W,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<W> {
+    write_archive(
+        &FormatRegistry::default(),
+        Format::SevenZ.extension(),
+        ArchiveOutput::seekable(&mut out),
+        files,
+        base_dir,
+        options,
+        reporter,
+    )?;
+    Ok(out)
+}
 
+/// 将文件压缩为 `format` 格式写入 `output_path`。
+fn write_archive_file(
+    format: Format,
+    files: &[PathBuf],
+    base_dir: &Path,
+    output_path: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    let file = File::create(output_path)
+        .with_context(|| format!("创建压缩包失败: {}", output_path.display()))?;
+    let mut out = io::BufWriter::new(file);
+    write_archive(
+        &FormatRegistry::default(),
+        format.extension(),
+        ArchiveOutput::seekable(&mut out),
+        files,
+        base_dir,
+        options,
+        reporter,
+    )?;
+    out.into_inner()
+        .map_err(|e| e.into_error())
+        .with_context(|| format!("写入压缩包失败: {}", output_path.display()))?;
+    Ok(())
+}
+
+/// 用注册表中名为 `format` 的写入器将文件与内嵌清单写入 `out`，并完成压缩包。
+pub(crate) fn write_archive(
+    registry: &FormatRegistry,
+    format: &str,
+    out: ArchiveOutput<'_>,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    let mut writer = registry.create(format, out, options)?;
     for file_path in files {
-        pb.inc(1);
+        append_file(writer.as_mut(), file_path, base_dir, options, reporter)?;
+    }
+    append_manifest(writer.as_mut(), options)?;
+    writer.finish()
+}
+
+/// 将一个文件（或 `Preserve` 策略下的符号链接）写为条目，目录会被跳过。
+pub(crate) fn append_file(
+    writer: &mut dyn ArchiveWriter,
+    file_path: &Path,
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<()> {
+    Cancelled::check(reporter)?;
+    let name = entry_name(base_dir, &options.mounts, file_path)?;
+    reporter.on_file_start(&name);
+    fault::check(&name, Stage::Read)
+        .with_context(|| format!("打开文件失败: {}", file_path.display()))?;
+
+    if is_preserved_link(file_path, options.symlinks) {
+        let metadata = file_path
+            .symlink_metadata()
+            .with_context(|| format!("读取文件信息失败: {}", file_path.display()))?;
+        let header = EntryHeader {
+            size: Some(0),
+            link_target: Some(link_target(file_path)?),
+            ..file_header(name, &metadata, options)
+        };
+        fault::check(&header.name, Stage::Write)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| writer.start_entry(&header))
+            .with_context(|| format!("添加符号链接失败: {}", file_path.display()))?;
+        return Ok(());
+    }
+
+    let file =
+        File::open(file_path).with_context(|| format!("打开文件失败: {}", file_path.display()))?;
+    let metadata = file
+        .metadata()
+        .with_context(|| format!("读取文件信息失败: {}", file_path.display()))?;
+    if metadata.is_dir() {
+        return Ok(());
+    }
+    let header = file_header(name, &metadata, options);
+    fault::check(&header.name, Stage::Write)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| writer.start_entry(&header))
+        .with_context(|| format!("添加文件失败: {}", file_path.display()))?;
+    let limited = file.take(options.entry_limit());
+    io::copy(&mut ProgressRead::new(limited, reporter), writer)
+        .with_context(|| format!("写入文件内容失败: {}", file_path.display()))?;
+    Ok(())
+}
+
+/// 根据文件元数据与条目选项构造条目头：压缩参数、元数据、修改时间、权限与所有者。
+/// 设置了 `fixed_mtime` 时使用确定性模式：修改时间固定、uid/gid 为 0、权限规范化为 644/755。
+fn file_header(name: String, metadata: &std::fs::Metadata, options: &EntryOptions) -> EntryHeader {
+    let mut header = EntryHeader::new(name, metadata.len().min(options.entry_limit()));
+    header.compression = options.compression_rule(&header.name);
+    header.metadata = options.metadata.lookup(options.rule_path(&header.name));
+    header.mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        header.mode = metadata.mode() & 0o7777;
+        header.uid = u64::from(metadata.uid());
+        header.gid = u64::from(metadata.gid());
+    }
+    #[cfg(not(unix))]
+    if metadata.permissions().readonly() {
+        header.mode = 0o444;
+    }
+    if let Some(mtime) = options.fixed_mtime {
+        header.mtime = mtime;
+        header.uid = 0;
+        header.gid = 0;
+        header.mode = if header.mode & 0o100 != 0 {
+            0o755
+        } else {
+            0o644
+        };
+    }
+    header
+}
+
+/// 配置了内嵌清单时将其写为最后一个条目。
+pub(crate) fn append_manifest(
+    writer: &mut dyn ArchiveWriter,
+    options: &EntryOptions,
+) -> Result<()> {
+    let Some(manifest) = &options.manifest else {
+        return Ok(());
+    };
+    let mut header = EntryHeader::new(archive_manifest::ENTRY_NAME, manifest.len() as u64);
+    header.mtime = options.fixed_mtime.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    });
+    writer
+        .start_entry(&header)
+        .and_then(|_| Ok(writer.write_all(manifest)?))
+        .context("写入内嵌清单失败")
+}
+
+/// 将内存中或动态生成的内容（而不是磁盘上的文件）压缩为压缩包。
+///
+/// 每个条目由名称与内容读取器组成，按顺序写入，适合直接归档数据库导出、渲染生成的报表等内容。
+/// 条目的修改时间为当前时间，权限为 644。
+///
+/// ZIP 条目的内容直接流式写入，并总是启用 ZIP64 扩展，4 GiB 以上的内容也能写入。
+/// TAR 头需要预先知道条目大小，因此 TAR.GZ 与 TAR.ZST 会先缓存每个条目；7z 也会先缓存每个条目再压缩。
+/// 不超过 16 MiB 的内容缓存在内存中，更大的内容转存到系统临时目录中的临时文件
+/// (关闭 `native` 特性时全部缓存在内存中)。
+///
+/// # 示例
+///
+/// ```no_run
+/// use std::io::Cursor;
+/// use ztr_lib::compressor::compress_entries;
+/// use ztr_lib::format::Format;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let entries = vec![
+///         ("export/users.csv".to_string(), Cursor::new(b"id,name\n1,alice\n".to_vec())),
+///         ("README.txt".to_string(), Cursor::new(b"nightly export".to_vec())),
+///     ];
+///     let archive = compress_entries(Format::Zip, entries, Cursor::new(Vec::new()))?;
+///     std::fs::write("export.zip", archive.into_inner())?;
+///     Ok(())
+/// }
+/// ```
+///
+/// # 参数
+/// - `format`: 压缩格式。
+/// - `entries`: `(条目名称, 内容读取器)` 序列，名称使用 `/` 分隔，不能为空、不能是绝对路径或包含 `..`。
+/// - `out`: 压缩包的写入目标，例如 [`File`] 或 `Cursor<Vec<u8>>`。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`；条目名称无效或读写失败时返回错误。
+pub fn compress_entries<I, R, W>(format: Format, entries: I, out: W) -> Result<W>
+where
+    I: IntoIterator<Item = (String, R)>,
+    R: Read,
+    W: Write + Seek,
+{
+    let mtime = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    compress_entries_at(format, entries, out, mtime)
+}
+
+/// 与 [`compress_entries`] 相同，但所有条目使用给定的修改时间 (Unix 时间戳)。
+pub(crate) fn compress_entries_at<I, R, W>(
+    format: Format,
+    entries: I,
+    mut out: W,
+    mtime: u64,
+) -> Result<W>
+where
+    I: IntoIterator<Item = (String, R)>,
+    R: Read,
+    W: Write + Seek,
+{
+    let options = EntryOptions::default();
+    let mut writer = FormatRegistry::default().create(
+        format.extension(),
+        ArchiveOutput::seekable(&mut out),
+        &options,
+    )?;
+    for (name, mut reader) in entries {
+        // 内容长度未知，由写入器决定如何处理 (ZIP64 或先缓存)
+        let header = EntryHeader {
+            size: None,
+            mtime,
+            ..EntryHeader::new(virtual_entry_name(&name)?, 0)
+        };
+        writer
+            .start_entry(&header)
+            .and_then(|_| Ok(io::copy(&mut reader, &mut writer)?))
+            .with_context(|| format!("写入条目失败: {}", header.name))?;
+    }
+    writer.finish()?;
+    Ok(out)
+}
+
+/// 规范化调用者提供的条目名称，拒绝会在解压时写到目标目录之外的名称。
+pub(crate) fn virtual_entry_name(name: &str) -> Result<String> {
+    let normalized = name.replace('\\', "/");
+    let normalized = normalized.trim_start_matches("./");
+    if normalized.is_empty()
+        || normalized.starts_with('/')
+        || normalized.split('/').any(|part| part == "..")
+    {
+        anyhow::bail!("无效的条目名称: {:?}", name);
+    }
+    Ok(normalized.to_string())
+}
 
-        let relative_path = file_path
-            .strip_prefix(base_dir)
-            .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extractor;
+    use std::cell::Cell;
+    use tempfile::tempdir;
 
-        if file_path.is_file() {
-            let mut file = File::open(file_path)
-                .with_context(|| format!("打开文件失败: {}", file_path.display()))?;
-            let mut content = Vec::new();
-            file.read_to_end(&mut content)
-                .with_context(|| format!("读取文件内容失败: {}", file_path.display()))?;
+    #[test]
+    fn test_compress_entries() -> Result<()> {
+        for format in Format::ALL {
+            let entries = vec![
+                (
+                    "export/users.csv".to_string(),
+                    b"id,name\n1,alice\n".as_slice(),
+                ),
+                ("./report.txt".to_string(), b"rendered".as_slice()),
+            ];
+            let data =
+                compress_entries(format, entries, std::io::Cursor::new(Vec::new()))?.into_inner();
 
-            let mut entry = SevenZArchiveEntry::default();
-            entry.name = relative_path.to_string_lossy().replace("\\", "/");
-            entry.size = content.len() as u64;
+            let mut read = Vec::new();
+            crate::reader::for_each_entry_in(std::io::Cursor::new(data), format, |name, r| {
+                let mut content = String::new();
+                r.read_to_string(&mut content)?;
+                read.push((name.to_string(), content));
+                Ok(())
+            })?;
+            assert_eq!(
+                read,
+                vec![
+                    (
+                        "export/users.csv".to_string(),
+                        "id,name\n1,alice\n".to_string()
+                    ),
+                    ("report.txt".to_string(), "rendered".to_string()),
+                ],
+                "{}",
+                format
+            );
+        }
 
-            sz_writer
-                .push_archive_entry(entry, Some(content.as_slice()))
-                .with_context(|| format!("添加文件到7Z失败: {}", file_path.display()))?;
+        for name in ["", "/etc/passwd", "a/../../b"] {
+            let entries = vec![(name.to_string(), b"x".as_slice())];
+            assert!(
+                compress_entries(Format::Zip, entries, std::io::Cursor::new(Vec::new())).is_err()
+            );
         }
+        Ok(())
     }
 
-    sz_writer.finish().context("完成7Z写入失败")?;
+    #[test]
+    fn test_cancellation_token() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::write(dir.path().join("a.txt"), "hello")?;
+            let config = Config {
+                format,
+                ..Default::default()
+            };
+            let output = dir.path().join(format!("out.{}", format.extension()));
+            let token = crate::progress::CancellationToken::new();
+            token.cancel();
 
-    Ok(())
+            let err = compress_files(
+                &config,
+                dir.path(),
+                vec![dir.path().join("a.txt")],
+                &output,
+                &token,
+            )
+            .unwrap_err();
+            assert!(Cancelled::is_cause_of(&err), "{}: {:#}", format, err);
+            assert!(!output.exists(), "{}", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_path() -> Result<()> {
+        let config = Config {
+            format: Format::SevenZ,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let base_dir = Path::new("/data/project");
+        assert_eq!(output_path(&config, base_dir), base_dir.join("out.7z"));
+
+        let config = Config {
+            output_dir: Some("../backups".to_string()),
+            ..config
+        };
+        assert_eq!(
+            output_path(&config, base_dir),
+            base_dir.join("../backups/out.7z")
+        );
+        let config = Config {
+            output_dir: Some("/var/backups".to_string()),
+            ..config
+        };
+        assert_eq!(
+            output_path(&config, base_dir),
+            Path::new("/var/backups/out.7z")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_archives() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        let mut config = Config {
+            format: Format::Zip,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let output = output_path(&config, base_dir);
+        let files = vec![
+            base_dir.join("a.txt"),
+            base_dir.join("out.zip"),
+            base_dir.join("out.tar.gz"),
+            base_dir.join("sub/out.7z"),
+        ];
+
+        let kept = exclude_archives(&config, base_dir, &output, files.clone());
+        assert_eq!(
+            kept,
+            vec![base_dir.join("a.txt"), base_dir.join("sub/out.7z")]
+        );
+
+        config.exclude_previous_archives = Some(false);
+        let kept = exclude_archives(&config, base_dir, &output, files);
+        assert_eq!(kept.len(), 3);
+        assert!(!kept.contains(&output));
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_only() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::create_dir(dir.path().join("img"))?;
+            std::fs::write(dir.path().join("img/logo.png"), "png ".repeat(100))?;
+            std::fs::write(dir.path().join("notes.txt"), "text ".repeat(100))?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                store_only: Some(vec!["*.png".to_string()]),
+                ..Default::default()
+            };
+            let files = vec![
+                dir.path().join("img/logo.png"),
+                dir.path().join("notes.txt"),
+            ];
+            let output = compress_directory(&config, dir.path(), files, &NoopReporter)?.output_path;
+            assert!(
+                crate::verifier::verify_archive(&output)?.is_ok(),
+                "{}",
+                format
+            );
+
+            if format == Format::Zip {
+                let mut archive = zip::ZipArchive::new(File::open(&output)?)?;
+                let png = archive.by_name("img/logo.png")?;
+                assert_eq!(png.compression(), zip::CompressionMethod::Stored);
+                assert_eq!(png.compressed_size(), 400);
+                drop(png);
+                let text = archive.by_name("notes.txt")?;
+                assert_eq!(text.compression(), zip::CompressionMethod::Deflated);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression_rules() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::write(dir.path().join("model.bin"), "bin ".repeat(100))?;
+            std::fs::write(dir.path().join("app.log"), "log ".repeat(100))?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                rules: Some(std::collections::BTreeMap::from([
+                    (
+                        "*.bin".to_string(),
+                        CompressionRule {
+                            method: Some(ZipMethod::Stored),
+                            level: Some(9),
+                        },
+                    ),
+                    (
+                        "*.log".to_string(),
+                        CompressionRule {
+                            method: None,
+                            level: Some(9),
+                        },
+                    ),
+                ])),
+                ..Default::default()
+            };
+            let files = vec![dir.path().join("model.bin"), dir.path().join("app.log")];
+            let output = compress_directory(&config, dir.path(), files, &NoopReporter)?.output_path;
+            assert!(
+                crate::verifier::verify_archive(&output)?.is_ok(),
+                "{}",
+                format
+            );
+
+            if format == Format::Zip {
+                let mut archive = zip::ZipArchive::new(File::open(&output)?)?;
+                let bin = archive.by_name("model.bin")?;
+                assert_eq!(bin.compression(), zip::CompressionMethod::Stored);
+                drop(bin);
+                let log = archive.by_name("app.log")?;
+                assert_eq!(log.compression(), zip::CompressionMethod::Deflated);
+                assert!(log.compressed_size() < 400);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sevenz_options() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::create_dir(dir.path().join("src"))?;
+        let mut files = Vec::new();
+        for i in 0..20 {
+            let path = dir.path().join("src").join(format!("{}.txt", i));
+            std::fs::write(&path, format!("shared content line {}\n", i % 3).repeat(50))?;
+            files.push(path);
+        }
+        files.push(dir.path().join("empty.txt"));
+        std::fs::write(dir.path().join("empty.txt"), "")?;
+
+        let mut sizes = Vec::new();
+        for solid in [false, true] {
+            let config = Config {
+                format: Format::SevenZ,
+                output_name: Some(format!("out-{}", solid)),
+                sevenz: Some(SevenZConfig {
+                    level: Some(9),
+                    solid: Some(solid),
+                    dict_size: Some(ByteSize(100 * 1024)),
+                }),
+                embed_manifest: Some(true),
+                ..Default::default()
+            };
+            let output =
+                compress_directory(&config, dir.path(), files.clone(), &NoopReporter)?.output_path;
+            assert!(crate::verifier::verify_archive(&output)?.is_ok());
+
+            let target = dir.path().join(format!("extracted-{}", solid));
+            extractor::extract_archive(&output, &target, &Default::default())?;
+            for file in &files {
+                let relative = file.strip_prefix(dir.path())?;
+                assert_eq!(std::fs::read(target.join(relative))?, std::fs::read(file)?);
+            }
+            sizes.push(std::fs::metadata(&output)?.len());
+        }
+        assert!(sizes[1] < sizes[0], "{:?}", sizes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_sevenz_options() {
+        let dir = tempdir().unwrap();
+        for sevenz in [
+            SevenZConfig {
+                level: Some(10),
+                ..Default::default()
+            },
+            SevenZConfig {
+                dict_size: Some(ByteSize(2 * 1024 * 1024 * 1024)),
+                ..Default::default()
+            },
+        ] {
+            let config = Config {
+                format: Format::SevenZ,
+                sevenz: Some(sevenz),
+                ..Default::default()
+            };
+            assert!(entry_options(&config, dir.path(), &[], None, None).is_err());
+        }
+    }
+
+    #[test]
+    fn test_zip_method() -> Result<()> {
+        let methods = [
+            ZipMethod::Stored,
+            ZipMethod::Deflate,
+            ZipMethod::Bzip2,
+            ZipMethod::Zstd,
+        ];
+        for method in methods.into_iter().filter(|m| m.is_available()) {
+            let dir = tempdir()?;
+            std::fs::write(dir.path().join("a.txt"), "hello ".repeat(100))?;
+            std::fs::write(dir.path().join("b.log"), "log ".repeat(100))?;
+            let config = Config {
+                format: Format::Zip,
+                output_name: Some("out".to_string()),
+                zip_method: Some(method),
+                rules: Some(std::collections::BTreeMap::from([(
+                    "*.log".to_string(),
+                    CompressionRule {
+                        method: None,
+                        level: Some(0),
+                    },
+                )])),
+                ..Default::default()
+            };
+            let files = vec![dir.path().join("a.txt"), dir.path().join("b.log")];
+            let output = compress_directory(&config, dir.path(), files, &NoopReporter)?.output_path;
+
+            let mut archive = zip::ZipArchive::new(File::open(&output)?)?;
+            for name in ["a.txt", "b.log"] {
+                let mut entry = archive.by_name(name)?;
+                assert_eq!(entry.compression(), method.to_zip(), "{}", method);
+                std::io::copy(&mut entry, &mut std::io::sink())?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_metadata() -> Result<()> {
+        use std::collections::BTreeMap;
+        use std::io::Read;
+
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir(base_dir.join("dist"))?;
+        std::fs::write(base_dir.join("dist/app.js"), "content")?;
+        std::fs::write(base_dir.join("main.rs"), "content")?;
+
+        let metadata = BTreeMap::from([(
+            "dist/*".to_string(),
+            crate::metadata::EntryMetadata {
+                comment: Some("generated".to_string()),
+                pax: Some(BTreeMap::from([(
+                    "ztr.tag".to_string(),
+                    "build".to_string(),
+                )])),
+            },
+        )]);
+        let files = vec![base_dir.join("dist/app.js"), base_dir.join("main.rs")];
+
+        let zip_config = Config {
+            format: Format::Zip,
+            output_name: Some("out".to_string()),
+            metadata: Some(metadata.clone()),
+            ..Default::default()
+        };
+        let output =
+            compress_directory(&zip_config, base_dir, files.clone(), &NoopReporter)?.output_path;
+        let mut archive = zip::ZipArchive::new(File::open(output)?)?;
+        assert_eq!(archive.by_name("dist/app.js")?.comment(), "generated");
+        assert_eq!(archive.by_name("main.rs")?.comment(), "");
+
+        let tar_config = Config {
+            format: Format::TarGz,
+            ..zip_config
+        };
+        let output = compress_directory(&tar_config, base_dir, files, &NoopReporter)?.output_path;
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(output)?));
+        let mut seen = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().to_string();
+            let mut records = Vec::new();
+            if let Some(extensions) = entry.pax_extensions()? {
+                for extension in extensions {
+                    let extension = extension?;
+                    records.push((extension.key()?.to_string(), extension.value()?.to_string()));
+                }
+            }
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            seen.push((path, records));
+        }
+        assert_eq!(
+            seen,
+            vec![
+                (
+                    "dist/app.js".to_string(),
+                    vec![
+                        ("comment".to_string(), "generated".to_string()),
+                        ("ztr.tag".to_string(), "build".to_string()),
+                    ]
+                ),
+                ("main.rs".to_string(), vec![]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressor_builder() -> Result<()> {
+        let dir = tempdir()?;
+        let out_dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir(base_dir.join("src"))?;
+        std::fs::write(base_dir.join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(base_dir.join("debug.log"), "log")?;
+
+        let output = out_dir.path().join("out.zip");
+        let report = Compressor::new(base_dir)
+            .format(Format::Zip)
+            .ignore(["*.log"])
+            .output(&output)
+            .run()?;
+
+        assert_eq!(report.output_path, output);
+        assert_eq!(report.files_count, 1);
+        let archive = zip::ZipArchive::new(File::open(output)?)?;
+        assert_eq!(
+            archive.file_names().collect::<Vec<_>>(),
+            vec!["src/main.rs"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressor_builder_default_output() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "content")?;
+
+        let archive_path = Compressor::new(dir.path())
+            .format(Format::SevenZ)
+            .output_name("named")
+            .run()?
+            .output_path;
+        assert_eq!(archive_path, dir.path().join("named.7z"));
+        assert!(archive_path.is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_dir() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "content")?;
+
+        for _ in 0..2 {
+            let report = Compressor::new(dir.path())
+                .format(Format::Zip)
+                .output_name("out")
+                .output_dir("dist/backups")
+                .on_conflict(ConflictPolicy::Overwrite)
+                .run()?;
+            assert_eq!(report.output_path, dir.path().join("dist/backups/out.zip"));
+            // 第二次运行时输出目录中上一次的压缩包不会被包含进来
+            let archive = zip::ZipArchive::new(File::open(&report.output_path)?)?;
+            assert_eq!(archive.file_names().collect::<Vec<_>>(), vec!["a.txt"]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_to_writer() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::write(base_dir.join("a.txt"), "hello")?;
+        let files = vec![base_dir.join("a.txt")];
+        let options = EntryOptions::from_config(&Config::default(), base_dir, &files)?;
+
+        for format in Format::ALL {
+            let out = std::io::Cursor::new(Vec::new());
+            let out = match format {
+                Format::Zip => compress_zip_to(out, &files, base_dir, &options, &NoopReporter)?,
+                Format::TarGz => {
+                    compress_tar_gz_to(out, &files, base_dir, &options, &NoopReporter)?
+                }
+                Format::TarZst => {
+                    compress_tar_zst_to(out, &files, base_dir, &options, &NoopReporter)?
+                }
+                Format::SevenZ => compress_7z_to(out, &files, base_dir, &options, &NoopReporter)?,
+            };
+
+            let mut read = Vec::new();
+            crate::reader::for_each_entry_in(
+                std::io::Cursor::new(out.into_inner()),
+                format,
+                |name, r| {
+                    let mut content = String::new();
+                    r.read_to_string(&mut content)?;
+                    read.push((name.to_string(), content));
+                    Ok(())
+                },
+            )?;
+            assert_eq!(
+                read,
+                vec![("a.txt".to_string(), "hello".to_string())],
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_to_vec() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir_all(base_dir.join("data"))?;
+        std::fs::write(base_dir.join("data/report.csv"), "id\n1\n")?;
+        let files = vec![base_dir.join("data/report.csv")];
+
+        for format in Format::ALL {
+            let bytes = compress_to_vec(format, base_dir, &files)?;
+            assert!(!bytes.is_empty());
+            let mut names = Vec::new();
+            crate::reader::for_each_entry_in(std::io::Cursor::new(bytes), format, |name, _| {
+                names.push(name.to_string());
+                Ok(())
+            })?;
+            assert_eq!(names, vec!["data/report.csv"], "{}", format);
+        }
+        // 没有在基础目录中写入任何文件
+        assert_eq!(std::fs::read_dir(base_dir)?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_to_stream() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir_all(base_dir.join("src"))?;
+        std::fs::write(base_dir.join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(base_dir.join("README.md"), "# readme")?;
+        let files = vec![base_dir.join("src/main.rs"), base_dir.join("README.md")];
+
+        for format in [Format::Zip, Format::TarGz, Format::TarZst] {
+            let config = Config {
+                format,
+                ..Default::default()
+            };
+            let mut data = Vec::new();
+            let report =
+                compress_to_stream(&config, base_dir, files.clone(), &mut data, &NoopReporter)?;
+            assert_eq!(report.files_count, 2);
+            assert_eq!(report.output_bytes, data.len() as u64);
+
+            let mut read = Vec::new();
+            crate::reader::for_each_entry_in(std::io::Cursor::new(data), format, |name, r| {
+                let mut content = String::new();
+                r.read_to_string(&mut content)?;
+                read.push((name.to_string(), content));
+                Ok(())
+            })?;
+            assert_eq!(
+                read,
+                vec![
+                    ("src/main.rs".to_string(), "fn main() {}".to_string()),
+                    ("README.md".to_string(), "# readme".to_string()),
+                ],
+                "{}",
+                format
+            );
+
+            // 没有文件时仍然写入有效的空压缩包
+            let mut data = Vec::new();
+            compress_to_stream(&config, base_dir, Vec::new(), &mut data, &NoopReporter)?;
+            crate::reader::for_each_entry_in(std::io::Cursor::new(data), format, |_, _| {
+                anyhow::bail!("空压缩包中不应有条目")
+            })?;
+        }
+
+        let config = Config {
+            format: Format::SevenZ,
+            ..Default::default()
+        };
+        assert!(compress_to_stream(&config, base_dir, files, Vec::new(), &NoopReporter).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mapped_sources() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("app");
+        std::fs::create_dir_all(base_dir.join("src"))?;
+        std::fs::create_dir_all(dir.path().join("docs/guide"))?;
+        std::fs::write(base_dir.join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(dir.path().join("docs/guide/intro.md"), "# intro")?;
+        std::fs::write(dir.path().join("LICENSE"), "MIT")?;
+
+        for format in Format::ALL {
+            let config: Config = toml::from_str(&format!(
+                r#"
+                format = "{}"
+                output_name = "bundle"
+                listing = true
+                sources = [".", {{ path = "../docs", prefix = "share/docs" }}, {{ path = "../LICENSE" }}]
+                "#,
+                format
+            ))?;
+            let files = collector::collect_for_config(&config, &base_dir)?;
+            let report = compress_directory(&config, &base_dir, files, &NoopReporter)?;
+
+            let mut names = Vec::new();
+            crate::reader::for_each_entry(&report.output_path, |name, _| {
+                names.push(name.to_string());
+                Ok(())
+            })?;
+            names.sort();
+            assert_eq!(
+                names,
+                vec!["LICENSE", "share/docs/guide/intro.md", "src/main.rs"],
+                "{}",
+                format
+            );
+            let listing_path = crate::listing::listing_path(&report.output_path);
+            let listing = std::fs::read_to_string(&listing_path)?;
+            assert!(listing.contains("share/docs/guide/intro.md"), "{}", format);
+            std::fs::remove_file(&report.output_path)?;
+            std::fs::remove_file(listing_path)?;
+        }
+
+        // 前缀不能跳出压缩包根目录
+        let config: Config = toml::from_str(
+            r#"
+            format = "zip"
+            sources = [{ path = "../docs", prefix = "../docs" }]
+            "#,
+        )?;
+        assert!(collector::mounts(&config, &base_dir).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_wrap_root() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("app");
+        std::fs::create_dir_all(base_dir.join("src"))?;
+        std::fs::create_dir_all(dir.path().join("docs"))?;
+        std::fs::write(base_dir.join("src/main.rs"), "fn main() {}")?;
+        std::fs::write(base_dir.join("src/skip.rs"), "")?;
+        std::fs::write(dir.path().join("docs/intro.md"), "# intro")?;
+        std::fs::write(dir.path().join("LICENSE"), "MIT")?;
+
+        for format in Format::ALL {
+            let config: Config = toml::from_str(&format!(
+                r#"
+                format = "{}"
+                output_name = "myproj"
+                wrap_root = true
+                ignore = ["/src/skip.rs"]
+                store_only = ["/src/*.rs"]
+                sources = [".", {{ path = "../docs" }}, {{ path = "../LICENSE", prefix = "" }}]
+                "#,
+                format
+            ))?;
+            let files = collector::collect_for_config(&config, &base_dir)?;
+            let files = ignore_rules::IgnoreRules::from_config(&config, &base_dir)?
+                .filter_files(files.into_iter())?;
+            let report = compress_directory(&config, &base_dir, files, &NoopReporter)?;
+
+            let mut names = Vec::new();
+            crate::reader::for_each_entry(&report.output_path, |name, _| {
+                names.push(name.to_string());
+                Ok(())
+            })?;
+            names.sort();
+            assert_eq!(
+                names,
+                vec![
+                    "myproj/LICENSE",
+                    "myproj/docs/intro.md",
+                    "myproj/src/main.rs"
+                ],
+                "{}",
+                format
+            );
+            if format == Format::Zip {
+                // 规则按去掉顶层目录后的路径匹配
+                let mut archive = zip::ZipArchive::new(File::open(&report.output_path)?)?;
+                assert_eq!(
+                    archive.by_name("myproj/src/main.rs")?.compression(),
+                    zip::CompressionMethod::Stored
+                );
+                assert_ne!(
+                    archive.by_name("myproj/LICENSE")?.compression(),
+                    zip::CompressionMethod::Stored
+                );
+            }
+            std::fs::remove_file(&report.output_path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_from_archive() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::write(base_dir.join("same.txt"), "same")?;
+        std::fs::write(base_dir.join("changed.txt"), "old")?;
+        let files = vec![base_dir.join("same.txt"), base_dir.join("changed.txt")];
+
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("full".to_string()),
+            ..Default::default()
+        };
+        let reference =
+            compress_directory(&config, base_dir, files.clone(), &NoopReporter)?.output_path;
+
+        std::fs::write(base_dir.join("changed.txt"), "new")?;
+        std::fs::write(base_dir.join("added.txt"), "added")?;
+        let mut files = files;
+        files.push(base_dir.join("added.txt"));
+
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("supplement".to_string()),
+            exclude_from_archive: Some(reference.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let report = compress_directory(&config, base_dir, files, &NoopReporter)?;
+        assert_eq!(report.skipped, vec![base_dir.join("same.txt")]);
+
+        let archive = zip::ZipArchive::new(File::open(report.output_path)?)?;
+        let mut names: Vec<_> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["added.txt", "changed.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_gzip_level() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        let content: String = (0..20000)
+            .map(|i| format!("{} ", i * 7919 % 1000))
+            .collect();
+        std::fs::write(base_dir.join("a.txt"), content)?;
+
+        let mut sizes = Vec::new();
+        for (level, xfl) in [(1, 4), (9, 2)] {
+            let config = Config {
+                format: Format::TarGz,
+                output_name: Some(format!("out-{}", level)),
+                gzip_level: Some(level),
+                ..Default::default()
+            };
+            let output = compress_directory(
+                &config,
+                base_dir,
+                vec![base_dir.join("a.txt")],
+                &NoopReporter,
+            )?
+            .output_path;
+            let data = std::fs::read(&output)?;
+            // gzip 头部的 XFL 字节: 4 为最快，2 为最高压缩率
+            assert_eq!(data[8], xfl);
+            assert!(crate::verifier::verify_archive(&output)?.is_ok());
+            sizes.push(data.len());
+        }
+        assert!(sizes[1] < sizes[0], "{:?}", sizes);
+
+        for level in [0, 10] {
+            let config = Config {
+                format: Format::TarGz,
+                gzip_level: Some(level),
+                ..Default::default()
+            };
+            assert!(
+                compress_directory(
+                    &config,
+                    base_dir,
+                    vec![base_dir.join("a.txt")],
+                    &NoopReporter
+                )
+                .is_err()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_gzip_header_fields() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::write(base_dir.join("a.txt"), "content")?;
+
+        let mut config = Config {
+            format: Format::TarGz,
+            output_name: Some("out".to_string()),
+            on_conflict: Some(ConflictPolicy::Overwrite),
+            ..Default::default()
+        };
+        let output = compress_directory(
+            &config,
+            base_dir,
+            vec![base_dir.join("a.txt")],
+            &NoopReporter,
+        )?
+        .output_path;
+        let data = std::fs::read(&output)?;
+        // 默认: mtime 为 0，操作系统为 255 (未知)，不包含文件名
+        assert_eq!(&data[4..8], &[0, 0, 0, 0]);
+        assert_eq!(data[3] & 0x08, 0);
+        assert_eq!(data[9], 255);
+
+        config.gzip_header = Some(GzipHeader {
+            mtime: Some(1_700_000_000),
+            os: Some(3),
+            filename: Some("out.tar".to_string()),
+        });
+        let output = compress_directory(
+            &config,
+            base_dir,
+            vec![base_dir.join("a.txt")],
+            &NoopReporter,
+        )?
+        .output_path;
+        let decoder = flate2::read::GzDecoder::new(File::open(output)?);
+        let header = decoder.header().unwrap();
+        assert_eq!(header.mtime(), 1_700_000_000);
+        assert_eq!(header.operating_system(), 3);
+        assert_eq!(header.filename(), Some(&b"out.tar"[..]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_injected_faults() -> Result<()> {
+        for format in Format::ALL {
+            for stage in [Stage::Read, Stage::Write] {
+                let dir = tempdir()?;
+                let base_dir = dir.path();
+                std::fs::write(base_dir.join("a.txt"), "content")?;
+                std::fs::write(base_dir.join("b.txt"), "content")?;
+                let config = Config {
+                    format,
+                    output_name: Some("out".to_string()),
+                    ..Default::default()
+                };
+
+                fault::inject("b.txt", stage);
+                let result = compress_directory(
+                    &config,
+                    base_dir,
+                    vec![base_dir.join("a.txt"), base_dir.join("b.txt")],
+                    &NoopReporter,
+                );
+                fault::clear();
+
+                let err = result.expect_err("注入的故障应导致压缩失败");
+                assert!(
+                    format!("{:#}", err).contains("注入的故障: b.txt"),
+                    "{} {:?}: {:#}",
+                    format,
+                    stage,
+                    err
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_directory_skips_own_output() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::write(base_dir.join("a.txt"), "content")?;
+        std::fs::write(base_dir.join("out.zip"), "stale archive")?;
+
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("out".to_string()),
+            on_conflict: Some(ConflictPolicy::Overwrite),
+            ..Default::default()
+        };
+        let files = vec![base_dir.join("a.txt"), base_dir.join("out.zip")];
+        let report = compress_directory(&config, base_dir, files, &NoopReporter)?;
+        assert_eq!(report.files_count, 1);
+        assert_eq!(report.input_bytes, 7);
+        assert_eq!(report.skipped, vec![base_dir.join("out.zip")]);
+        assert_eq!(
+            report.output_bytes,
+            std::fs::metadata(&report.output_path)?.len()
+        );
+        assert!(report.ratio() > 0.0);
+
+        let archive = zip::ZipArchive::new(File::open(report.output_path)?)?;
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive.file_names().collect::<Vec<_>>(), vec!["a.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_conflict() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::write(base_dir.join("a.txt"), "content")?;
+        std::fs::write(base_dir.join("out.tar.gz"), "previous backup")?;
+        let files = vec![base_dir.join("a.txt")];
+
+        let mut config = Config {
+            format: Format::TarGz,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let err = compress_directory(&config, base_dir, files.clone(), &NoopReporter).unwrap_err();
+        assert!(err.to_string().contains("输出文件已存在"));
+        assert_eq!(
+            std::fs::read_to_string(base_dir.join("out.tar.gz"))?,
+            "previous backup"
+        );
+
+        config.on_conflict = Some(ConflictPolicy::Rename);
+        let report = compress_directory(&config, base_dir, files, &NoopReporter)?;
+        assert_eq!(report.output_path, base_dir.join("out-1.tar.gz"));
+        assert_eq!(
+            std::fs::read_to_string(base_dir.join("out.tar.gz"))?,
+            "previous backup"
+        );
+        Ok(())
+    }
+
+    /// 在压缩过程中检查完整的压缩包是否存在的报告器。
+    struct ArchiveWatcher {
+        archive: PathBuf,
+        checks: Cell<usize>,
+        seen: Cell<bool>,
+    }
+
+    impl ProgressReporter for ArchiveWatcher {
+        fn on_bytes(&self, _bytes: u64) {
+            self.checks.set(self.checks.get() + 1);
+            if self.archive.exists() {
+                self.seen.set(true);
+            }
+        }
+
+        fn on_done(&self) {
+            self.on_bytes(0);
+        }
+    }
+
+    #[test]
+    fn test_split_volumes() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("src");
+        std::fs::create_dir_all(&base_dir)?;
+        // 不可压缩的内容，保证压缩包超过多个分卷
+        let mut state = 0x2545_f491_u32;
+        let data: Vec<u8> = (0..20_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        std::fs::write(base_dir.join("data.bin"), &data)?;
+        std::fs::write(base_dir.join("notes.txt"), "notes")?;
+
+        for format in Format::ALL {
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                split_size: Some(ByteSize(4_096)),
+                checksum: Some(vec![ChecksumAlgorithm::Sha256]),
+                ..Default::default()
+            };
+            let files = vec![base_dir.join("data.bin"), base_dir.join("notes.txt")];
+            let archive = base_dir.join(format!("out.{}", format.extension()));
+            let watcher = ArchiveWatcher {
+                archive: archive.clone(),
+                checks: Cell::new(0),
+                seen: Cell::new(false),
+            };
+            let report = compress_directory(&config, &base_dir, files.clone(), &watcher)?;
+            assert_eq!(report.output_path, split::volume_path(&archive, 1));
+            // 直接写入分卷，完整的压缩包从未出现过
+            assert!(watcher.checks.get() > 0);
+            assert!(!watcher.seen.get(), "{}", format);
+            assert!(!archive.exists());
+            let volumes = split::find_volumes(&archive).unwrap();
+            assert!(volumes.len() > 1, "{}", format);
+            assert_eq!(
+                volumes
+                    .iter()
+                    .map(|v| std::fs::metadata(v).map(|m| m.len()))
+                    .sum::<std::io::Result<u64>>()?,
+                report.output_bytes
+            );
+            // 校验和按合并后的压缩包计算
+            assert_eq!(report.checksums.len(), 1);
+            let checks = checksum::verify_sidecars(&volumes[0])?;
+            assert_eq!(checks[0].actual, report.checksums[0].1);
+            assert!(checks[0].is_ok());
+
+            // 第一个分卷已存在时按冲突处理
+            assert!(compress_directory(&config, &base_dir, files.clone(), &NoopReporter).is_err());
+            // 以前的分卷不会被压缩进新的压缩包
+            let all_files = collector::collect_files(&base_dir, None, SymlinkPolicy::Follow)?;
+            assert_eq!(
+                exclude_archives(&config, &base_dir, &archive, all_files).len(),
+                2
+            );
+
+            let target = dir.path().join(format!("out-{}", format));
+            extractor::extract_archive(&volumes[0], &target, &Default::default())?;
+            assert_eq!(std::fs::read(target.join("data.bin"))?, data);
+            assert!(crate::verifier::verify_archive(&archive)?.is_ok());
+            for volume in volumes {
+                std::fs::remove_file(volume)?;
+            }
+        }
+
+        // minisign 只能为完整的文件签名，在写入之前报错
+        let config = Config {
+            output_name: Some("signed".to_string()),
+            split_size: Some(ByteSize(4_096)),
+            sign: Some(signing::SignConfig {
+                tool: SignTool::Minisign,
+                key: None,
+            }),
+            ..Default::default()
+        };
+        let files = vec![base_dir.join("notes.txt")];
+        let err = compress_directory(&config, &base_dir, files, &NoopReporter).unwrap_err();
+        assert!(err.to_string().contains("minisign"));
+        assert!(split::find_volumes(&base_dir.join("signed.tar.gz")).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_mode() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        let files: Vec<PathBuf> = (0..4)
+            .map(|i| base_dir.join(format!("{}.bin", i)))
+            .collect();
+        for file in &files {
+            std::fs::write(file, vec![b'x'; 100 * 1024])?;
+        }
+
+        for format in Format::ALL {
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                sample_bytes: Some(150 * 1024),
+                ..Default::default()
+            };
+            let report = compress_directory(&config, base_dir, files.clone(), &NoopReporter)?;
+            assert_eq!(
+                report.output_path,
+                base_dir.join(format!("out-sample.{}", format))
+            );
+            assert_eq!(report.files_count, 2);
+            assert_eq!(report.input_bytes, 2 * MIN_SAMPLE_BYTES);
+            assert_eq!(report.skipped, vec![files[1].clone(), files[3].clone()]);
+
+            let mut sizes = Vec::new();
+            crate::reader::for_each_entry(&report.output_path, |name, reader| {
+                sizes.push((
+                    name.to_string(),
+                    std::io::copy(reader, &mut std::io::sink())?,
+                ));
+                Ok(())
+            })?;
+            assert_eq!(
+                sizes,
+                vec![
+                    ("0.bin".to_string(), MIN_SAMPLE_BYTES),
+                    ("2.bin".to_string(), MIN_SAMPLE_BYTES)
+                ],
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip64_many_entries() -> Result<()> {
+        let dir = tempdir()?;
+        let count = validate::ZIP_MAX_ENTRIES_WITHOUT_ZIP64 + 100;
+        let files: Vec<PathBuf> = (0..count)
+            .map(|i| {
+                let path = dir.path().join(format!("{:05}.txt", i));
+                std::fs::write(&path, i.to_string()).map(|_| path)
+            })
+            .collect::<std::io::Result<_>>()?;
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("out".to_string()),
+            // 只存储，避免为每个条目初始化 Deflate 压缩器
+            store_only: Some(vec!["*.txt".to_string()]),
+            ..Default::default()
+        };
+        let output = compress_directory(&config, dir.path(), files, &NoopReporter)?.output_path;
+
+        let mut archive = zip::ZipArchive::new(File::open(&output)?)?;
+        assert_eq!(archive.len(), count);
+        let mut content = String::new();
+        archive
+            .by_name(&format!("{:05}.txt", count - 1))?
+            .read_to_string(&mut content)?;
+        assert_eq!(content, (count - 1).to_string());
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "读写 4 GiB 以上的数据，耗时较长"]
+    fn test_zip64_large_file() -> Result<()> {
+        let dir = tempdir()?;
+        let size = crate::archive_writer::ZIP64_SIZE_THRESHOLD + 1024;
+        // 稀疏文件，不占用实际的磁盘空间
+        File::create(dir.path().join("large.bin"))?.set_len(size)?;
+        std::fs::write(dir.path().join("small.txt"), "small")?;
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let files = vec![dir.path().join("large.bin"), dir.path().join("small.txt")];
+        let output = compress_directory(&config, dir.path(), files, &NoopReporter)?.output_path;
+
+        let mut archive = zip::ZipArchive::new(File::open(&output)?)?;
+        assert_eq!(archive.by_name("large.bin")?.size(), size);
+        let mut content = String::new();
+        archive.by_name("small.txt")?.read_to_string(&mut content)?;
+        assert_eq!(content, "small");
+        let report = crate::verifier::verify_archive(&output)?;
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!(report.bytes, size + 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_datetime() {
+        let dt = zip_datetime(1_000_000_000);
+        assert_eq!(
+            (
+                dt.year(),
+                dt.month(),
+                dt.day(),
+                dt.hour(),
+                dt.minute(),
+                dt.second()
+            ),
+            (2001, 9, 9, 1, 46, 40)
+        );
+        assert_eq!(zip_datetime(0).year(), 1980);
+    }
+
+    #[test]
+    fn test_reproducible_archives() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().join("src");
+        std::fs::create_dir_all(base_dir.join("sub"))?;
+        std::fs::write(base_dir.join("a.txt"), "hello")?;
+        std::fs::write(base_dir.join("sub/b.txt"), "world")?;
+
+        for format in Format::ALL {
+            let config = Config {
+                format,
+                reproducible: Some(true),
+                ..Default::default()
+            };
+            let first = dir.path().join(format!("first.{}", format));
+            let second = dir.path().join(format!("second.{}", format));
+
+            let files = vec![base_dir.join("sub/b.txt"), base_dir.join("a.txt")];
+            compress_files(&config, &base_dir, files, &first, &NoopReporter)?;
+
+            // 修改时间和输入顺序的变化不影响输出
+            let mtime = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1 << 30);
+            File::options()
+                .write(true)
+                .open(base_dir.join("a.txt"))?
+                .set_modified(mtime)?;
+            let files = vec![base_dir.join("a.txt"), base_dir.join("sub/b.txt")];
+            compress_files(&config, &base_dir, files, &second, &NoopReporter)?;
+
+            assert_eq!(
+                std::fs::read(&first)?,
+                std::fs::read(&second)?,
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_collisions() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir_all(base_dir.join("docs"))?;
+        std::fs::create_dir_all(base_dir.join("DOCS"))?;
+        let files = vec![base_dir.join("docs/a.txt"), base_dir.join("DOCS/A.txt")];
+        for file in &files {
+            std::fs::write(file, "content")?;
+        }
+        if std::fs::read_dir(base_dir)?.count() < 2 {
+            // 大小写不敏感的文件系统上无法构造冲突
+            return Ok(());
+        }
+
+        let mut config = Config {
+            format: Format::Zip,
+            output_name: Some("out".to_string()),
+            on_name_collision: Some(CollisionPolicy::Error),
+            ..Default::default()
+        };
+        let err = compress_directory(&config, base_dir, files.clone(), &NoopReporter)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("DOCS/A.txt"));
+        assert!(!base_dir.join("out.zip").exists());
+
+        config.on_name_collision = Some(CollisionPolicy::Warn);
+        let report = compress_directory(&config, base_dir, files, &NoopReporter)?;
+        assert_eq!(report.files_count, 2);
+        Ok(())
+    }
 }
@@ -1,30 +1,247 @@
+use crate::checksum::ChecksumAlgorithm;
+use crate::collector::{PathRewrite, Source, SourceEntry, SymlinkPolicy};
+use crate::format::Format;
+use crate::hooks::Hooks;
+use crate::ignore_rules::{self, RuleSource};
+use crate::metadata::EntryMetadata;
+use crate::notify::{NotifyConfig, NotifyOn};
+use crate::retention::RetentionPolicy;
+use crate::rules::{CompressionRule, ZipMethod};
+use crate::signing::SignConfig;
+use crate::template::OutputNaming;
+use crate::uploader::UploadConfig;
+use crate::validate::{CollisionPolicy, ConflictPolicy, DiskSpacePolicy};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use std::path::Path;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// 配置文件的默认文件名。
+pub const CONFIG_FILE_NAME: &str = "ztr.toml";
 
 /// 表示 ZTR 压缩工具的配置。
 /// 包含压缩格式、输出文件名、忽略规则和忽略文件路径。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
-    /// 压缩格式: "zip", "tar.gz", "7z"
-    pub format: String,
+    /// 压缩格式: "zip", "tar.gz", "tar.zst", "7z"
+    pub format: Format,
     /// 输出文件名 (可选)
     pub output_name: Option<String>,
+    /// 压缩包的输出目录 (可选)，相对路径相对于配置文件所在的目录 (配置不来自文件时相对于基础目录)，
+    /// 不存在时会被创建；默认写入基础目录
+    pub output_dir: Option<String>,
+    /// 旧压缩包的保留策略 (可选)，如 `{ keep_last = 5, keep_days = 30 }`，由 `ztr clean` 使用
+    pub retention: Option<RetentionPolicy>,
+    /// `ztr backup` 的备份目录 (可选)，相对路径相对于配置文件所在的目录；
+    /// 默认为基础目录下的 `.ztr-backups`，其中的快照由 `catalog.toml` 索引
+    pub backup_dir: Option<String>,
+    /// 输出文件已存在时的处理方式: "error" (默认), "overwrite", "rename" (追加 `-1`、`-2`……)
+    pub on_conflict: Option<ConflictPolicy>,
+    /// 文件列表的来源: "walk" (遍历目录，默认), "git" (只收集 git 跟踪的文件)
+    pub source: Option<Source>,
+    /// 源路径列表 (可选)，每一项是相对于基础目录的 glob 模式，如 "packages/*/dist"，
+    /// 或放到压缩包中指定前缀下的目录，如 `{ path = "../docs", prefix = "docs" }`
+    pub sources: Option<Vec<SourceEntry>>,
+    /// 文件列表 (可选)：从该文件 (`"-"` 为标准输入) 读取要压缩的路径，每行一个或以 NUL 分隔，
+    /// 代替遍历目录 (类似 `tar -T`)。列表文件与其中的相对路径都相对于基础目录
+    pub files_from: Option<String>,
+    /// 是否把所有条目放在与压缩包同名的顶层目录中 (可选，默认为 false)，
+    /// 如 `myproj/src/main.rs`，解压时不会把文件散落在目标目录中
+    pub wrap_root: Option<bool>,
+    /// 条目名称的改写规则 (可选)，如 `[{ from = "build/output/", to = "" }]`，
+    /// 写入前把以 `from` 开头的条目名称中的这一前缀替换为 `to`，有多条匹配时最长的 `from` 生效
+    pub path_rewrites: Option<Vec<PathRewrite>>,
+    /// 符号链接的处理方式: "preserve" (保存为链接条目), "follow" (跟随，默认), "skip" (忽略)
+    pub symlinks: Option<SymlinkPolicy>,
     /// 忽略规则列表
     pub ignore: Option<Vec<String>>,
-    /// 忽略文件路径
-    pub ignore_file: Option<String>,
-    /// 已经解析的忽略文件内容 (在加载配置时读取并存储)
+    /// 包含规则列表 (可选)，设置后只压缩匹配其中任一模式的文件，如 `["src/", "Cargo.*", "/README.md"]`
+    pub include: Option<Vec<String>>,
+    /// 忽略文件路径，可以是单个路径或路径列表 (如 `[".gitignore", ".ztrignore"]`)，
+    /// 相对路径相对于配置文件所在的目录，多个文件中的规则按列表顺序生效
+    pub ignore_file: Option<IgnoreFiles>,
+    /// 是否同时遵循目录树中各级目录的 `.gitignore` 文件 (默认为 false)
+    pub gitignore: Option<bool>,
+    /// 是否自动读取基础目录中的 `.ztrignore` 文件 (默认为 true)，无需在 `ignore_file` 中列出
+    pub ztrignore: Option<bool>,
+    /// 参考压缩包路径 (可选)，其中路径与内容均相同的文件不会被再次压缩
+    pub exclude_from_archive: Option<String>,
+    /// 是否启用增量压缩 (默认为 false)：每次压缩后在完整压缩包旁写入清单 `<输出名>.manifest`
+    /// (路径、大小、SHA-256)，之后只压缩新增或修改的文件，输出 `<输出名>.incr-<N>.<扩展名>`
+    pub incremental: Option<bool>,
+    /// 是否更新已有的压缩包 (默认为 false)：未变化的条目从原压缩包中直接复制，只压缩新增或修改的文件，
+    /// 已删除的文件被移除。仅支持 zip、tar.gz 与 tar.zst
+    pub update: Option<bool>,
+    /// 只存储不压缩的文件 (可选)，Gitignore 语法的模式列表，如 `["*.png", "*.jpg", "*.mp4", "*.zip"]`。
+    /// 匹配的文件在 zip 中以 stored 方式写入，在 7z 中使用最快的 LZMA2 预设；tar 格式整体压缩，不受影响
+    pub store_only: Option<Vec<String>>,
+    /// 按文件类型调整的压缩参数 (可选)，即 `[rules]`，键为 Gitignore 语法的模式，
+    /// 如 `"*.log" = { level = 9 }`、`"*.bin" = { method = "stored" }`。仅 zip 与 7z 格式使用
+    pub rules: Option<BTreeMap<String, CompressionRule>>,
+    /// zip 条目的压缩方法 (可选): "deflate" (默认), "stored", "bzip2", "zstd"。
+    /// bzip2 与 zstd 需要构建时启用 `zip-bzip2`、`zip-zstd` 特性 (默认启用)
+    pub zip_method: Option<ZipMethod>,
+    /// 7z 压缩参数 (可选)，即 `[sevenz]`，如 `level = 9`、`solid = true`、`dict_size = "64MB"`
+    pub sevenz: Option<SevenZConfig>,
+    /// 是否在压缩包旁写入清单文件 `<压缩包>.list` (路径、大小、SHA-256)
+    pub listing: Option<bool>,
+    /// 是否保留 Windows 文件属性（只读/隐藏/系统）及 ACL，写入压缩包旁的附属文件
+    pub preserve_attributes: Option<bool>,
+    /// 是否排除基础目录下以前生成的同名压缩包 (默认为 true)
+    pub exclude_previous_archives: Option<bool>,
+    /// 条目元数据 (可选)，键为相对路径的 glob 模式，值为注释和 PAX 扩展记录
+    pub metadata: Option<BTreeMap<String, EntryMetadata>>,
+    /// gzip 头部字段 (可选，仅 tar.gz 格式使用)
+    pub gzip_header: Option<GzipHeader>,
+    /// gzip 压缩级别 1–9 (可选，仅 tar.gz 格式使用)，默认为 6；1 最快，适合打包构建产物
+    pub gzip_level: Option<u32>,
+    /// 运行结束后执行的通知命令 (可选)，通知内容以 JSON 写入其标准输入
+    pub notify_command: Option<String>,
+    /// 何时发送通知: "always" (默认), "failure", "success", "never"
+    pub notify_on: Option<NotifyOn>,
+    /// 通知设置 (可选)，即 `[notify]`，如 `webhook_url`
+    pub notify: Option<NotifyConfig>,
+    /// 上传设置 (可选)，即 `[upload]`，如 `[upload.s3]` 中的存储桶、前缀和区域
+    pub upload: Option<UploadConfig>,
+    /// 压缩前后执行的钩子命令 (可选)，即 `[hooks]` 中的 `pre` 和 `post`
+    pub hooks: Option<Hooks>,
+    /// 是否使用预压缩模式 (默认为 false)：不生成压缩包，而是为每个文件在其旁边生成
+    /// `.gz` 和 `.br` 预压缩版本，用于部署静态网站
+    pub precompress: Option<bool>,
+    /// 发现重复或仅大小写不同的条目名称时的处理方式: "error", "warn" (默认), "ignore"
+    pub on_name_collision: Option<CollisionPolicy>,
+    /// 输出目录所在磁盘的可用空间小于压缩包在最坏情况下 (内容无法压缩) 的大小时的处理方式:
+    /// "error" (默认), "warn", "ignore"
+    pub on_low_disk_space: Option<DiskSpacePolicy>,
+    /// 是否生成可复现的压缩包 (默认为 false)：条目按路径排序，修改时间统一为
+    /// `SOURCE_DATE_EPOCH` (未设置时为 0)，并清除 TAR 头中的 uid/gid，相同输入总是得到逐字节相同的输出
+    pub reproducible: Option<bool>,
+    /// 单个文件的最大大小 (可选)，如 `"100MB"` 或字节数，更大的文件被跳过并给出警告
+    pub max_file_size: Option<ByteSize>,
+    /// 单个文件的最小大小 (可选)，更小的文件被跳过
+    pub min_file_size: Option<ByteSize>,
+    /// 是否跳过二进制文件 (默认为 false)：文件开头出现 NUL 字节即视为二进制文件，
+    /// 用于只分享源码与文档
+    pub skip_binary: Option<bool>,
+    /// 分卷大小 (可选)，如 `"2GB"`。设置后压缩包直接写入分卷 `<压缩包>.001`、`.002`……，
+    /// 每个分卷不超过该大小，不会生成完整的压缩包；解压和校验时自动拼接
+    pub split_size: Option<ByteSize>,
+    /// 压缩完成后生成的校验和附属文件 (可选)，如 `["sha256", "blake3"]`，
+    /// 分别写入 `<压缩包>.sha256`、`<压缩包>.blake3`，可用 `ztr verify --checksum` 校验
+    pub checksum: Option<Vec<ChecksumAlgorithm>>,
+    /// 是否在压缩包中写入内嵌清单 `ZTR_MANIFEST.json` (默认为 false)，记录每个文件的大小与
+    /// SHA-256、ztr 版本和所用的配置，`ztr verify` 会据此校验压缩包中的文件
+    pub embed_manifest: Option<bool>,
+    /// 压缩包的签名配置 (可选)，如 `{ tool = "minisign", key = "release.key" }`。
+    /// 压缩完成后调用外部工具生成分离签名 `<压缩包>.minisig` 或 `<压缩包>.sig`
+    pub sign: Option<SignConfig>,
+    /// 只压缩修改时间晚于该时间点的文件 (可选)，可以是时长 (`"24h"`、`"7d"`，从运行时刻往前计算)、
+    /// Unix 时间戳或本地时间的日期 (`"2024-05-01 08:30"`)，用于生成每日增量压缩包
+    pub newer_than: Option<String>,
+    /// 采样模式的字节预算 (可选)。设置后只压缩总量不超过该值的代表性样本，
+    /// 输出文件名追加 `-sample`，用于在正式运行前快速检验配置
+    pub sample_bytes: Option<u64>,
+    /// 是否严格解析配置 (默认为 false)：启用后未知的配置项 (通常是拼写错误) 会导致加载失败，
+    /// 而不是被静默忽略
+    pub strict_parsing: Option<bool>,
+    /// 命名的配置方案 (可选)，如 `[profile.backup]`。方案中的配置项覆盖顶层的同名配置项
+    /// (`ignore` 等列表整体替换)，通过 `ztr compress --profile <名称>` 选择
+    pub profile: Option<BTreeMap<String, toml::Table>>,
+    /// 压缩目标列表 (可选)，即 `[[target]]`，每个目标压缩自己的目录并生成一个压缩包
+    pub target: Option<Vec<Target>>,
+    /// 运行时追加的忽略规则 (如命令行 `--exclude`)，不从配置文件读取
+    #[serde(skip)]
+    pub extra_ignore: Option<Vec<String>>,
+    /// 已经读取的忽略文件路径及其内容 (在加载配置时读取并存储)，顺序与 `ignore_file` 相同
     #[serde(skip)]
-    pub resolved_ignore_file_content: Option<String>,
+    pub resolved_ignore_files: Vec<(PathBuf, String)>,
+    /// 配置文件所在的目录 (从文件加载时设置)，`ignore_file`、`output_dir` 等相对路径以此为基准
+    #[serde(skip)]
+    pub config_dir: Option<PathBuf>,
+}
+
+/// `ignore_file` 配置项：单个路径或路径列表。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IgnoreFiles {
+    /// 单个忽略文件
+    One(String),
+    /// 多个忽略文件，按顺序生效
+    Many(Vec<String>),
+}
+
+impl IgnoreFiles {
+    /// 所有忽略文件的路径（配置中的原文）。
+    pub fn paths(&self) -> &[String] {
+        match self {
+            IgnoreFiles::One(path) => std::slice::from_ref(path),
+            IgnoreFiles::Many(paths) => paths,
+        }
+    }
+}
+
+/// `[[target]]` 中的一个压缩目标。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Target {
+    /// 目标名称 (可选)，用于输出和诊断信息，默认为 `path`
+    pub name: Option<String>,
+    /// 要压缩的目录，相对路径相对于配置文件所在的目录
+    pub path: String,
+    /// 覆盖顶层配置的配置项，如 `format`、`output_name`、`ignore`
+    #[serde(flatten)]
+    pub overrides: toml::Table,
+}
+
+/// 解析后的压缩目标。
+#[derive(Debug, Clone)]
+pub struct ResolvedTarget {
+    /// 目标名称
+    pub name: String,
+    /// 要压缩的目录
+    pub base_dir: PathBuf,
+    /// 目标的配置
+    pub config: Config,
+}
+
+/// 7z 压缩参数配置，用于在内存占用与压缩率之间取舍。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SevenZConfig {
+    /// LZMA2 预设 0–9，默认为 6；9 接近 `7z -mx=9`
+    pub level: Option<u32>,
+    /// 是否固实压缩 (默认为 false)：所有文件写入同一个数据块，小文件多时压缩率明显更高，
+    /// 但解压单个文件需要解压它之前的全部数据，且不再按文件应用 `[rules]` 与 `store_only`
+    pub solid: Option<bool>,
+    /// LZMA2 字典大小 (可选)，如 `"64MB"`，默认由 `level` 决定 (预设 9 为 64MB)。
+    /// 字典越大压缩率越高，压缩和解压所需的内存也越多
+    pub dict_size: Option<ByteSize>,
+}
+
+/// gzip 头部字段配置，未设置的字段使用不泄露机器信息的默认值。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GzipHeader {
+    /// 头部记录的修改时间 (Unix 时间戳)，默认为 0
+    pub mtime: Option<u32>,
+    /// 操作系统标识字节，默认为 255 (未知)
+    pub os: Option<u8>,
+    /// 头部记录的原始文件名，默认不记录
+    pub filename: Option<String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            format: "tar.gz".to_string(),
+            format: Format::TarGz,
             output_name: None,
+            output_dir: None,
+            retention: None,
+            backup_dir: None,
+            on_conflict: None,
+            source: None,
+            sources: None,
+            files_from: None,
+            wrap_root: None,
+            path_rewrites: None,
+            symlinks: None,
             ignore: Some(vec![
                 "target/".to_string(),
                 "*.tmp".to_string(),
@@ -50,8 +267,47 @@ impl Default for Config {
                 ".vscode/".to_string(),
                 "*.iml".to_string(),
             ]),
+            include: None,
             ignore_file: None,
-            resolved_ignore_file_content: None, // 默认初始化为 None
+            gitignore: None,
+            ztrignore: None,
+            exclude_from_archive: None,
+            incremental: None,
+            update: None,
+            store_only: None,
+            rules: None,
+            zip_method: None,
+            sevenz: None,
+            listing: None,
+            preserve_attributes: None,
+            exclude_previous_archives: None,
+            metadata: None,
+            gzip_header: None,
+            gzip_level: None,
+            notify_command: None,
+            notify_on: None,
+            notify: None,
+            upload: None,
+            hooks: None,
+            precompress: None,
+            on_name_collision: None,
+            on_low_disk_space: None,
+            reproducible: None,
+            max_file_size: None,
+            min_file_size: None,
+            skip_binary: None,
+            split_size: None,
+            checksum: None,
+            embed_manifest: None,
+            sign: None,
+            newer_than: None,
+            sample_bytes: None,
+            strict_parsing: None,
+            profile: None,
+            target: None,
+            extra_ignore: None,
+            resolved_ignore_files: Vec::new(),
+            config_dir: None,
         }
     }
 }
@@ -59,7 +315,8 @@ impl Default for Config {
 impl Config {
     /// 从指定路径加载配置文件并解析为 Config 结构体。
     ///
-    /// 如果配置中指定了 `ignore_file`，则会尝试读取其内容并存储在 `resolved_ignore_file_content` 字段中。
+    /// 如果配置中指定了 `ignore_file`，则会读取其内容并存储在 `resolved_ignore_files` 字段中。
+    /// 配置中的相对路径（`ignore_file`、`output_dir`）相对于配置文件所在的目录，而不是当前目录。
     ///
     /// # 参数
     /// - `path`: 配置文件的路径。
@@ -70,26 +327,209 @@ impl Config {
         let content = std::fs::read_to_string(&path)
             .with_context(|| format!("无法读取配置文件: {}", path.as_ref().display()))?;
 
-        let mut config: Config = toml::from_str(&content).with_context(|| "解析配置文件失败")?;
+        let config_dir = path.as_ref().parent().unwrap_or(Path::new(""));
+        Self::parse(&content, Some(config_dir))
+    }
 
-        // 验证压缩格式
-        if !["zip", "tar.gz", "7z"].contains(&config.format.as_str()) {
-            anyhow::bail!(
-                "不支持的压缩格式: {}，支持的格式: zip, tar.gz, 7z",
-                config.format
-            );
+    /// 从 `start_dir` 开始向上查找配置文件 `ztr.toml`（类似 cargo 和 git）。
+    ///
+    /// 依次检查 `start_dir` 及其各级父目录，遇到包含 `.git` 的目录（仓库根目录）时检查完该目录后停止，
+    /// 因此不会用到仓库外（例如主目录中）的配置文件。
+    ///
+    /// # 参数
+    /// - `start_dir`: 开始查找的目录，通常为当前目录。
+    ///
+    /// # 返回
+    /// `Option<PathBuf>`: 找到的配置文件路径，未找到时返回 `None`。
+    pub fn discover(start_dir: &Path) -> Option<PathBuf> {
+        for dir in start_dir.ancestors() {
+            let candidate = dir.join(CONFIG_FILE_NAME);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+            if dir.join(".git").exists() {
+                break;
+            }
         }
+        None
+    }
 
-        // 如果指定了忽略文件路径，则读取其内容
-        if let Some(ignore_file_path) = &config.ignore_file {
-            if let Ok(file_content) = std::fs::read_to_string(ignore_file_path) {
-                config.resolved_ignore_file_content = Some(file_content);
-            }
+    /// 从任意读取器（例如标准输入）加载配置。
+    ///
+    /// # 参数
+    /// - `reader`: 提供 TOML 配置内容的读取器。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 成功时返回解析后的 Config 结构体，失败时返回错误信息。
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> Result<Self> {
+        let mut content = String::new();
+        reader
+            .read_to_string(&mut content)
+            .context("无法读取配置内容")?;
+
+        Self::from_toml_str(&content)
+    }
+
+    /// 从 TOML 字符串解析配置。
+    ///
+    /// 如果配置中指定了 `ignore_file`，则会读取其内容并存储在 `resolved_ignore_files` 字段中，
+    /// 文件无法读取时返回错误。配置中的相对路径相对于当前目录。
+    /// 启用 `strict_parsing` 时，未知的配置项会返回错误。
+    ///
+    /// # 参数
+    /// - `content`: TOML 格式的配置内容。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 成功时返回解析后的 Config 结构体，失败时返回错误信息。
+    pub fn from_toml_str(content: &str) -> Result<Self> {
+        Self::parse(content, None)
+    }
+
+    /// 从 JSON 字符串解析配置，字段与 TOML 配置相同，例如 `{"format": "zip", "ignore": ["*.log"]}`。
+    ///
+    /// 供无法方便地生成 TOML 的调用者使用（如 C FFI 接口），`ignore_file` 与 `strict_parsing`
+    /// 的处理与 [`Config::from_toml_str`] 相同。
+    ///
+    /// # 参数
+    /// - `content`: JSON 格式的配置内容。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 成功时返回解析后的 Config 结构体，失败时返回错误信息。
+    pub fn from_json_str(content: &str) -> Result<Self> {
+        let mut unknown_keys = Vec::new();
+        let mut deserializer = serde_json::Deserializer::from_str(content);
+        let config: Config = serde_ignored::deserialize(&mut deserializer, |path| {
+            unknown_keys.push(path.to_string().replace("?.", ""))
+        })
+        .and_then(|config| deserializer.end().map(|()| config))
+        .map_err(|e| anyhow::anyhow!("解析 JSON 配置失败: {}", e))?;
+        Self::finish(config, unknown_keys, None)
+    }
+
+    /// 解析配置内容，`config_dir` 为配置文件所在的目录（配置不来自文件时为 `None`）。
+    fn parse(content: &str, config_dir: Option<&Path>) -> Result<Self> {
+        // 无效的压缩格式会在反序列化时被拒绝
+        let mut unknown_keys = Vec::new();
+        let config: Config = serde_ignored::deserialize(toml::Deserializer::new(content), |path| {
+            // 可选字段在路径中显示为 `?`，如 `gzip_header.?.nmae`
+            unknown_keys.push(path.to_string().replace("?.", ""))
+        })
+        .map_err(|e| anyhow::anyhow!("解析配置文件失败: {}", e))?;
+        Self::finish(config, unknown_keys, config_dir)
+    }
+
+    /// 反序列化之后的处理：检查未知配置项并读取忽略文件。
+    fn finish(
+        mut config: Config,
+        unknown_keys: Vec<String>,
+        config_dir: Option<&Path>,
+    ) -> Result<Self> {
+        if config.strict_parsing.unwrap_or(false) && !unknown_keys.is_empty() {
+            anyhow::bail!("未知的配置项: {}", unknown_keys.join(", "));
+        }
+        config.config_dir = config_dir.map(Path::to_path_buf);
+
+        // 如果指定了忽略文件路径，则读取其内容；文件不存在时报错，而不是静默丢失所有规则
+        let ignore_files = config.ignore_file.as_ref().map(IgnoreFiles::paths);
+        for ignore_file in ignore_files.into_iter().flatten() {
+            let path = config.resolve_path(ignore_file, Path::new(""));
+            let file_content = std::fs::read_to_string(&path)
+                .with_context(|| format!("无法读取忽略文件: {}", path.display()))?;
+            config.resolved_ignore_files.push((path, file_content));
         }
 
         Ok(config)
     }
 
+    /// 应用命名的配置方案，返回方案中的配置项覆盖顶层配置后的结果。
+    ///
+    /// 方案中的相对路径同样相对于配置文件所在的目录；方案指定了 `ignore_file` 时会重新读取。
+    ///
+    /// # 参数
+    /// - `name`: 方案名称，即 `[profile.<名称>]` 中的名称。
+    ///
+    /// # 返回
+    /// `Result<Config>`: 合并后的配置（不再包含 `profile`）；方案不存在或合并结果无效时返回错误。
+    pub fn with_profile(&self, name: &str) -> Result<Config> {
+        let profiles = self.profile.as_ref();
+        let overrides = profiles.and_then(|p| p.get(name)).with_context(|| {
+            let available: Vec<&str> = profiles
+                .map(|p| p.keys().map(String::as_str).collect())
+                .unwrap_or_default();
+            if available.is_empty() {
+                format!("未找到配置方案: {}（配置中没有定义任何方案）", name)
+            } else {
+                format!(
+                    "未找到配置方案: {}（可用的方案: {}）",
+                    name,
+                    available.join(", ")
+                )
+            }
+        })?;
+
+        self.overlay(overrides, &["profile"])
+            .with_context(|| format!("配置方案 {} 无效", name))
+    }
+
+    /// 获取配置中的所有 `[[target]]` 压缩目标。
+    ///
+    /// 每个目标的配置为目标中的配置项覆盖顶层配置后的结果，`path` 相对于配置文件所在的目录
+    /// (配置不来自文件时相对于 `default_base`)。
+    ///
+    /// # 参数
+    /// - `default_base`: 配置不来自文件时的基准目录。
+    ///
+    /// # 返回
+    /// `Result<Vec<ResolvedTarget>>`: 按配置中的顺序排列的目标；没有定义目标时为空，某个目标的配置无效时返回错误。
+    pub fn targets(&self, default_base: &Path) -> Result<Vec<ResolvedTarget>> {
+        self.target
+            .iter()
+            .flatten()
+            .map(|target| {
+                let name = target.name.clone().unwrap_or_else(|| target.path.clone());
+                let config = self
+                    .overlay(&target.overrides, &["profile", "target"])
+                    .with_context(|| format!("压缩目标 {} 无效", name))?;
+                Ok(ResolvedTarget {
+                    base_dir: self.resolve_path(&target.path, default_base),
+                    name,
+                    config,
+                })
+            })
+            .collect()
+    }
+
+    /// 用 `overrides` 中的配置项覆盖当前配置，并移除 `remove` 中列出的配置项。
+    fn overlay(&self, overrides: &toml::Table, remove: &[&str]) -> Result<Config> {
+        let mut table = match toml::Value::try_from(self).context("无法序列化配置")? {
+            toml::Value::Table(table) => table,
+            _ => unreachable!("配置总是序列化为表"),
+        };
+        for key in remove {
+            table.remove(*key);
+        }
+        for (key, value) in overrides {
+            // 方案和目标不能嵌套
+            if key != "profile" && key != "target" {
+                table.insert(key.clone(), value.clone());
+            }
+        }
+        let content = toml::to_string(&table).context("无法序列化配置")?;
+        Self::parse(&content, self.config_dir.as_deref())
+    }
+
+    /// 解析配置中的相对路径：从文件加载的配置相对于配置文件所在的目录，否则相对于 `default_base`。
+    ///
+    /// # 参数
+    /// - `path`: 配置中的路径，绝对路径原样返回。
+    /// - `default_base`: 配置不来自文件时的基准目录。
+    pub fn resolve_path(&self, path: &str, default_base: &Path) -> PathBuf {
+        self.config_dir
+            .as_deref()
+            .unwrap_or(default_base)
+            .join(path)
+    }
+
     /// 创建一个默认的 `ztr.toml` 配置文件。
     ///
     /// # 参数
@@ -101,7 +541,7 @@ impl Config {
         let config = Config::default();
         let toml_content = toml::to_string_pretty(&config).context("无法序列化默认配置")?;
 
-        let path = output_path.unwrap_or(&Path::new("ztr.toml"));
+        let path = output_path.unwrap_or(Path::new(CONFIG_FILE_NAME));
         std::fs::write(path, toml_content)
             .with_context(|| format!("无法写入配置文件: {}", path.display()))?;
 
@@ -109,49 +549,116 @@ impl Config {
     }
 
     /// 获取压缩包的输出名称。
-    /// 如果配置中指定了输出名称，则使用该名称；否则，使用当前目录名作为输出名称。
+    /// 如果配置中指定了输出名称，则使用该名称；否则，使用基础目录的名称作为输出名称。
+    ///
+    /// 输出名称中可以使用占位符 `{dirname}`、`{date}`、`{time}`、`{timestamp}`、`{git_hash}`、
+    /// `{hostname}`，例如 `backup-{date}-{git_hash}`，未知的占位符按原样保留。详见 [`OutputNaming`]。
+    ///
+    /// # 参数
+    /// - `base_dir`: 要压缩的基础目录。
     ///
     /// # 返回
     /// `String`: 压缩包的输出名称。
-    pub fn get_output_name(&self) -> String {
-        if let Some(name) = &self.output_name {
-            name.clone()
-        } else {
-            // 使用当前目录名
-            match std::env::current_dir() {
-                Ok(path) => path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("archive")
-                    .to_string(),
-                Err(_) => "archive".to_string(),
-            }
-        }
+    pub fn get_output_name(&self, base_dir: &Path) -> String {
+        OutputNaming::new(self.output_name.as_deref(), base_dir).name()
     }
 
-    /// 获取忽略规则列表，优先使用 `ignore` 字段，其次是 `resolved_ignore_file_content`。
+    /// 获取忽略规则列表：依次为 `ignore` 字段、各个忽略文件 (`resolved_ignore_files`)、运行时追加的 `extra_ignore`
+    /// (命令行 `--exclude`)。
+    ///
+    /// 规则的顺序保持稳定（对 gitignore 的优先级和 `!` 否定规则很重要）。重复的规则只保留最后一次出现的位置，
+    /// 因为后面的规则覆盖前面的规则，这样去重不会改变匹配结果。
     pub fn get_ignore_rules(&self) -> Vec<String> {
-        let mut all_rules: HashSet<String> = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut rules: Vec<String> = self
+            .get_ignore_rules_with_source()
+            .into_iter()
+            .rev()
+            .map(|(_, rule)| rule)
+            .filter(|rule| seen.insert(rule.clone()))
+            .collect();
+        rules.reverse();
+        rules
+    }
 
-        if let Some(ignore_list) = &self.ignore {
-            for rule in ignore_list {
-                all_rules.insert(rule.clone());
-            }
+    /// 获取忽略规则及其来源，按 `ignore`、忽略文件、运行时追加的规则 (`--exclude`) 的顺序排列。
+    ///
+    /// 忽略文件中的空行和以 `#` 开头的注释行会被跳过。
+    pub fn get_ignore_rules_with_source(&self) -> Vec<(RuleSource, String)> {
+        let mut rules = Vec::new();
+
+        for rule in self.ignore.iter().flatten() {
+            rules.push((RuleSource::Config, rule.clone()));
         }
 
-        if let Some(content) = &self.resolved_ignore_file_content {
-            for line in content.lines() {
-                let trimmed_line = line.trim();
-                if !trimmed_line.is_empty() && !trimmed_line.starts_with('#') {
-                    all_rules.insert(trimmed_line.to_string());
-                }
+        for (path, content) in &self.resolved_ignore_files {
+            for rule in ignore_rules::ignore_file_lines(content) {
+                rules.push((RuleSource::IgnoreFile(path.clone()), rule.to_string()));
             }
         }
 
-        all_rules.into_iter().collect()
+        for rule in self.extra_ignore.iter().flatten() {
+            rules.push((RuleSource::Cli, rule.clone()));
+        }
+
+        rules
     }
 }
 
+/// 配置中的大小，可以写成带单位的字符串（如 `"100MB"`，见 [`parse_size`]）或字节数。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl Serialize for ByteSize {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bytes(u64),
+            Text(String),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Bytes(bytes) => Ok(ByteSize(bytes)),
+            Raw::Text(text) => parse_size(&text)
+                .map(ByteSize)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// 解析带单位的大小，例如 `100MB`、`1.5G`、`512k`、`4096`（不区分大小写，按 1024 进位）。
+///
+/// # 参数
+/// - `s`: 大小字符串，支持的单位为 B、K/KB/KiB、M/MB/MiB、G/GB/GiB、T/TB/TiB。
+///
+/// # 返回
+/// `Result<u64>`: 对应的字节数。
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("无效的大小: {}", s))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" | "kib" => 1 << 10,
+        "m" | "mb" | "mib" => 1 << 20,
+        "g" | "gb" | "gib" => 1 << 30,
+        "t" | "tb" | "tib" => 1 << 40,
+        _ => anyhow::bail!("无效的大小单位: {}", s),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -166,18 +673,27 @@ mod tests {
             ignore = [".test_ignore"]
             ignore_file = "./test_ignore_file.txt"
         "#;
-        let mut file = NamedTempFile::new()?;
-        write!(file, "{}", toml_content)?;
-        let config = Config::load(file.path())?;
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ztr.toml");
+        std::fs::write(&path, toml_content)?;
+        // 忽略文件相对于配置文件所在的目录
+        std::fs::write(dir.path().join("test_ignore_file.txt"), "*.bak\n")?;
+        let config = Config::load(&path)?;
 
-        assert_eq!(config.format, "zip");
+        assert_eq!(config.format, Format::Zip);
         assert_eq!(config.output_name, Some("test_archive".to_string()));
         assert_eq!(config.ignore, Some(vec![".test_ignore".to_string()]));
         assert_eq!(
             config.ignore_file,
-            Some("./test_ignore_file.txt".to_string())
+            Some(IgnoreFiles::One("./test_ignore_file.txt".to_string()))
+        );
+        assert_eq!(
+            config.resolved_ignore_files,
+            vec![(
+                dir.path().join("./test_ignore_file.txt"),
+                "*.bak\n".to_string()
+            )]
         );
-        assert_eq!(config.resolved_ignore_file_content, None); // ignore_file.txt 不存在，所以内容应为 None
         Ok(())
     }
 
@@ -198,11 +714,17 @@ mod tests {
         write!(config_file, "{}", toml_content)?;
         let config = Config::load(config_file.path())?;
 
-        assert_eq!(config.format, "zip");
-        assert_eq!(config.ignore_file, Some(ignore_file_path.clone()));
+        assert_eq!(config.format, Format::Zip);
         assert_eq!(
-            config.resolved_ignore_file_content,
-            Some("file_from_ignore.txt\n".to_string())
+            config.ignore_file,
+            Some(IgnoreFiles::One(ignore_file_path.clone()))
+        );
+        assert_eq!(
+            config.resolved_ignore_files,
+            vec![(
+                PathBuf::from(&ignore_file_path),
+                "file_from_ignore.txt\n".to_string()
+            )]
         );
 
         let rules = config.get_ignore_rules();
@@ -211,6 +733,109 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_multiple_ignore_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir_all(dir.path().join("project/shared"))?;
+        std::fs::write(dir.path().join("project/.gitignore"), "target/\n")?;
+        std::fs::write(dir.path().join("project/.ztrignore"), "*.mp4\n")?;
+        std::fs::write(dir.path().join("project/shared/team.ignore"), "!keep.mp4\n")?;
+        let config_path = dir.path().join("project/ztr.toml");
+        std::fs::write(
+            &config_path,
+            "format = \"zip\"\nignore_file = [\".gitignore\", \".ztrignore\", \"shared/team.ignore\"]\n",
+        )?;
+
+        let config = Config::load(&config_path)?;
+        assert_eq!(
+            config.get_ignore_rules(),
+            vec!["target/", "*.mp4", "!keep.mp4"]
+        );
+        let sources: Vec<RuleSource> = config
+            .get_ignore_rules_with_source()
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect();
+        assert_eq!(
+            sources[2],
+            RuleSource::IgnoreFile(dir.path().join("project/shared/team.ignore"))
+        );
+
+        // 列表中任一文件不存在时报错
+        std::fs::remove_file(dir.path().join("project/.ztrignore"))?;
+        let error = format!("{:#}", Config::load(&config_path).unwrap_err());
+        assert!(error.contains(".ztrignore"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_from_reader() -> Result<()> {
+        let toml_content = "format = \"7z\"\noutput_name = \"from_stdin\"\n";
+        let config = Config::from_reader(toml_content.as_bytes())?;
+
+        assert_eq!(config.format, Format::SevenZ);
+        assert_eq!(config.output_name, Some("from_stdin".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_parsing() -> Result<()> {
+        let lenient = Config::from_toml_str("format = \"zip\"\nignroe = [\"*.log\"]\n")?;
+        assert!(lenient.ignore.is_none());
+
+        let error = Config::from_toml_str(
+            "format = \"zip\"\nstrict_parsing = true\nignroe = [\"*.log\"]\n[gzip_header]\nnmae = \"x\"\n",
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("ignroe"));
+        assert!(error.to_string().contains("gzip_header.nmae"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_json_str() -> Result<()> {
+        let config = Config::from_json_str(
+            r#"{"format": "tar.gz", "ignore": ["*.log"], "max_file_size": "10MB"}"#,
+        )?;
+        assert_eq!(config.format, Format::TarGz);
+        assert_eq!(config.ignore, Some(vec!["*.log".to_string()]));
+        assert_eq!(config.max_file_size, Some(ByteSize(10 * 1024 * 1024)));
+
+        let error =
+            Config::from_json_str(r#"{"format": "zip", "strict_parsing": true, "ignroe": []}"#)
+                .unwrap_err();
+        assert!(error.to_string().contains("ignroe"));
+        assert!(Config::from_json_str(r#"{"format": "zip"} trailing"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths_relative_to_config_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("conf"))?;
+        std::fs::write(dir.path().join("conf/.ztrignore"), "*.bak\n")?;
+        let config_path = dir.path().join("conf/ztr.toml");
+        std::fs::write(
+            &config_path,
+            "format = \"zip\"\nignore_file = \".ztrignore\"\noutput_dir = \"../backups\"\n",
+        )?;
+
+        // 与当前目录无关
+        let config = Config::load(&config_path)?;
+        assert_eq!(config.resolved_ignore_files[0].1, "*.bak\n");
+        assert_eq!(
+            config.resolve_path("../backups", Path::new("/elsewhere")),
+            dir.path().join("conf/../backups")
+        );
+
+        // 忽略文件不存在时报错，而不是静默丢失规则
+        std::fs::remove_file(dir.path().join("conf/.ztrignore"))?;
+        let error = Config::load(&config_path).unwrap_err();
+        assert!(format!("{:#}", error).contains("无法读取忽略文件"));
+        Ok(())
+    }
+
     #[test]
     fn test_config_load_invalid_format() -> Result<()> {
         let toml_content = r#"
@@ -223,36 +848,154 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_with_profile() -> Result<()> {
+        let config = Config::from_toml_str(
+            r#"
+            format = "tar.gz"
+            output_name = "source"
+            ignore = ["target/", "*.log"]
+
+            [profile.backup]
+            format = "7z"
+            output_name = "backup-{date}"
+            ignore = ["*.tmp"]
+
+            [profile.release]
+            format = "zip"
+            "#,
+        )?;
+
+        let backup = config.with_profile("backup")?;
+        assert_eq!(backup.format, Format::SevenZ);
+        assert_eq!(backup.output_name.as_deref(), Some("backup-{date}"));
+        assert_eq!(backup.get_ignore_rules(), vec!["*.tmp".to_string()]);
+        assert!(backup.profile.is_none());
+
+        // 方案中未设置的配置项沿用顶层配置
+        let release = config.with_profile("release")?;
+        assert_eq!(release.format, Format::Zip);
+        assert_eq!(release.output_name.as_deref(), Some("source"));
+        assert_eq!(release.ignore, config.ignore);
+
+        let error = format!("{:#}", config.with_profile("nightly").unwrap_err());
+        assert!(error.contains("可用的方案: backup, release"));
+
+        let invalid = Config::from_toml_str("format = \"zip\"\n[profile.bad]\nformat = \"rar\"")?;
+        assert!(invalid.with_profile("bad").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_targets() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ztr.toml");
+        std::fs::write(
+            &path,
+            r#"
+            format = "tar.gz"
+            ignore = ["*.log"]
+
+            [[target]]
+            path = "packages/core"
+
+            [[target]]
+            name = "web"
+            path = "packages/web"
+            format = "zip"
+            output_name = "web-{date}"
+            ignore = ["dist/"]
+            "#,
+        )?;
+        let config = Config::load(&path)?;
+        let targets = config.targets(Path::new("/elsewhere"))?;
+        assert_eq!(targets.len(), 2);
+
+        assert_eq!(targets[0].name, "packages/core");
+        assert_eq!(targets[0].base_dir, dir.path().join("packages/core"));
+        assert_eq!(targets[0].config.format, Format::TarGz);
+        assert_eq!(targets[0].config.get_ignore_rules(), vec!["*.log"]);
+        assert!(targets[0].config.target.is_none());
+
+        assert_eq!(targets[1].name, "web");
+        assert_eq!(targets[1].config.format, Format::Zip);
+        assert_eq!(targets[1].config.output_name.as_deref(), Some("web-{date}"));
+        assert_eq!(targets[1].config.get_ignore_rules(), vec!["dist/"]);
+
+        assert!(Config::default().targets(dir.path())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_discover() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = dir.path().join("repo");
+        let nested = repo.join("src/deep");
+        std::fs::create_dir_all(&nested)?;
+        std::fs::create_dir(repo.join(".git"))?;
+        // 仓库外的配置文件不会被使用
+        std::fs::write(dir.path().join(CONFIG_FILE_NAME), "format = \"zip\"")?;
+        assert_eq!(Config::discover(&nested), None);
+
+        std::fs::write(repo.join(CONFIG_FILE_NAME), "format = \"7z\"")?;
+        assert_eq!(Config::discover(&nested), Some(repo.join(CONFIG_FILE_NAME)));
+        // 最近的配置文件优先
+        std::fs::write(nested.join(CONFIG_FILE_NAME), "format = \"zip\"")?;
+        assert_eq!(
+            Config::discover(&nested),
+            Some(nested.join(CONFIG_FILE_NAME))
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_get_output_name_from_config() {
         let config = Config {
-            format: "zip".to_string(),
+            format: Format::Zip,
             output_name: Some("my_custom_name".to_string()),
             ignore: None,
             ignore_file: None,
-            resolved_ignore_file_content: None,
+            ..Default::default()
+        };
+        assert_eq!(config.get_output_name(Path::new(".")), "my_custom_name");
+    }
+
+    #[test]
+    fn test_get_output_name_template() {
+        let config = Config {
+            output_name: Some("backup-{timestamp}-{unknown}".to_string()),
+            ..Default::default()
         };
-        assert_eq!(config.get_output_name(), "my_custom_name");
+        let output_name = config.get_output_name(Path::new("."));
+        let stamp = output_name
+            .strip_prefix("backup-")
+            .and_then(|rest| rest.strip_suffix("-{unknown}"))
+            .unwrap();
+        assert!(stamp.parse::<u64>().is_ok());
     }
 
     #[test]
     fn test_get_output_name_default() {
         let config = Config::default();
-        // 假设当前目录名不是 "archive"，这里需要一个更健壮的测试，可能需要模拟当前目录
-        // 为了测试目的，我们只检查它不是 None 并且不是空字符串
-        let output_name = config.get_output_name();
-        assert!(!output_name.is_empty());
-        assert_ne!(output_name, "archive"); // 除非当前目录是根目录，否则不会是 "archive"
+        // 默认名称取自要压缩的目录，而不是当前工作目录
+        assert_eq!(
+            config.get_output_name(Path::new("/data/other-project")),
+            "other-project"
+        );
+        assert_eq!(config.get_output_name(Path::new("/")), "archive");
     }
 
     #[test]
     fn test_get_ignore_rules_from_config() {
         let config = Config {
-            format: "zip".to_string(),
+            format: Format::Zip,
             output_name: None,
+            source: None,
+            sources: None,
+            symlinks: None,
             ignore: Some(vec!["rule1".to_string(), "rule2".to_string()]),
             ignore_file: None,
-            resolved_ignore_file_content: None,
+            ..Default::default()
         };
         let rules = config.get_ignore_rules();
         assert!(rules.contains(&"rule1".to_string()));
@@ -262,9 +1005,14 @@ mod tests {
 
     #[test]
     fn test_get_ignore_rules_from_resolved_file_content() {
-        let mut config_with_file_content = Config::default();
-        config_with_file_content.resolved_ignore_file_content =
-            Some("# 注释\nrule_from_file1\n\nrule_from_file2".to_string());
+        let mut config_with_file_content = Config {
+            ignore: None,
+            ..Default::default()
+        };
+        config_with_file_content.resolved_ignore_files = vec![(
+            PathBuf::from(".ztrignore"),
+            "# 注释\nrule_from_file1\n\nrule_from_file2".to_string(),
+        )];
         let rules = config_with_file_content.get_ignore_rules();
         assert!(rules.contains(&"rule_from_file1".to_string()));
         assert!(rules.contains(&"rule_from_file2".to_string()));
@@ -274,20 +1022,92 @@ mod tests {
     #[test]
     fn test_get_ignore_rules_priority() {
         let mut config = Config {
-            format: "zip".to_string(),
+            format: Format::Zip,
             output_name: None,
+            source: None,
+            sources: None,
+            symlinks: None,
             ignore: Some(vec![
                 "rule_from_config".to_string(),
                 "common_rule".to_string(),
             ]),
             ignore_file: None,
-            resolved_ignore_file_content: None,
+            ..Default::default()
         };
-        config.resolved_ignore_file_content = Some("rule_from_file\ncommon_rule".to_string());
+        config.resolved_ignore_files = vec![(
+            PathBuf::from(".ztrignore"),
+            "rule_from_file\ncommon_rule".to_string(),
+        )];
+        config.extra_ignore = Some(vec![
+            "rule_from_cli".to_string(),
+            "!common_rule".to_string(),
+        ]);
         let rules = config.get_ignore_rules();
-        assert!(rules.contains(&"rule_from_config".to_string()));
-        assert!(rules.contains(&"rule_from_file".to_string()));
-        assert!(rules.contains(&"common_rule".to_string()));
-        assert_eq!(rules.len(), 3); // "common_rule" 不会重复
+        // 顺序稳定: 配置、忽略文件、命令行，"common_rule" 只保留最后一次出现的位置
+        assert_eq!(
+            rules,
+            vec![
+                "rule_from_config",
+                "rule_from_file",
+                "common_rule",
+                "rule_from_cli",
+                "!common_rule",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_ignore_rules_dedup_keeps_semantics() -> Result<()> {
+        let config = Config {
+            ignore: Some(vec![
+                "*.log".to_string(),
+                "!a.log".to_string(),
+                "*.log".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let rules = config.get_ignore_rules();
+        assert_eq!(rules, vec!["!a.log", "*.log"]);
+
+        let dir = tempfile::tempdir()?;
+        let ignore_rules = crate::ignore_rules::IgnoreRules::new(&rules, dir.path())?;
+        assert!(ignore_rules.should_ignore(&dir.path().join("a.log"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_ignore_rules_with_extra() {
+        let config = Config {
+            ignore: Some(vec!["*.log".to_string()]),
+            extra_ignore: Some(vec!["*.mp4".to_string(), "dist/".to_string()]),
+            ..Default::default()
+        };
+        let rules = config.get_ignore_rules();
+        assert!(rules.contains(&"*.log".to_string()));
+        assert!(rules.contains(&"*.mp4".to_string()));
+        assert!(rules.contains(&"dist/".to_string()));
+        assert_eq!(rules.len(), 3);
+    }
+
+    #[test]
+    fn test_byte_size() -> Result<()> {
+        let config = Config::from_toml_str(
+            "format = \"zip\"\nmax_file_size = \"100MB\"\nmin_file_size = 1\n",
+        )?;
+        assert_eq!(config.max_file_size, Some(ByteSize(100 * 1024 * 1024)));
+        assert_eq!(config.min_file_size, Some(ByteSize(1)));
+        assert!(Config::from_toml_str("format = \"zip\"\nmax_file_size = \"lots\"\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_size() -> Result<()> {
+        assert_eq!(parse_size("4096")?, 4096);
+        assert_eq!(parse_size("100MB")?, 100 * 1024 * 1024);
+        assert_eq!(parse_size("1.5g")?, 3 * 512 * 1024 * 1024);
+        assert_eq!(parse_size("512 KiB")?, 512 * 1024);
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,244 @@
+use crate::archive_writer::{ArchiveOutput, EntryHeader, FormatRegistry};
+use crate::compressor::{self, EntryOptions};
+use crate::format::Format;
+use crate::progress::{ProgressRead, ProgressReporter};
+use crate::reader::{self, EntryInfo};
+use crate::split;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 一次格式转换的统计结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertReport {
+    /// 输出压缩包的路径
+    pub output_path: PathBuf,
+    /// 转换的条目数
+    pub entries: usize,
+    /// 条目内容的总大小 (字节)
+    pub bytes: u64,
+    /// 耗时
+    pub duration: Duration,
+}
+
+/// 将压缩包转换为另一种格式。
+///
+/// 条目从源压缩包中依次读出并直接写入新的压缩包，不会解压到磁盘。
+/// 条目名称、内容、修改时间与权限保持不变；目录与符号链接条目不会被转换。
+///
+/// # 参数
+/// - `input`: 源压缩包，格式由文件内容判断；可以是分卷压缩包。
+/// - `output`: 输出压缩包的路径，已存在时会被覆盖。
+/// - `to`: 输出格式。
+/// - `reporter`: 进度回调。
+///
+/// # 返回
+/// `Result<ConvertReport>`: 转换结果；失败或被取消时删除写了一半的输出文件并返回错误。
+pub fn convert_archive(
+    input: &Path,
+    output: &Path,
+    to: Format,
+    reporter: &dyn ProgressReporter,
+) -> Result<ConvertReport> {
+    let start = Instant::now();
+    let (source, from) = split::open_archive(input)?;
+    if output.exists() && output.canonicalize().ok() == input.canonicalize().ok() {
+        anyhow::bail!("输出文件不能与源压缩包相同: {}", output.display());
+    }
+    reporter.on_message(&format!(
+        "正在转换: {} ({} -> {})",
+        input.display(),
+        from,
+        to
+    ));
+    if let Some(output_dir) = output.parent()
+        && !output_dir.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("创建输出目录失败: {}", output_dir.display()))?;
+    }
+
+    reporter.on_start(0, source.len());
+    let mut report = ConvertReport {
+        output_path: output.to_path_buf(),
+        entries: 0,
+        bytes: 0,
+        duration: Default::default(),
+    };
+    let result = write_entries(source, from, to, output, reporter, &mut report);
+    reporter.on_done();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(output);
+        return Err(e);
+    }
+    report.duration = start.elapsed();
+    Ok(report)
+}
+
+/// 返回转换后压缩包的默认路径：将源压缩包的扩展名替换为新格式的扩展名，
+/// 如 `dist/app.zip` -> `dist/app.tar.zst`。分卷压缩包按合并后的名称计算。
+pub fn default_output(input: &Path, to: Format) -> PathBuf {
+    let input = split::archive_of_first_volume(input).unwrap_or_else(|| input.to_path_buf());
+    let name = input
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lower = name.to_lowercase();
+    let stem = [".tar.gz", ".tgz", ".tar.zst", ".tzst", ".zip", ".7z"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(name.as_str(), |ext| &name[..name.len() - ext.len()]);
+    input.with_file_name(format!("{}.{}", stem, to.extension()))
+}
+
+/// 从源压缩包中依次读出条目，用注册表中 `to` 格式的写入器写入 `output`。
+fn write_entries(
+    source: split::VolumeReader,
+    from: Format,
+    to: Format,
+    output: &Path,
+    reporter: &dyn ProgressReporter,
+    report: &mut ConvertReport,
+) -> Result<()> {
+    let file =
+        File::create(output).with_context(|| format!("创建压缩包失败: {}", output.display()))?;
+    let mut out = BufWriter::new(file);
+    let options = EntryOptions::default();
+    let mut writer = FormatRegistry::default().create(
+        to.extension(),
+        ArchiveOutput::seekable(&mut out),
+        &options,
+    )?;
+    reader::for_each_entry_info_in(source, from, |info, entry| {
+        reporter.on_file_start(&info.name);
+        let header = entry_header(info)?;
+        writer.start_entry(&header)?;
+        io::copy(
+            &mut ProgressRead::new(entry.take(info.size), reporter),
+            &mut writer,
+        )
+        .with_context(|| format!("写入条目失败: {}", header.name))?;
+        report.entries += 1;
+        report.bytes += info.size;
+        Ok(())
+    })?;
+    writer.finish()?;
+    out.into_inner()
+        .map_err(|e| e.into_error())
+        .context("写入压缩包失败")?;
+    Ok(())
+}
+
+/// 按源条目的名称、大小、修改时间与权限构造新条目的头部，缺少的信息使用默认值。
+fn entry_header(info: &EntryInfo) -> Result<EntryHeader> {
+    let mut header = EntryHeader::new(compressor::virtual_entry_name(&info.name)?, info.size);
+    header.mtime = info.mtime.unwrap_or(0);
+    if let Some(mode) = info.mode {
+        header.mode = mode & 0o7777;
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoopReporter;
+    use std::io::Cursor;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_default_output() {
+        assert_eq!(
+            default_output(Path::new("in/app.ZIP"), Format::TarZst),
+            Path::new("in/app.tar.zst")
+        );
+        assert_eq!(
+            default_output(Path::new("app.tgz.001"), Format::SevenZ),
+            Path::new("app.7z")
+        );
+        assert_eq!(
+            default_output(Path::new("download"), Format::Zip),
+            Path::new("download.zip")
+        );
+    }
+
+    #[test]
+    fn test_convert_all_formats() -> Result<()> {
+        let dir = tempdir()?;
+        let source = dir.path().join("source.zip");
+        let entries = vec![
+            ("README.md".to_string(), Cursor::new(b"# app".to_vec())),
+            (
+                "src/main.rs".to_string(),
+                Cursor::new(b"fn main() {}".to_vec()),
+            ),
+        ];
+        let data = compressor::compress_entries(Format::Zip, entries, Cursor::new(Vec::new()))?;
+        std::fs::write(&source, data.into_inner())?;
+
+        let mut previous = source;
+        for to in [Format::TarZst, Format::SevenZ, Format::TarGz, Format::Zip] {
+            let output = dir.path().join(format!("out/converted.{}", to.extension()));
+            let report = convert_archive(&previous, &output, to, &NoopReporter)?;
+            assert_eq!(report.entries, 2);
+            assert_eq!(report.bytes, 17);
+            assert_eq!(Format::detect(&output)?, to);
+
+            let mut converted = Vec::new();
+            reader::for_each_entry(&output, |name, entry| {
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                converted.push((name.to_string(), content));
+                Ok(())
+            })?;
+            assert_eq!(
+                converted,
+                [
+                    ("README.md".to_string(), "# app".to_string()),
+                    ("src/main.rs".to_string(), "fn main() {}".to_string()),
+                ],
+                "{}",
+                to
+            );
+            previous = output;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_preserves_mtime() -> Result<()> {
+        let dir = tempdir()?;
+        let source = dir.path().join("source.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&source)?,
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_mode(0o755);
+        header.set_mtime(1_700_000_000);
+        builder.append_data(&mut header, "run.sh", &b"hi"[..])?;
+        builder.into_inner()?.finish()?;
+
+        for to in [Format::Zip, Format::SevenZ, Format::TarZst] {
+            let output = dir.path().join(format!("out.{}", to.extension()));
+            convert_archive(&source, &output, to, &NoopReporter)?;
+            let (data, format) = split::open_archive(&output)?;
+            let mut infos = Vec::new();
+            reader::for_each_entry_info_in(data, format, |info, _| {
+                infos.push(info.clone());
+                Ok(())
+            })?;
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].mtime, Some(1_700_000_000), "{}", to);
+            assert_eq!(infos[0].mode.map(|m| m & 0o777), Some(0o755), "{}", to);
+        }
+
+        // 不能覆盖源压缩包
+        assert!(convert_archive(&source, &source, Format::TarGz, &NoopReporter).is_err());
+        assert!(source.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::archive_manifest::{self, CountingRead};
+use crate::checksum;
+use crate::collector::{self, SymlinkPolicy};
+use crate::compressor;
+use crate::config::Config;
+use crate::ignore_rules::IgnoreRules;
+use crate::incremental::{Manifest, ManifestDiff};
+use crate::listing::{self, ListingEntry};
+use crate::reader;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// 读取压缩包中每个文件条目的大小与 SHA-256。
+///
+/// 内嵌清单 `ZTR_MANIFEST.json` 不是被压缩的文件，不会被列出。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，可以是分卷压缩包。
+///
+/// # 返回
+/// `Result<Vec<ListingEntry>>`: 按条目顺序排列的记录。
+pub fn snapshot_archive(archive_path: &Path) -> Result<Vec<ListingEntry>> {
+    let mut entries = Vec::new();
+    reader::for_each_entry(archive_path, |name, entry| {
+        if name == archive_manifest::ENTRY_NAME {
+            return Ok(());
+        }
+        let mut counter = CountingRead::new(entry);
+        let sha256 = checksum::sha256_reader(&mut counter)
+            .with_context(|| format!("读取条目失败: {}", name))?;
+        entries.push(ListingEntry {
+            path: name.to_string(),
+            size: counter.count,
+            sha256,
+        });
+        Ok(())
+    })?;
+    Ok(entries)
+}
+
+/// 读取目录中每个文件的大小与 SHA-256。
+///
+/// # 参数
+/// - `dir`: 目录路径。
+/// - `config`: 用于选择文件的配置：与 `ztr compress` 相同地应用源路径、忽略规则，并排除以前的压缩包；
+///   为 `None` 时列出目录中的所有文件。
+///
+/// # 返回
+/// `Result<Vec<ListingEntry>>`: 路径相对于 `dir` 的记录。
+pub fn snapshot_directory(dir: &Path, config: Option<&Config>) -> Result<Vec<ListingEntry>> {
+    if !dir.is_dir() {
+        anyhow::bail!("目录不存在: {}", dir.display());
+    }
+    let (files, mounts) = match config {
+        Some(config) => {
+            let files = collector::collect_for_config(config, dir)?;
+            let files = IgnoreRules::from_config(config, dir)?.filter_files(files.into_iter())?;
+            let output_path = compressor::output_path(config, dir);
+            (
+                compressor::exclude_archives(config, dir, &output_path, files),
+                collector::mounts(config, dir)?,
+            )
+        }
+        None => (
+            collector::collect_all_files(dir, SymlinkPolicy::Follow)?,
+            Vec::new(),
+        ),
+    };
+    listing::build_listing(&files, dir, &mounts)
+}
+
+/// 比较两组文件记录，大小或 SHA-256 不同的文件视为已修改。
+///
+/// # 参数
+/// - `old`: 旧的记录，如上一次发布的压缩包。
+/// - `new`: 新的记录，如当前的源目录。
+///
+/// # 返回
+/// `ManifestDiff`: `new` 相对于 `old` 新增、修改与删除的文件，各自按路径排序。
+pub fn compare(old: &[ListingEntry], new: &[ListingEntry]) -> ManifestDiff {
+    Manifest {
+        increment: 0,
+        entries: old.to_vec(),
+    }
+    .diff(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    fn release(dir: &Path, name: &str, format: Format) -> Result<std::path::PathBuf> {
+        let config = Config {
+            format,
+            output_name: Some(name.to_string()),
+            output_dir: Some("../dist".to_string()),
+            ..Default::default()
+        };
+        let files = collector::collect_for_config(&config, dir)?;
+        Ok(compressor::compress_directory(&config, dir, files, &NoopReporter)?.output_path)
+    }
+
+    #[test]
+    fn test_archive_against_directory() -> Result<()> {
+        for format in Format::ALL {
+            let root = tempdir()?;
+            let src = root.path().join("src");
+            std::fs::create_dir_all(src.join("sub"))?;
+            std::fs::write(src.join("keep.txt"), "same")?;
+            std::fs::write(src.join("sub/edit.txt"), "old")?;
+            std::fs::write(src.join("gone.txt"), "bye")?;
+            let archive = release(&src, "v1", format)?;
+
+            std::fs::write(src.join("sub/edit.txt"), "new")?;
+            std::fs::remove_file(src.join("gone.txt"))?;
+            std::fs::write(src.join("new.txt"), "hi")?;
+
+            let old = snapshot_archive(&archive)?;
+            let new = snapshot_directory(&src, Some(&Config::default()))?;
+            let diff = compare(&old, &new);
+            assert_eq!(diff.added, vec!["new.txt"], "{}", format);
+            assert_eq!(diff.modified, vec!["sub/edit.txt"], "{}", format);
+            assert_eq!(diff.removed, vec!["gone.txt"], "{}", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_against_archive() -> Result<()> {
+        let root = tempdir()?;
+        let src = root.path().join("src");
+        std::fs::create_dir(&src)?;
+        std::fs::write(src.join("a.txt"), "a")?;
+        let v1 = release(&src, "v1", Format::Zip)?;
+        let same = release(&src, "same", Format::TarZst)?;
+        std::fs::write(src.join("a.txt"), "b")?;
+        let v2 = release(&src, "v2", Format::SevenZ)?;
+
+        assert!(compare(&snapshot_archive(&v1)?, &snapshot_archive(&same)?).is_empty());
+        let diff = compare(&snapshot_archive(&v1)?, &snapshot_archive(&v2)?);
+        assert_eq!(diff.modified, vec!["a.txt"]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_directory_without_config() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join(".gitignore"), "*.log\n")?;
+        std::fs::write(dir.path().join("app.log"), "log")?;
+        let all = snapshot_directory(dir.path(), None)?;
+        assert_eq!(all.len(), 2);
+        assert!(snapshot_directory(&dir.path().join("missing"), None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1075 @@
+//! 解压压缩包。
+//!
+//! 压缩包可能来自不可信的来源，因此条目只会写入解压目录内部：绝对路径、包含 `..` 的条目
+//! (zip-slip) 以及指向解压目录之外的符号链接都会被拒绝，写入前还会解析已有的符号链接，
+//! 确认实际位置仍在解压目录内（防止先写入符号链接 `a -> /etc`，再写入 `a/cron.d/x`）。
+//! 解压受信任的压缩包时可以通过 [`ExtractOptions::unsafe_paths`] 关闭这些检查。
+
+use crate::attributes;
+use crate::format::ForeignFormat;
+use crate::guard;
+use crate::ignore_rules;
+use crate::progress::{CancellationToken, Cancelled, ProgressRead};
+use crate::reader::{self, ArchiveReader, EntryKind, TarArchiveReader};
+use crate::split;
+use anyhow::{Context, Result};
+use ignore::gitignore::Gitignore;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// 解压选项。
+///
+/// 默认恢复修改时间和权限，不恢复所有者。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractOptions {
+    /// 是否恢复条目的修改时间。关闭时文件使用解压时的时间，便于构建系统判断文件已更新
+    pub restore_mtime: bool,
+    /// 是否恢复条目的权限位（以及 `.attrs` 附属文件中的文件属性）
+    pub restore_permissions: bool,
+    /// 是否恢复条目的所有者 (uid/gid)，仅在以 root 身份运行时生效
+    pub restore_ownership: bool,
+    /// 只解压这些路径（相对路径，包括目录下的所有条目），为空时解压全部条目
+    pub paths: Vec<String>,
+    /// 只解压匹配这些 glob 模式的条目，语法与忽略规则相同，如 `src/**/*.rs`、`*.md`、`docs/`。
+    /// 与 `paths` 同时设置时条目满足其中之一即被解压；设置后没有任何条目被解压时返回错误
+    pub patterns: Vec<String>,
+    /// 解压时去掉条目名称开头的路径层级数（与 `tar --strip-components` 相同），用于去掉
+    /// `project-1.2.0/` 这样的顶层目录；层级不足的条目被跳过。`paths` 与 `patterns` 按去掉前的名称匹配
+    pub strip_components: usize,
+    /// 取消令牌：请求取消后在处理下一个条目或读取下一块数据前以 [`Cancelled`] 错误结束，
+    /// 写了一半的文件会被删除，已解压完成的文件保留
+    pub cancel: Option<CancellationToken>,
+    /// 是否允许不安全的路径：绝对路径的条目写入对应的绝对路径，包含 `..` 的条目与符号链接
+    /// 可以离开解压目录，写入时跟随已有的符号链接。只应用于受信任的压缩包
+    pub unsafe_paths: bool,
+}
+
+impl ExtractOptions {
+    /// 判断条目是否在 `paths` 选定的范围内（不考虑 `patterns`）。
+    pub fn selects(&self, name: &str) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+        let name = name.trim_start_matches("./").trim_end_matches('/');
+        self.paths.iter().any(|path| {
+            let path = path.trim_start_matches("./").trim_end_matches('/');
+            path.is_empty()
+                || name == path
+                || name
+                    .strip_prefix(path)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            restore_mtime: true,
+            restore_permissions: true,
+            restore_ownership: false,
+            paths: Vec::new(),
+            patterns: Vec::new(),
+            strip_components: 0,
+            cancel: None,
+            unsafe_paths: false,
+        }
+    }
+}
+
+/// 一次解压的统计结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractReport {
+    /// 解压出的文件数
+    pub entries: usize,
+    /// 解压出的总字节数
+    pub bytes: u64,
+}
+
+/// 压缩包条目中与恢复相关的元数据。
+#[derive(Debug, Clone, Default)]
+struct EntryMeta {
+    mtime: Option<SystemTime>,
+    mode: Option<u32>,
+    owner: Option<(u32, u32)>,
+}
+
+/// 将压缩包解压到目标目录。
+///
+/// 如果压缩包旁存在属性附属文件 (`.attrs`/`.acl`) 且启用了权限恢复，解压后还会恢复其中记录的文件属性。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，格式由文件内容判断，无法识别时根据扩展名判断。
+///   也可以是分卷压缩包的第一个分卷 (`.001`)，各分卷会被依次拼接。除了 ztr 生成的格式，
+///   还支持只读的 [`ForeignFormat`]：`tar.bz2`、`tar.xz`、单个 gzip/bzip2/xz 压缩的文件（解压为去掉扩展名的文件）
+///   与 `rar`（需要安装 `unrar`、`bsdtar` 或 `7z` 之一）。
+/// - `target_dir`: 解压目标目录，不存在时会被创建。
+/// - `options`: 解压选项。
+///
+/// # 返回
+/// `Result<ExtractReport>`: 成功时返回解压统计，失败时返回错误信息。
+pub fn extract_archive(
+    archive_path: &Path,
+    target_dir: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractReport> {
+    if let Some(format) = ForeignFormat::detect(archive_path) {
+        let mut extractor = Extractor::new(target_dir, options)?;
+        guard::catch_panic(&format!("解压{}", format), || {
+            extractor.extract_foreign(archive_path, format)
+        })?;
+        return extractor.finish();
+    }
+
+    let (file, format) = split::open_archive(archive_path)?;
+    let mut extractor = Extractor::new(target_dir, options)?;
+    extractor.extract_entries(reader::open_reader(file, format)?)?;
+    let report = extractor.finish()?;
+    if options.restore_permissions {
+        // 分卷压缩包的附属文件以合并后的压缩包命名
+        let archive_path = split::archive_of_first_volume(archive_path)
+            .unwrap_or_else(|| archive_path.to_path_buf());
+        let sidecar = attributes::sidecar_path(&archive_path);
+        if sidecar.exists() {
+            let content = std::fs::read_to_string(&sidecar)
+                .with_context(|| format!("读取属性附属文件失败: {}", sidecar.display()))?;
+            attributes::restore(&attributes::parse_sidecar(&content)?, target_dir)?;
+        }
+        let acl = attributes::acl_sidecar_path(&archive_path);
+        if acl.exists() {
+            attributes::restore_acl(&acl, target_dir)?;
+        }
+    }
+
+    Ok(report)
+}
+
+struct Extractor<'a> {
+    target_dir: &'a Path,
+    /// 解析符号链接后的解压目录
+    root: PathBuf,
+    /// 由 `options.patterns` 构建的匹配器，未设置模式时为 `None`
+    patterns: Option<Gitignore>,
+    options: &'a ExtractOptions,
+    report: ExtractReport,
+}
+
+impl<'a> Extractor<'a> {
+    /// 创建解压目录（已存在时直接使用）。`options.patterns` 中的模式无效时返回错误。
+    fn new(target_dir: &'a Path, options: &'a ExtractOptions) -> Result<Self> {
+        let patterns = if options.patterns.is_empty() {
+            None
+        } else {
+            Some(ignore_rules::pattern_matcher(
+                &options.patterns,
+                Path::new(""),
+                "解压模式",
+            )?)
+        };
+        std::fs::create_dir_all(target_dir)
+            .with_context(|| format!("创建解压目录失败: {}", target_dir.display()))?;
+        let root = std::fs::canonicalize(target_dir)
+            .with_context(|| format!("解析解压目录失败: {}", target_dir.display()))?;
+        Ok(Self {
+            target_dir,
+            root,
+            patterns,
+            options,
+            report: ExtractReport::default(),
+        })
+    }
+
+    /// 结束解压：设置了 `patterns` 但没有解压任何条目时返回错误。
+    fn finish(self) -> Result<ExtractReport> {
+        if !self.options.patterns.is_empty() && self.report.entries == 0 {
+            anyhow::bail!(
+                "压缩包中没有与 {} 匹配的条目",
+                self.options.patterns.join(", ")
+            );
+        }
+        Ok(self.report)
+    }
+
+    /// 条目解压后的名称：未被 `paths` 或 `patterns` 选中，或去掉 `strip_components` 级路径后
+    /// 没有剩余部分时返回 `None`。
+    fn output_name(&self, name: &str, is_dir: bool) -> Option<String> {
+        if !self.selects(name, is_dir) {
+            return None;
+        }
+        let strip = self.options.strip_components;
+        if strip == 0 {
+            return Some(name.to_string());
+        }
+        let rest: PathBuf = Path::new(name)
+            .components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .skip(strip)
+            .collect();
+        let rest = rest.to_string_lossy().replace('\\', "/");
+        (!rest.is_empty()).then_some(rest)
+    }
+
+    /// 判断条目是否被 `paths` 或 `patterns` 选中，两者都未设置时选中所有条目。
+    fn selects(&self, name: &str, is_dir: bool) -> bool {
+        let by_paths = !self.options.paths.is_empty() && self.options.selects(name);
+        match &self.patterns {
+            None => self.options.paths.is_empty() || by_paths,
+            Some(matcher) => {
+                let path = Path::new(name.trim_start_matches("./").trim_end_matches('/'));
+                // 模式只匹配相对路径
+                by_paths
+                    || (!path.has_root()
+                        && matcher
+                            .matched_path_or_any_parents(path, is_dir)
+                            .is_ignore())
+            }
+        }
+    }
+
+    /// 已请求取消时返回 [`Cancelled`] 错误。
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.options.cancel {
+            Some(token) => Cancelled::check(token),
+            None => Ok(()),
+        }
+    }
+
+    /// 解压读取器交出的所有条目，各种格式都经过同样的路径与符号链接检查。
+    fn extract_entries(&mut self, reader: Box<dyn ArchiveReader + '_>) -> Result<()> {
+        reader.read_all_entries(&mut |info, entry| match info.kind {
+            EntryKind::Directory => self.create_dir(&info.name),
+            EntryKind::Symlink => {
+                self.create_symlink(&info.name, info.link_target.as_deref().unwrap_or_default())
+            }
+            EntryKind::File => {
+                let meta = EntryMeta {
+                    mtime: info
+                        .mtime
+                        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+                    mode: info.mode,
+                    owner: info.owner,
+                };
+                self.write_file(&info.name, entry, &meta)
+            }
+        })
+    }
+
+    fn extract_foreign(&mut self, archive_path: &Path, format: ForeignFormat) -> Result<()> {
+        if format == ForeignFormat::Rar {
+            return self.extract_rar(archive_path);
+        }
+        let file = File::open(archive_path)
+            .with_context(|| format!("打开压缩包失败: {}", archive_path.display()))?;
+        let reader = std::io::BufReader::new(file);
+        match format {
+            #[cfg(feature = "tar-bz2")]
+            ForeignFormat::TarBz2 => self.extract_entries(Box::new(TarArchiveReader::new(
+                bzip2::read::MultiBzDecoder::new(reader),
+            ))),
+            #[cfg(not(feature = "tar-bz2"))]
+            ForeignFormat::TarBz2 => anyhow::bail!("解压 tar.bz2 需要启用 tar-bz2 特性"),
+            ForeignFormat::TarXz => self.extract_entries(Box::new(TarArchiveReader::new(
+                lzma_rust2::XzReader::new(reader, true),
+            ))),
+            ForeignFormat::Gzip => self.write_file(
+                &decompressed_name(archive_path, format),
+                &mut flate2::read::MultiGzDecoder::new(reader),
+                &EntryMeta::default(),
+            ),
+            #[cfg(feature = "tar-bz2")]
+            ForeignFormat::Bzip2 => self.write_file(
+                &decompressed_name(archive_path, format),
+                &mut bzip2::read::MultiBzDecoder::new(reader),
+                &EntryMeta::default(),
+            ),
+            #[cfg(not(feature = "tar-bz2"))]
+            ForeignFormat::Bzip2 => anyhow::bail!("解压 bzip2 文件需要启用 tar-bz2 特性"),
+            ForeignFormat::Xz => self.write_file(
+                &decompressed_name(archive_path, format),
+                &mut lzma_rust2::XzReader::new(reader, true),
+                &EntryMeta::default(),
+            ),
+            ForeignFormat::Rar => unreachable!(),
+        }
+    }
+
+    /// 使用外部程序解压 RAR：先解压到解压目录中的临时目录，再像其他格式的条目一样写入解压目录，
+    /// 因此同样受路径检查、`patterns` 与 `strip_components` 的约束。
+    fn extract_rar(&mut self, archive_path: &Path) -> Result<()> {
+        self.check_cancelled()?;
+        let staging = self
+            .target_dir
+            .join(format!(".ztr-rar-{}", std::process::id()));
+        std::fs::create_dir(&staging)
+            .with_context(|| format!("创建临时目录失败: {}", staging.display()))?;
+        let result = run_rar_tool(archive_path, &staging).and_then(|()| self.import_dir(&staging));
+        let _ = std::fs::remove_dir_all(&staging);
+        result
+    }
+
+    /// 将目录中的文件、目录与符号链接作为条目写入解压目录，条目名称为相对于 `dir` 的路径。
+    fn import_dir(&mut self, dir: &Path) -> Result<()> {
+        for entry in WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+            let entry = entry.with_context(|| format!("读取目录失败: {}", dir.display()))?;
+            let relative = entry.path().strip_prefix(dir)?;
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let file_type = entry.file_type();
+            if file_type.is_dir() {
+                self.create_dir(&name)?;
+            } else if file_type.is_symlink() {
+                let target = std::fs::read_link(entry.path())
+                    .with_context(|| format!("读取符号链接失败: {}", entry.path().display()))?;
+                self.create_symlink(&name, &target.to_string_lossy())?;
+            } else {
+                let metadata = entry.metadata()?;
+                #[cfg(unix)]
+                let mode = {
+                    use std::os::unix::fs::PermissionsExt;
+                    Some(metadata.permissions().mode())
+                };
+                #[cfg(not(unix))]
+                let mode = None;
+                let meta = EntryMeta {
+                    mtime: metadata.modified().ok(),
+                    mode,
+                    owner: None,
+                };
+                let mut file = File::open(entry.path())
+                    .with_context(|| format!("读取文件失败: {}", entry.path().display()))?;
+                self.write_file(&name, &mut file, &meta)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 计算条目在目标目录中的路径，拒绝绝对路径和包含 `..` 的条目。
+    ///
+    /// 允许不安全的路径时不做检查，绝对路径保持不变。
+    fn entry_path(&self, name: &str) -> Result<PathBuf> {
+        let relative = Path::new(name);
+        if self.options.unsafe_paths {
+            return Ok(self.target_dir.join(relative));
+        }
+        let is_safe = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !is_safe {
+            anyhow::bail!("拒绝解压不安全的条目路径: {}", name);
+        }
+        Ok(self.target_dir.join(relative))
+    }
+
+    fn create_dir(&self, name: &str) -> Result<()> {
+        self.check_cancelled()?;
+        let Some(name) = self.output_name(name, true) else {
+            return Ok(());
+        };
+        let name = name.as_str();
+        let path = self.entry_path(name)?;
+        self.ensure_inside(&path, name)?;
+        std::fs::create_dir_all(&path).with_context(|| format!("创建目录失败: {}", path.display()))
+    }
+
+    /// 确认 `path` 解析已有的符号链接后仍位于解压目录内。
+    ///
+    /// `path` 尚不存在时检查它最近的已存在的上级目录，之后创建的目录都是普通目录。
+    /// 允许不安全的路径时不做检查。
+    fn ensure_inside(&self, path: &Path, name: &str) -> Result<()> {
+        if self.options.unsafe_paths {
+            return Ok(());
+        }
+        let existing = path
+            .ancestors()
+            .find_map(|ancestor| std::fs::canonicalize(ancestor).ok())
+            .with_context(|| format!("解析条目路径失败: {}", path.display()))?;
+        if !existing.starts_with(&self.root) {
+            anyhow::bail!("拒绝经由符号链接解压到解压目录之外的条目: {}", name);
+        }
+        Ok(())
+    }
+
+    /// 创建符号链接条目。链接目标必须是指向解压目录内部的相对路径。
+    ///
+    /// 非 Unix 平台上创建符号链接通常需要特殊权限，此时链接条目会被跳过。
+    fn create_symlink(&mut self, name: &str, target: &str) -> Result<()> {
+        self.check_cancelled()?;
+        let Some(name) = self.output_name(name, false) else {
+            return Ok(());
+        };
+        let name = name.as_str();
+        let path = self.entry_path(name)?;
+        if let Some(parent) = path.parent() {
+            self.ensure_inside(parent, name)?;
+        }
+
+        // 以链接所在目录为起点按字面解析目标，确认其不会离开解压目录
+        let mut depth: usize = 0;
+        let parent = Path::new(name).parent().unwrap_or(Path::new(""));
+        for component in parent.join(target).components() {
+            match component {
+                _ if self.options.unsafe_paths => break,
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir if depth > 0 => depth -= 1,
+                _ => anyhow::bail!("拒绝解压指向解压目录之外的符号链接: {} -> {}", name, target),
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("创建目录失败: {}", parent.display()))?;
+            }
+            if path.symlink_metadata().is_ok() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("替换已有文件失败: {}", path.display()))?;
+            }
+            std::os::unix::fs::symlink(target, &path)
+                .with_context(|| format!("创建符号链接失败: {}", path.display()))?;
+            self.report.entries += 1;
+        }
+
+        #[cfg(not(unix))]
+        let _ = path;
+
+        Ok(())
+    }
+
+    fn write_file(&mut self, name: &str, reader: &mut dyn Read, meta: &EntryMeta) -> Result<()> {
+        self.check_cancelled()?;
+        let Some(name) = self.output_name(name, false) else {
+            // 读完跳过的条目，7z 固实压缩块中后续条目的数据紧随其后
+            std::io::copy(reader, &mut std::io::sink())
+                .with_context(|| format!("读取条目失败: {}", name))?;
+            return Ok(());
+        };
+        let name = name.as_str();
+        let path = self.entry_path(name)?;
+        if let Some(parent) = path.parent() {
+            self.ensure_inside(parent, name)?;
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("创建目录失败: {}", parent.display()))?;
+        }
+        // 与 tar 相同，已有的符号链接被替换为普通文件，而不是写入链接指向的文件
+        if !self.options.unsafe_paths
+            && path
+                .symlink_metadata()
+                .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("替换已有的符号链接失败: {}", path.display()))?;
+        }
+
+        let mut file =
+            File::create(&path).with_context(|| format!("创建文件失败: {}", path.display()))?;
+        let copied = match &self.options.cancel {
+            Some(token) => std::io::copy(&mut ProgressRead::new(reader, token), &mut file),
+            None => std::io::copy(reader, &mut file),
+        };
+        let bytes = match copied {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // 不留下写了一半的文件
+                drop(file);
+                let _ = std::fs::remove_file(&path);
+                return Err(anyhow::Error::new(e).context(format!("解压条目失败: {}", name)));
+            }
+        };
+
+        if self.options.restore_mtime
+            && let Some(mtime) = meta.mtime
+        {
+            file.set_modified(mtime)
+                .with_context(|| format!("恢复修改时间失败: {}", path.display()))?;
+        }
+        drop(file);
+
+        #[cfg(unix)]
+        if self.options.restore_ownership
+            && is_root()
+            && let Some((uid, gid)) = meta.owner
+        {
+            std::os::unix::fs::chown(&path, Some(uid), Some(gid))
+                .with_context(|| format!("恢复所有者失败: {}", path.display()))?;
+        }
+
+        #[cfg(unix)]
+        if self.options.restore_permissions
+            && let Some(mode) = meta.mode
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode & 0o7777))
+                .with_context(|| format!("恢复权限失败: {}", path.display()))?;
+        }
+
+        self.report.entries += 1;
+        self.report.bytes += bytes;
+        Ok(())
+    }
+}
+
+/// 单个压缩的文件解压后的名称：去掉 `.gz`、`.bz2` 或 `.xz` 扩展名（与 `gunzip` 等相同），
+/// 没有该扩展名时追加 `.out`。
+fn decompressed_name(archive_path: &Path, format: ForeignFormat) -> String {
+    let name = archive_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = format!(".{}", format.extension());
+    let stem = name.len().checked_sub(extension.len()).filter(|&i| {
+        i > 0
+            && name
+                .get(i..)
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(&extension))
+    });
+    match stem {
+        Some(i) => name[..i].to_string(),
+        None => format!("{}.out", name),
+    }
+}
+
+/// 解压 RAR 的外部程序及其命令，按顺序尝试：`unrar`、`bsdtar` (libarchive)、`7z`。
+fn rar_commands(archive_path: &Path, dest: &Path) -> Vec<(&'static str, Command)> {
+    let mut unrar = Command::new("unrar");
+    unrar
+        .args(["x", "-idq", "-o+", "-y"])
+        .arg(archive_path)
+        .arg(format!("{}{}", dest.display(), std::path::MAIN_SEPARATOR));
+    let mut bsdtar = Command::new("bsdtar");
+    bsdtar.arg("-xf").arg(archive_path).arg("-C").arg(dest);
+    let mut sevenz = Command::new("7z");
+    sevenz
+        .args(["x", "-y", "-bd"])
+        .arg(format!("-o{}", dest.display()))
+        .arg(archive_path);
+    vec![("unrar", unrar), ("bsdtar", bsdtar), ("7z", sevenz)]
+}
+
+/// 使用第一个已安装的外部程序将 RAR 解压到 `dest`。
+fn run_rar_tool(archive_path: &Path, dest: &Path) -> Result<()> {
+    for (program, mut cmd) in rar_commands(archive_path, dest) {
+        match cmd.stdout(Stdio::null()).status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => anyhow::bail!("{} 解压 RAR 失败 ({})", program, status),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(anyhow::Error::new(e).context(format!("无法执行 {}", program)));
+            }
+        }
+    }
+    anyhow::bail!("解压 RAR 需要安装 unrar、bsdtar 或 7z 之一")
+}
+
+/// 当前进程是否以 root 身份运行。
+#[cfg(unix)]
+fn is_root() -> bool {
+    // SAFETY: geteuid 没有前置条件，也不会失败。
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// 将 Windows FILETIME (自 1601-01-01 起的 100 纳秒数，7Z 使用) 转换为 `SystemTime`。
+pub(crate) fn filetime_to_system_time(ticks: u64) -> Option<SystemTime> {
+    /// 1601-01-01 (Windows FILETIME 起点) 到 1970-01-01 的秒数。
+    const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+
+    (ticks / 10_000_000)
+        .checked_sub(FILETIME_UNIX_OFFSET)
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// 7-Zip 用于标记高 16 位存放 Unix 权限的属性位。
+pub(crate) const UNIX_EXTENSION: u32 = 0x8000;
+
+/// 读取 7Z 条目属性高 16 位中的 Unix 权限 (p7zip 的约定)。
+pub(crate) fn sevenz_unix_mode(entry: &sevenz_rust::SevenZArchiveEntry) -> Option<u32> {
+    let attributes = entry.windows_attributes();
+    (entry.has_windows_attributes && attributes & UNIX_EXTENSION != 0).then_some(attributes >> 16)
+}
+
+/// 将 ZIP 中的 MS-DOS 时间（视为 UTC）转换为 `SystemTime`。
+pub(crate) fn zip_datetime_to_system_time(dt: zip::DateTime) -> Option<SystemTime> {
+    // 公历日期到 1970-01-01 的天数 (Howard Hinnant 的 days_from_civil 算法)
+    let (year, month, day) = (dt.year() as i64, dt.month() as i64, dt.day() as i64);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs =
+        days * 86_400 + dt.hour() as i64 * 3_600 + dt.minute() as i64 * 60 + dt.second() as i64;
+    u64::try_from(secs)
+        .ok()
+        .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor;
+    use crate::config::Config;
+    use crate::format::Format;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    fn make_archive(format: Format, dir: &Path) -> Result<PathBuf> {
+        let src = dir.join("src");
+        std::fs::create_dir_all(src.join("sub"))?;
+        std::fs::write(src.join("a.txt"), "hello")?;
+        std::fs::write(src.join("sub/b.txt"), "world")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(src.join("a.txt"), std::fs::Permissions::from_mode(0o750))?;
+        }
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(src.join("a.txt"))?
+            .set_modified(mtime)?;
+
+        let config = Config {
+            format,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let files = vec![src.join("a.txt"), src.join("sub/b.txt")];
+        let report = compressor::compress_files(
+            &config,
+            &src,
+            files,
+            &dir.join(format!("out.{}", format.extension())),
+            &NoopReporter,
+        )?;
+        Ok(report.output_path)
+    }
+
+    #[test]
+    fn test_extract_round_trip() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let archive = make_archive(format, dir.path())?;
+            let target = dir.path().join("out");
+
+            let report = extract_archive(&archive, &target, &ExtractOptions::default())?;
+            assert_eq!(report.entries, 2, "{}", format);
+            assert_eq!(report.bytes, 10);
+            assert_eq!(std::fs::read_to_string(target.join("a.txt"))?, "hello");
+            assert_eq!(std::fs::read_to_string(target.join("sub/b.txt"))?, "world");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_cancelled() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let archive = make_archive(format, dir.path())?;
+            let target = dir.path().join("out");
+            let token = CancellationToken::new();
+            let options = ExtractOptions {
+                cancel: Some(token.clone()),
+                ..Default::default()
+            };
+            token.cancel();
+
+            let err = extract_archive(&archive, &target, &options).unwrap_err();
+            assert!(Cancelled::is_cause_of(&err), "{}: {:#}", format, err);
+            assert!(!target.join("a.txt").exists(), "{}", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_selected_paths() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let archive = make_archive(format, dir.path())?;
+            let target = dir.path().join("out");
+            let options = ExtractOptions {
+                paths: vec!["sub/".to_string()],
+                ..Default::default()
+            };
+            let report = extract_archive(&archive, &target, &options)?;
+            assert_eq!(report.entries, 1, "{}", format);
+            assert!(target.join("sub/b.txt").exists());
+            assert!(!target.join("a.txt").exists());
+        }
+
+        let options = ExtractOptions {
+            paths: vec!["./src".to_string(), "a.txt".to_string()],
+            ..Default::default()
+        };
+        assert!(options.selects("src/main.rs"));
+        assert!(options.selects("src/"));
+        assert!(options.selects("a.txt"));
+        assert!(!options.selects("a.txt.bak"));
+        assert!(!options.selects("srcs/x"));
+        Ok(())
+    }
+
+    #[cfg(feature = "tar-bz2")]
+    #[test]
+    fn test_extract_foreign_formats() -> Result<()> {
+        use std::io::Write;
+
+        let dir = tempdir()?;
+        let mut tar = tar::Builder::new(Vec::new());
+        for (name, content) in [("pkg/a.txt", "hello"), ("pkg/sub/b.txt", "world")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, content.as_bytes())?;
+        }
+        let tar = tar.into_inner()?;
+
+        let mut bz2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        bz2.write_all(&tar)?;
+        let mut xz = lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::default())?;
+        xz.write_all(&tar)?;
+        let bz2 = bz2.finish()?;
+        let archives = [
+            ("src.tar.bz2", bz2.clone()),
+            // 扩展名错误的压缩包按内容识别
+            ("src.download", bz2),
+            ("src.tar.xz", xz.finish()?),
+        ];
+        for (name, data) in &archives {
+            let archive = dir.path().join(name);
+            std::fs::write(&archive, data)?;
+            let target = dir.path().join(format!("out-{}", name));
+            let options = ExtractOptions {
+                strip_components: 1,
+                ..Default::default()
+            };
+            let report = extract_archive(&archive, &target, &options)?;
+            assert_eq!(report.entries, 2, "{}", name);
+            assert_eq!(std::fs::read_to_string(target.join("sub/b.txt"))?, "world");
+        }
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(b"plain text")?;
+        let archive = dir.path().join("notes.TXT.gz");
+        std::fs::write(&archive, gz.finish()?)?;
+        let target = dir.path().join("out-gz");
+        let report = extract_archive(&archive, &target, &ExtractOptions::default())?;
+        assert_eq!(report.entries, 1);
+        assert_eq!(
+            std::fs::read_to_string(target.join("notes.TXT"))?,
+            "plain text"
+        );
+
+        // 内容不是 TAR 的 bzip2、xz 数据解压为单个文件
+        let mut bz2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        bz2.write_all(b"bz2 text")?;
+        let mut xz = lzma_rust2::XzWriter::new(Vec::new(), lzma_rust2::XzOptions::default())?;
+        xz.write_all(b"xz text")?;
+        for (name, data, output, content) in [
+            ("data.json.bz2", bz2.finish()?, "data.json", "bz2 text"),
+            ("download", xz.finish()?, "download.out", "xz text"),
+        ] {
+            let archive = dir.path().join(name);
+            std::fs::write(&archive, data)?;
+            let target = dir.path().join(format!("out-{}", name));
+            extract_archive(&archive, &target, &ExtractOptions::default())?;
+            assert_eq!(std::fs::read_to_string(target.join(output))?, content);
+        }
+
+        let gz = ForeignFormat::Gzip;
+        assert_eq!(decompressed_name(Path::new("a/b.gz"), gz), "b");
+        assert_eq!(decompressed_name(Path::new(".gz"), gz), ".gz.out");
+        assert_eq!(
+            decompressed_name(Path::new("a.TXT.BZ2"), ForeignFormat::Bzip2),
+            "a.TXT"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rar_commands() {
+        let commands = rar_commands(Path::new("a.rar"), Path::new("out"));
+        let programs: Vec<&str> = commands.iter().map(|(program, _)| *program).collect();
+        assert_eq!(programs, ["unrar", "bsdtar", "7z"]);
+        let args: Vec<String> = commands[1]
+            .1
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(args, ["-xf", "a.rar", "-C", "out"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_import_dir() -> Result<()> {
+        let dir = tempdir()?;
+        let staging = dir.path().join("staging");
+        std::fs::create_dir_all(staging.join("top/sub"))?;
+        std::fs::write(staging.join("top/sub/a.txt"), "a")?;
+        std::os::unix::fs::symlink("sub/a.txt", staging.join("top/link"))?;
+        let target = dir.path().join("target");
+        let options = ExtractOptions {
+            strip_components: 1,
+            ..Default::default()
+        };
+        let mut extractor = Extractor::new(&target, &options)?;
+        extractor.import_dir(&staging)?;
+        assert_eq!(std::fs::read_to_string(target.join("sub/a.txt"))?, "a");
+        assert_eq!(
+            std::fs::read_link(target.join("link"))?,
+            Path::new("sub/a.txt")
+        );
+        assert_eq!(extractor.report.entries, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_components() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let archive = make_archive(format, dir.path())?;
+            let target = dir.path().join("out");
+            let options = ExtractOptions {
+                strip_components: 1,
+                patterns: vec!["sub/".to_string()],
+                ..Default::default()
+            };
+            let report = extract_archive(&archive, &target, &options)?;
+            assert_eq!(report.entries, 1, "{}", format);
+            assert_eq!(std::fs::read_to_string(target.join("b.txt"))?, "world");
+            assert!(!target.join("sub").exists());
+            assert!(!target.join("a.txt").exists());
+        }
+
+        let dir = tempdir()?;
+        let options = ExtractOptions {
+            strip_components: 1,
+            ..Default::default()
+        };
+        let extractor = Extractor::new(dir.path(), &options)?;
+        assert_eq!(
+            extractor
+                .output_name("./pkg-1.0/src/lib.rs", false)
+                .as_deref(),
+            Some("src/lib.rs")
+        );
+        assert_eq!(extractor.output_name("pkg-1.0/", true), None);
+        assert_eq!(extractor.output_name("README.md", false), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_patterns() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let archive = make_archive(format, dir.path())?;
+            let target = dir.path().join("out");
+            let options = ExtractOptions {
+                patterns: vec!["sub/**/*.txt".to_string()],
+                ..Default::default()
+            };
+            let report = extract_archive(&archive, &target, &options)?;
+            assert_eq!(report.entries, 1, "{}", format);
+            assert!(target.join("sub/b.txt").exists());
+            assert!(!target.join("a.txt").exists());
+
+            let options = ExtractOptions {
+                patterns: vec!["*.md".to_string()],
+                ..Default::default()
+            };
+            let err = extract_archive(&archive, &target, &options).unwrap_err();
+            assert!(
+                err.to_string().contains("没有与 *.md 匹配的条目"),
+                "{:#}",
+                err
+            );
+        }
+
+        let dir = tempdir()?;
+        let options = ExtractOptions {
+            paths: vec!["a.txt".to_string()],
+            patterns: vec!["*.rs".to_string(), "docs/".to_string()],
+            ..Default::default()
+        };
+        let extractor = Extractor::new(dir.path(), &options)?;
+        assert!(extractor.selects("a.txt", false));
+        assert!(extractor.selects("src/deep/lib.rs", false));
+        assert!(extractor.selects("docs/guide/intro.md", false));
+        assert!(!extractor.selects("sub/a.txt", false));
+        assert!(!extractor.selects("README.md", false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_restore_toggles() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = make_archive(Format::TarGz, dir.path())?;
+        let expected = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        let faithful = dir.path().join("faithful");
+        extract_archive(&archive, &faithful, &ExtractOptions::default())?;
+        let metadata = std::fs::metadata(faithful.join("a.txt"))?;
+        assert_eq!(metadata.modified()?, expected);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o750);
+        }
+
+        let fresh = dir.path().join("fresh");
+        let options = ExtractOptions {
+            restore_mtime: false,
+            restore_permissions: false,
+            restore_ownership: false,
+            paths: Vec::new(),
+            patterns: Vec::new(),
+            strip_components: 0,
+            cancel: None,
+            unsafe_paths: false,
+        };
+        extract_archive(&archive, &fresh, &options)?;
+        let metadata = std::fs::metadata(fresh.join("a.txt"))?;
+        assert_ne!(metadata.modified()?, expected);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_ne!(metadata.permissions().mode() & 0o777, 0o750);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_datetime_conversion() -> Result<()> {
+        let dt = zip::DateTime::from_date_and_time(2001, 9, 9, 1, 46, 40)?;
+        assert_eq!(
+            zip_datetime_to_system_time(dt),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000))
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_round_trip() -> Result<()> {
+        use crate::collector::SymlinkPolicy;
+
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let src = dir.path().join("src");
+            std::fs::create_dir_all(src.join("sub"))?;
+            std::fs::write(src.join("sub/a.txt"), "hello")?;
+            std::os::unix::fs::symlink("sub/a.txt", src.join("link.txt"))?;
+
+            let config = Config {
+                format,
+                symlinks: Some(SymlinkPolicy::Preserve),
+                ..Default::default()
+            };
+            let files = vec![src.join("link.txt"), src.join("sub/a.txt")];
+            let archive = dir.path().join(format!("out.{}", format.extension()));
+            let report = compressor::compress_files(&config, &src, files, &archive, &NoopReporter)?;
+            assert_eq!(report.input_bytes, 5);
+
+            let target = dir.path().join("out");
+            extract_archive(&archive, &target, &ExtractOptions::default())?;
+            assert_eq!(
+                std::fs::read_link(target.join("link.txt"))?,
+                PathBuf::from("sub/a.txt"),
+                "{}",
+                format
+            );
+            assert_eq!(std::fs::read_to_string(target.join("link.txt"))?, "hello");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_escaping_symlinks() -> Result<()> {
+        let dir = tempdir()?;
+        let options = ExtractOptions::default();
+        let mut extractor = Extractor::new(dir.path(), &options)?;
+        assert!(
+            extractor
+                .create_symlink("a/link", "../../etc/passwd")
+                .is_err()
+        );
+        assert!(extractor.create_symlink("link", "/etc/passwd").is_err());
+        assert!(extractor.create_symlink("a/link", "../b.txt").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_unsafe_paths() -> Result<()> {
+        let dir = tempdir()?;
+        let options = ExtractOptions::default();
+        let extractor = Extractor::new(dir.path(), &options)?;
+        assert!(extractor.entry_path("../evil.txt").is_err());
+        assert!(extractor.entry_path("/etc/passwd").is_err());
+        assert_eq!(extractor.entry_path("a/b.txt")?, dir.path().join("a/b.txt"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_writes_through_symlinks() -> Result<()> {
+        let dir = tempdir()?;
+        let outside = dir.path().join("outside");
+        let target = dir.path().join("target");
+        std::fs::create_dir_all(&outside)?;
+        std::fs::create_dir_all(&target)?;
+        std::fs::write(outside.join("passwd"), "root")?;
+        let options = ExtractOptions::default();
+        let mut extractor = Extractor::new(&target, &options)?;
+
+        // 条目名称本身安全，但经由解压目录中已有的链接位于外部
+        std::os::unix::fs::symlink(&outside, target.join("etc"))?;
+        let meta = EntryMeta::default();
+        let err = extractor
+            .write_file("etc/cron.d/x", &mut "evil".as_bytes(), &meta)
+            .unwrap_err();
+        assert!(err.to_string().contains("解压目录之外"), "{:#}", err);
+        assert!(extractor.create_dir("etc/sub").is_err());
+        assert!(extractor.create_symlink("etc/link", "x").is_err());
+        assert!(!outside.join("cron.d").exists());
+
+        // 已有的指向外部文件的链接被替换，而不是写入外部文件
+        std::os::unix::fs::symlink(outside.join("passwd"), target.join("passwd"))?;
+        extractor.write_file("passwd", &mut "new".as_bytes(), &meta)?;
+        assert_eq!(std::fs::read_to_string(outside.join("passwd"))?, "root");
+        assert_eq!(std::fs::read_to_string(target.join("passwd"))?, "new");
+        assert!(!target.join("passwd").symlink_metadata()?.is_symlink());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsafe_paths() -> Result<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("target");
+        let options = ExtractOptions {
+            unsafe_paths: true,
+            ..Default::default()
+        };
+        let mut extractor = Extractor::new(&target, &options)?;
+        let meta = EntryMeta::default();
+        extractor.write_file("../escaped.txt", &mut "hi".as_bytes(), &meta)?;
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("escaped.txt"))?,
+            "hi"
+        );
+
+        let absolute = dir.path().join("absolute.txt");
+        extractor.write_file(absolute.to_str().unwrap(), &mut "abs".as_bytes(), &meta)?;
+        assert_eq!(std::fs::read_to_string(&absolute)?, "abs");
+        Ok(())
+    }
+}
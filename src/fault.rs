@@ -0,0 +1,83 @@
+//! 故障注入钩子，用于确定性地测试错误恢复路径。
+//!
+//! 注册函数只在启用 `fault-injection` 特性时可用（ztr 自身的单元测试中始终可用）。
+//! 注册的故障保存在线程局部存储中，只影响在当前线程上执行的压缩操作，
+//! 因此并行运行的测试之间互不干扰。
+//!
+//! ```ignore
+//! use ztr_lib::fault::{self, Stage};
+//!
+//! fault::inject("data/b.txt", Stage::Read);
+//! let result = ztr_lib::compressor::compress_directory(&config, &base_dir, files);
+//! assert!(result.is_err());
+//! fault::clear();
+//! ```
+
+/// 故障发生的阶段。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// 读取源文件时
+    Read,
+    /// 向压缩包写入条目时
+    Write,
+}
+
+#[cfg(any(test, feature = "fault-injection"))]
+thread_local! {
+    static FAULTS: std::cell::RefCell<Vec<(String, Stage)>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// 注册一个故障：当名为 `entry` 的条目（相对路径，使用 `/` 分隔）到达 `stage` 阶段时返回 IO 错误。
+#[cfg(any(test, feature = "fault-injection"))]
+pub fn inject<S: Into<String>>(entry: S, stage: Stage) {
+    FAULTS.with(|faults| faults.borrow_mut().push((entry.into(), stage)));
+}
+
+/// 清除当前线程上注册的所有故障。
+#[cfg(any(test, feature = "fault-injection"))]
+pub fn clear() {
+    FAULTS.with(|faults| faults.borrow_mut().clear());
+}
+
+/// 检查点：如果为该条目与阶段注册了故障，则返回模拟的 IO 错误。
+pub(crate) fn check(entry: &str, stage: Stage) -> std::io::Result<()> {
+    #[cfg(any(test, feature = "fault-injection"))]
+    {
+        let hit = FAULTS.with(|faults| {
+            faults
+                .borrow()
+                .iter()
+                .any(|(name, s)| name == entry && *s == stage)
+        });
+        if hit {
+            return Err(std::io::Error::other(format!(
+                "注入的故障: {} ({:?})",
+                entry, stage
+            )));
+        }
+    }
+
+    #[cfg(not(any(test, feature = "fault-injection")))]
+    let _ = (entry, stage);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_and_clear() {
+        assert!(check("a.txt", Stage::Read).is_ok());
+
+        inject("a.txt", Stage::Read);
+        assert!(check("a.txt", Stage::Read).is_err());
+        assert!(check("a.txt", Stage::Write).is_ok());
+        assert!(check("b.txt", Stage::Read).is_ok());
+
+        clear();
+        assert!(check("a.txt", Stage::Read).is_ok());
+    }
+}
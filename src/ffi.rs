@@ -0,0 +1,171 @@
+//! C FFI 接口，需要启用 `ztr-ffi` 特性。
+//!
+//! 供 C/C++/Go 等语言的工具复用 ztr 的压缩功能（包括忽略规则、源路径与过滤）。
+//! 函数声明见 `include/ztr.h`。构建动态库或静态库：
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ztr-ffi --crate-type cdylib
+//! ```
+//!
+//! 所有函数在出错时返回 `-1`，错误信息通过 [`ztr_last_error`] 获取。错误信息保存在
+//! 线程局部变量中，因此多个线程可以同时调用这些函数。Rust 代码中的 panic 不会越过
+//! FFI 边界，而是作为错误返回。
+
+use crate::compressor::Compressor;
+use crate::config::Config;
+use crate::report::CompressionReport;
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// 压缩 `base_dir`，行为与 `ztr compress` 相同（不执行钩子与上传）。
+///
+/// # 参数
+/// - `config_json`: JSON 格式的配置，字段与 `ztr.toml` 相同（见 [`Config::from_json_str`]），
+///   如 `{"format": "tar.gz", "ignore": ["*.log"]}`；为 NULL 或空字符串时使用默认配置。
+/// - `base_dir`: 要压缩的目录，不能为 NULL。
+/// - `out_path`: 压缩包的完整输出路径；为 NULL 时按配置中的 `output_dir` 与 `output_name` 决定。
+///
+/// # 返回
+/// 成功时返回 `0`，失败时返回 `-1`，错误信息可通过 [`ztr_last_error`] 获取。
+///
+/// # Safety
+/// 非 NULL 的参数必须指向以 NUL 结尾的 UTF-8 字符串，并在调用期间保持有效。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ztr_compress(
+    config_json: *const c_char,
+    base_dir: *const c_char,
+    out_path: *const c_char,
+) -> c_int {
+    run(|| {
+        // SAFETY: 由调用者保证指针有效
+        let (config_json, base_dir, out_path) = unsafe {
+            (
+                c_str(config_json, "config_json")?,
+                c_str(base_dir, "base_dir")?,
+                c_str(out_path, "out_path")?,
+            )
+        };
+        let base_dir = base_dir.context("base_dir 不能为 NULL")?;
+        compress(config_json, base_dir, out_path).map(|_| ())
+    })
+}
+
+/// 返回当前线程中最近一次失败的调用的错误信息（包含完整的错误链），没有错误时返回 NULL。
+///
+/// 返回的字符串由 ztr 持有，在当前线程下一次调用 ztr 函数之前有效，调用者不能释放它。
+#[unsafe(no_mangle)]
+pub extern "C" fn ztr_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|error| error.as_ref().map_or(std::ptr::null(), |e| e.as_ptr()))
+}
+
+fn compress(
+    config_json: Option<&str>,
+    base_dir: &str,
+    out_path: Option<&str>,
+) -> Result<CompressionReport> {
+    let config = match config_json {
+        Some(json) if !json.trim().is_empty() => Config::from_json_str(json)?,
+        _ => Config::default(),
+    };
+    let mut compressor = Compressor::new(base_dir).config(config);
+    if let Some(out_path) = out_path {
+        compressor = compressor.output(out_path);
+    }
+    compressor.run()
+}
+
+/// 执行 `f`，将错误与 panic 转换为返回值 `-1` 并记录错误信息。
+fn run(f: impl FnOnce() -> Result<()>) -> c_int {
+    LAST_ERROR.set(None);
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow::anyhow!("ztr 内部错误 (panic): {}", message))
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            // 错误信息中不应出现 NUL，以防万一将其替换掉
+            let message = format!("{:#}", e).replace('\0', " ");
+            LAST_ERROR.set(CString::new(message).ok());
+            -1
+        }
+    }
+}
+
+/// 将可能为 NULL 的 C 字符串转换为 `&str`。
+///
+/// # Safety
+/// `ptr` 为 NULL 或指向以 NUL 结尾的字符串。
+unsafe fn c_str<'a>(ptr: *const c_char, name: &str) -> Result<Option<&'a str>> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    // SAFETY: 由调用者保证
+    let s = unsafe { CStr::from_ptr(ptr) };
+    s.to_str()
+        .map(Some)
+        .with_context(|| format!("{} 不是有效的 UTF-8 字符串", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn last_error() -> Option<String> {
+        let ptr = ztr_last_error();
+        (!ptr.is_null()).then(|| {
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    #[test]
+    fn test_ztr_compress() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "hello")?;
+        std::fs::write(dir.path().join("debug.log"), "noise")?;
+        let out = dir.path().join("out.tar.gz");
+
+        let config = CString::new(r#"{"format": "tar.gz", "ignore": ["*.log"]}"#)?;
+        let base_dir = CString::new(dir.path().to_str().unwrap())?;
+        let out_path = CString::new(out.to_str().unwrap())?;
+        let status = unsafe { ztr_compress(config.as_ptr(), base_dir.as_ptr(), out_path.as_ptr()) };
+        assert_eq!(status, 0, "{:?}", last_error());
+        assert_eq!(last_error(), None);
+
+        let mut names = Vec::new();
+        crate::reader::for_each_entry(&out, |name, _| {
+            names.push(name.to_string());
+            Ok(())
+        })?;
+        assert_eq!(names, ["a.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ztr_compress_errors() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = CString::new(dir.path().to_str().unwrap())?;
+        let config = CString::new(r#"{"format": "rar"}"#)?;
+        let status = unsafe { ztr_compress(config.as_ptr(), base_dir.as_ptr(), std::ptr::null()) };
+        assert_eq!(status, -1);
+        assert!(last_error().unwrap().contains("解析 JSON 配置失败"));
+
+        let status = unsafe { ztr_compress(std::ptr::null(), std::ptr::null(), std::ptr::null()) };
+        assert_eq!(status, -1);
+        assert!(last_error().unwrap().contains("base_dir"));
+        Ok(())
+    }
+}
@@ -0,0 +1,664 @@
+use crate::config::Config;
+use crate::ignore_rules::IgnoreRules;
+use crate::report::human_size;
+use crate::template;
+use anyhow::{Context, Result};
+use std::fs::Metadata;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 文件过滤器：决定收集到的文件是否写入压缩包。
+///
+/// 内置的过滤器有忽略规则 ([`IgnoreRules`])、[`ModifiedSinceFilter`]、[`SizeFilter`] 与
+/// [`BinaryFilter`]，由 [`FilterPipeline`] 按顺序组合；库的使用者也可以实现自己的过滤器。
+pub trait FileFilter {
+    /// 过滤器的名称，如 `"size"`，用于区分被各个过滤器跳过的文件。
+    fn name(&self) -> &'static str;
+
+    /// 是否保留文件。
+    ///
+    /// # 参数
+    /// - `path`: 文件路径。
+    /// - `metadata`: 文件的元数据 (跟随符号链接)，无法获取时为 `None`。
+    ///
+    /// # 返回
+    /// `Result<bool>`: 保留时返回 `true`；返回错误时整个过滤过程失败。
+    fn keep(&self, path: &Path, metadata: Option<&Metadata>) -> Result<bool>;
+
+    /// 被该过滤器跳过的文件的警告信息，默认不给出警告。
+    ///
+    /// # 参数
+    /// - `skipped`: 被该过滤器跳过的文件。
+    fn warning(&self, _skipped: &[PathBuf]) -> Option<String> {
+        None
+    }
+}
+
+/// 按顺序组合的文件过滤器，文件只有通过所有过滤器才会保留。
+///
+/// # 示例
+///
+/// ```no_run
+/// use ztr_lib::config::Config;
+/// use ztr_lib::filter::{BinaryFilter, FilterPipeline};
+/// use std::path::{Path, PathBuf};
+///
+/// fn main() -> anyhow::Result<()> {
+///     let config = Config::default();
+///     let mut pipeline = FilterPipeline::from_config(&config, Path::new("."))?;
+///     pipeline.push(BinaryFilter::default());
+///     let filtered = pipeline.filter_files(vec![PathBuf::from("./README.md")])?;
+///     println!("{:?}", filtered.kept);
+///     Ok(())
+/// }
+/// ```
+#[derive(Default)]
+pub struct FilterPipeline {
+    filters: Vec<Box<dyn FileFilter>>,
+}
+
+/// [`FilterPipeline`] 的过滤结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineFiltered {
+    /// 保留的文件，顺序与输入相同
+    pub kept: Vec<PathBuf>,
+    /// 被跳过的文件及跳过它的过滤器的名称
+    pub skipped: Vec<(&'static str, PathBuf)>,
+    /// 各过滤器给出的警告信息
+    pub warnings: Vec<String>,
+}
+
+impl PipelineFiltered {
+    /// 因 `min_file_size`/`max_file_size` 被跳过的文件。
+    ///
+    /// 命令行将它们计入压缩统计中被跳过的文件；忽略规则跳过的文件（如 `target/`）通常很多，不计入。
+    pub fn skipped_by_size(&self) -> Vec<PathBuf> {
+        self.skipped
+            .iter()
+            .filter(|(name, _)| *name == SIZE_FILTER)
+            .map(|(_, path)| path.clone())
+            .collect()
+    }
+}
+
+impl FilterPipeline {
+    /// 创建空的过滤管道，保留所有文件。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据配置创建过滤管道：依次为忽略规则、`newer_than`、`min_file_size`/`max_file_size` 与
+    /// `skip_binary`，未配置的过滤器不会加入。
+    ///
+    /// # 参数
+    /// - `config`: 压缩配置。
+    /// - `base_dir`: 基础目录，忽略规则相对于它匹配。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 忽略规则或 `newer_than` 无效时返回错误。
+    pub fn from_config(config: &Config, base_dir: &Path) -> Result<Self> {
+        let mut pipeline = Self::new();
+        pipeline.push(IgnoreRules::from_config(config, base_dir)?);
+        if let Some(filter) = ModifiedSinceFilter::from_config(config)? {
+            pipeline.push(filter);
+        }
+        let size_filter = SizeFilter::from_config(config);
+        if size_filter.is_active() {
+            pipeline.push(size_filter);
+        }
+        if config.skip_binary.unwrap_or(false) {
+            pipeline.push(BinaryFilter::default());
+        }
+        Ok(pipeline)
+    }
+
+    /// 在管道末尾添加过滤器。
+    pub fn push<F: FileFilter + 'static>(&mut self, filter: F) -> &mut Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// 管道中各过滤器的名称，按执行顺序排列。
+    pub fn names(&self) -> Vec<&'static str> {
+        self.filters.iter().map(|filter| filter.name()).collect()
+    }
+
+    /// 过滤文件列表，每个文件只获取一次元数据。
+    ///
+    /// # 参数
+    /// - `files`: 待过滤的文件路径列表。
+    ///
+    /// # 返回
+    /// `Result<PipelineFiltered>`: 保留与被跳过的文件以及警告信息；任一过滤器出错时返回错误。
+    pub fn filter_files(&self, files: Vec<PathBuf>) -> Result<PipelineFiltered> {
+        let mut result = PipelineFiltered::default();
+        let mut skipped_by: Vec<Vec<PathBuf>> = vec![Vec::new(); self.filters.len()];
+        'files: for path in files {
+            let metadata = std::fs::metadata(&path).ok();
+            for (filter, skipped) in self.filters.iter().zip(&mut skipped_by) {
+                if !filter.keep(&path, metadata.as_ref())? {
+                    result.skipped.push((filter.name(), path.clone()));
+                    skipped.push(path);
+                    continue 'files;
+                }
+            }
+            result.kept.push(path);
+        }
+        for (filter, skipped) in self.filters.iter().zip(&skipped_by) {
+            if !skipped.is_empty()
+                && let Some(warning) = filter.warning(skipped)
+            {
+                result.warnings.push(warning);
+            }
+        }
+        Ok(result)
+    }
+}
+
+impl FileFilter for IgnoreRules {
+    fn name(&self) -> &'static str {
+        "ignore"
+    }
+
+    fn keep(&self, path: &Path, metadata: Option<&Metadata>) -> Result<bool> {
+        let is_dir = metadata.is_some_and(Metadata::is_dir);
+        Ok(!self.should_ignore(path, is_dir))
+    }
+}
+
+/// 按文件大小过滤文件，与 [`IgnoreRules`](crate::ignore_rules::IgnoreRules) 一起使用。
+///
+/// [`SizeFilter`] 的名称。
+const SIZE_FILTER: &str = "size";
+
+/// 用于避免误将巨大的数据集打包进压缩包（`max_file_size`），或跳过空文件等过小的文件（`min_file_size`）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeFilter {
+    /// 最小文件大小 (字节)，小于该值的文件被跳过
+    pub min: Option<u64>,
+    /// 最大文件大小 (字节)，大于该值的文件被跳过
+    pub max: Option<u64>,
+}
+
+/// 按大小过滤的结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SizeFiltered {
+    /// 保留的文件
+    pub kept: Vec<PathBuf>,
+    /// 超过最大大小而被跳过的文件及其大小
+    pub too_large: Vec<(PathBuf, u64)>,
+    /// 小于最小大小而被跳过的文件及其大小
+    pub too_small: Vec<(PathBuf, u64)>,
+}
+
+impl SizeFiltered {
+    /// 被跳过的大文件的警告信息，没有跳过任何大文件时返回 `None`。
+    ///
+    /// # 参数
+    /// - `max`: 最大文件大小 (字节)。
+    pub fn oversized_warning(&self, max: u64) -> Option<String> {
+        oversized_message(&self.too_large, max)
+    }
+}
+
+/// 被跳过的大文件的警告信息，列表为空时返回 `None`。
+fn oversized_message(too_large: &[(PathBuf, u64)], max: u64) -> Option<String> {
+    if too_large.is_empty() {
+        return None;
+    }
+    let mut message = format!(
+        "跳过 {} 个超过 {} 的文件:",
+        too_large.len(),
+        human_size(max)
+    );
+    for (path, size) in too_large {
+        message.push_str(&format!("\n  {} ({})", path.display(), human_size(*size)));
+    }
+    Some(message)
+}
+
+impl SizeFilter {
+    /// 根据配置中的 `min_file_size` 和 `max_file_size` 创建过滤器。
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            min: config.min_file_size.map(|size| size.0),
+            max: config.max_file_size.map(|size| size.0),
+        }
+    }
+
+    /// 是否设置了任何限制。
+    pub fn is_active(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    /// 过滤文件列表。
+    ///
+    /// 目录和无法获取大小的文件总是保留（后者会在压缩时报告错误）。
+    ///
+    /// # 参数
+    /// - `files`: 待过滤的文件路径列表。
+    ///
+    /// # 返回
+    /// `SizeFiltered`: 保留的文件以及因过大或过小被跳过的文件。
+    pub fn filter_files(&self, files: Vec<PathBuf>) -> SizeFiltered {
+        let mut result = SizeFiltered::default();
+        if !self.is_active() {
+            result.kept = files;
+            return result;
+        }
+        for path in files {
+            let size = match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.is_file() => metadata.len(),
+                _ => {
+                    result.kept.push(path);
+                    continue;
+                }
+            };
+            if self.max.is_some_and(|max| size > max) {
+                result.too_large.push((path, size));
+            } else if self.min.is_some_and(|min| size < min) {
+                result.too_small.push((path, size));
+            } else {
+                result.kept.push(path);
+            }
+        }
+        result
+    }
+}
+
+impl FileFilter for SizeFilter {
+    fn name(&self) -> &'static str {
+        SIZE_FILTER
+    }
+
+    fn keep(&self, _path: &Path, metadata: Option<&Metadata>) -> Result<bool> {
+        let size = match metadata {
+            Some(metadata) if metadata.is_file() => metadata.len(),
+            _ => return Ok(true),
+        };
+        Ok(self.max.is_none_or(|max| size <= max) && self.min.is_none_or(|min| size >= min))
+    }
+
+    fn warning(&self, skipped: &[PathBuf]) -> Option<String> {
+        let max = self.max?;
+        let too_large: Vec<(PathBuf, u64)> = skipped
+            .iter()
+            .filter_map(|path| {
+                let size = std::fs::metadata(path).ok()?.len();
+                (size > max).then(|| (path.clone(), size))
+            })
+            .collect();
+        oversized_message(&too_large, max)
+    }
+}
+
+/// 只保留修改时间晚于某个时间点的文件，用于生成每日增量压缩包。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModifiedSinceFilter {
+    /// 时间点，修改时间不晚于它的文件被跳过
+    pub since: SystemTime,
+}
+
+impl ModifiedSinceFilter {
+    /// 根据配置中的 `newer_than` 创建过滤器，未配置时返回 `None`。
+    ///
+    /// # 返回
+    /// `Result<Option<Self>>`: `newer_than` 无法解析时返回错误。
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        config
+            .newer_than
+            .as_deref()
+            .map(|value| {
+                let since = parse_time_point(value, SystemTime::now())
+                    .with_context(|| format!("无效的 newer_than: {}", value))?;
+                Ok(Self { since })
+            })
+            .transpose()
+    }
+
+    /// 过滤文件列表，只保留修改时间晚于 `since` 的文件；无法获取修改时间的文件总是保留。
+    pub fn filter_files(&self, files: Vec<PathBuf>) -> Vec<PathBuf> {
+        files
+            .into_iter()
+            .filter(|path| {
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .map(|modified| modified > self.since)
+                    .unwrap_or(true)
+            })
+            .collect()
+    }
+}
+
+impl FileFilter for ModifiedSinceFilter {
+    fn name(&self) -> &'static str {
+        "newer_than"
+    }
+
+    fn keep(&self, _path: &Path, metadata: Option<&Metadata>) -> Result<bool> {
+        Ok(metadata
+            .and_then(|metadata| metadata.modified().ok())
+            .is_none_or(|modified| modified > self.since))
+    }
+}
+
+/// 跳过二进制文件，只压缩文本文件（例如只分享源码与文档）。
+///
+/// 与 git 相同，文件开头的 `sniff_bytes` 个字节中出现 NUL 字节即视为二进制文件；
+/// 目录和无法读取的文件总是保留（后者会在压缩时报告错误）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryFilter {
+    /// 检查的字节数
+    pub sniff_bytes: u64,
+}
+
+impl Default for BinaryFilter {
+    fn default() -> Self {
+        Self { sniff_bytes: 8000 }
+    }
+}
+
+impl BinaryFilter {
+    /// 文件开头是否包含 NUL 字节。
+    ///
+    /// # 返回
+    /// `std::io::Result<bool>`: 无法读取文件时返回错误。
+    pub fn is_binary(&self, path: &Path) -> std::io::Result<bool> {
+        let mut head = Vec::new();
+        std::fs::File::open(path)?
+            .take(self.sniff_bytes)
+            .read_to_end(&mut head)?;
+        Ok(head.contains(&0))
+    }
+}
+
+impl FileFilter for BinaryFilter {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
+    fn keep(&self, path: &Path, metadata: Option<&Metadata>) -> Result<bool> {
+        if !metadata.is_some_and(Metadata::is_file) {
+            return Ok(true);
+        }
+        Ok(!self.is_binary(path).unwrap_or(false))
+    }
+}
+
+/// 解析时间点：相对于 `now` 的时长（如 `24h`、`7d`、`30m`、`90s`、`2w`）、Unix 时间戳（如 `1700000000`）
+/// 或本地时间的日期（`2024-05-01`、`2024-05-01 08:30`、`2024-05-01T08:30:00`）。
+///
+/// # 参数
+/// - `s`: 时间点字符串。
+/// - `now`: 当前时间，时长从此时间往前计算。
+///
+/// # 返回
+/// `Result<SystemTime>`: 对应的时间点。
+pub fn parse_time_point(s: &str, now: SystemTime) -> Result<SystemTime> {
+    let s = s.trim();
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        let secs: u64 = s.parse().context("时间戳超出范围")?;
+        return Ok(UNIX_EPOCH + Duration::from_secs(secs));
+    }
+    if let Some(duration) = parse_duration(s) {
+        return now.checked_sub(duration).context("时长超出范围");
+    }
+    parse_local_datetime(s).with_context(|| {
+        format!(
+            "无法识别的时间: {}（支持 24h、7d 等时长，Unix 时间戳或 2024-05-01 08:30 形式的日期）",
+            s
+        )
+    })
+}
+
+/// 解析带单位的时长：`s`、`m`、`h`、`d`、`w`。
+fn parse_duration(s: &str) -> Option<Duration> {
+    let split = s.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return None,
+    };
+    Some(Duration::from_secs(number.checked_mul(seconds)?))
+}
+
+/// 解析本地时间的 `YYYY-MM-DD[( |T)HH:MM[:SS]]`。
+fn parse_local_datetime(s: &str) -> Option<SystemTime> {
+    let (date, time) = match s.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds_of_day = 0;
+    if let Some(time) = time {
+        let parts: Vec<&str> = time.split(':').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return None;
+        }
+        let mut units = [0i64; 3];
+        for (unit, part) in units.iter_mut().zip(&parts) {
+            *unit = part.parse().ok()?;
+        }
+        let [hour, minute, second] = units;
+        if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60).contains(&second) {
+            return None;
+        }
+        seconds_of_day = hour * 3_600 + minute * 60 + second;
+    }
+    let local = template::days_from_civil(year, month, day) * 86_400 + seconds_of_day;
+    // 以该时刻附近的时区偏移换算为 UTC
+    let utc = local - template::local_utc_offset(local.max(0) as u64);
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(utc).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ByteSize;
+
+    #[test]
+    fn test_size_filter() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let empty = dir.path().join("empty.txt");
+        let small = dir.path().join("small.txt");
+        let large = dir.path().join("dataset.bin");
+        std::fs::write(&empty, "")?;
+        std::fs::write(&small, "hello")?;
+        std::fs::write(&large, vec![0u8; 4096])?;
+        let files = vec![empty.clone(), small.clone(), large.clone()];
+
+        let config = Config {
+            min_file_size: Some(ByteSize(1)),
+            max_file_size: Some(ByteSize(1024)),
+            ..Default::default()
+        };
+        let filter = SizeFilter::from_config(&config);
+        let result = filter.filter_files(files.clone());
+        assert_eq!(result.kept, vec![small]);
+        assert_eq!(result.too_large, vec![(large, 4096)]);
+        assert_eq!(result.too_small, vec![(empty, 0)]);
+        let warning = result.oversized_warning(1024).unwrap();
+        assert!(warning.contains("跳过 1 个超过 1024 bytes 的文件"));
+        assert!(warning.contains("dataset.bin (4.00 KB)"));
+
+        let unlimited = SizeFilter::default().filter_files(files.clone());
+        assert_eq!(unlimited.kept, files);
+        assert!(unlimited.oversized_warning(1024).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_pipeline() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let base = dir.path();
+        std::fs::write(base.join("main.rs"), "fn main() {}")?;
+        std::fs::write(base.join("debug.log"), "noise")?;
+        std::fs::write(base.join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0, 0, 0])?;
+        std::fs::write(base.join("data.bin"), vec![b'x'; 4096])?;
+        std::fs::write(base.join("empty.txt"), "")?;
+        let files: Vec<PathBuf> = ["main.rs", "debug.log", "logo.png", "data.bin", "empty.txt"]
+            .iter()
+            .map(|name| base.join(name))
+            .collect();
+
+        let config = Config {
+            ignore: Some(vec!["*.log".to_string()]),
+            min_file_size: Some(ByteSize(1)),
+            max_file_size: Some(ByteSize(1024)),
+            skip_binary: Some(true),
+            ..Default::default()
+        };
+        let pipeline = FilterPipeline::from_config(&config, base)?;
+        assert_eq!(pipeline.names(), ["ignore", "size", "binary"]);
+        let result = pipeline.filter_files(files.clone())?;
+        assert_eq!(result.kept, [base.join("main.rs")]);
+        assert_eq!(
+            result.skipped,
+            [
+                ("ignore", base.join("debug.log")),
+                ("binary", base.join("logo.png")),
+                ("size", base.join("data.bin")),
+                ("size", base.join("empty.txt")),
+            ]
+        );
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("跳过 1 个超过 1024 bytes 的文件"));
+
+        let all = FilterPipeline::new().filter_files(files.clone())?;
+        assert_eq!(all.kept, files);
+        assert!(all.skipped.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_skips_in_report() -> anyhow::Result<()> {
+        use crate::compressor;
+        use crate::progress::NoopReporter;
+
+        let dir = tempfile::tempdir()?;
+        let base = dir.path();
+        std::fs::write(base.join("main.rs"), "fn main() {}")?;
+        std::fs::write(base.join("debug.log"), "noise")?;
+        std::fs::write(base.join("data.bin"), vec![b'x'; 4096])?;
+        std::fs::write(base.join("empty.txt"), "")?;
+        let files: Vec<PathBuf> = ["main.rs", "debug.log", "data.bin", "empty.txt"]
+            .iter()
+            .map(|name| base.join(name))
+            .collect();
+        let config = Config {
+            ignore: Some(vec!["*.log".to_string()]),
+            min_file_size: Some(ByteSize(1)),
+            max_file_size: Some(ByteSize(1024)),
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+
+        // 与命令行相同：大小限制跳过的文件计入统计，忽略规则跳过的不计入
+        let filtered = FilterPipeline::from_config(&config, base)?.filter_files(files)?;
+        let mut report =
+            compressor::compress_directory(&config, base, filtered.kept.clone(), &NoopReporter)?;
+        report.skipped.extend(filtered.skipped_by_size());
+        assert_eq!(report.files_count, 1);
+        assert_eq!(
+            report.skipped,
+            [base.join("data.bin"), base.join("empty.txt")]
+        );
+        assert!(report.to_string().contains("跳过 2 个文件"), "{}", report);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_filter() -> anyhow::Result<()> {
+        struct Extension(&'static str);
+
+        impl FileFilter for Extension {
+            fn name(&self) -> &'static str {
+                "extension"
+            }
+
+            fn keep(&self, path: &Path, _metadata: Option<&Metadata>) -> Result<bool> {
+                Ok(path.extension().is_some_and(|ext| ext == self.0))
+            }
+        }
+
+        let mut pipeline = FilterPipeline::new();
+        pipeline.push(Extension("rs"));
+        let result = pipeline.filter_files(vec![PathBuf::from("a.rs"), PathBuf::from("b.md")])?;
+        assert_eq!(result.kept, [PathBuf::from("a.rs")]);
+        assert_eq!(result.skipped, [("extension", PathBuf::from("b.md"))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_time_point() -> anyhow::Result<()> {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            parse_time_point("24h", now)?,
+            now - Duration::from_secs(86_400)
+        );
+        assert_eq!(
+            parse_time_point("2w", now)?,
+            now - Duration::from_secs(14 * 86_400)
+        );
+        assert_eq!(
+            parse_time_point("1700000000", now)?,
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+        let offset = template::local_utc_offset(1_700_000_000);
+        let expected = (1_699_920_000 + 8 * 3_600 + 30 * 60 - offset) as u64;
+        assert_eq!(
+            parse_time_point("2023-11-14 08:30", now)?,
+            UNIX_EPOCH + Duration::from_secs(expected)
+        );
+        assert_eq!(
+            parse_time_point("2023-11-14T08:30:00", now)?,
+            UNIX_EPOCH + Duration::from_secs(expected)
+        );
+        assert!(parse_time_point("yesterday", now).is_err());
+        assert!(parse_time_point("24x", now).is_err());
+        assert!(parse_time_point("2023-13-01", now).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_since_filter() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let old = dir.path().join("old.txt");
+        let new = dir.path().join("new.txt");
+        std::fs::write(&old, "old")?;
+        std::fs::write(&new, "new")?;
+        let since = SystemTime::now() - Duration::from_secs(3_600);
+        std::fs::File::options()
+            .write(true)
+            .open(&old)?
+            .set_modified(since - Duration::from_secs(86_400))?;
+
+        let filter = ModifiedSinceFilter { since };
+        assert_eq!(filter.filter_files(vec![old, new.clone()]), vec![new]);
+
+        let config = Config {
+            newer_than: Some("24h".to_string()),
+            ..Default::default()
+        };
+        assert!(ModifiedSinceFilter::from_config(&config)?.is_some());
+        assert!(ModifiedSinceFilter::from_config(&Config::default())?.is_none());
+        let invalid = Config {
+            newer_than: Some("soon".to_string()),
+            ..Default::default()
+        };
+        assert!(ModifiedSinceFilter::from_config(&invalid).is_err());
+        Ok(())
+    }
+}
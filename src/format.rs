@@ -0,0 +1,396 @@
+use crate::format_detect::DetectedFormat;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// 支持的压缩格式。
+///
+/// 在配置文件中以字符串表示（`"zip"`、`"tar.gz"`、`"tar.zst"`、`"7z"`），无效的格式会在反序列化时被拒绝。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// ZIP 格式
+    Zip,
+    /// 使用 gzip 压缩的 TAR 格式
+    TarGz,
+    /// 使用 zstd 压缩的 TAR 格式
+    TarZst,
+    /// 7Z 格式
+    SevenZ,
+}
+
+impl Format {
+    /// 所有支持的格式。
+    pub const ALL: [Format; 4] = [Format::Zip, Format::TarGz, Format::TarZst, Format::SevenZ];
+
+    /// 格式名称，同时也是输出文件的扩展名（不含前导 `.`）。
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::TarGz => "tar.gz",
+            Format::TarZst => "tar.zst",
+            Format::SevenZ => "7z",
+        }
+    }
+
+    /// 格式的简短说明。
+    pub fn description(&self) -> &'static str {
+        match self {
+            Format::Zip => "兼容性最好，几乎所有系统都支持",
+            Format::TarGz => "Linux 常用格式，压缩率适中",
+            Format::TarZst => "压缩与解压速度快，压缩率高于 tar.gz",
+            Format::SevenZ => "压缩率最高，支持多种算法",
+        }
+    }
+
+    /// 根据文件扩展名判断压缩格式（不区分大小写，`.tgz` 视为 `tar.gz`，`.tzst` 视为 `tar.zst`）。
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Some(Format::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Format::TarGz)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Some(Format::TarZst)
+        } else if name.ends_with(".7z") {
+            Some(Format::SevenZ)
+        } else {
+            None
+        }
+    }
+
+    /// 根据文件开头的魔数判断压缩格式。
+    ///
+    /// gzip 数据一律视为 `tar.gz`，zstd 数据一律视为 `tar.zst`。
+    ///
+    /// # 参数
+    /// - `header`: 文件开头的若干字节（至少 6 个字节才能识别 7Z）。
+    pub fn from_magic(header: &[u8]) -> Option<Format> {
+        if header.starts_with(b"PK\x03\x04")
+            || header.starts_with(b"PK\x05\x06")
+            || header.starts_with(b"PK\x07\x08")
+        {
+            Some(Format::Zip)
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Format::TarGz)
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Format::TarZst)
+        } else if header.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            Some(Format::SevenZ)
+        } else {
+            None
+        }
+    }
+
+    /// 判断已有压缩包的格式：优先根据文件内容的魔数，无法识别时再根据扩展名。
+    ///
+    /// 因此被重命名或没有扩展名的压缩包（常见于下载流水线）也能被正确读取，见 [`crate::format_detect`]。
+    ///
+    /// # 参数
+    /// - `path`: 压缩包路径。
+    ///
+    /// # 返回
+    /// `Result<Format>`: 识别出的格式；文件无法读取、格式无法识别或是只能解压的外部格式时返回错误。
+    pub fn detect(path: &Path) -> anyhow::Result<Format> {
+        crate::format_detect::detect_native(path)
+    }
+
+    /// 是否为 TAR 格式（`tar.gz`、`tar.zst`）。
+    pub fn is_tar(&self) -> bool {
+        matches!(self, Format::TarGz | Format::TarZst)
+    }
+
+    /// 为 TAR 格式的压缩包数据创建解压后的 TAR 数据流。
+    ///
+    /// # 返回
+    /// `std::io::Result<Box<dyn Read>>`: 解压后的数据流；不是 TAR 格式时返回错误。
+    pub(crate) fn tar_decoder<'a, R: std::io::Read + 'a>(
+        &self,
+        reader: R,
+    ) -> std::io::Result<Box<dyn std::io::Read + 'a>> {
+        match self {
+            Format::TarGz => Ok(Box::new(flate2::read::GzDecoder::new(reader))),
+            #[cfg(feature = "tar-zst")]
+            Format::TarZst => Ok(Box::new(zstd::Decoder::new(reader)?)),
+            #[cfg(not(feature = "tar-zst"))]
+            Format::TarZst => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "TAR.ZST 格式需要启用 tar-zst 特性",
+            )),
+            Format::Zip | Format::SevenZ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} 不是 TAR 格式", self),
+            )),
+        }
+    }
+}
+
+/// 只能解压、不能创建的外部压缩格式，供 `ztr extract` 使用。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ForeignFormat {
+    /// 使用 bzip2 压缩的 TAR 格式
+    TarBz2,
+    /// 使用 xz 压缩的 TAR 格式
+    TarXz,
+    /// 单个 gzip 压缩的文件（不是 TAR）
+    Gzip,
+    /// 单个 bzip2 压缩的文件（不是 TAR）
+    Bzip2,
+    /// 单个 xz 压缩的文件（不是 TAR）
+    Xz,
+    /// RAR 格式，通过外部程序解压
+    Rar,
+}
+
+impl ForeignFormat {
+    /// 所有外部格式。
+    pub const ALL: [ForeignFormat; 6] = [
+        ForeignFormat::TarBz2,
+        ForeignFormat::TarXz,
+        ForeignFormat::Gzip,
+        ForeignFormat::Bzip2,
+        ForeignFormat::Xz,
+        ForeignFormat::Rar,
+    ];
+
+    /// 格式名称，同时也是常用的扩展名（不含前导 `.`）。
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ForeignFormat::TarBz2 => "tar.bz2",
+            ForeignFormat::TarXz => "tar.xz",
+            ForeignFormat::Gzip => "gz",
+            ForeignFormat::Bzip2 => "bz2",
+            ForeignFormat::Xz => "xz",
+            ForeignFormat::Rar => "rar",
+        }
+    }
+
+    /// 是否为单个压缩的文件（解压为一个文件，而不是 TAR）。
+    pub fn is_single_stream(&self) -> bool {
+        matches!(
+            self,
+            ForeignFormat::Gzip | ForeignFormat::Bzip2 | ForeignFormat::Xz
+        )
+    }
+
+    /// 根据文件扩展名判断外部格式（不区分大小写，`.tbz2`/`.tbz` 视为 `tar.bz2`，`.txz` 视为 `tar.xz`）。
+    ///
+    /// `.tar.gz` 与 `.tgz` 是 [`Format::TarGz`]，不会被识别为 [`ForeignFormat::Gzip`]。
+    pub fn from_path(path: &Path) -> Option<ForeignFormat> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if Format::from_path(path).is_some() {
+            None
+        } else if [".tar.bz2", ".tbz2", ".tbz"]
+            .iter()
+            .any(|e| name.ends_with(e))
+        {
+            Some(ForeignFormat::TarBz2)
+        } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+            Some(ForeignFormat::TarXz)
+        } else if name.ends_with(".gz") {
+            Some(ForeignFormat::Gzip)
+        } else if name.ends_with(".bz2") {
+            Some(ForeignFormat::Bzip2)
+        } else if name.ends_with(".xz") {
+            Some(ForeignFormat::Xz)
+        } else if name.ends_with(".rar") {
+            Some(ForeignFormat::Rar)
+        } else {
+            None
+        }
+    }
+
+    /// 根据文件开头的魔数判断外部格式。
+    ///
+    /// 只看文件头无法区分压缩的 TAR 与单个压缩的文件，bzip2 数据返回 `tar.bz2`，xz 数据返回 `tar.xz`，
+    /// gzip 数据（[`Format::TarGz`]）不会被识别；[`detect`](Self::detect) 会再查看解压后的数据。
+    pub fn from_magic(header: &[u8]) -> Option<ForeignFormat> {
+        if header.starts_with(b"BZh") {
+            Some(ForeignFormat::TarBz2)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]) {
+            Some(ForeignFormat::TarXz)
+        } else if header.starts_with(b"Rar!\x1a\x07") {
+            Some(ForeignFormat::Rar)
+        } else {
+            None
+        }
+    }
+
+    /// 判断文件是否为外部格式（见 [`crate::format_detect::detect`]），文件无法读取时返回 `None`。
+    pub fn detect(path: &Path) -> Option<ForeignFormat> {
+        match crate::format_detect::detect(path).ok()? {
+            DetectedFormat::Foreign(format) => Some(format),
+            DetectedFormat::Native(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ForeignFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|f| f.extension() == s)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "不支持的压缩格式: {}，支持的格式: zip, tar.gz, tar.zst, 7z",
+                    s
+                )
+            })
+    }
+}
+
+impl Serialize for Format {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.extension())
+    }
+}
+
+impl<'de> Deserialize<'de> for Format {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str_and_display() -> anyhow::Result<()> {
+        for format in Format::ALL {
+            assert_eq!(format.to_string().parse::<Format>()?, format);
+        }
+        assert_eq!("tar.gz".parse::<Format>()?, Format::TarGz);
+        assert!("rar".parse::<Format>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(Format::from_path(Path::new("a.ZIP")), Some(Format::Zip));
+        assert_eq!(Format::from_path(Path::new("a.tgz")), Some(Format::TarGz));
+        assert_eq!(
+            Format::from_path(Path::new("dir/a.tar.gz")),
+            Some(Format::TarGz)
+        );
+        assert_eq!(Format::from_path(Path::new("a.7z")), Some(Format::SevenZ));
+        assert_eq!(
+            Format::from_path(Path::new("a.tar.zst")),
+            Some(Format::TarZst)
+        );
+        assert_eq!(Format::from_path(Path::new("a.rar")), None);
+    }
+
+    #[test]
+    fn test_foreign_format() -> anyhow::Result<()> {
+        let cases = [
+            ("a.tar.bz2", Some(ForeignFormat::TarBz2)),
+            ("a.TBZ2", Some(ForeignFormat::TarBz2)),
+            ("a.txz", Some(ForeignFormat::TarXz)),
+            ("notes.txt.gz", Some(ForeignFormat::Gzip)),
+            ("data.json.bz2", Some(ForeignFormat::Bzip2)),
+            ("dump.XZ", Some(ForeignFormat::Xz)),
+            ("a.rar", Some(ForeignFormat::Rar)),
+            ("a.tar.gz", None),
+            ("a.tgz", None),
+            ("a.zip", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(
+                ForeignFormat::from_path(Path::new(name)),
+                expected,
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            ForeignFormat::from_magic(b"BZh91AY"),
+            Some(ForeignFormat::TarBz2)
+        );
+        assert_eq!(
+            ForeignFormat::from_magic(b"Rar!\x1a\x07\x01\x00"),
+            Some(ForeignFormat::Rar)
+        );
+
+        let dir = tempfile::tempdir()?;
+        let gzip = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0];
+        // gzip 数据只有在扩展名为 .gz 而不是 .tar.gz 时才是单个文件
+        for (name, content, expected) in [
+            ("notes.txt.gz", &gzip[..], Some(ForeignFormat::Gzip)),
+            ("app.tar.gz", &gzip[..], None),
+            ("download.dat", &gzip[..], None),
+            (
+                "misnamed.zip",
+                &[0xfd, b'7', b'z', b'X', b'Z', 0][..],
+                Some(ForeignFormat::TarXz),
+            ),
+            ("real.zip", &b"PK\x03\x04"[..], None),
+        ] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content)?;
+            assert_eq!(ForeignFormat::detect(&path), expected, "{}", name);
+        }
+        assert_eq!(ForeignFormat::detect(&dir.path().join("missing.rar")), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect() -> anyhow::Result<()> {
+        assert_eq!(Format::from_magic(b"PK\x03\x04rest"), Some(Format::Zip));
+        assert_eq!(Format::from_magic(&[0x1f, 0x8b, 8]), Some(Format::TarGz));
+        assert_eq!(
+            Format::from_magic(&[0x28, 0xb5, 0x2f, 0xfd, 0]),
+            Some(Format::TarZst)
+        );
+        assert_eq!(
+            Format::from_magic(b"7z\xbc\xaf\x27\x1c\x00\x04"),
+            Some(Format::SevenZ)
+        );
+        assert_eq!(Format::from_magic(b"PK"), None);
+
+        let dir = tempfile::tempdir()?;
+        // 内容优先于扩展名
+        let renamed = dir.path().join("download.tar.gz");
+        std::fs::write(&renamed, b"PK\x05\x06")?;
+        assert_eq!(Format::detect(&renamed)?, Format::Zip);
+        // 无法从内容识别时回退到扩展名
+        let empty = dir.path().join("empty.7z");
+        std::fs::write(&empty, b"")?;
+        assert_eq!(Format::detect(&empty)?, Format::SevenZ);
+        let unknown = dir.path().join("download");
+        std::fs::write(&unknown, b"hello")?;
+        assert!(Format::detect(&unknown).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_serde() -> anyhow::Result<()> {
+        #[derive(Deserialize, Serialize)]
+        struct Wrapper {
+            format: Format,
+        }
+
+        let wrapper: Wrapper = toml::from_str("format = \"7z\"")?;
+        assert_eq!(wrapper.format, Format::SevenZ);
+        assert_eq!(toml::to_string(&wrapper)?.trim(), "format = \"7z\"");
+
+        let err = toml::from_str::<Wrapper>("format = \"rar\"").err().unwrap();
+        assert!(err.to_string().contains("不支持的压缩格式"));
+        Ok(())
+    }
+}
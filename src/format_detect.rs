@@ -0,0 +1,293 @@
+//! 根据文件内容识别压缩包格式，内容无法识别时再根据扩展名判断。
+//!
+//! 下载流水线中常见被重命名或没有扩展名的压缩包（例如名为 `.dat` 的 zip），`ztr list`、
+//! `ztr extract`、`ztr verify` 等读取压缩包的命令都通过这里判断格式，而不是只看扩展名。
+
+use crate::format::{ForeignFormat, Format};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+
+/// 识别魔数时读取的文件头字节数。
+const HEADER_LEN: u64 = 8;
+
+/// TAR 头的大小。
+const TAR_BLOCK_LEN: usize = 512;
+
+/// 识别出的压缩包格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DetectedFormat {
+    /// ztr 可以创建与读取的格式
+    Native(Format),
+    /// 只能解压的外部格式
+    Foreign(ForeignFormat),
+}
+
+impl fmt::Display for DetectedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectedFormat::Native(format) => format.fmt(f),
+            DetectedFormat::Foreign(format) => format.fmt(f),
+        }
+    }
+}
+
+/// 根据文件开头的魔数判断格式。
+///
+/// 只看文件头无法区分压缩的 TAR 与单个压缩的文件：gzip 数据视为 `tar.gz`，bzip2、xz 数据视为
+/// `tar.bz2`、`tar.xz`（[`detect`] 会再查看解压后的数据）。
+///
+/// # 参数
+/// - `header`: 文件开头的若干字节（至少 6 个字节才能识别 7Z 与 xz）。
+pub fn from_magic(header: &[u8]) -> Option<DetectedFormat> {
+    Format::from_magic(header)
+        .map(DetectedFormat::Native)
+        .or_else(|| ForeignFormat::from_magic(header).map(DetectedFormat::Foreign))
+}
+
+/// 根据文件扩展名判断格式（不区分大小写）。
+pub fn from_path(path: &Path) -> Option<DetectedFormat> {
+    Format::from_path(path)
+        .map(DetectedFormat::Native)
+        .or_else(|| ForeignFormat::from_path(path).map(DetectedFormat::Foreign))
+}
+
+/// 判断文件的格式：优先根据文件内容的魔数，无法识别时再根据扩展名。
+///
+/// gzip、bzip2 与 xz 数据解压后的开头是 TAR 头时为压缩的 TAR，否则为单个压缩的文件。
+///
+/// # 参数
+/// - `path`: 文件路径。
+///
+/// # 返回
+/// `Result<DetectedFormat>`: 识别出的格式；文件无法读取或格式无法识别时返回错误。
+pub fn detect(path: &Path) -> Result<DetectedFormat> {
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    std::fs::File::open(path)
+        .and_then(|file| file.take(HEADER_LEN).read_to_end(&mut header))
+        .with_context(|| format!("打开压缩包失败: {}", path.display()))?;
+    let detected = match from_magic(&header) {
+        Some(tar @ DetectedFormat::Native(Format::TarGz)) => {
+            Some(tar_or_single(path, tar, ForeignFormat::Gzip))
+        }
+        Some(tar @ DetectedFormat::Foreign(ForeignFormat::TarBz2)) => {
+            Some(tar_or_single(path, tar, ForeignFormat::Bzip2))
+        }
+        Some(tar @ DetectedFormat::Foreign(ForeignFormat::TarXz)) => {
+            Some(tar_or_single(path, tar, ForeignFormat::Xz))
+        }
+        Some(detected) => Some(detected),
+        None => from_path(path),
+    };
+    detected.with_context(|| format!("无法识别压缩包格式: {}", path.display()))
+}
+
+/// 区分压缩的 TAR 与单个压缩的文件：解压数据的开头，是 TAR 头时为 `tar`，否则为 `single`。
+///
+/// 解压后不是 TAR 头但扩展名表明是 TAR（如没有 `ustar` 魔数的旧式 TAR）时仍为 `tar`；
+/// 无法解压（数据截断、未启用解码器的特性）时根据扩展名判断，扩展名也无法判断时为 `tar`。
+fn tar_or_single(path: &Path, tar: DetectedFormat, single: ForeignFormat) -> DetectedFormat {
+    let single = DetectedFormat::Foreign(single);
+    let by_name = from_path(path);
+    match decompressed_header(path, tar).map(|header| is_tar_header(&header)) {
+        Some(true) => tar,
+        Some(false) if by_name != Some(tar) => single,
+        _ if by_name == Some(single) => single,
+        _ => tar,
+    }
+}
+
+/// 解压 `tar` 格式的数据，读取开头的一个 TAR 块；无法解压时返回 `None`。
+fn decompressed_header(path: &Path, tar: DetectedFormat) -> Option<Vec<u8>> {
+    let file = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+    let decoder: Box<dyn Read> = match tar {
+        DetectedFormat::Native(Format::TarGz) => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        #[cfg(feature = "tar-bz2")]
+        DetectedFormat::Foreign(ForeignFormat::TarBz2) => {
+            Box::new(bzip2::read::MultiBzDecoder::new(file))
+        }
+        DetectedFormat::Foreign(ForeignFormat::TarXz) => {
+            Box::new(lzma_rust2::XzReader::new(file, true))
+        }
+        _ => return None,
+    };
+    let mut header = Vec::with_capacity(TAR_BLOCK_LEN);
+    decoder
+        .take(TAR_BLOCK_LEN as u64)
+        .read_to_end(&mut header)
+        .ok()?;
+    Some(header)
+}
+
+/// 数据是否以 TAR 头开始：偏移 257 处为 `ustar` 魔数（POSIX 与 GNU 格式），或者是全为 0 的结束块（空的 TAR）。
+fn is_tar_header(block: &[u8]) -> bool {
+    block.get(257..262) == Some(b"ustar")
+        || (block.len() == TAR_BLOCK_LEN && block.iter().all(|&b| b == 0))
+}
+
+/// 判断 ztr 可以读取（列出、校验、转换）的压缩包格式。
+///
+/// # 返回
+/// `Result<Format>`: 识别出的格式；只能解压的外部格式也返回错误。
+pub fn detect_native(path: &Path) -> Result<Format> {
+    match detect(path)? {
+        DetectedFormat::Native(format) => Ok(format),
+        DetectedFormat::Foreign(format) => anyhow::bail!(
+            "{} 格式只支持用 ztr extract 解压: {}",
+            format,
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect_misnamed() -> Result<()> {
+        let dir = tempdir()?;
+        let cases: [(&str, &[u8], DetectedFormat); 6] = [
+            (
+                "download.dat",
+                b"PK\x03\x04",
+                DetectedFormat::Native(Format::Zip),
+            ),
+            (
+                "backup.zip",
+                &[0x28, 0xb5, 0x2f, 0xfd, 0],
+                DetectedFormat::Native(Format::TarZst),
+            ),
+            (
+                "blob",
+                &[0xfd, b'7', b'z', b'X', b'Z', 0],
+                DetectedFormat::Foreign(ForeignFormat::TarXz),
+            ),
+            (
+                "notes.txt.gz",
+                &[0x1f, 0x8b, 8],
+                DetectedFormat::Foreign(ForeignFormat::Gzip),
+            ),
+            (
+                "app.tgz",
+                &[0x1f, 0x8b, 8],
+                DetectedFormat::Native(Format::TarGz),
+            ),
+            // 内容无法识别时根据扩展名判断
+            ("empty.7z", b"", DetectedFormat::Native(Format::SevenZ)),
+        ];
+        for (name, content, expected) in cases {
+            let path = dir.path().join(name);
+            std::fs::write(&path, content)?;
+            assert_eq!(detect(&path)?, expected, "{}", name);
+        }
+
+        let unknown = dir.path().join("data.bin");
+        std::fs::write(&unknown, "plain text")?;
+        assert!(detect(&unknown).is_err());
+        assert!(detect(&dir.path().join("missing.zip")).is_err());
+
+        let rar = dir.path().join("a.rar");
+        std::fs::write(&rar, b"Rar!\x1a\x07\x00")?;
+        let err = detect_native(&rar).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("rar 格式只支持用 ztr extract 解压")
+        );
+        assert_eq!(
+            detect_native(&dir.path().join("download.dat"))?,
+            Format::Zip
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_tar_or_single() -> Result<()> {
+        use std::io::Write;
+
+        let dir = tempdir()?;
+        let mut tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_ustar();
+        header.set_size(5);
+        header.set_cksum();
+        tar.append_data(&mut header, "a.txt", &b"hello"[..])?;
+        let tar = tar.into_inner()?;
+        let gzip = |data: &[u8]| -> Result<Vec<u8>> {
+            let mut gz = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            gz.write_all(data)?;
+            Ok(gz.finish()?)
+        };
+        let xz = |data: &[u8]| -> Result<Vec<u8>> {
+            let mut xz = lzma_rust2::XzWriter::new(Vec::new(), Default::default())?;
+            xz.write_all(data)?;
+            Ok(xz.finish()?)
+        };
+
+        // 解压后的内容决定是 TAR 还是单个文件，与扩展名无关
+        let cases = [
+            (
+                "download",
+                gzip(&tar)?,
+                DetectedFormat::Native(Format::TarGz),
+            ),
+            (
+                "download.gz",
+                gzip(&tar)?,
+                DetectedFormat::Native(Format::TarGz),
+            ),
+            (
+                "download",
+                gzip(b"text")?,
+                DetectedFormat::Foreign(ForeignFormat::Gzip),
+            ),
+            (
+                "blob",
+                xz(&tar)?,
+                DetectedFormat::Foreign(ForeignFormat::TarXz),
+            ),
+            (
+                "blob.xz",
+                xz(b"text")?,
+                DetectedFormat::Foreign(ForeignFormat::Xz),
+            ),
+            // 空的 TAR 只有全为 0 的结束块
+            (
+                "empty",
+                gzip(&[0; 1024])?,
+                DetectedFormat::Native(Format::TarGz),
+            ),
+            // 扩展名表明是 TAR 时，没有 ustar 魔数的旧式 TAR 仍按 TAR 处理
+            (
+                "old.tar.gz",
+                gzip(&[1; 512])?,
+                DetectedFormat::Native(Format::TarGz),
+            ),
+        ];
+        for (i, (name, data, expected)) in cases.into_iter().enumerate() {
+            let path = dir.path().join(i.to_string()).join(name);
+            std::fs::create_dir(path.parent().unwrap())?;
+            std::fs::write(&path, data)?;
+            assert_eq!(detect(&path)?, expected, "{}", name);
+        }
+
+        #[cfg(feature = "tar-bz2")]
+        for (name, data, expected) in [
+            ("a.tbz2", &tar[..], ForeignFormat::TarBz2),
+            ("data.bin", &b"text"[..], ForeignFormat::Bzip2),
+        ] {
+            let mut bz2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+            bz2.write_all(data)?;
+            let path = dir.path().join(name);
+            std::fs::write(&path, bz2.finish()?)?;
+            assert_eq!(
+                detect(&path)?,
+                DetectedFormat::Foreign(expected),
+                "{}",
+                name
+            );
+        }
+        Ok(())
+    }
+}
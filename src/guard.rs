@@ -0,0 +1,51 @@
+//! 将第三方解析器中的 panic 转换为错误。
+//!
+//! ztr 自身的代码在遇到格式错误的输入时总是返回错误，但底层的压缩包解析库在面对
+//! 精心构造或截断的数据时仍可能 panic（例如越界索引、超大的内存分配请求）。
+//! 读取压缩包的公开入口都经过 [`catch_panic`]，嵌入 ztr_lib 的长期运行服务不会因此崩溃。
+
+use anyhow::Result;
+use std::panic::{self, AssertUnwindSafe};
+
+/// 执行 `f`，将其中发生的 panic 转换为错误。
+///
+/// panic 时 `f` 捕获的状态可能只更新了一部分，调用方在收到错误后不应再使用这些状态。
+///
+/// # 参数
+/// - `what`: 正在进行的操作，用于错误信息，如 `读取ZIP`。
+/// - `f`: 要执行的操作。
+///
+/// # 返回
+/// `Result<T>`: `f` 的结果；发生 panic 时返回包含 panic 信息的错误。
+pub(crate) fn catch_panic<T, F>(what: &str, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "未知错误".to_string());
+            anyhow::bail!("{}失败，数据格式错误: {}", what, message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_panic() {
+        assert_eq!(catch_panic("测试", || Ok(1)).unwrap(), 1);
+
+        let error = catch_panic::<(), _>("读取ZIP", || panic!("index out of bounds"))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("读取ZIP失败"));
+        assert!(error.contains("index out of bounds"));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::config::Config;
+use crate::notify;
+use crate::report::CompressionReport;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 压缩前后执行的钩子命令，即配置中的 `[hooks]`。
+///
+/// 命令通过系统 shell 在基础目录中执行，可以使用模板变量 `{archive}` (压缩包路径)、
+/// `{size}` (压缩包大小，字节)、`{base_dir}` (基础目录) 和 `{format}` (压缩格式)，
+/// 路径会加上 shell 引号后再替换；
+/// 同样的值也通过环境变量 `ZTR_ARCHIVE`、`ZTR_SIZE`、`ZTR_BASE_DIR`、`ZTR_FORMAT` 提供。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hooks {
+    /// 收集文件之前依次执行的命令，如 `["cargo clean"]`；任一命令失败时不进行压缩
+    pub pre: Option<Vec<String>>,
+    /// 生成压缩包之后依次执行的命令，如 `["scp {archive} backup:/srv/"]`；任一命令失败时停止并返回错误
+    pub post: Option<Vec<String>>,
+}
+
+/// 钩子命令中模板变量的取值。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookVars {
+    /// 压缩包路径；压缩前为预计的输出路径
+    pub archive: String,
+    /// 压缩包大小 (字节)；压缩前为 0
+    pub size: u64,
+    /// 基础目录
+    pub base_dir: String,
+    /// 压缩格式
+    pub format: String,
+}
+
+impl HookVars {
+    /// 压缩前的变量：`{archive}` 为预计的输出路径。
+    pub fn before(config: &Config, base_dir: &Path) -> Self {
+        Self {
+            archive: crate::compressor::output_path(config, base_dir)
+                .display()
+                .to_string(),
+            size: 0,
+            base_dir: base_dir.display().to_string(),
+            format: config.format.to_string(),
+        }
+    }
+
+    /// 压缩后的变量。
+    pub fn after(config: &Config, base_dir: &Path, report: &CompressionReport) -> Self {
+        Self {
+            archive: report.output_path.display().to_string(),
+            size: report.output_bytes,
+            base_dir: base_dir.display().to_string(),
+            format: config.format.to_string(),
+        }
+    }
+
+    /// 将命令中的模板变量替换为实际的值，未知的 `{...}` 保持原样。
+    ///
+    /// `{archive}` 与 `{base_dir}` 会加上 shell 引号，路径中的空格、`;`、`$()` 等不会被 shell 解释。
+    pub fn expand(&self, command: &str) -> String {
+        command
+            .replace("{archive}", &shell_quote(&self.archive))
+            .replace("{size}", &self.size.to_string())
+            .replace("{base_dir}", &shell_quote(&self.base_dir))
+            .replace("{format}", &self.format)
+    }
+}
+
+/// 为 [`notify::shell`] 使用的 shell 加上引号，使值作为一个参数原样传递：
+/// unix 上使用单引号 (值中的 `'` 写作 `'\''`)，Windows 上使用双引号 (值中的 `"` 写作 `""`)。
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// 执行配置中的 `pre` 钩子。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录，也是命令的工作目录。
+///
+/// # 返回
+/// `Result<()>`: 任一命令无法执行或以非零状态退出时返回错误。
+pub fn run_pre(config: &Config, base_dir: &Path) -> Result<()> {
+    let Some(commands) = config.hooks.as_ref().and_then(|h| h.pre.as_ref()) else {
+        return Ok(());
+    };
+    run_commands(
+        "pre",
+        commands,
+        base_dir,
+        &HookVars::before(config, base_dir),
+    )
+}
+
+/// 执行配置中的 `post` 钩子。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录，也是命令的工作目录。
+/// - `report`: 压缩结果，提供 `{archive}` 和 `{size}`。
+///
+/// # 返回
+/// `Result<()>`: 任一命令无法执行或以非零状态退出时返回错误。
+pub fn run_post(config: &Config, base_dir: &Path, report: &CompressionReport) -> Result<()> {
+    let Some(commands) = config.hooks.as_ref().and_then(|h| h.post.as_ref()) else {
+        return Ok(());
+    };
+    run_commands(
+        "post",
+        commands,
+        base_dir,
+        &HookVars::after(config, base_dir, report),
+    )
+}
+
+fn run_commands(stage: &str, commands: &[String], base_dir: &Path, vars: &HookVars) -> Result<()> {
+    for command in commands {
+        let command = vars.expand(command);
+        let status = notify::shell(&command)
+            .current_dir(base_dir)
+            .env("ZTR_ARCHIVE", &vars.archive)
+            .env("ZTR_SIZE", vars.size.to_string())
+            .env("ZTR_BASE_DIR", &vars.base_dir)
+            .env("ZTR_FORMAT", &vars.format)
+            .status()
+            .with_context(|| format!("执行 {} 钩子失败: {}", stage, command))?;
+        if !status.success() {
+            anyhow::bail!("{} 钩子以非零状态退出: {} ({})", stage, command, status);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_expand() {
+        let vars = HookVars {
+            archive: "/out/app.zip".to_string(),
+            size: 1024,
+            base_dir: "/src/my app $HOME".to_string(),
+            format: "zip".to_string(),
+        };
+        let expected = if cfg!(windows) {
+            r#"scp "/out/app.zip" backup:/srv/ # 1024 "/src/my app $HOME" zip {date}"#
+        } else {
+            "scp '/out/app.zip' backup:/srv/ # 1024 '/src/my app $HOME' zip {date}"
+        };
+        assert_eq!(
+            vars.expand("scp {archive} backup:/srv/ # {size} {base_dir} {format} {date}"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        if cfg!(windows) {
+            assert_eq!(shell_quote(r#"say "hi""#), r#""say ""hi""""#);
+        } else {
+            assert_eq!(shell_quote("it's $(x)"), r"'it'\''s $(x)'");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hooks() -> Result<()> {
+        let dir = tempdir()?;
+        // 路径中的空格与 `$()` 不会被 shell 解释
+        let base_dir = &dir.path().join("my app $(touch pwned)");
+        std::fs::create_dir(base_dir)?;
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("app".to_string()),
+            hooks: Some(Hooks {
+                pre: Some(vec!["echo pre {archive} > pre.txt".to_string()]),
+                post: Some(vec![
+                    "echo {base_dir} > base_dir.txt".to_string(),
+                    "echo \"{size} $ZTR_SIZE\" > post.txt".to_string(),
+                    "false".to_string(),
+                    "touch never.txt".to_string(),
+                ]),
+            }),
+            ..Default::default()
+        };
+
+        run_pre(&config, base_dir)?;
+        assert_eq!(
+            std::fs::read_to_string(base_dir.join("pre.txt"))?.trim(),
+            format!("pre {}", base_dir.join("app.zip").display())
+        );
+
+        let report = CompressionReport {
+            output_path: PathBuf::from("app.zip"),
+            files_count: 1,
+            input_bytes: 10,
+            output_bytes: 42,
+            duration: Default::default(),
+            skipped: Vec::new(),
+            checksums: Vec::new(),
+            signature: None,
+        };
+        // 失败的命令之后的命令不会执行
+        assert!(run_post(&config, base_dir, &report).is_err());
+        assert_eq!(
+            std::fs::read_to_string(base_dir.join("post.txt"))?.trim(),
+            "42 42"
+        );
+        assert_eq!(
+            std::fs::read_to_string(base_dir.join("base_dir.txt"))?.trim(),
+            base_dir.display().to_string()
+        );
+        assert!(!base_dir.join("never.txt").exists());
+        assert!(!base_dir.join("pwned").exists());
+        assert!(!dir.path().join("pwned").exists());
+
+        // 未配置钩子时什么都不做
+        run_pre(&Config::default(), base_dir)?;
+        Ok(())
+    }
+}
@@ -1,12 +1,100 @@
+use crate::collector::{self, Mount};
+use crate::config::Config;
 use anyhow::{Context, Result};
-use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 基础目录中自动读取的忽略文件名。
+pub const ZTRIGNORE_FILE_NAME: &str = ".ztrignore";
+
+/// 返回忽略文件内容中的规则，跳过空行和以 `#` 开头的注释行。
+pub fn ignore_file_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// 规则的来源。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleSource {
+    /// 配置中的 `ignore` 列表
+    Config,
+    /// 配置中的 `ignore_file` 指定的忽略文件
+    IgnoreFile(PathBuf),
+    /// 命令行 `--exclude`
+    Cli,
+    /// 目录树中的 `.gitignore` 文件 (启用 `gitignore` 时)
+    Gitignore(PathBuf),
+    /// 配置中的 `include` 列表或命令行 `--include`
+    Include,
+}
+
+impl fmt::Display for RuleSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSource::Config => f.write_str("配置 ignore"),
+            RuleSource::IgnoreFile(path) => write!(f, "忽略文件 {}", path.display()),
+            RuleSource::Cli => f.write_str("命令行 --exclude"),
+            RuleSource::Gitignore(path) => write!(f, "{}", path.display()),
+            RuleSource::Include => f.write_str("包含规则"),
+        }
+    }
+}
+
+/// 匹配到的规则。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedRule {
+    /// 规则的原文，如 `target/`、`!keep.log`
+    pub pattern: String,
+    /// 规则的来源
+    pub source: RuleSource,
+}
+
+impl fmt::Display for MatchedRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.pattern, self.source)
+    }
+}
+
+/// 路径是否会被压缩，以及起决定作用的规则。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Explanation {
+    /// 没有任何规则匹配，会被压缩
+    NotMatched,
+    /// 被忽略规则排除
+    Ignored(MatchedRule),
+    /// 被 `!` 规则重新包含，会被压缩
+    Whitelisted(MatchedRule),
+    /// 匹配了包含规则，会被压缩
+    Included(MatchedRule),
+    /// 配置了包含规则但不匹配其中任何一条，被排除
+    NotIncluded,
+}
+
+impl Explanation {
+    /// 路径是否会被忽略。
+    pub fn is_ignored(&self) -> bool {
+        matches!(self, Explanation::Ignored(_) | Explanation::NotIncluded)
+    }
+}
 
 /// 管理文件和目录的忽略规则。
 /// 使用 Gitignore 语法来匹配路径。
 pub struct IgnoreRules {
     gitignore: Gitignore,
     base_dir: PathBuf,
+    /// 目录树中发现的 `.gitignore` 文件，按目录深度从深到浅排列
+    nested: Vec<Gitignore>,
+    /// 包含规则 (白名单)，设置后只保留匹配的文件
+    include: Option<Gitignore>,
+    /// `gitignore` 中各条规则的来源，规则的 `from` 字段记录其下标
+    sources: Vec<RuleSource>,
+    /// 映射的源路径，其中的文件按 `前缀/相对路径` 匹配
+    mounts: Vec<Mount>,
 }
 
 impl IgnoreRules {
@@ -19,11 +107,29 @@ impl IgnoreRules {
     /// # 返回
     /// `Result<Self>`: 成功时返回 `IgnoreRules` 实例，失败时返回错误信息。
     pub fn new(rules: &[String], base_dir: &Path) -> Result<Self> {
+        let rules: Vec<(RuleSource, String)> = rules
+            .iter()
+            .map(|rule| (RuleSource::Config, rule.clone()))
+            .collect();
+        Self::with_sources(&rules, base_dir)
+    }
+
+    /// 创建带有规则来源的 `IgnoreRules` 实例，来源用于 [`IgnoreRules::explain`]。
+    ///
+    /// 规则按给定的顺序生效，后面的规则（包括 `!` 规则）覆盖前面的规则。
+    ///
+    /// # 参数
+    /// - `rules`: 规则来源与规则。
+    /// - `base_dir`: 基础目录，所有路径都将相对于此目录进行匹配。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 成功时返回 `IgnoreRules` 实例，规则无效时返回错误信息。
+    pub fn with_sources(rules: &[(RuleSource, String)], base_dir: &Path) -> Result<Self> {
         let mut builder = GitignoreBuilder::new(base_dir);
 
-        for rule in rules {
+        for (index, (_, rule)) in rules.iter().enumerate() {
             builder
-                .add_line(None, rule)
+                .add_line(Some(PathBuf::from(index.to_string())), rule)
                 .with_context(|| format!("无效的忽略规则: {}", rule))?;
         }
 
@@ -32,9 +138,104 @@ impl IgnoreRules {
         Ok(Self {
             gitignore,
             base_dir: base_dir.to_path_buf(),
+            nested: Vec::new(),
+            include: None,
+            sources: rules.iter().map(|(source, _)| source.clone()).collect(),
+            mounts: Vec::new(),
         })
     }
 
+    /// 根据配置创建忽略规则。
+    ///
+    /// 除了配置中的 `ignore` 与 `ignore_file` 规则外，还会自动读取基础目录中的 `.ztrignore`
+    /// (位于忽略文件之后、命令行规则之前，`ztrignore = false` 时不读取)；如果启用了 `gitignore`，
+    /// 还会加载基础目录树中所有的 `.gitignore` 文件；配置了 `include` 时只保留匹配其中任一模式的文件。
+    /// `sources` 中映射的源路径里的文件按其在压缩包中的路径 (`前缀/相对路径`) 匹配。
+    ///
+    /// # 参数
+    /// - `config`: 压缩配置。
+    /// - `base_dir`: 基础目录。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 成功时返回 `IgnoreRules` 实例，失败时返回错误信息。
+    pub fn from_config(config: &Config, base_dir: &Path) -> Result<Self> {
+        let mut sourced = config.get_ignore_rules_with_source();
+        if config.ztrignore.unwrap_or(true) {
+            let path = base_dir.join(ZTRIGNORE_FILE_NAME);
+            let listed = config
+                .resolved_ignore_files
+                .iter()
+                .any(|(listed, _)| same_file(listed, &path));
+            if !listed && path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("无法读取忽略文件: {}", path.display()))?;
+                let at = sourced
+                    .iter()
+                    .position(|(source, _)| *source == RuleSource::Cli)
+                    .unwrap_or(sourced.len());
+                let file_rules = ignore_file_lines(&content)
+                    .map(|rule| (RuleSource::IgnoreFile(path.clone()), rule.to_string()));
+                sourced.splice(at..at, file_rules.collect::<Vec<_>>());
+            }
+        }
+        let mut rules = Self::with_sources(&sourced, base_dir)?;
+        rules.mounts = collector::mapped_mounts(config, base_dir)?;
+        if config.gitignore.unwrap_or(false) {
+            rules.load_nested_gitignores()?;
+        }
+        if let Some(include) = &config.include {
+            rules.set_include(include)?;
+        }
+        Ok(rules)
+    }
+
+    /// 设置包含规则 (白名单)：在忽略规则之后再过滤一遍，只保留匹配其中任一模式的文件。
+    ///
+    /// 模式使用与忽略规则相同的 gitignore 语法，相对于基础目录，例如 `src/`、`Cargo.*`、`/README.md`；
+    /// 文件所在的任一上级目录匹配时也视为匹配。包含规则不会让被忽略的文件重新出现。
+    ///
+    /// # 参数
+    /// - `patterns`: 包含模式列表，为空时不保留任何文件。
+    ///
+    /// # 返回
+    /// `Result<()>`: 模式无效时返回错误。
+    pub fn set_include(&mut self, patterns: &[String]) -> Result<()> {
+        self.include = Some(pattern_matcher(patterns, &self.base_dir, "包含规则")?);
+        Ok(())
+    }
+
+    /// 遍历基础目录，加载每个目录（包括基础目录本身）中的 `.gitignore` 文件。
+    ///
+    /// 与 git 相同，每个 `.gitignore` 中的规则相对于其所在目录，且更深层目录中的规则优先。
+    /// 已被忽略的目录不会被遍历。
+    pub fn load_nested_gitignores(&mut self) -> Result<()> {
+        let mut found = Vec::new();
+        let walker = WalkDir::new(&self.base_dir)
+            .into_iter()
+            .filter_entry(|e| !e.file_type().is_dir() || !self.should_ignore(e.path(), true));
+        for entry in walker.filter_map(|e| e.ok()) {
+            if !entry.file_type().is_dir() {
+                continue;
+            }
+            let path = entry.path().join(".gitignore");
+            if !path.is_file() {
+                continue;
+            }
+            let mut builder = GitignoreBuilder::new(entry.path());
+            if let Some(e) = builder.add(&path) {
+                return Err(e).with_context(|| format!("无效的忽略文件: {}", path.display()));
+            }
+            let gitignore = builder
+                .build()
+                .with_context(|| format!("构建忽略规则失败: {}", path.display()))?;
+            found.push((entry.depth(), gitignore));
+        }
+
+        found.sort_by_key(|(depth, _)| std::cmp::Reverse(*depth));
+        self.nested = found.into_iter().map(|(_, g)| g).collect();
+        Ok(())
+    }
+
     /// 检查给定的路径是否应该被忽略。
     ///
     /// # 参数
@@ -44,14 +245,103 @@ impl IgnoreRules {
     /// # 返回
     /// `bool`: 如果路径应该被忽略，则返回 `true`，否则返回 `false`。
     pub fn should_ignore(&self, path: &Path, is_dir: bool) -> bool {
-        let relative_path = match path.strip_prefix(&self.base_dir) {
-            Ok(p) => p,
-            Err(_) => return false, // 如果无法获取相对路径，则不忽略
+        self.explain(path, is_dir).is_ignored()
+    }
+
+    /// 检查给定的路径是否被 `!` 规则重新包含（即本会被前面的规则忽略，但被后面的否定规则保留）。
+    ///
+    /// # 参数
+    /// - `path`: 要检查的路径。
+    /// - `is_dir`: 指示路径是否是目录。
+    ///
+    /// # 返回
+    /// `bool`: 决定结果的规则是否为否定规则；未配置包含规则或路径匹配包含规则时才可能为 `true`。
+    pub fn is_whitelisted(&self, path: &Path, is_dir: bool) -> bool {
+        matches!(self.explain(path, is_dir), Explanation::Whitelisted(_))
+    }
+
+    /// 说明给定的路径是否会被忽略，以及是哪条规则（和它的来源）决定的。
+    ///
+    /// 判断顺序：配置、忽略文件与命令行的规则中最后匹配的一条；其次是从最深的 `.gitignore` 开始
+    /// 第一个给出明确结论的规则；最后是包含规则（只作用于文件）。
+    ///
+    /// # 参数
+    /// - `path`: 要检查的路径。
+    /// - `is_dir`: 指示路径是否是目录。
+    ///
+    /// # 返回
+    /// `Explanation`: 判断结果；不在基础目录或映射的源路径下的路径不会被忽略。
+    pub fn explain(&self, path: &Path, is_dir: bool) -> Explanation {
+        let relative_path = match collector::entry_path(&self.base_dir, &self.mounts, path) {
+            Some(p) => p,
+            None => return Explanation::NotMatched, // 如果无法获取相对路径，则不忽略
         };
+        let relative_path = relative_path.as_path();
+
+        let mut whitelisted = None;
+        match matched_with_parents(&self.gitignore, relative_path, is_dir) {
+            Match::Ignore(glob) => return Explanation::Ignored(self.matched_rule(glob)),
+            Match::Whitelist(glob) => whitelisted = Some(self.matched_rule(glob)),
+            Match::None => {}
+        }
+
+        // 从最深的 .gitignore 开始，第一个给出明确结论（忽略或 `!` 白名单）的规则生效
+        for gitignore in &self.nested {
+            if !path.starts_with(gitignore.path()) || path == gitignore.path() {
+                continue;
+            }
+            let source = RuleSource::Gitignore(gitignore.path().join(".gitignore"));
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(glob) => {
+                    return Explanation::Ignored(MatchedRule {
+                        pattern: glob.original().to_string(),
+                        source,
+                    });
+                }
+                Match::Whitelist(glob) => {
+                    whitelisted = Some(MatchedRule {
+                        pattern: glob.original().to_string(),
+                        source,
+                    });
+                    break;
+                }
+                Match::None => {}
+            }
+        }
+
+        // 包含规则只作用于文件，目录中可能还有匹配的文件，不能因此跳过
+        if let Some(include) = &self.include
+            && !is_dir
+        {
+            match include.matched_path_or_any_parents(relative_path, false) {
+                Match::Ignore(glob) if whitelisted.is_none() => {
+                    return Explanation::Included(MatchedRule {
+                        pattern: glob.original().to_string(),
+                        source: RuleSource::Include,
+                    });
+                }
+                Match::Ignore(_) => {}
+                _ => return Explanation::NotIncluded,
+            }
+        }
 
-        self.gitignore
-            .matched_path_or_any_parents(relative_path, is_dir)
-            .is_ignore()
+        match whitelisted {
+            Some(rule) => Explanation::Whitelisted(rule),
+            None => Explanation::NotMatched,
+        }
+    }
+
+    /// 根据规则的 `from` 字段找到 `gitignore` 中规则的来源。
+    fn matched_rule(&self, glob: &Glob) -> MatchedRule {
+        let source = glob
+            .from()
+            .and_then(|from| from.to_str()?.parse::<usize>().ok())
+            .and_then(|index| self.sources.get(index).cloned())
+            .unwrap_or(RuleSource::Config);
+        MatchedRule {
+            pattern: glob.original().to_string(),
+            source,
+        }
     }
 
     /// 过滤给定的文件路径列表，移除所有应该被忽略的文件。
@@ -75,6 +365,53 @@ impl IgnoreRules {
     }
 }
 
+/// 两个路径是否指向同一个文件（无法解析时按字面比较）。
+/// 用 Gitignore 语法的模式列表构建匹配器，匹配结果为 `is_ignore()` 表示命中。
+///
+/// # 参数
+/// - `patterns`: 模式列表，如 `["*.png", "assets/video/"]`。
+/// - `base_dir`: 模式所相对的目录。
+/// - `kind`: 规则的名称，用于错误信息，如 `"包含规则"`。
+///
+/// # 返回
+/// `Result<Gitignore>`: 模式无效时返回错误。
+pub fn pattern_matcher(patterns: &[String], base_dir: &Path, kind: &str) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(base_dir);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .with_context(|| format!("无效的{}: {}", kind, pattern))?;
+    }
+    builder.build().with_context(|| format!("构建{}失败", kind))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 与 git 相同地匹配相对路径：任一上级目录被忽略时路径即被忽略（`!` 规则无法重新包含被忽略目录中的文件），
+/// 否则由最后一条匹配路径本身的规则决定。
+fn matched_with_parents<'a>(
+    gitignore: &'a Gitignore,
+    relative_path: &Path,
+    is_dir: bool,
+) -> Match<&'a Glob> {
+    let parents: Vec<&Path> = relative_path
+        .ancestors()
+        .skip(1)
+        .filter(|p| !p.as_os_str().is_empty())
+        .collect();
+    for parent in parents.into_iter().rev() {
+        if let m @ Match::Ignore(_) = gitignore.matched(parent, true) {
+            return m;
+        }
+    }
+    gitignore.matched(relative_path, is_dir)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,4 +485,209 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_nested_gitignore() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("packages/app/dist"))?;
+        fs::create_dir_all(base_dir.join("packages/lib"))?;
+        fs::write(base_dir.join(".gitignore"), "*.log\n")?;
+        fs::write(
+            base_dir.join("packages/app/.gitignore"),
+            "dist/\n!keep.log\n",
+        )?;
+
+        let config = Config {
+            ignore: Some(vec!["secret.txt".to_string()]),
+            gitignore: Some(true),
+            ..Default::default()
+        };
+        let rules = IgnoreRules::from_config(&config, base_dir)?;
+
+        assert!(rules.should_ignore(&base_dir.join("a.log"), false));
+        assert!(rules.should_ignore(&base_dir.join("packages/lib/b.log"), false));
+        assert!(rules.should_ignore(&base_dir.join("packages/app/dist/app.js"), false));
+        assert!(!rules.should_ignore(&base_dir.join("packages/lib/dist/lib.js"), false));
+        // 更深层的白名单覆盖上层规则
+        assert!(!rules.should_ignore(&base_dir.join("packages/app/keep.log"), false));
+        // 配置中的规则始终生效
+        assert!(rules.should_ignore(&base_dir.join("packages/app/secret.txt"), false));
+
+        // 未启用时只使用配置中的规则
+        let rules = IgnoreRules::from_config(
+            &Config {
+                gitignore: None,
+                ..config
+            },
+            base_dir,
+        )?;
+        assert!(!rules.should_ignore(&base_dir.join("a.log"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_include() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("src/bin"))?;
+
+        let config = Config {
+            ignore: Some(vec!["*.bak".to_string()]),
+            include: Some(vec![
+                "src/".to_string(),
+                "Cargo.*".to_string(),
+                "/README.md".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let rules = IgnoreRules::from_config(&config, base_dir)?;
+
+        assert!(!rules.should_ignore(&base_dir.join("src/bin/main.rs"), false));
+        assert!(!rules.should_ignore(&base_dir.join("Cargo.toml"), false));
+        assert!(!rules.should_ignore(&base_dir.join("README.md"), false));
+        assert!(rules.should_ignore(&base_dir.join("docs/README.md"), false));
+        assert!(rules.should_ignore(&base_dir.join("notes.txt"), false));
+        // 目录不受包含规则影响，忽略规则仍然优先
+        assert!(!rules.should_ignore(&base_dir.join("docs"), true));
+        assert!(rules.should_ignore(&base_dir.join("src/old.bak"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::create_dir_all(base_dir.join("pkg"))?;
+        fs::write(base_dir.join("pkg/.gitignore"), "*.gen\n")?;
+        fs::write(base_dir.join(".ztrignore"), "*.log\n")?;
+
+        let config = Config {
+            ignore: Some(vec!["target/".to_string()]),
+            resolved_ignore_files: vec![(base_dir.join(".ztrignore"), "*.log\n".to_string())],
+            config_dir: Some(base_dir.to_path_buf()),
+            extra_ignore: Some(vec!["*.mp4".to_string(), "!keep.log".to_string()]),
+            gitignore: Some(true),
+            ..Default::default()
+        };
+        let rules = IgnoreRules::from_config(&config, base_dir)?;
+
+        let explain = |path: &str| rules.explain(&base_dir.join(path), false);
+        assert_eq!(
+            explain("target/debug/app"),
+            Explanation::Ignored(MatchedRule {
+                pattern: "target/".to_string(),
+                source: RuleSource::Config,
+            })
+        );
+        assert_eq!(
+            explain("a.log"),
+            Explanation::Ignored(MatchedRule {
+                pattern: "*.log".to_string(),
+                source: RuleSource::IgnoreFile(base_dir.join(".ztrignore")),
+            })
+        );
+        assert_eq!(
+            explain("keep.log"),
+            Explanation::Whitelisted(MatchedRule {
+                pattern: "!keep.log".to_string(),
+                source: RuleSource::Cli,
+            })
+        );
+        assert_eq!(
+            explain("pkg/x.gen"),
+            Explanation::Ignored(MatchedRule {
+                pattern: "*.gen".to_string(),
+                source: RuleSource::Gitignore(base_dir.join("pkg/.gitignore")),
+            })
+        );
+        assert_eq!(explain("src/main.rs"), Explanation::NotMatched);
+        assert!(!explain("src/main.rs").is_ignored());
+        Ok(())
+    }
+
+    #[test]
+    fn test_negation() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        let rules = vec![
+            "*.log".to_string(),
+            "!important.log".to_string(),
+            "logs/".to_string(),
+        ];
+        let ignore_rules = IgnoreRules::new(&rules, base_dir)?;
+
+        assert!(ignore_rules.should_ignore(&base_dir.join("debug.log"), false));
+        assert!(!ignore_rules.should_ignore(&base_dir.join("important.log"), false));
+        assert!(ignore_rules.is_whitelisted(&base_dir.join("important.log"), false));
+        assert!(!ignore_rules.is_whitelisted(&base_dir.join("main.rs"), false));
+        // 与 git 相同，父目录被忽略时无法重新包含其中的文件
+        assert!(ignore_rules.should_ignore(&base_dir.join("logs/important.log"), false));
+
+        // 顺序决定结果：否定规则在前时会被后面的规则覆盖
+        let reversed = vec!["!important.log".to_string(), "*.log".to_string()];
+        let ignore_rules = IgnoreRules::new(&reversed, base_dir)?;
+        assert!(ignore_rules.should_ignore(&base_dir.join("important.log"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_precedence_between_sources() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        // 后面的来源覆盖前面的来源: ignore < ignore_file < --exclude
+        let config = Config {
+            ignore: Some(vec!["*.log".to_string(), "*.tmp".to_string()]),
+            resolved_ignore_files: vec![(
+                base_dir.join(".ztrignore"),
+                "!keep.log\n*.bak\n".to_string(),
+            )],
+            extra_ignore: Some(vec!["!restore.bak".to_string(), "keep.log".to_string()]),
+            ..Default::default()
+        };
+        let rules = IgnoreRules::from_config(&config, base_dir)?;
+
+        assert!(rules.should_ignore(&base_dir.join("a.log"), false));
+        assert!(rules.should_ignore(&base_dir.join("a.bak"), false));
+        assert!(!rules.should_ignore(&base_dir.join("restore.bak"), false));
+        assert_eq!(
+            rules.explain(&base_dir.join("keep.log"), false),
+            Explanation::Ignored(MatchedRule {
+                pattern: "keep.log".to_string(),
+                source: RuleSource::Cli,
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ztrignore_autodetect() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        fs::write(base_dir.join(ZTRIGNORE_FILE_NAME), "# 数据集\n*.csv\n")?;
+
+        let config = Config {
+            ignore: None,
+            extra_ignore: Some(vec!["!keep.csv".to_string()]),
+            ..Default::default()
+        };
+        let rules = IgnoreRules::from_config(&config, base_dir)?;
+        assert_eq!(
+            rules.explain(&base_dir.join("data.csv"), false),
+            Explanation::Ignored(MatchedRule {
+                pattern: "*.csv".to_string(),
+                source: RuleSource::IgnoreFile(base_dir.join(ZTRIGNORE_FILE_NAME)),
+            })
+        );
+        // 命令行规则在 .ztrignore 之后生效
+        assert!(!rules.should_ignore(&base_dir.join("keep.csv"), false));
+
+        let disabled = Config {
+            ztrignore: Some(false),
+            ..config
+        };
+        let rules = IgnoreRules::from_config(&disabled, base_dir)?;
+        assert!(!rules.should_ignore(&base_dir.join("data.csv"), false));
+        Ok(())
+    }
 }
@@ -0,0 +1,352 @@
+use crate::collector;
+use crate::compressor;
+use crate::config::Config;
+use crate::format::Format;
+use crate::listing::{self, ListingEntry};
+use crate::progress::ProgressReporter;
+use crate::report::CompressionReport;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// 清单文件中记录增量序号的行的前缀。
+const INCREMENT_HEADER: &str = "#increment\t";
+
+/// 增量压缩的清单：上一次运行时所有文件的路径、大小与 SHA-256，以及最近一个增量压缩包的序号。
+///
+/// 清单保存在完整压缩包旁的 `<输出名>.manifest` 中，格式与 `<压缩包>.list` 相同，
+/// 另有一行 `#increment\t<N>` 记录序号（完整压缩包为 0）。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// 最近一个增量压缩包的序号，0 表示只有完整压缩包
+    pub increment: u32,
+    /// 所有文件的记录
+    pub entries: Vec<ListingEntry>,
+}
+
+/// 当前文件与清单的差异。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestDiff {
+    /// 新增的文件 (相对路径)
+    pub added: Vec<String>,
+    /// 内容发生变化的文件 (相对路径)
+    pub modified: Vec<String>,
+    /// 已删除的文件 (相对路径)
+    pub removed: Vec<String>,
+}
+
+impl ManifestDiff {
+    /// 是否没有任何变化。
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Manifest {
+    /// 解析清单文本。
+    pub fn parse(content: &str) -> Result<Self> {
+        let increment = match content
+            .lines()
+            .find_map(|line| line.strip_prefix(INCREMENT_HEADER))
+        {
+            Some(value) => value
+                .trim()
+                .parse()
+                .with_context(|| format!("清单中的增量序号无效: {}", value))?,
+            None => 0,
+        };
+        Ok(Self {
+            increment,
+            entries: listing::parse_listing(content)?,
+        })
+    }
+
+    /// 读取清单文件，文件不存在时返回 `None`。
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("读取清单文件失败: {}", path.display()))?;
+        Self::parse(&content)
+            .with_context(|| format!("解析清单文件失败: {}", path.display()))
+            .map(Some)
+    }
+
+    /// 将清单写入文件。
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = format!(
+            "{}{}\n{}",
+            INCREMENT_HEADER,
+            self.increment,
+            listing::to_listing_string(&self.entries)
+        );
+        std::fs::write(path, content)
+            .with_context(|| format!("写入清单文件失败: {}", path.display()))
+    }
+
+    /// 比较当前文件与清单，大小或 SHA-256 不同的文件视为已修改。
+    ///
+    /// # 参数
+    /// - `current`: 当前所有文件的记录。
+    ///
+    /// # 返回
+    /// `ManifestDiff`: 新增、修改与删除的文件，各自按路径排序。
+    pub fn diff(&self, current: &[ListingEntry]) -> ManifestDiff {
+        let previous: BTreeMap<&str, &ListingEntry> = self
+            .entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), entry))
+            .collect();
+        let mut diff = ManifestDiff::default();
+        for entry in current {
+            match previous.get(entry.path.as_str()) {
+                None => diff.added.push(entry.path.clone()),
+                Some(old) if old.size != entry.size || old.sha256 != entry.sha256 => {
+                    diff.modified.push(entry.path.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        let current: HashSet<&str> = current.iter().map(|entry| entry.path.as_str()).collect();
+        diff.removed = previous
+            .keys()
+            .filter(|path| !current.contains(*path))
+            .map(|path| path.to_string())
+            .collect();
+        diff.added.sort();
+        diff.modified.sort();
+        diff
+    }
+}
+
+/// 去掉完整压缩包路径中的扩展名，返回 `(输出目录, 输出名)`。
+fn split_output_path(output_path: &Path, format: Format) -> (PathBuf, String) {
+    let file_name = output_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = file_name
+        .strip_suffix(&format!(".{}", format.extension()))
+        .unwrap_or(&file_name)
+        .to_string();
+    let dir = output_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    (dir, stem)
+}
+
+/// 返回完整压缩包对应的清单文件路径 (`<输出名>.manifest`)。
+pub fn manifest_path(output_path: &Path, format: Format) -> PathBuf {
+    let (dir, stem) = split_output_path(output_path, format);
+    dir.join(format!("{}.manifest", stem))
+}
+
+/// 返回第 `increment` 个增量压缩包的路径 (`<输出名>.incr-<N>.<扩展名>`)。
+pub fn increment_path(output_path: &Path, format: Format, increment: u32) -> PathBuf {
+    let (dir, stem) = split_output_path(output_path, format);
+    dir.join(format!(
+        "{}.incr-{}.{}",
+        stem,
+        increment,
+        format.extension()
+    ))
+}
+
+/// 判断文件名是否为 `output_name` 的增量压缩包或清单文件。
+pub fn is_incremental_artifact(file_name: &str, output_name: &str) -> bool {
+    let Some(rest) = file_name.strip_prefix(output_name) else {
+        return false;
+    };
+    if rest == ".manifest" {
+        return true;
+    }
+    let Some(rest) = rest.strip_prefix(".incr-") else {
+        return false;
+    };
+    Format::ALL.iter().any(|format| {
+        rest.strip_suffix(&format!(".{}", format.extension()))
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+    })
+}
+
+/// 增量压缩：与上一次的清单比较，只压缩新增或修改的文件。
+///
+/// 没有清单时生成完整压缩包 `output_path`，否则生成下一个增量压缩包 `<输出名>.incr-<N>.<扩展名>`。
+/// 压缩成功后清单更新为当前所有文件的状态；没有任何新增或修改的文件时不生成压缩包，
+/// 返回的报告中 `files_count` 为 0。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `base_dir`: 基础目录。
+/// - `files`: 过滤后的全部文件路径列表。
+/// - `output_path`: 完整压缩包的路径。
+/// - `reporter`: 进度与日志回调。
+///
+/// # 返回
+/// `Result<CompressionReport>`: 本次压缩的报告，输出路径为完整或增量压缩包。
+pub fn compress_incremental(
+    config: &Config,
+    base_dir: &Path,
+    files: Vec<PathBuf>,
+    output_path: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<CompressionReport> {
+    let manifest_path = manifest_path(output_path, config.format);
+    let files = compressor::exclude_archives(config, base_dir, output_path, files);
+    let entries = listing::build_listing(&files, base_dir, &collector::mounts(config, base_dir)?)?;
+
+    let (archive_path, increment, files) = match Manifest::load(&manifest_path)? {
+        None => {
+            reporter.on_message(&format!(
+                "未找到清单 {}，生成完整压缩包",
+                manifest_path.display()
+            ));
+            (output_path.to_path_buf(), 0, files)
+        }
+        Some(previous) => {
+            let diff = previous.diff(&entries);
+            reporter.on_message(&format!(
+                "与上次相比: 新增 {} 个, 修改 {} 个, 删除 {} 个文件",
+                diff.added.len(),
+                diff.modified.len(),
+                diff.removed.len()
+            ));
+            let changed: HashSet<&String> = diff.added.iter().chain(&diff.modified).collect();
+            let files: Vec<PathBuf> = files
+                .into_iter()
+                .zip(&entries)
+                .filter(|(_, entry)| changed.contains(&entry.path))
+                .map(|(file, _)| file)
+                .collect();
+            if files.is_empty() {
+                // 没有需要打包的文件时不生成压缩包，也不占用新的序号
+                reporter.on_message("没有新增或修改的文件。");
+                Manifest {
+                    increment: previous.increment,
+                    entries,
+                }
+                .save(&manifest_path)?;
+                return Ok(CompressionReport {
+                    output_path: output_path.to_path_buf(),
+                    files_count: 0,
+                    input_bytes: 0,
+                    output_bytes: 0,
+                    duration: Default::default(),
+                    skipped: Vec::new(),
+                    checksums: Vec::new(),
+                    signature: None,
+                });
+            }
+            let increment = previous.increment + 1;
+            (
+                increment_path(output_path, config.format, increment),
+                increment,
+                files,
+            )
+        }
+    };
+
+    let report = compressor::compress_files(config, base_dir, files, &archive_path, reporter)?;
+    Manifest { increment, entries }.save(&manifest_path)?;
+    reporter.on_message(&format!("清单已更新: {}", manifest_path.display()));
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    fn entry(path: &str, size: u64, sha256: &str) -> ListingEntry {
+        ListingEntry {
+            path: path.to_string(),
+            size,
+            sha256: sha256.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_roundtrip_and_diff() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("backup.manifest");
+        let manifest = Manifest {
+            increment: 2,
+            entries: vec![entry("a.txt", 1, "aa"), entry("b.txt", 2, "bb")],
+        };
+        manifest.save(&path)?;
+        assert_eq!(Manifest::load(&path)?, Some(manifest.clone()));
+        assert_eq!(Manifest::load(&dir.path().join("missing"))?, None);
+
+        let diff = manifest.diff(&[entry("b.txt", 2, "b2"), entry("c.txt", 3, "cc")]);
+        assert_eq!(diff.added, vec!["c.txt"]);
+        assert_eq!(diff.modified, vec!["b.txt"]);
+        assert_eq!(diff.removed, vec!["a.txt"]);
+        assert!(manifest.diff(&manifest.entries).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_artifact_paths() {
+        let output = Path::new("out/backup.tar.gz");
+        assert_eq!(
+            manifest_path(output, Format::TarGz),
+            PathBuf::from("out/backup.manifest")
+        );
+        assert_eq!(
+            increment_path(output, Format::TarGz, 3),
+            PathBuf::from("out/backup.incr-3.tar.gz")
+        );
+        assert!(is_incremental_artifact("backup.manifest", "backup"));
+        assert!(is_incremental_artifact("backup.incr-12.zip", "backup"));
+        assert!(!is_incremental_artifact("backup.incr-.zip", "backup"));
+        assert!(!is_incremental_artifact("backup.incr-1.txt", "backup"));
+        assert!(!is_incremental_artifact("other.manifest", "backup"));
+    }
+
+    #[test]
+    fn test_compress_incremental() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::write(base_dir.join("a.txt"), "a")?;
+        std::fs::write(base_dir.join("b.txt"), "b")?;
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("backup".to_string()),
+            ..Default::default()
+        };
+        let output = compressor::output_path(&config, base_dir);
+        let collect = || -> Result<Vec<PathBuf>> {
+            let mut files = crate::collector::collect_files(base_dir, None, Default::default())?;
+            files.sort();
+            Ok(files)
+        };
+
+        let full = compress_incremental(&config, base_dir, collect()?, &output, &NoopReporter)?;
+        assert_eq!(full.output_path, base_dir.join("backup.zip"));
+        assert_eq!(full.files_count, 2);
+
+        std::fs::write(base_dir.join("b.txt"), "changed")?;
+        std::fs::write(base_dir.join("c.txt"), "c")?;
+        let incr = compress_incremental(&config, base_dir, collect()?, &output, &NoopReporter)?;
+        assert_eq!(incr.output_path, base_dir.join("backup.incr-1.zip"));
+        assert_eq!(incr.files_count, 2);
+
+        // 没有变化时不生成新的增量压缩包
+        let none = compress_incremental(&config, base_dir, collect()?, &output, &NoopReporter)?;
+        assert_eq!(none.files_count, 0);
+        assert!(!base_dir.join("backup.incr-2.zip").exists());
+
+        std::fs::remove_file(base_dir.join("a.txt"))?;
+        std::fs::write(base_dir.join("a.txt"), "new a")?;
+        let incr = compress_incremental(&config, base_dir, collect()?, &output, &NoopReporter)?;
+        assert_eq!(incr.output_path, base_dir.join("backup.incr-2.zip"));
+        assert_eq!(incr.files_count, 1);
+        let manifest = Manifest::load(&base_dir.join("backup.manifest"))?.unwrap();
+        assert_eq!(manifest.increment, 2);
+        assert_eq!(manifest.entries.len(), 3);
+        Ok(())
+    }
+}
@@ -1,28 +1,88 @@
-//! `ztr_lib` 是一个可配置的压缩工具库，支持多种压缩格式（zip, tar.gz, 7z），
+//! `ztr_lib` 是一个可配置的压缩工具库，支持多种压缩格式（zip, tar.gz, tar.zst, 7z），
 //! 并能根据配置忽略指定文件和目录。
 //!
 //! 主要功能包括：
 //! - 从配置文件加载压缩配置。
 //! - 根据 Gitignore 风格的规则过滤文件。
 //! - 支持多种压缩格式进行文件压缩。
+//! - 校验压缩包的完整性。
+//!
+//! 解析配置、忽略规则和压缩包的公开函数在遇到格式错误的输入时总是返回错误而不会 panic，
+//! 可以安全地嵌入长期运行的服务中。`fuzz/` 目录包含这些解析器的模糊测试目标。
 //!
 //! # 示例
 //!
 //! ```no_run
 //! use ztr_lib::config::Config;
+//! use ztr_lib::collector;
 //! use ztr_lib::compressor;
+//! use ztr_lib::ignore_rules::IgnoreRules;
+//! use ztr_lib::progress::NoopReporter;
 //! use std::path::PathBuf;
 //!
 //! fn main() -> anyhow::Result<()> {
 //!     let config_path = PathBuf::from("ztr.toml");
 //!     let config = Config::load(&config_path)?;
-//!     
+//!
 //!     let current_dir = std::env::current_dir()?;
-//!     compressor::compress_directory(&config, &current_dir)?;
-//!     
+//!     let all_files = collector::collect_files(
+//!         &current_dir,
+//!         config.sources.as_deref(),
+//!         config.symlinks.unwrap_or_default(),
+//!     )?;
+//!
+//!     let ignore_rules = IgnoreRules::new(&config.get_ignore_rules(), &current_dir)?;
+//!     let files = ignore_rules.filter_files(all_files.into_iter())?;
+//!     let report = compressor::compress_directory(&config, &current_dir, files, &NoopReporter)?;
+//!     println!("压缩文件已创建: {}", report.output_path.display());
+//!
 //!     Ok(())
 //! }
 //! ```
+pub mod archive_manifest;
+pub mod archive_writer;
+#[cfg(feature = "tokio")]
+pub mod async_api;
+pub mod attributes;
+pub mod backup;
+pub mod checksum;
+pub mod collector;
 pub mod compressor;
 pub mod config;
+pub mod convert;
+pub mod diff;
+pub mod extractor;
+pub mod fault;
+#[cfg(feature = "ztr-ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod format;
+pub mod format_detect;
+mod guard;
+pub mod hooks;
 pub mod ignore_rules;
+pub mod incremental;
+pub mod listing;
+pub mod metadata;
+pub mod notify;
+pub mod precompress;
+pub mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reader;
+pub mod report;
+pub mod retention;
+pub mod rules;
+pub mod schedule;
+pub mod search;
+pub mod signing;
+pub mod split;
+pub mod stats;
+pub mod template;
+pub mod update;
+pub mod uploader;
+pub mod validate;
+pub mod verifier;
+pub mod vfs;
+#[cfg(feature = "native")]
+pub mod watch;
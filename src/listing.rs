@@ -0,0 +1,158 @@
+use crate::checksum;
+use crate::collector::{self, Mount};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// 清单中的一个文件记录。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingEntry {
+    /// 相对于基础目录的路径 (使用 `/` 分隔)
+    pub path: String,
+    /// 文件大小 (字节)
+    pub size: u64,
+    /// SHA-256 摘要
+    pub sha256: String,
+}
+
+/// 返回清单文件的路径 (`<压缩包>.list`)。
+pub fn listing_path(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(".list");
+    PathBuf::from(name)
+}
+
+/// 为文件列表生成清单记录。
+///
+/// # 参数
+/// - `files`: 被压缩的文件路径列表。
+/// - `base_dir`: 基础目录，用于计算相对路径。
+/// - `mounts`: 映射到压缩包中指定前缀的源路径，其中的文件记录为 `前缀/相对路径`。
+///
+/// # 返回
+/// `Result<Vec<ListingEntry>>`: 与 `files` 顺序一致的清单记录。
+pub fn build_listing(
+    files: &[PathBuf],
+    base_dir: &Path,
+    mounts: &[Mount],
+) -> Result<Vec<ListingEntry>> {
+    let mut entries = Vec::with_capacity(files.len());
+    for file_path in files {
+        let relative_path = collector::entry_path(base_dir, mounts, file_path)
+            .with_context(|| format!("计算相对路径失败: {}", file_path.display()))?;
+        let size = std::fs::metadata(file_path)
+            .with_context(|| format!("读取文件信息失败: {}", file_path.display()))?
+            .len();
+        entries.push(ListingEntry {
+            path: relative_path.to_string_lossy().replace("\\", "/"),
+            size,
+            sha256: checksum::sha256_file(file_path)?,
+        });
+    }
+    Ok(entries)
+}
+
+/// 将清单记录序列化为文本，每行格式为 `<路径>\t<大小>\t<sha256>`。
+pub fn to_listing_string(entries: &[ListingEntry]) -> String {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&format!(
+            "{}\t{}\t{}\n",
+            entry.path, entry.size, entry.sha256
+        ));
+    }
+    content
+}
+
+/// 解析 [`to_listing_string`] 生成的清单文本，忽略空行和以 `#` 开头的行。
+///
+/// # 返回
+/// `Result<Vec<ListingEntry>>`: 清单记录；某一行格式错误时返回错误。
+pub fn parse_listing(content: &str) -> Result<Vec<ListingEntry>> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // 路径中可能包含制表符，从右侧拆分
+        let mut fields = line.rsplitn(3, '\t');
+        let (Some(sha256), Some(size), Some(path)) = (fields.next(), fields.next(), fields.next())
+        else {
+            anyhow::bail!("清单第 {} 行格式错误: {}", index + 1, line);
+        };
+        let size = size
+            .parse()
+            .with_context(|| format!("清单第 {} 行的大小无效: {}", index + 1, size))?;
+        entries.push(ListingEntry {
+            path: path.to_string(),
+            size,
+            sha256: sha256.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// 为压缩包写入清单文件 `<压缩包>.list`。
+pub fn write_listing(
+    files: &[PathBuf],
+    base_dir: &Path,
+    mounts: &[Mount],
+    archive_path: &Path,
+) -> Result<PathBuf> {
+    let entries = build_listing(files, base_dir, mounts)?;
+    let path = listing_path(archive_path);
+    std::fs::write(&path, to_listing_string(&entries))
+        .with_context(|| format!("写入清单文件失败: {}", path.display()))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_listing_path() {
+        assert_eq!(
+            listing_path(Path::new("out/a.tar.gz")),
+            PathBuf::from("out/a.tar.gz.list")
+        );
+    }
+
+    #[test]
+    fn test_write_listing() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path();
+        std::fs::create_dir(base_dir.join("sub"))?;
+        std::fs::write(base_dir.join("sub/a.txt"), "abc")?;
+
+        let archive = base_dir.join("out.zip");
+        let path = write_listing(&[base_dir.join("sub/a.txt")], base_dir, &[], &archive)?;
+
+        assert_eq!(
+            std::fs::read_to_string(path)?,
+            "sub/a.txt\t3\tba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_listing() -> Result<()> {
+        let entries = vec![
+            ListingEntry {
+                path: "a.txt".to_string(),
+                size: 3,
+                sha256: "abc".to_string(),
+            },
+            ListingEntry {
+                path: "dir/tab\tname".to_string(),
+                size: 0,
+                sha256: "def".to_string(),
+            },
+        ];
+        let content = format!("# comment\n\n{}", to_listing_string(&entries));
+        assert_eq!(parse_listing(&content)?, entries);
+        assert!(parse_listing("a.txt\t3").is_err());
+        assert!(parse_listing("a.txt\tbig\tabc").is_err());
+        Ok(())
+    }
+}
@@ -1,12 +1,39 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
-use walkdir::WalkDir;
 
+use ztr_lib::archive_manifest;
+use ztr_lib::backup::{self, Catalog};
+use ztr_lib::checksum;
+use ztr_lib::collector::{self, Source, SourceEntry};
 use ztr_lib::compressor;
-use ztr_lib::config::Config;
-use ztr_lib::ignore_rules::IgnoreRules;
+use ztr_lib::config::{self, ByteSize, Config, ResolvedTarget};
+use ztr_lib::convert;
+use ztr_lib::diff;
+use ztr_lib::extractor::{self, ExtractOptions};
+use ztr_lib::filter::{FilterPipeline, PipelineFiltered};
+use ztr_lib::format::Format;
+use ztr_lib::hooks;
+use ztr_lib::ignore_rules::{Explanation, IgnoreRules};
+use ztr_lib::listing::ListingEntry;
+use ztr_lib::notify::{self, Notification};
+use ztr_lib::precompress;
+use ztr_lib::progress::{CancellationToken, Cancelled, CliReporter};
+use ztr_lib::reader;
+use ztr_lib::report::{CompressionReport, PhaseTimings, human_size};
+use ztr_lib::retention;
+use ztr_lib::schedule::{self, CronSchedule};
+use ztr_lib::search::{self, SearchOptions};
+use ztr_lib::signing;
+use ztr_lib::stats;
+use ztr_lib::uploader;
+use ztr_lib::validate::{self, ConflictPolicy, PlannedRun};
+use ztr_lib::verifier;
+use ztr_lib::watch;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -14,9 +41,13 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// 指定配置文件路径
-    #[arg(short, long, value_name = "FILE")]
+    /// 指定配置文件路径，使用 `-` 从标准输入读取
+    #[arg(short, long, value_name = "FILE", global = true)]
     config: Option<PathBuf>,
+
+    /// 不读取任何配置文件，使用默认配置（可与命令行参数组合使用）
+    #[arg(long, global = true, conflicts_with = "config")]
+    no_config: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -27,93 +58,1409 @@ enum Commands {
     Show,
     /// 压缩指定目录
     Compress {
-        /// 要压缩的目录路径，默认为当前目录
+        /// 只压缩这些文件、目录或 glob 模式（如 src/ Cargo.toml 'assets/**/*.svg'），覆盖配置中的 sources
+        #[arg(value_name = "FILES", conflicts_with = "files_from")]
+        files: Vec<String>,
+
+        /// 要压缩的目录路径，默认为向上找到的 ztr.toml 所在的目录，没有时为当前目录
         #[arg(short, long, value_name = "PATH")]
         path: Option<PathBuf>,
+
+        /// 显示各阶段的耗时与吞吐量
+        #[arg(long)]
+        timings: bool,
+
+        /// 压缩完成后显示按扩展名的大小与压缩比，以及最大的 10 个文件
+        #[arg(long)]
+        stats: bool,
+
+        /// 跳过在参考压缩包中已存在且内容相同的文件，只压缩新增或修改的文件
+        #[arg(long, value_name = "ARCHIVE")]
+        exclude_from_archive: Option<PathBuf>,
+
+        /// 只压缩 git 跟踪的文件（等同于配置 source = "git"）
+        #[arg(long)]
+        git_tracked: bool,
+
+        /// 从文件（- 为标准输入）读取要压缩的路径，每行一个或以 NUL 分隔，代替遍历目录（类似 tar -T）
+        #[arg(short = 'T', long, value_name = "FILE")]
+        files_from: Option<String>,
+
+        /// 额外压缩目录 DIR，在压缩包中放在 PREFIX 下（默认为目录名），可多次指定，如 --source ../docs=docs
+        #[arg(long = "source", value_name = "DIR[=PREFIX]")]
+        extra_sources: Vec<String>,
+
+        /// 把所有条目放在与压缩包同名的顶层目录中，如 myproj/src/main.rs（等同于配置 wrap_root = true）
+        #[arg(long)]
+        wrap_root: bool,
+
+        /// 不生成压缩包，而是为每个文件生成 .gz 和 .br 预压缩版本（静态网站部署）
+        #[arg(long)]
+        precompress: bool,
+
+        /// 跳过大于 SIZE（如 100MB）的文件并列出它们，覆盖配置中的 max_file_size
+        #[arg(long, value_name = "SIZE", value_parser = parse_size_arg)]
+        max_size: Option<u64>,
+
+        /// 增量压缩：只压缩相对上一次清单新增或修改的文件，输出 <名称>.incr-<N>.<格式>
+        #[arg(long)]
+        incremental: bool,
+
+        /// 更新已有的压缩包：只压缩新增或修改的文件，未变化的条目直接从原压缩包复制（zip、tar.gz、tar.zst）
+        #[arg(long, conflicts_with = "incremental")]
+        update: bool,
+
+        /// 压缩完成后将压缩包上传到配置中的上传目标 [upload.s3]、[upload.http]
+        #[arg(long)]
+        upload: bool,
+
+        /// 只压缩修改时间晚于 WHEN 的文件（如 24h、7d、1700000000、2024-05-01），覆盖配置中的 newer_than
+        #[arg(long, value_name = "WHEN")]
+        newer_than: Option<String>,
+
+        /// 只压缩总量不超过 SIZE（如 100MB）的代表性样本，用于快速检验配置
+        #[arg(long, value_name = "SIZE", value_parser = parse_size_arg)]
+        sample: Option<u64>,
+
+        /// 压缩包的输出目录，不存在时会被创建（默认为要压缩的目录）；`-` 将压缩包写入标准输出
+        #[arg(short, long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        /// 压缩格式（zip、tar.gz、tar.zst、7z），覆盖配置文件中的 format
+        #[arg(long, value_name = "FORMAT")]
+        format: Option<Format>,
+
+        /// 输出文件名（不含扩展名，支持 {date} 等占位符），覆盖配置文件中的 output_name
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+
+        /// 覆盖已存在的同名压缩包（等同于配置 on_conflict = "overwrite"）
+        #[arg(short, long)]
+        force: bool,
+
+        /// 追加忽略规则（gitignore 语法，可重复指定），与配置中的规则合并，只对本次运行有效
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+
+        /// 只压缩匹配该模式的文件（gitignore 语法，可重复指定），追加到配置中的 include
+        #[arg(long, value_name = "PATTERN")]
+        include: Vec<String>,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]，其中的配置项覆盖顶层配置
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// 压缩配置文件中的所有 [[target]]（未指定 --path 时的默认行为，没有定义目标时报错）
+        #[arg(long, conflicts_with = "path")]
+        all: bool,
+    },
+    /// 解压压缩包
+    Extract {
+        /// 要解压的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 只解压匹配这些 glob 模式的条目（如 'src/**/*.rs'，语法与忽略规则相同），默认解压全部条目
+        #[arg(value_name = "PATTERN")]
+        patterns: Vec<String>,
+
+        /// 解压目标目录，默认为当前目录
+        #[arg(short = 'C', long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+
+        /// 去掉条目名称开头的 N 级路径（如压缩包中的 project-1.2.0/ 顶层目录），层级不足的条目被跳过
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        strip_components: usize,
+
+        /// 不恢复修改时间，解压出的文件使用当前时间
+        #[arg(long)]
+        touch: bool,
+
+        /// 不恢复权限位和文件属性
+        #[arg(long)]
+        no_permissions: bool,
+
+        /// 恢复文件所有者（仅 root 有效）
+        #[arg(long)]
+        same_owner: bool,
+
+        /// 允许绝对路径、包含 .. 的条目以及指向解压目录之外的符号链接（仅用于受信任的压缩包）
+        #[arg(long)]
+        unsafe_paths: bool,
+    },
+    /// 监视目录，文件变化后自动重新压缩（覆盖上一次的压缩包）
+    Watch {
+        /// 要监视并压缩的目录，默认与 compress 相同
+        #[arg(short = 'p', long = "path", value_name = "DIR")]
+        base_dir: Option<PathBuf>,
+
+        /// 最后一次变化之后等待的毫秒数，期间的变化合并为一次压缩
+        #[arg(long, value_name = "MS", default_value_t = 500)]
+        debounce: u64,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// 在前台按 cron 表达式定时压缩，并记录每次运行的结果
+    Schedule {
+        /// cron 表达式 "分 时 日 月 周"（本地时间），如 "0 2 * * *"，也支持 @daily 等简写
+        #[arg(value_name = "CRON")]
+        cron: String,
+
+        /// 要压缩的目录，默认与 compress 相同（配置了 [[target]] 时压缩所有目标）
+        #[arg(short = 'p', long = "path", value_name = "DIR")]
+        path: Option<PathBuf>,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// 创建带时间戳的备份快照，并记录到备份目录的快照目录 catalog.toml 中
+    Backup {
+        /// 要备份的目录，默认与 compress 相同
+        #[arg(short = 'p', long = "path", value_name = "DIR")]
+        base_dir: Option<PathBuf>,
+
+        /// 备份目录，覆盖配置中的 backup_dir（默认为要备份的目录下的 .ztr-backups）
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        /// 不创建快照，而是列出备份目录中的所有快照
+        #[arg(long)]
+        list: bool,
+    },
+    /// 从备份快照恢复文件
+    Restore {
+        /// 只恢复这些路径（相对于被备份的目录，目录包括其中的所有文件），默认恢复全部
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// 快照 ID（可以是唯一的前缀），latest 表示最新的快照
+        #[arg(long, value_name = "ID", default_value = "latest")]
+        snapshot: String,
+
+        /// 恢复到的目录，默认为当前目录
+        #[arg(short = 'C', long, value_name = "DIR")]
+        dir: Option<PathBuf>,
+
+        /// 被备份的目录，用于确定默认的备份目录，默认与 compress 相同
+        #[arg(short = 'p', long = "path", value_name = "DIR")]
+        base_dir: Option<PathBuf>,
+
+        /// 备份目录，覆盖配置中的 backup_dir
+        #[arg(long, value_name = "DIR")]
+        backup_dir: Option<PathBuf>,
+
+        /// 不恢复修改时间，恢复出的文件使用当前时间
+        #[arg(long)]
+        touch: bool,
+    },
+    /// 检查路径是否会被压缩，并显示起决定作用的规则及其来源
+    CheckIgnore {
+        /// 要检查的路径（相对于当前目录）
+        #[arg(value_name = "PATH", required = true)]
+        paths: Vec<PathBuf>,
+
+        /// 要压缩的目录，默认与 compress 相同
+        #[arg(short = 'p', long = "path", value_name = "DIR")]
+        base_dir: Option<PathBuf>,
+
+        /// 追加忽略规则，与 compress --exclude 相同
+        #[arg(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+
+        /// 包含规则，与 compress --include 相同
+        #[arg(long, value_name = "PATTERN")]
+        include: Vec<String>,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// 固定压缩包，使其不会被自动清理（写入 <压缩包>.pin 标记文件）
+    Pin {
+        /// 要固定的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 固定的原因，写入标记文件中
+        #[arg(long, value_name = "TEXT")]
+        reason: Option<String>,
+
+        /// 取消固定
+        #[arg(long)]
+        remove: bool,
+    },
+    /// 将压缩包上传到配置中的上传目标 [upload.s3]、[upload.http]
+    Upload {
+        /// 要上传的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// 按保留策略删除输出目录中的旧压缩包（被固定的压缩包不会被删除）
+    Clean {
+        /// 要压缩的目录，默认与 compress 相同
+        #[arg(short = 'p', long = "path", value_name = "DIR")]
+        base_dir: Option<PathBuf>,
+
+        /// 保留最新的 N 个压缩包，覆盖配置中的 retention.keep_last
+        #[arg(long, value_name = "N")]
+        keep_last: Option<usize>,
+
+        /// 保留最近 N 天内的压缩包，覆盖配置中的 retention.keep_days
+        #[arg(long, value_name = "N")]
+        keep_days: Option<u64>,
+
+        /// 只列出将被删除的压缩包，不实际删除
+        #[arg(long)]
+        dry_run: bool,
+
+        /// 使用配置文件中的命名方案 [profile.<NAME>]
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+    },
+    /// 将压缩包转换为另一种格式，条目直接写入新的压缩包而不解压到磁盘
+    Convert {
+        /// 要转换的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 目标格式（zip、tar.gz、tar.zst、7z）
+        #[arg(long, value_name = "FORMAT")]
+        to: Format,
+
+        /// 输出文件路径，默认为替换源压缩包扩展名后的路径
+        #[arg(short = 'o', long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// 覆盖已存在的输出文件
+        #[arg(short = 'f', long)]
+        force: bool,
+    },
+    /// 比较两个压缩包或目录，列出新增、删除与修改的文件 (按大小与 SHA-256 判断)
+    Diff {
+        /// 旧的压缩包或目录，如上一次发布的压缩包
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        /// 新的压缩包或目录，如当前的源目录
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
+
+        /// 列出目录中的所有文件，不应用目录下 ztr.toml 的源路径与忽略规则
+        #[arg(long)]
+        no_ignore: bool,
+    },
+    /// 列出压缩包中的文件及其大小（格式按文件内容判断）
+    List {
+        /// 要列出的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 同时显示条目的修改时间与权限
+        #[arg(short, long)]
+        long: bool,
+    },
+    /// 显示压缩包按扩展名的大小与压缩比，以及最大的文件
+    Stats {
+        /// 要统计的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 列出的最大文件数
+        #[arg(long, value_name = "N", default_value_t = stats::DEFAULT_TOP)]
+        top: usize,
+    },
+    /// 在压缩包中查找条目，可选地查找条目内容，不需要解压
+    Find {
+        /// 要查找的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 条目名称的模式 (Gitignore 语法，如 '*.sql')，不指定时查找所有条目
+        #[arg(value_name = "PATTERN")]
+        patterns: Vec<String>,
+
+        /// 只保留内容包含该文本的条目，并显示匹配的行
+        #[arg(long, value_name = "TEXT")]
+        contains: Option<String>,
+
+        /// 查找内容时忽略大小写
+        #[arg(short, long, requires = "contains")]
+        ignore_case: bool,
+    },
+    /// 校验压缩包的完整性
+    Verify {
+        /// 要校验的压缩包路径
+        #[arg(value_name = "ARCHIVE")]
+        archive: PathBuf,
+
+        /// 同时根据压缩包旁的 .sha256/.blake3 文件校验压缩包的摘要
+        #[arg(long)]
+        checksum: bool,
+
+        /// 同时根据压缩包旁的 .minisig/.sig 文件校验压缩包的签名
+        #[arg(long)]
+        signature: bool,
+
+        /// 校验 minisign 签名所用的公钥文件 (默认为当前目录下的 minisign.pub)
+        #[arg(long, value_name = "FILE", requires = "signature")]
+        public_key: Option<PathBuf>,
     },
 }
 
+/// 因 Ctrl-C 被取消时的退出码 (128 + SIGINT)。
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// `ztr diff` 发现差异时的退出码，与 `diff(1)` 相同。
+const EXIT_DIFFERENT: i32 = 1;
+
+/// `ztr find` 没有找到条目时的退出码，与 `grep(1)` 相同。
+const EXIT_NOT_FOUND: i32 = 1;
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
         Some(Commands::Init) => {
-            Config::create_default_config_file(Some(&PathBuf::from("ztr.toml")))?;
+            Config::create_default_config_file(Some(Path::new(config::CONFIG_FILE_NAME)))?;
             println!("默认配置文件 ztr.toml 已创建。");
         }
         Some(Commands::Show) => {
             println!("支持的压缩格式：");
-            println!("- zip: 兼容性最好，几乎所有系统都支持");
-            println!("- tar.gz: Linux 常用格式，压缩率适中");
-            println!("- 7z: 压缩率最高，支持多种算法，但需要系统安装 7z 命令行工具");
+            for format in Format::ALL {
+                println!("- {}: {}", format, format.description());
+            }
         }
-        Some(Commands::Compress { path }) => {
-            let config_path = cli.config.unwrap_or_else(|| PathBuf::from("ztr.toml"));
-            let config = Config::load(&config_path)
-                .with_context(|| format!("无法加载配置文件: {}", config_path.display()))?;
+        Some(Commands::Compress {
+            files,
+            path,
+            timings,
+            stats,
+            exclude_from_archive,
+            git_tracked,
+            files_from,
+            extra_sources,
+            wrap_root,
+            precompress,
+            sample,
+            max_size,
+            newer_than,
+            incremental,
+            update,
+            upload,
+            output,
+            format,
+            name,
+            force,
+            exclude,
+            include,
+            profile,
+            all,
+        }) => {
+            let (mut config, default_base_dir) =
+                resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            let mut runs = plan_runs(config, path, default_base_dir, all)?;
+            let to_stdout = output.as_deref() == Some(Path::new("-"));
+            if to_stdout && runs.len() > 1 {
+                anyhow::bail!("--output - 只能用于单个压缩目标");
+            }
+            // 命令行中的相对路径相对于当前目录，而不是要压缩的目录
+            let output = output
+                .filter(|_| !to_stdout)
+                .map(|o| current_dir().map(|cwd| cwd.join(o)))
+                .transpose()?;
+            let files_from = match files_from {
+                Some(list) if list != "-" => {
+                    Some(current_dir()?.join(list).to_string_lossy().to_string())
+                }
+                other => other,
+            };
+            let sources = if files.is_empty() {
+                None
+            } else {
+                if runs.len() > 1 {
+                    anyhow::bail!("指定要压缩的文件时只能有一个压缩目标，请使用 --path 指定目录");
+                }
+                Some(collector::sources_from_paths(
+                    &files,
+                    &current_dir()?,
+                    &runs[0].base_dir,
+                )?)
+            };
+            let mut mapped = Vec::new();
+            for source in &extra_sources {
+                let (dir, prefix) = match source.split_once('=') {
+                    Some((dir, prefix)) => (dir, Some(prefix.to_string())),
+                    None => (source.as_str(), None),
+                };
+                mapped.push(SourceEntry::Mapped {
+                    path: current_dir()?.join(dir).to_string_lossy().to_string(),
+                    prefix,
+                });
+            }
+            if files_from.as_deref() == Some("-") && runs.len() > 1 {
+                anyhow::bail!("--files-from - 只能用于单个压缩目标，标准输入只能读取一次");
+            }
 
-            let base_dir =
-                path.unwrap_or_else(|| std::env::current_dir().expect("无法获取当前目录"));
-            if !base_dir.is_dir() {
-                anyhow::bail!("要压缩的路径不是一个目录: {}", base_dir.display());
+            // 命令行参数覆盖配置（包括各个压缩目标的配置）中的选项
+            for run in &mut runs {
+                let config = &mut run.config;
+                if let Some(format) = format {
+                    config.format = format;
+                }
+                if let Some(name) = &name {
+                    config.output_name = Some(name.clone());
+                }
+                if let Some(reference) = &exclude_from_archive {
+                    config.exclude_from_archive = Some(reference.to_string_lossy().to_string());
+                }
+                if git_tracked {
+                    config.source = Some(Source::Git);
+                }
+                if files_from.is_some() {
+                    config.files_from = files_from.clone();
+                }
+                if let Some(sources) = &sources {
+                    config.sources = Some(sources.iter().cloned().map(SourceEntry::from).collect());
+                }
+                if !mapped.is_empty() {
+                    // 只指定额外的源目录时，基础目录本身仍然整体压缩
+                    config
+                        .sources
+                        .get_or_insert_with(|| vec![SourceEntry::from(".")])
+                        .extend(mapped.iter().cloned());
+                }
+                if wrap_root {
+                    config.wrap_root = Some(true);
+                }
+                if precompress {
+                    config.precompress = Some(true);
+                }
+                if sample.is_some() {
+                    config.sample_bytes = sample;
+                }
+                if let Some(max_size) = max_size {
+                    config.max_file_size = Some(ByteSize(max_size));
+                }
+                if incremental {
+                    config.incremental = Some(true);
+                }
+                if update {
+                    config.update = Some(true);
+                }
+                if upload {
+                    config.upload.get_or_insert_with(Default::default).auto = Some(true);
+                }
+                if let Some(newer_than) = &newer_than {
+                    config.newer_than = Some(newer_than.clone());
+                }
+                if force {
+                    config.on_conflict = Some(ConflictPolicy::Overwrite);
+                }
+                if !exclude.is_empty() {
+                    config
+                        .extra_ignore
+                        .get_or_insert_with(Vec::new)
+                        .extend(exclude.iter().cloned());
+                }
+                if !include.is_empty() {
+                    config
+                        .include
+                        .get_or_insert_with(Vec::new)
+                        .extend(include.iter().cloned());
+                }
+                if let Some(output) = &output {
+                    config.output_dir = Some(output.to_string_lossy().to_string());
+                }
             }
 
-            // 收集所有文件路径
-            let all_files = collect_all_files(&base_dir)?;
+            if to_stdout {
+                compress_to_stdout(
+                    &runs[0].config,
+                    &runs[0].base_dir,
+                    &install_interrupt_handler(),
+                )?;
+            } else {
+                let report_options = ReportOptions { timings, stats };
+                run_all(&runs, report_options, &install_interrupt_handler())?;
+            }
+        }
+        Some(Commands::Extract {
+            archive,
+            patterns,
+            dir,
+            strip_components,
+            touch,
+            no_permissions,
+            same_owner,
+            unsafe_paths,
+        }) => {
+            let target_dir = match dir {
+                Some(dir) => dir,
+                None => current_dir()?,
+            };
+            let options = ExtractOptions {
+                restore_mtime: !touch,
+                restore_permissions: !no_permissions,
+                restore_ownership: same_owner,
+                patterns,
+                strip_components,
+                cancel: Some(CancellationToken::from(install_interrupt_handler())),
+                unsafe_paths,
+                ..Default::default()
+            };
+            let report = match extractor::extract_archive(&archive, &target_dir, &options) {
+                Ok(report) => report,
+                Err(e) if Cancelled::is_cause_of(&e) => {
+                    eprintln!("✗ 已取消，目标目录中只有部分文件");
+                    std::process::exit(EXIT_INTERRUPTED);
+                }
+                Err(e) => return Err(e),
+            };
+            println!(
+                "✓ 已解压到 {}: {} 个文件, 共 {} 字节",
+                target_dir.display(),
+                report.entries,
+                report.bytes
+            );
+        }
+        Some(Commands::Watch {
+            base_dir,
+            debounce,
+            profile,
+        }) => {
+            let (mut config, default_base_dir) =
+                resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            // 每次变化后都替换上一次的压缩包
+            config.on_conflict = Some(ConflictPolicy::Overwrite);
+            let base_dir = current_dir()?.join(base_dir.unwrap_or(default_base_dir));
+            let cancel = install_interrupt_handler();
 
-            // 应用忽略规则
-            let ignore_rules = IgnoreRules::new(&config.get_ignore_rules(), &base_dir)?;
-            let files_to_compress = ignore_rules.filter_files(all_files.into_iter())?;
+            run_compress(&config, &base_dir, ReportOptions::default(), &cancel)?;
+            println!("正在监视 {}，按 Ctrl-C 退出...", base_dir.display());
+            watch::watch(
+                &config,
+                &base_dir,
+                Duration::from_millis(debounce),
+                &cancel,
+                |changed| {
+                    println!("\n==> 检测到 {} 处变化，重新压缩", changed.len());
+                    // 单次压缩失败（例如文件正在写入）不结束监视
+                    if let Err(e) =
+                        run_compress(&config, &base_dir, ReportOptions::default(), &cancel)
+                    {
+                        eprintln!("✗ 压缩失败: {:#}", e);
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        Some(Commands::Schedule {
+            cron,
+            path,
+            profile,
+        }) => {
+            let schedule = CronSchedule::parse(&cron)?;
+            let (mut config, default_base_dir) =
+                resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            let runs = plan_runs(config, path, default_base_dir, false)?;
+            let cancel = install_interrupt_handler();
+            println!("已启动定时压缩 ({})，按 Ctrl-C 退出", cron);
+            loop {
+                let now = unix_now();
+                let Some(next) = schedule.next_after_local(now) else {
+                    anyhow::bail!("cron 表达式永远不会触发: {}", cron);
+                };
+                println!("下一次运行: {}", schedule::format_local_time(next));
+                // 分段等待，以便及时响应 Ctrl-C
+                while unix_now() < next {
+                    if cancel.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
+                    std::thread::sleep(Duration::from_millis(500));
+                }
 
-            if files_to_compress.is_empty() {
-                println!("没有需要压缩的文件。");
+                println!("[{}] 开始运行", schedule::format_local_time(unix_now()));
+                // 单次运行失败只记录日志，不结束定时任务
+                match run_all(&runs, ReportOptions::default(), &cancel) {
+                    Ok(()) => println!("[{}] 运行完成", schedule::format_local_time(unix_now())),
+                    Err(e) => eprintln!(
+                        "[{}] ✗ 运行失败: {:#}",
+                        schedule::format_local_time(unix_now()),
+                        e
+                    ),
+                }
+            }
+        }
+        Some(Commands::Backup {
+            base_dir,
+            backup_dir,
+            profile,
+            list,
+        }) => {
+            let (mut config, default_base_dir) =
+                resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            let cwd = current_dir()?;
+            let base_dir = cwd.join(base_dir.unwrap_or(default_base_dir));
+            if let Some(dir) = backup_dir {
+                config.backup_dir = Some(cwd.join(dir).to_string_lossy().to_string());
+            }
+            if list {
+                let backup_dir = backup::backup_dir(&config, &base_dir);
+                let catalog = Catalog::load(&backup_dir)?;
+                if catalog.snapshots.is_empty() {
+                    println!("{} 中还没有任何快照。", backup_dir.display());
+                }
+                for snapshot in &catalog.snapshots {
+                    println!(
+                        "{}  {} 个文件, {} -> {}  {}",
+                        snapshot.id,
+                        snapshot.files,
+                        human_size(snapshot.bytes),
+                        human_size(snapshot.archive_bytes),
+                        snapshot.source
+                    );
+                }
                 return Ok(());
             }
 
-            let output_archive_path =
-                compressor::compress_directory(&config, &base_dir, files_to_compress)?;
-            println!("压缩文件已创建: {}", output_archive_path.display());
+            let cancel = install_interrupt_handler();
+            let reporter = CliReporter::new().with_cancel_flag(cancel);
+            match backup::create_snapshot(&config, &base_dir, &reporter) {
+                Ok(snapshot) => println!(
+                    "✓ 已创建快照 {}: {} 个文件, {} -> {}",
+                    snapshot.id,
+                    snapshot.files,
+                    human_size(snapshot.bytes),
+                    human_size(snapshot.archive_bytes)
+                ),
+                Err(e) if Cancelled::is_cause_of(&e) => {
+                    eprintln!("✗ 已取消，未创建快照");
+                    std::process::exit(EXIT_INTERRUPTED);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Some(Commands::Restore {
+            paths,
+            snapshot,
+            dir,
+            base_dir,
+            backup_dir,
+            touch,
+        }) => {
+            let (config, default_base_dir) = resolve_config(cli.config.as_deref(), cli.no_config)?;
+            let cwd = current_dir()?;
+            let backup_dir = match backup_dir {
+                Some(dir) => cwd.join(dir),
+                None => {
+                    backup::backup_dir(&config, &cwd.join(base_dir.unwrap_or(default_base_dir)))
+                }
+            };
+            let target_dir = match dir {
+                Some(dir) => dir,
+                None => cwd,
+            };
+            let options = ExtractOptions {
+                restore_mtime: !touch,
+                paths,
+                ..Default::default()
+            };
+            let (snapshot, report) =
+                backup::restore_snapshot(&backup_dir, &snapshot, &target_dir, &options)?;
+            println!(
+                "✓ 已将快照 {} 恢复到 {}: {} 个文件, 共 {} 字节",
+                snapshot.id,
+                target_dir.display(),
+                report.entries,
+                report.bytes
+            );
+        }
+        Some(Commands::CheckIgnore {
+            paths,
+            base_dir,
+            exclude,
+            include,
+            profile,
+        }) => {
+            let (mut config, default_base_dir) =
+                resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            if !exclude.is_empty() {
+                config
+                    .extra_ignore
+                    .get_or_insert_with(Vec::new)
+                    .extend(exclude);
+            }
+            if !include.is_empty() {
+                config.include.get_or_insert_with(Vec::new).extend(include);
+            }
+            let cwd = current_dir()?;
+            let base_dir = normalize_path(&cwd.join(base_dir.unwrap_or(default_base_dir)));
+            let rules = IgnoreRules::from_config(&config, &base_dir)?;
+            for path in paths {
+                let explanation = rules.explain(&normalize_path(&cwd.join(&path)), path.is_dir());
+                let verdict = if explanation.is_ignored() {
+                    "✗ 忽略"
+                } else {
+                    "✓ 压缩"
+                };
+                let reason = match &explanation {
+                    Explanation::NotMatched => "没有匹配的规则".to_string(),
+                    Explanation::Ignored(rule) => format!("匹配 {}", rule),
+                    Explanation::Whitelisted(rule) => format!("被 {} 重新包含", rule),
+                    Explanation::Included(rule) => format!("匹配包含规则 {}", rule.pattern),
+                    Explanation::NotIncluded => "不匹配任何包含规则".to_string(),
+                };
+                println!("{} {}: {}", verdict, path.display(), reason);
+            }
+        }
+        Some(Commands::Pin {
+            archive,
+            reason,
+            remove,
+        }) => {
+            if remove {
+                if retention::unpin(&archive)? {
+                    println!("✓ 已取消固定: {}", archive.display());
+                } else {
+                    println!("压缩包未被固定: {}", archive.display());
+                }
+            } else {
+                retention::pin(&archive, reason.as_deref())?;
+                println!("✓ 已固定: {}", archive.display());
+            }
+        }
+        Some(Commands::Upload { archive, profile }) => {
+            let (mut config, _) = resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            upload_archive(&config, &archive, &install_interrupt_handler())?;
+        }
+        Some(Commands::Clean {
+            base_dir,
+            keep_last,
+            keep_days,
+            dry_run,
+            profile,
+        }) => {
+            let (mut config, default_base_dir) =
+                resolve_config(cli.config.as_deref(), cli.no_config)?;
+            if let Some(profile) = profile {
+                config = config.with_profile(&profile)?;
+            }
+            let base_dir = current_dir()?.join(base_dir.unwrap_or(default_base_dir));
+            let mut policy = config.retention.unwrap_or_default();
+            if keep_last.is_some() {
+                policy.keep_last = keep_last;
+            }
+            if keep_days.is_some() {
+                policy.keep_days = keep_days;
+            }
+            if !policy.is_set() {
+                anyhow::bail!(
+                    "未配置保留策略：请在配置中设置 retention = {{ keep_last = 5, keep_days = 30 }} 或使用 --keep-last/--keep-days"
+                );
+            }
+
+            let plan = retention::plan_for_config(&config, &base_dir, &policy)?;
+            for archive in plan.keep.iter().filter(|a| a.pinned) {
+                println!("  保留 {} (已固定)", archive.path.display());
+            }
+            for archive in &plan.delete {
+                if dry_run {
+                    println!("  将删除 {}", archive.path.display());
+                } else {
+                    retention::remove_archive(&archive.path)?;
+                    println!("  已删除 {}", archive.path.display());
+                }
+            }
+            let verb = if dry_run { "将删除" } else { "已删除" };
+            println!(
+                "✓ {} {} 个压缩包，保留 {} 个",
+                verb,
+                plan.delete.len(),
+                plan.keep.len()
+            );
+        }
+        Some(Commands::Convert {
+            archive,
+            to,
+            output,
+            force,
+        }) => {
+            let output = output.unwrap_or_else(|| convert::default_output(&archive, to));
+            if output.exists() && !force {
+                anyhow::bail!("输出文件已存在: {} (使用 --force 覆盖)", output.display());
+            }
+            let reporter = CliReporter::new()
+                .with_cancel_flag(install_interrupt_handler())
+                .with_messages("正在转换...", "转换完成");
+            let report = convert::convert_archive(&archive, &output, to, &reporter)?;
+            println!(
+                "✓ 已转换为 {}: {} 个条目, 共 {}, 耗时 {:.2}s",
+                report.output_path.display(),
+                report.entries,
+                human_size(report.bytes),
+                report.duration.as_secs_f64()
+            );
+        }
+        Some(Commands::Diff {
+            old,
+            new,
+            no_ignore,
+        }) => {
+            let old_entries = diff_snapshot(&old, no_ignore)?;
+            let new_entries = diff_snapshot(&new, no_ignore)?;
+            let changes = diff::compare(&old_entries, &new_entries);
+            for path in &changes.added {
+                println!("+ {}", path);
+            }
+            for path in &changes.removed {
+                println!("- {}", path);
+            }
+            for path in &changes.modified {
+                println!("~ {}", path);
+            }
+            if changes.is_empty() {
+                println!("✓ 没有差异: 共 {} 个文件", new_entries.len());
+            } else {
+                println!(
+                    "新增 {} 个, 删除 {} 个, 修改 {} 个文件",
+                    changes.added.len(),
+                    changes.removed.len(),
+                    changes.modified.len()
+                );
+                std::process::exit(EXIT_DIFFERENT);
+            }
+        }
+        Some(Commands::List { archive, long }) => {
+            let format = Format::detect(&archive)?;
+            if Format::from_path(&archive).is_some_and(|by_name| by_name != format) {
+                eprintln!("⚠ 扩展名与内容不符，按内容识别为 {} 格式", format);
+            }
+            let entries = reader::open_archive_reader(&archive)?.list()?;
+            for entry in &entries {
+                if long {
+                    let mode = entry
+                        .mode
+                        .map(|mode| format!("{:04o}", mode & 0o7777))
+                        .unwrap_or_else(|| "-".repeat(4));
+                    let mtime = entry
+                        .mtime
+                        .map(schedule::format_local_time)
+                        .unwrap_or_else(|| "-".to_string());
+                    println!(
+                        "{}  {:>19}  {:>10}  {}",
+                        mode,
+                        mtime,
+                        human_size(entry.size),
+                        entry.name
+                    );
+                } else {
+                    println!("{:>10}  {}", human_size(entry.size), entry.name);
+                }
+            }
+            let total: u64 = entries.iter().map(|entry| entry.size).sum();
+            println!(
+                "共 {} 个文件, {} ({})",
+                entries.len(),
+                human_size(total),
+                format
+            );
+        }
+        Some(Commands::Stats { archive, top }) => {
+            println!("{}", stats::archive_stats(&archive, top)?);
+        }
+        Some(Commands::Find {
+            archive,
+            patterns,
+            contains,
+            ignore_case,
+        }) => {
+            let options = SearchOptions {
+                patterns,
+                contains,
+                ignore_case,
+            };
+            let matches = search::find_entries(&archive, &options)?;
+            for found in &matches {
+                let note = if found.binary {
+                    "  (二进制文件匹配)"
+                } else {
+                    ""
+                };
+                println!(
+                    "{:>10}  {}{}",
+                    human_size(found.entry.size),
+                    found.entry.name,
+                    note
+                );
+                for line in &found.lines {
+                    println!("{:>10}: {}", line.number, line.text);
+                }
+            }
+            let total: u64 = matches.iter().map(|found| found.entry.size).sum();
+            println!("找到 {} 个文件, {}", matches.len(), human_size(total));
+            if matches.is_empty() {
+                std::process::exit(EXIT_NOT_FOUND);
+            }
+        }
+        Some(Commands::Verify {
+            archive,
+            checksum,
+            signature,
+            public_key,
+        }) => {
+            if signature {
+                let tool = signing::verify_signature(&archive, public_key.as_deref())?;
+                println!("✓ {} 签名有效", tool);
+            }
+            if checksum {
+                let checks = checksum::verify_sidecars(&archive)?;
+                for check in &checks {
+                    if check.is_ok() {
+                        println!("✓ {} 摘要一致: {}", check.algorithm, check.actual);
+                    } else {
+                        println!(
+                            "✗ {} 摘要不一致: 期望 {}, 实际 {}",
+                            check.algorithm, check.expected, check.actual
+                        );
+                    }
+                }
+                if !checks.iter().all(|check| check.is_ok()) {
+                    anyhow::bail!("压缩包摘要与校验和文件不一致: {}", archive.display());
+                }
+            }
+            let report = verifier::verify_archive(&archive)?;
+            for error in &report.errors {
+                println!("✗ {}: {}", error.name, error.message);
+            }
+            if !report.is_ok() {
+                anyhow::bail!("压缩包已损坏: {} 个条目校验失败", report.errors.len());
+            }
+            println!(
+                "✓ 压缩包完好: {} 个条目, 共 {} 字节",
+                report.entries, report.bytes
+            );
+            if let Some(errors) = archive_manifest::verify_embedded(&archive)? {
+                for error in &errors {
+                    println!("✗ {}: {}", error.name, error.message);
+                }
+                if !errors.is_empty() {
+                    anyhow::bail!("压缩包与内嵌清单不一致: {} 个文件", errors.len());
+                }
+                println!("✓ 与内嵌清单 {} 一致", archive_manifest::ENTRY_NAME);
+            }
         }
         None => {
-            let config_path = cli.config.unwrap_or_else(|| PathBuf::from("ztr.toml"));
-            if !config_path.exists() {
-                println!("未找到配置文件 ztr.toml。您可以运行 `ztr init` 创建一个默认配置文件。");
+            if cli.config.is_none() && !cli.no_config && Config::discover(&current_dir()?).is_none()
+            {
+                println!(
+                    "未找到配置文件 ztr.toml。您可以运行 `ztr init` 创建一个默认配置文件，或使用 `ztr --no-config` 以默认配置压缩当前目录。"
+                );
                 return Ok(());
             }
-            let config = Config::load(&config_path)
-                .with_context(|| format!("无法加载配置文件: {}", config_path.display()))?;
+            let (config, base_dir) = resolve_config(cli.config.as_deref(), cli.no_config)?;
+            let runs = plan_runs(config, None, base_dir, false)?;
+            run_all(
+                &runs,
+                ReportOptions::default(),
+                &install_interrupt_handler(),
+            )?;
+        }
+    }
 
-            let base_dir = std::env::current_dir().expect("无法获取当前目录");
+    Ok(())
+}
 
-            // 收集所有文件路径
-            let all_files = collect_all_files(&base_dir)?;
+/// 确定本次要执行的压缩。
+///
+/// 未指定 `--path` 且配置中定义了 `[[target]]` 时压缩所有目标，否则压缩 `path`（默认为 `default_base_dir`）。
+fn plan_runs(
+    config: Config,
+    path: Option<PathBuf>,
+    default_base_dir: PathBuf,
+    all: bool,
+) -> Result<Vec<ResolvedTarget>> {
+    let targets = match path {
+        Some(_) => Vec::new(),
+        None => config.targets(&default_base_dir)?,
+    };
+    if all && targets.is_empty() {
+        anyhow::bail!("配置中没有定义任何压缩目标 [[target]]");
+    }
+    if !targets.is_empty() {
+        return Ok(targets);
+    }
+    let base_dir = path.unwrap_or(default_base_dir);
+    Ok(vec![ResolvedTarget {
+        name: base_dir.display().to_string(),
+        base_dir,
+        config,
+    }])
+}
 
-            // 应用忽略规则
-            let ignore_rules = IgnoreRules::new(&config.get_ignore_rules(), &base_dir)?;
-            let files_to_compress = ignore_rules.filter_files(all_files.into_iter())?;
+/// 压缩完成后额外打印的统计信息。
+#[derive(Debug, Clone, Copy, Default)]
+struct ReportOptions {
+    /// 各阶段的耗时与吞吐量 (`--timings`)
+    timings: bool,
+    /// 按扩展名的大小与压缩比，以及最大的文件 (`--stats`)
+    stats: bool,
+}
 
-            if files_to_compress.is_empty() {
-                println!("没有需要压缩的文件。");
-                return Ok(());
-            }
+/// 依次执行所有压缩，遇到错误时停止。
+///
+/// 开始之前检查各次压缩是否写入同一个输出文件或源目录重叠，避免后面的压缩覆盖前面的结果。
+fn run_all(
+    runs: &[ResolvedTarget],
+    options: ReportOptions,
+    cancel: &Arc<AtomicBool>,
+) -> Result<()> {
+    for run in runs {
+        if !run.base_dir.is_dir() {
+            anyhow::bail!("要压缩的路径不是一个目录: {}", run.base_dir.display());
+        }
+    }
+    if runs.len() > 1 {
+        let planned: Vec<PlannedRun> = runs
+            .iter()
+            .map(|run| PlannedRun {
+                name: run.name.clone(),
+                base_dir: run.base_dir.clone(),
+                output_path: compressor::output_path(&run.config, &run.base_dir),
+            })
+            .collect();
+        validate::check_planned_runs(&planned)?;
+    }
 
-            let output_archive_path =
-                compressor::compress_directory(&config, &base_dir, files_to_compress)?;
-            println!("压缩文件已创建: {}", output_archive_path.display());
+    for run in runs {
+        if runs.len() > 1 {
+            println!("==> {} ({})", run.name, run.base_dir.display());
         }
+        run_compress(&run.config, &run.base_dir, options, cancel)?;
     }
+    Ok(())
+}
+
+/// 收集、过滤并压缩基础目录中的文件，结束后根据配置发送通知。
+///
+/// 按下 Ctrl-C 时删除写了一半的输出文件，并以退出码 130 结束进程。
+fn run_compress(
+    config: &Config,
+    base_dir: &Path,
+    options: ReportOptions,
+    cancel: &Arc<AtomicBool>,
+) -> Result<()> {
+    let started = Instant::now();
+    let result = compress_and_report(config, base_dir, options, cancel);
+    let mut notification = match &result {
+        Ok(Some(report)) => Notification::from_report(report),
+        Ok(None) => Notification {
+            success: true,
+            ..Default::default()
+        },
+        Err(e) => Notification::from_error(e),
+    };
+    notification.duration_secs = started.elapsed().as_secs_f64();
+    if let Err(e) = notify::send(config, &notification) {
+        eprintln!("⚠ 发送通知失败: {:#}", e);
+    }
+    if let Err(e) = &result
+        && Cancelled::is_cause_of(e)
+    {
+        eprintln!("✗ 已取消，未生成压缩包");
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+    result.map(|_| ())
+}
 
+/// 注册 Ctrl-C 处理函数，返回收到信号时被置位的取消标志。
+///
+/// 第一次 Ctrl-C 请求取消并等待清理完成，第二次立即退出。
+fn install_interrupt_handler() -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = cancel.clone();
+    let result = ctrlc::set_handler(move || {
+        if flag.swap(true, Ordering::SeqCst) {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        eprintln!("\n正在取消，再次按 Ctrl-C 立即退出...");
+    });
+    if let Err(e) = result {
+        eprintln!("⚠ 无法注册 Ctrl-C 处理函数: {}", e);
+    }
+    cancel
+}
+
+/// 收集、过滤并压缩基础目录中的文件，返回压缩结果（没有生成压缩包时为 `None`）。
+fn compress_and_report(
+    config: &Config,
+    base_dir: &Path,
+    options: ReportOptions,
+    cancel: &Arc<AtomicBool>,
+) -> Result<Option<CompressionReport>> {
+    let mut timings = PhaseTimings::new();
+    hooks::run_pre(config, base_dir)?;
+
+    let filtered = filtered_files(config, base_dir, &mut timings)?;
+    // 统计中的跳过数包括因大小限制被跳过的文件
+    let size_skipped = filtered.skipped_by_size();
+    let files_to_compress = filtered.kept;
+    if files_to_compress.is_empty() {
+        println!("没有需要压缩的文件。");
+        return Ok(None);
+    }
+
+    if config.precompress.unwrap_or(false) {
+        let report = timings.time("compress", || {
+            let report = precompress::precompress_files(
+                &files_to_compress,
+                &CliReporter::new().with_cancel_flag(cancel.clone()),
+            );
+            let (files, bytes) = report
+                .as_ref()
+                .map(|r| (r.files, r.input_bytes))
+                .unwrap_or_default();
+            (report, files, bytes)
+        })?;
+        println!(
+            "已为 {} 个文件生成预压缩版本: {} 字节 -> gzip {} 字节, brotli {} 字节, 跳过 {} 个已压缩的文件",
+            report.files,
+            report.input_bytes,
+            report.gzip_bytes,
+            report.brotli_bytes,
+            report.skipped.len()
+        );
+        if options.timings {
+            println!("{}", timings);
+        }
+        return Ok(None);
+    }
+
+    let mut report = timings.time("compress", || {
+        let reporter = CliReporter::new().with_cancel_flag(cancel.clone());
+        let report = compressor::compress_directory(config, base_dir, files_to_compress, &reporter);
+        let (files, bytes) = report
+            .as_ref()
+            .map(|r| (r.files_count, r.input_bytes))
+            .unwrap_or_default();
+        (report, files, bytes)
+    })?;
+    if report.files_count == 0 {
+        // 例如增量压缩或参考压缩包中没有新增或修改的文件，没有生成压缩包
+        println!("没有需要压缩的文件。");
+        return Ok(None);
+    }
+    report.skipped.extend(size_skipped);
+    // 摘要由命令行根据返回的报告打印，库本身不产生任何标准输出
+    println!("✓ 压缩完成");
+    println!("文件大小: {}", human_size(report.output_bytes));
+    println!("压缩文件已创建: {}", report.output_path.display());
+    for (algorithm, digest) in &report.checksums {
+        println!("{}: {}", algorithm, digest);
+    }
+    if let Some(signature) = &report.signature {
+        println!("签名文件已创建: {}", signature.display());
+    }
+    println!("{}", report);
+
+    if options.stats {
+        println!();
+        println!(
+            "{}",
+            stats::archive_stats(&report.output_path, stats::DEFAULT_TOP)?
+        );
+    }
+    if options.timings {
+        println!("{}", timings);
+    }
+    if config
+        .upload
+        .as_ref()
+        .is_some_and(|upload| upload.auto.unwrap_or(false))
+    {
+        upload_archive(config, &report.output_path, cancel)?;
+    }
+    hooks::run_post(config, base_dir, &report)?;
+
+    Ok(Some(report))
+}
+
+/// 收集基础目录中的文件，并应用配置中的过滤器（见 [`FilterPipeline::from_config`]）。
+fn filtered_files(
+    config: &Config,
+    base_dir: &Path,
+    timings: &mut PhaseTimings,
+) -> Result<PipelineFiltered> {
+    // 收集所有文件路径
+    let all_files = timings.time("walk", || {
+        let files = collector::collect_for_config(config, base_dir);
+        let count = files.as_ref().map(|f| f.len()).unwrap_or(0);
+        (files, count, 0)
+    })?;
+
+    // 应用忽略规则、修改时间、大小限制与二进制文件过滤
+    let pipeline = FilterPipeline::from_config(config, base_dir)?;
+    let filtered = timings.time("filter", || {
+        let files = pipeline.filter_files(all_files);
+        let count = files.as_ref().map(|f| f.kept.len()).unwrap_or(0);
+        (files, count, 0)
+    })?;
+    for warning in &filtered.warnings {
+        eprintln!("⚠ {}", warning);
+    }
+    Ok(filtered)
+}
+
+/// 将压缩包写入标准输出（`--output -`），不打印任何提示信息，也不执行钩子与上传。
+fn compress_to_stdout(config: &Config, base_dir: &Path, cancel: &Arc<AtomicBool>) -> Result<()> {
+    if !base_dir.is_dir() {
+        anyhow::bail!("要压缩的路径不是一个目录: {}", base_dir.display());
+    }
+    if config.precompress.unwrap_or(false) {
+        anyhow::bail!("--output - 不能与 --precompress 一起使用");
+    }
+    let files = filtered_files(config, base_dir, &mut PhaseTimings::new())?.kept;
+    let reporter = CliReporter::new().quiet().with_cancel_flag(cancel.clone());
+    let stdout = std::io::stdout().lock();
+    match compressor::compress_to_stream(config, base_dir, files, stdout, &reporter) {
+        Ok(_) => Ok(()),
+        Err(e) if Cancelled::is_cause_of(&e) => {
+            eprintln!("✗ 已取消，输出的压缩包不完整");
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// 将压缩包上传到配置中的上传目标并打印结果，上传过程中显示进度条。
+fn upload_archive(config: &Config, archive: &Path, cancel: &Arc<AtomicBool>) -> Result<()> {
+    let reporter = CliReporter::new()
+        .with_cancel_flag(cancel.clone())
+        .with_messages("正在上传...", "上传完成");
+    for report in uploader::upload(config, archive, &reporter)? {
+        println!(
+            "✓ 已上传到 {} ({}, 耗时 {:.2}s)",
+            report.url,
+            human_size(report.bytes),
+            report.duration.as_secs_f64()
+        );
+    }
     Ok(())
 }
 
-/// 递归地收集指定目录中所有文件的路径。
-fn collect_all_files(dir: &Path) -> Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-    for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path().to_path_buf();
-        if path.is_file() {
-            files.push(path);
+/// 当前的 Unix 时间戳 (秒)。
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 获取当前工作目录（例如目录已被删除时返回错误而不是 panic）。
+fn current_dir() -> Result<PathBuf> {
+    std::env::current_dir().context("无法获取当前目录")
+}
+
+/// 按字面去掉路径中的 `.` 和 `..`（不访问文件系统），使 `../a.log` 这样的参数能与以基础目录为起点的规则匹配。
+fn normalize_path(path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
         }
     }
-    Ok(files)
+    normalized
+}
+
+/// 解析命令行中带单位的大小参数。
+fn parse_size_arg(s: &str) -> Result<u64, String> {
+    config::parse_size(s).map_err(|e| e.to_string())
+}
+
+/// 判断路径是否为代表标准输入的 `-`。
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// 确定本次运行使用的配置，以及未指定 `--path` 时要压缩的目录。
+///
+/// 指定了 `--config` 时加载该文件（不存在时报错）；`--no-config` 时使用默认配置；
+/// 否则从当前目录向上查找 `ztr.toml`（在仓库根目录处停止），未找到时使用默认配置。
+/// 向上找到的配置文件所在的目录即为默认要压缩的目录，因此可以在项目的任意子目录中运行，
+/// 其他情况下默认压缩当前目录。
+fn resolve_config(config_path: Option<&Path>, no_config: bool) -> Result<(Config, PathBuf)> {
+    let cwd = current_dir()?;
+    match config_path {
+        Some(path) => Ok((load_config(path)?, cwd)),
+        None if no_config => Ok((Config::default(), cwd)),
+        None => match Config::discover(&cwd) {
+            Some(path) => {
+                let base_dir = path.parent().map(Path::to_path_buf).unwrap_or(cwd);
+                Ok((load_config(&path)?, base_dir))
+            }
+            None => Ok((Config::default(), cwd)),
+        },
+    }
+}
+
+/// 读取 `ztr diff` 一侧的文件记录：文件按压缩包读取，目录按其中的 `ztr.toml`
+/// (不存在时使用默认配置) 选择文件；`no_ignore` 时列出目录中的所有文件。
+fn diff_snapshot(path: &Path, no_ignore: bool) -> Result<Vec<ListingEntry>> {
+    if !path.is_dir() {
+        return diff::snapshot_archive(path)
+            .with_context(|| format!("读取压缩包失败: {}", path.display()));
+    }
+    if no_ignore {
+        return diff::snapshot_directory(path, None);
+    }
+    let config_path = path.join(config::CONFIG_FILE_NAME);
+    let config = if config_path.is_file() {
+        load_config(&config_path)?
+    } else {
+        Config::default()
+    };
+    diff::snapshot_directory(path, Some(&config))
+}
+
+/// 加载配置文件，路径为 `-` 时从标准输入读取。
+fn load_config(config_path: &Path) -> Result<Config> {
+    if is_stdin(config_path) {
+        return Config::from_reader(std::io::stdin().lock()).context("无法从标准输入加载配置");
+    }
+    Config::load(config_path)
+        .with_context(|| format!("无法加载配置文件: {}", config_path.display()))
 }
@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 附加到压缩包条目上的元数据。
+///
+/// - `comment`: ZIP 中写为条目注释；TAR 中写为 PAX 的 `comment` 记录。
+/// - `pax`: 额外的 PAX 扩展记录，仅 TAR 格式使用。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryMetadata {
+    /// 条目注释
+    pub comment: Option<String>,
+    /// PAX 扩展记录 (键值对)
+    pub pax: Option<BTreeMap<String, String>>,
+}
+
+impl EntryMetadata {
+    /// 是否没有任何元数据。
+    pub fn is_empty(&self) -> bool {
+        self.comment.is_none() && self.pax.as_ref().is_none_or(|p| p.is_empty())
+    }
+
+    /// 返回写入 TAR 时使用的全部 PAX 记录，包括由 `comment` 转换而来的记录。
+    pub fn pax_records(&self) -> Vec<(String, String)> {
+        let mut records = Vec::new();
+        if let Some(comment) = &self.comment {
+            records.push(("comment".to_string(), comment.clone()));
+        }
+        if let Some(pax) = &self.pax {
+            for (key, value) in pax {
+                records.push((key.clone(), value.clone()));
+            }
+        }
+        records
+    }
+
+    /// 将另一份元数据合并到当前元数据上，`other` 中的值优先。
+    fn merge(&mut self, other: &EntryMetadata) {
+        if other.comment.is_some() {
+            self.comment = other.comment.clone();
+        }
+        if let Some(pax) = &other.pax {
+            self.pax
+                .get_or_insert_with(BTreeMap::new)
+                .extend(pax.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+}
+
+/// 按 glob 模式为条目查找元数据。
+///
+/// 模式相对于基础目录匹配条目的相对路径，`*` 不跨越目录分隔符，`**` 匹配任意层级目录。
+/// 当多个模式匹配同一条目时，按模式的字典序依次合并，后者覆盖前者。
+#[derive(Debug, Default)]
+pub struct MetadataRules {
+    rules: Vec<(glob::Pattern, EntryMetadata)>,
+}
+
+impl MetadataRules {
+    /// 从配置中的 `模式 -> 元数据` 映射创建规则。
+    ///
+    /// # 参数
+    /// - `map`: glob 模式到元数据的映射。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 成功时返回规则，模式无效时返回错误信息。
+    pub fn new(map: &BTreeMap<String, EntryMetadata>) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (pattern, metadata) in map {
+            let compiled = glob::Pattern::new(pattern)
+                .with_context(|| format!("无效的元数据匹配模式: {}", pattern))?;
+            rules.push((compiled, metadata.clone()));
+        }
+        Ok(Self { rules })
+    }
+
+    /// 查找给定相对路径（使用 `/` 分隔）的元数据。
+    pub fn lookup(&self, relative_path: &str) -> EntryMetadata {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+
+        let mut metadata = EntryMetadata::default();
+        for (pattern, rule_metadata) in &self.rules {
+            if pattern.matches_with(relative_path, options) {
+                metadata.merge(rule_metadata);
+            }
+        }
+        metadata
+    }
+}
+
+/// 将 PAX 记录编码为扩展头的数据部分 (`"<长度> <键>=<值>\n"`)。
+pub fn encode_pax_records(records: &[(String, String)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (key, value) in records {
+        // 记录长度包含长度字段本身，因此需要迭代求解
+        let rest = key.len() + value.len() + 3; // 空格、等号与换行
+        let mut len = rest + 1;
+        while len != rest + len.to_string().len() {
+            len = rest + len.to_string().len();
+        }
+        data.extend_from_slice(format!("{} {}={}\n", len, key, value).as_bytes());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_merges_matching_rules() -> Result<()> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "dist/**".to_string(),
+            EntryMetadata {
+                comment: Some("generated".to_string()),
+                pax: Some(BTreeMap::from([(
+                    "ztr.kind".to_string(),
+                    "build".to_string(),
+                )])),
+            },
+        );
+        map.insert(
+            "dist/*.map".to_string(),
+            EntryMetadata {
+                comment: Some("source map".to_string()),
+                pax: None,
+            },
+        );
+        let rules = MetadataRules::new(&map)?;
+
+        let metadata = rules.lookup("dist/app.js.map");
+        assert_eq!(metadata.comment, Some("source map".to_string()));
+        assert_eq!(
+            metadata.pax_records(),
+            vec![
+                ("comment".to_string(), "source map".to_string()),
+                ("ztr.kind".to_string(), "build".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            rules.lookup("dist/js/app.js").comment,
+            Some("generated".to_string())
+        );
+        assert!(rules.lookup("src/main.rs").is_empty());
+        // `*` 不跨越目录分隔符
+        assert_eq!(
+            rules.lookup("dist/js/app.js.map").comment,
+            Some("generated".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let map = BTreeMap::from([("[".to_string(), EntryMetadata::default())]);
+        assert!(MetadataRules::new(&map).is_err());
+    }
+
+    #[test]
+    fn test_encode_pax_records() {
+        let data = encode_pax_records(&[("comment".to_string(), "hi".to_string())]);
+        assert_eq!(data, b"14 comment=hi\n");
+
+        // 长度字段位数变化时仍然正确
+        let value = "x".repeat(90);
+        let data = encode_pax_records(&[("k".to_string(), value)]);
+        let text = String::from_utf8(data.clone()).unwrap();
+        let (len, _) = text.split_once(' ').unwrap();
+        assert_eq!(len.parse::<usize>().unwrap(), data.len());
+    }
+}
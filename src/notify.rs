@@ -0,0 +1,349 @@
+use crate::config::Config;
+use crate::report::CompressionReport;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// 通知中最多列出的被跳过文件数。
+const MAX_SKIPPED_IN_PAYLOAD: usize = 20;
+
+/// webhook 请求的超时时间。
+#[cfg(feature = "http")]
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// 配置中的 `[notify]` 表。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotifyConfig {
+    /// 运行结束后以 POST 方式发送 JSON 通知内容的地址 (可选)，如 CI 看板或聊天机器人的 webhook
+    pub webhook_url: Option<String>,
+}
+
+/// 何时发送通知。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    /// 每次运行结束都发送 (默认)
+    #[default]
+    Always,
+    /// 仅在失败时发送
+    Failure,
+    /// 仅在成功时发送
+    Success,
+    /// 从不发送
+    Never,
+}
+
+impl NotifyOn {
+    /// 给定运行是否成功，判断是否需要发送通知。
+    pub fn should_notify(self, success: bool) -> bool {
+        match self {
+            NotifyOn::Always => true,
+            NotifyOn::Failure => !success,
+            NotifyOn::Success => success,
+            NotifyOn::Never => false,
+        }
+    }
+}
+
+/// 通知的内容，以 JSON 格式发送。
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Notification {
+    /// 运行是否成功
+    pub success: bool,
+    /// 输出文件路径
+    pub output_path: Option<String>,
+    /// 写入压缩包的文件数
+    pub files: usize,
+    /// 写入压缩包的文件总大小 (字节)
+    pub input_bytes: u64,
+    /// 压缩包大小 (字节)
+    pub output_bytes: u64,
+    /// 运行耗时 (秒)
+    pub duration_secs: f64,
+    /// 被跳过的文件数
+    pub skipped_count: usize,
+    /// 被跳过的文件（最多列出前 20 个）
+    pub skipped: Vec<String>,
+    /// 失败时的错误信息（包含完整的上下文链）
+    pub error: Option<String>,
+    /// 失败时最底层的错误原因
+    pub root_cause: Option<String>,
+}
+
+impl Notification {
+    /// 由成功的压缩结果构造通知。
+    pub fn from_report(report: &CompressionReport) -> Self {
+        Self {
+            success: true,
+            output_path: Some(report.output_path.to_string_lossy().to_string()),
+            files: report.files_count,
+            input_bytes: report.input_bytes,
+            output_bytes: report.output_bytes,
+            duration_secs: report.duration.as_secs_f64(),
+            skipped_count: report.skipped.len(),
+            skipped: report
+                .skipped
+                .iter()
+                .take(MAX_SKIPPED_IN_PAYLOAD)
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            error: None,
+            root_cause: None,
+        }
+    }
+
+    /// 由失败的错误构造通知。
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        Self {
+            success: false,
+            error: Some(format!("{:#}", error)),
+            root_cause: Some(error.root_cause().to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// 根据配置发送通知。
+///
+/// 依次执行 `notify_command` 并向 `[notify]` 中的 `webhook_url` 发送请求，二者都未配置或
+/// `notify_on` 不匹配本次结果时不做任何事。命令通过系统 shell 执行，通知内容以 JSON 写入其标准输入，
+/// 并通过环境变量 `ZTR_STATUS` (`success`/`failure`) 提供运行状态；webhook 以 POST 方式发送同样的 JSON。
+///
+/// # 参数
+/// - `config`: 压缩配置。
+/// - `notification`: 要发送的通知。
+///
+/// # 返回
+/// `Result<()>`: 命令无法执行或以非零状态退出，或 webhook 请求失败时返回错误。
+/// 其中一种方式失败时仍会尝试另一种。
+pub fn send(config: &Config, notification: &Notification) -> Result<()> {
+    let webhook_url = config
+        .notify
+        .as_ref()
+        .and_then(|notify| notify.webhook_url.as_deref());
+    if config.notify_command.is_none() && webhook_url.is_none() {
+        return Ok(());
+    }
+    if !config
+        .notify_on
+        .unwrap_or_default()
+        .should_notify(notification.success)
+    {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_vec(notification).context("序列化通知内容失败")?;
+    let command_result = match &config.notify_command {
+        Some(command) => run_command(command, notification.success, &payload),
+        None => Ok(()),
+    };
+    let webhook_result = match webhook_url {
+        Some(url) => post_webhook(url, &payload),
+        None => Ok(()),
+    };
+    match (command_result, webhook_result) {
+        (Err(command), Err(webhook)) => Err(command.context(format!("{:#}", webhook))),
+        (command, webhook) => command.and(webhook),
+    }
+}
+
+/// 执行通知命令，将通知内容写入其标准输入。
+fn run_command(command: &str, success: bool, payload: &[u8]) -> Result<()> {
+    let status = if success { "success" } else { "failure" };
+    let mut child = shell(command)
+        .env("ZTR_STATUS", status)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("执行通知命令失败: {}", command))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // 命令可能不读取标准输入，忽略管道关闭的错误
+        let _ = stdin.write_all(payload);
+    }
+    let exit = child.wait().context("等待通知命令失败")?;
+    if !exit.success() {
+        anyhow::bail!("通知命令以非零状态退出: {} ({})", command, exit);
+    }
+    Ok(())
+}
+
+/// 以 POST 方式将通知内容发送到 webhook，非 2xx 响应视为失败。
+#[cfg(feature = "http")]
+fn post_webhook(url: &str, payload: &[u8]) -> Result<()> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(WEBHOOK_TIMEOUT))
+        .build()
+        .into();
+    agent
+        .post(url)
+        .header("Content-Type", "application/json")
+        .send(payload)
+        .with_context(|| format!("发送 webhook 通知失败: {}", url))?;
+    Ok(())
+}
+
+/// 未启用 `http` 特性时没有 HTTP 客户端，webhook 通知总是失败。
+#[cfg(not(feature = "http"))]
+fn post_webhook(url: &str, _payload: &[u8]) -> Result<()> {
+    anyhow::bail!("发送 webhook 通知需要启用 http 特性: {}", url)
+}
+
+/// 构造通过系统 shell 执行命令的 `Command`。
+pub(crate) fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_notify_on() {
+        assert!(NotifyOn::Always.should_notify(true));
+        assert!(NotifyOn::Failure.should_notify(false));
+        assert!(!NotifyOn::Failure.should_notify(true));
+        assert!(!NotifyOn::Success.should_notify(false));
+        assert!(!NotifyOn::Never.should_notify(false));
+    }
+
+    #[test]
+    fn test_payload() {
+        let report = CompressionReport {
+            output_path: PathBuf::from("out.zip"),
+            files_count: 2,
+            input_bytes: 100,
+            output_bytes: 50,
+            duration: Default::default(),
+            skipped: (0..30)
+                .map(|i| PathBuf::from(format!("{}.log", i)))
+                .collect(),
+            checksums: Vec::new(),
+            signature: None,
+        };
+        let notification = Notification::from_report(&report);
+        assert_eq!(notification.skipped_count, 30);
+        assert_eq!(notification.skipped.len(), MAX_SKIPPED_IN_PAYLOAD);
+
+        let error = anyhow::anyhow!("磁盘已满").context("写入ZIP失败");
+        let notification = Notification::from_error(&error);
+        assert!(!notification.success);
+        assert_eq!(notification.error.as_deref(), Some("写入ZIP失败: 磁盘已满"));
+        assert_eq!(notification.root_cause.as_deref(), Some("磁盘已满"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_only_on_failure() -> Result<()> {
+        let dir = tempdir()?;
+        let log = dir.path().join("notify.log");
+        let config = Config {
+            notify_command: Some(format!("cat >> '{}'", log.display())),
+            notify_on: Some(NotifyOn::Failure),
+            ..Default::default()
+        };
+
+        send(
+            &config,
+            &Notification {
+                success: true,
+                ..Default::default()
+            },
+        )?;
+        assert!(!log.exists());
+
+        send(
+            &config,
+            &Notification::from_error(&anyhow::anyhow!("出错了")),
+        )?;
+        let payload: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&log)?)?;
+        assert_eq!(payload["success"], false);
+        assert_eq!(payload["error"], "出错了");
+        Ok(())
+    }
+
+    /// 测试服务器收到的请求行与请求体。
+    type Received = std::thread::JoinHandle<(String, Vec<u8>)>;
+
+    /// 在本地端口上接收一个 HTTP 请求，返回请求行与请求体。
+    fn serve_once(status: &'static str) -> Result<(String, Received)> {
+        use std::io::{BufRead, BufReader, Read};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/hook", listener.local_addr()?);
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                status
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            (request_line.trim().to_string(), body)
+        });
+        Ok((url, handle))
+    }
+
+    #[test]
+    fn test_send_webhook() -> Result<()> {
+        let (url, server) = serve_once("200 OK")?;
+        let config = Config {
+            notify: Some(NotifyConfig {
+                webhook_url: Some(url),
+            }),
+            ..Default::default()
+        };
+        let notification = Notification {
+            success: true,
+            output_path: Some("out.zip".to_string()),
+            output_bytes: 50,
+            duration_secs: 1.5,
+            ..Default::default()
+        };
+        send(&config, &notification)?;
+        let (request_line, body) = server.join().unwrap();
+        assert_eq!(request_line, "POST /hook HTTP/1.1");
+        let payload: serde_json::Value = serde_json::from_slice(&body)?;
+        assert_eq!(payload["success"], true);
+        assert_eq!(payload["output_path"], "out.zip");
+        assert_eq!(payload["output_bytes"], 50);
+        assert_eq!(payload["duration_secs"], 1.5);
+
+        // 非 2xx 响应视为失败
+        let (url, server) = serve_once("500 Internal Server Error")?;
+        let config = Config {
+            notify: Some(NotifyConfig {
+                webhook_url: Some(url),
+            }),
+            ..Default::default()
+        };
+        assert!(send(&config, &notification).is_err());
+        server.join().unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::progress::{Cancelled, ProgressRead, ProgressReporter};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// 本身已经压缩过、再次压缩几乎没有收益的文件扩展名（小写，不含前导 `.`）。
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "gz", "br", "zst", "xz", "bz2", "lz", "lzma", "zip", "7z", "rar", "tgz", "jar", "apk", "png",
+    "jpg", "jpeg", "gif", "webp", "avif", "heic", "ico", "mp3", "mp4", "m4a", "m4v", "ogg", "oga",
+    "ogv", "webm", "opus", "flac", "mov", "avi", "mkv", "woff", "woff2", "pdf", "docx", "xlsx",
+    "pptx",
+];
+
+/// 一次预压缩的统计结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrecompressReport {
+    /// 生成了预压缩文件的源文件数
+    pub files: usize,
+    /// 源文件总大小 (字节)
+    pub input_bytes: u64,
+    /// 写入的 `.gz` 文件总大小 (字节)
+    pub gzip_bytes: u64,
+    /// 写入的 `.br` 文件总大小 (字节)
+    pub brotli_bytes: u64,
+    /// 因类型已压缩而跳过的文件
+    pub skipped: Vec<PathBuf>,
+}
+
+/// 判断文件是否为已压缩的类型（包括之前生成的 `.gz`/`.br` 文件）。
+pub fn is_compressed_type(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| COMPRESSED_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// 为每个文件在其旁边生成 gzip (`<文件>.gz`) 和 brotli (`<文件>.br`) 预压缩版本，
+/// 供静态网站服务器直接发送。
+///
+/// 已压缩的类型会被跳过；压缩后不比原文件小的版本不会被写入（已存在的旧版本会被删除），
+/// 服务器此时会回退到原文件。输出不包含时间戳，相同输入总是得到相同的结果。
+///
+/// # 参数
+/// - `files`: 要预压缩的文件路径列表。
+/// - `reporter`: 进度回调。
+///
+/// # 返回
+/// `Result<PrecompressReport>`: 成功时返回统计结果，失败时返回错误信息。
+pub fn precompress_files(
+    files: &[PathBuf],
+    reporter: &dyn ProgressReporter,
+) -> Result<PrecompressReport> {
+    let mut report = PrecompressReport::default();
+    let (targets, skipped): (Vec<&PathBuf>, Vec<&PathBuf>) =
+        files.iter().partition(|f| !is_compressed_type(f));
+    report.skipped = skipped.into_iter().cloned().collect();
+
+    let total_bytes = targets
+        .iter()
+        .filter_map(|f| f.metadata().ok())
+        .map(|m| m.len())
+        .sum();
+    reporter.on_start(targets.len(), total_bytes);
+
+    for file_path in targets {
+        Cancelled::check(reporter)?;
+        reporter.on_file_start(&file_path.to_string_lossy());
+
+        let mut content = Vec::new();
+        File::open(file_path)
+            .map(|file| ProgressRead::new(file, reporter))
+            .and_then(|mut reader| reader.read_to_end(&mut content))
+            .with_context(|| format!("读取文件失败: {}", file_path.display()))?;
+
+        let gzip = gzip(&content)?;
+        let brotli = brotli(&content)?;
+        report.gzip_bytes += write_if_smaller(&sibling(file_path, "gz"), &gzip, content.len())?;
+        report.brotli_bytes += write_if_smaller(&sibling(file_path, "br"), &brotli, content.len())?;
+        report.input_bytes += content.len() as u64;
+        report.files += 1;
+    }
+
+    reporter.on_done();
+    Ok(report)
+}
+
+/// 返回 `<文件>.<ext>` 形式的兄弟文件路径。
+fn sibling(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+/// 以最高压缩级别进行 gzip 压缩，头部不记录时间与文件名。
+fn gzip(content: &[u8]) -> Result<Vec<u8>> {
+    use flate2::{Compression, GzBuilder};
+
+    let mut encoder = GzBuilder::new()
+        .mtime(0)
+        .operating_system(255)
+        .write(Vec::new(), Compression::best());
+    encoder.write_all(content).context("gzip 压缩失败")?;
+    encoder.finish().context("gzip 压缩失败")
+}
+
+/// 以最高质量进行 brotli 压缩。
+fn brotli(content: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+    encoder.write_all(content).context("brotli 压缩失败")?;
+    encoder.flush().context("brotli 压缩失败")?;
+    Ok(encoder.into_inner())
+}
+
+/// 压缩结果比原文件小时写入 `path` 并返回写入的字节数，否则删除已有的旧文件并返回 0。
+fn write_if_smaller(path: &Path, data: &[u8], original_len: usize) -> Result<u64> {
+    if data.len() < original_len {
+        std::fs::write(path, data).with_context(|| format!("写入文件失败: {}", path.display()))?;
+        Ok(data.len() as u64)
+    } else {
+        if path.exists() {
+            std::fs::remove_file(path)
+                .with_context(|| format!("删除过期的预压缩文件失败: {}", path.display()))?;
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_precompress_files() -> Result<()> {
+        let dir = tempdir()?;
+        let html = dir.path().join("index.html");
+        let png = dir.path().join("logo.PNG");
+        let tiny = dir.path().join("a.txt");
+        std::fs::write(&html, "<p>hello</p>\n".repeat(100))?;
+        std::fs::write(&png, "not really a png")?;
+        std::fs::write(&tiny, "a")?;
+        std::fs::write(dir.path().join("a.txt.gz"), "stale")?;
+
+        let files = vec![html.clone(), png.clone(), tiny.clone()];
+        let report = precompress_files(&files, &NoopReporter)?;
+        assert_eq!(report.files, 2);
+        assert_eq!(report.skipped, vec![png]);
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(dir.path().join("index.html.gz"))?)
+            .read_to_string(&mut decoded)?;
+        assert_eq!(decoded, std::fs::read_to_string(&html)?);
+
+        let mut decoded = String::new();
+        brotli::Decompressor::new(File::open(dir.path().join("index.html.br"))?, 4096)
+            .read_to_string(&mut decoded)?;
+        assert_eq!(decoded, std::fs::read_to_string(&html)?);
+
+        // 压缩后没有变小的文件不生成预压缩版本，旧版本被删除
+        assert!(!dir.path().join("a.txt.gz").exists());
+        assert!(!dir.path().join("a.txt.br").exists());
+        assert!(!dir.path().join("logo.PNG.gz").exists());
+        Ok(())
+    }
+}
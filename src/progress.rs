@@ -0,0 +1,384 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fmt;
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 压缩过程中的进度与日志回调。
+///
+/// 库本身不会向标准输出打印任何内容，所有进度和提示信息都通过此 trait 报告，
+/// 以便 GUI、服务端等嵌入方自行展示。所有方法都有空的默认实现。
+pub trait ProgressReporter {
+    /// 压缩开始，给出将要写入的文件总数和总字节数。
+    fn on_start(&self, _total_files: usize, _total_bytes: u64) {}
+
+    /// 开始处理一个条目 (相对路径，使用 `/` 分隔)。
+    fn on_file_start(&self, _name: &str) {}
+
+    /// 又读取了 `bytes` 字节的输入数据。大文件会分多次报告。
+    fn on_bytes(&self, _bytes: u64) {}
+
+    /// 压缩结束（无论成功与否）。
+    fn on_done(&self) {}
+
+    /// 提示信息，例如输出路径、压缩格式。
+    fn on_message(&self, _message: &str) {}
+
+    /// 是否请求取消当前操作（例如用户按下了 Ctrl-C）。
+    ///
+    /// 每次读取输入数据前都会检查，返回 `true` 时操作以 [`Cancelled`] 错误结束，
+    /// 已写入一部分的输出文件会被删除。
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// 操作因 [`ProgressReporter::is_cancelled`] 被取消时返回的错误。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("操作已取消")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl Cancelled {
+    /// 判断错误（包括其上下文链中的 IO 错误）是否由取消引起。
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| {
+            cause.is::<Cancelled>()
+                || cause
+                    .downcast_ref::<io::Error>()
+                    .and_then(|e| e.get_ref())
+                    .is_some_and(|inner| inner.is::<Cancelled>())
+        })
+    }
+
+    /// 已请求取消时返回 [`Cancelled`] 错误，在逐个文件处理的循环中调用。
+    pub fn check(reporter: &dyn ProgressReporter) -> anyhow::Result<()> {
+        if reporter.is_cancelled() {
+            return Err(Cancelled.into());
+        }
+        Ok(())
+    }
+}
+
+/// 可以在其他线程中请求取消的令牌，克隆得到的令牌共享同一个状态。
+///
+/// 令牌本身实现了 [`ProgressReporter`] (只响应取消)，可以直接传给压缩函数；
+/// 解压时通过 [`ExtractOptions::cancel`](crate::extractor::ExtractOptions::cancel) 传入。
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// 创建尚未取消的令牌。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消，正在进行的操作在处理下一个文件或读取下一块数据前以 [`Cancelled`] 结束。
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// 是否已请求取消。
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+impl From<Arc<AtomicBool>> for CancellationToken {
+    /// 使用已有的标志，例如在 Ctrl-C 信号处理函数中置位的标志。
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self { flag }
+    }
+}
+
+impl PartialEq for CancellationToken {
+    /// 共享同一个状态的令牌相等。
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.flag, &other.flag)
+    }
+}
+
+impl Eq for CancellationToken {}
+
+impl ProgressReporter for CancellationToken {
+    fn is_cancelled(&self) -> bool {
+        CancellationToken::is_cancelled(self)
+    }
+}
+
+/// 忽略所有回调的报告器。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopReporter;
+
+impl ProgressReporter for NoopReporter {}
+
+/// 在读取时通过 [`ProgressReporter::on_bytes`] 报告已读字节数的读取器包装。
+pub struct ProgressRead<'a, R> {
+    inner: R,
+    reporter: &'a dyn ProgressReporter,
+}
+
+impl<'a, R: Read> ProgressRead<'a, R> {
+    /// 包装读取器 `inner`。
+    pub fn new(inner: R, reporter: &'a dyn ProgressReporter) -> Self {
+        Self { inner, reporter }
+    }
+}
+
+impl<R: Read> Read for ProgressRead<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.reporter.is_cancelled() {
+            return Err(io::Error::other(Cancelled));
+        }
+        let n = self.inner.read(buf)?;
+        if n > 0 {
+            self.reporter.on_bytes(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+/// 命令行进度条的模板。
+const BAR_TEMPLATE: &str = "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({binary_bytes_per_sec}, 剩余 {eta}) {msg}";
+
+/// 根据模板创建进度条样式，模板无效时退回到不带样式的默认进度条，而不是 panic。
+fn bar_style(template: &str) -> ProgressStyle {
+    match ProgressStyle::default_bar().template(template) {
+        Ok(style) => style.progress_chars("#>-"),
+        Err(_) => ProgressStyle::default_bar(),
+    }
+}
+
+/// 命令行使用的默认报告器：在终端中按字节显示进度条（含吞吐量与剩余时间），
+/// 并将提示信息打印到标准输出。
+#[derive(Debug)]
+pub struct CliReporter {
+    bar: ProgressBar,
+    cancel: Option<Arc<AtomicBool>>,
+    /// 进度条开始时与完成时显示的消息
+    messages: (&'static str, &'static str),
+    /// 不显示进度条与提示信息
+    quiet: bool,
+}
+
+impl CliReporter {
+    /// 创建命令行报告器。
+    pub fn new() -> Self {
+        Self {
+            bar: ProgressBar::hidden(),
+            cancel: None,
+            messages: ("正在压缩...", "压缩完成"),
+            quiet: false,
+        }
+    }
+
+    /// 不显示进度条与提示信息，只保留取消检查，例如压缩包写入标准输出时。
+    pub fn quiet(mut self) -> Self {
+        self.quiet = true;
+        self
+    }
+
+    /// 设置进度条开始时与完成时显示的消息（默认为压缩），例如用于上传。
+    pub fn with_messages(mut self, start: &'static str, done: &'static str) -> Self {
+        self.messages = (start, done);
+        self
+    }
+
+    /// 当 `flag` 被置为 `true` 时（例如在 Ctrl-C 信号处理函数中）请求取消操作。
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+}
+
+impl Default for CliReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressReporter for CliReporter {
+    fn on_start(&self, _total_files: usize, total_bytes: u64) {
+        if self.quiet {
+            return;
+        }
+        self.bar
+            .set_draw_target(indicatif::ProgressDrawTarget::stderr());
+        self.bar.set_length(total_bytes);
+        self.bar.set_style(bar_style(BAR_TEMPLATE));
+        self.bar.set_message(self.messages.0);
+    }
+
+    fn on_file_start(&self, name: &str) {
+        self.bar.set_message(name.to_string());
+    }
+
+    fn on_bytes(&self, bytes: u64) {
+        self.bar.inc(bytes);
+    }
+
+    fn on_done(&self) {
+        if self.is_cancelled() {
+            self.bar.abandon_with_message("已取消");
+        } else {
+            self.bar.finish_with_message(self.messages.1);
+        }
+    }
+
+    fn on_message(&self, message: &str) {
+        if self.quiet {
+            return;
+        }
+        self.bar.suspend(|| println!("{}", message));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// 记录所有回调的测试报告器。
+    #[derive(Default)]
+    struct Recorder {
+        events: RefCell<Vec<String>>,
+    }
+
+    impl ProgressReporter for Recorder {
+        fn on_start(&self, total_files: usize, total_bytes: u64) {
+            self.events
+                .borrow_mut()
+                .push(format!("start {} {}", total_files, total_bytes));
+        }
+        fn on_file_start(&self, name: &str) {
+            self.events.borrow_mut().push(format!("file {}", name));
+        }
+        fn on_bytes(&self, bytes: u64) {
+            self.events.borrow_mut().push(format!("bytes {}", bytes));
+        }
+        fn on_done(&self) {
+            self.events.borrow_mut().push("done".to_string());
+        }
+    }
+
+    #[test]
+    fn test_bar_style_falls_back_on_invalid_template() {
+        let _ = bar_style(BAR_TEMPLATE);
+        let _ = bar_style("{bar:40.cyan/blue");
+
+        let reporter = CliReporter::new();
+        reporter.on_start(1, 10);
+        reporter.on_bytes(10);
+        reporter.on_done();
+    }
+
+    #[test]
+    fn test_progress_read_reports_chunks() -> io::Result<()> {
+        let recorder = Recorder::default();
+        let data = vec![7u8; 10];
+        let mut reader = ProgressRead::new(data.as_slice(), &recorder);
+        let mut buf = [0u8; 4];
+        while reader.read(&mut buf)? > 0 {}
+        assert_eq!(
+            *recorder.events.borrow(),
+            vec!["bytes 4", "bytes 4", "bytes 2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reporter_receives_events() -> anyhow::Result<()> {
+        use crate::compressor;
+        use crate::config::Config;
+        use crate::format::Format;
+
+        for format in Format::ALL {
+            let dir = tempfile::tempdir()?;
+            std::fs::write(dir.path().join("a.txt"), "hello")?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+
+            let recorder = Recorder::default();
+            compressor::compress_directory(
+                &config,
+                dir.path(),
+                vec![dir.path().join("a.txt")],
+                &recorder,
+            )?;
+            assert_eq!(
+                *recorder.events.borrow(),
+                vec!["start 1 5", "file a.txt", "bytes 5", "done"],
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancel_removes_partial_output() -> anyhow::Result<()> {
+        use crate::compressor;
+        use crate::config::Config;
+        use crate::format::Format;
+
+        /// 读取到第一块数据后请求取消。
+        #[derive(Default)]
+        struct CancelAfterFirstRead {
+            read: std::cell::Cell<bool>,
+        }
+
+        impl ProgressReporter for CancelAfterFirstRead {
+            fn on_bytes(&self, _bytes: u64) {
+                self.read.set(true);
+            }
+            fn is_cancelled(&self) -> bool {
+                self.read.get()
+            }
+        }
+
+        for format in Format::ALL {
+            let dir = tempfile::tempdir()?;
+            let files: Vec<_> = (0..3)
+                .map(|i| dir.path().join(format!("{}.txt", i)))
+                .collect();
+            for file in &files {
+                std::fs::write(file, "content")?;
+            }
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+
+            let err = compressor::compress_directory(
+                &config,
+                dir.path(),
+                files,
+                &CancelAfterFirstRead::default(),
+            )
+            .unwrap_err();
+            assert!(Cancelled::is_cause_of(&err), "{}: {:#}", format, err);
+            assert!(!compressor::output_path(&config, dir.path()).exists());
+        }
+
+        assert!(!Cancelled::is_cause_of(&anyhow::anyhow!("其他错误")));
+        Ok(())
+    }
+}
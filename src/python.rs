@@ -0,0 +1,189 @@
+//! Python 绑定，需要启用 `python` 特性。
+//!
+//! 使用 [maturin](https://www.maturin.rs) 构建为 Python 模块 `ztr`（配置见 `pyproject.toml`）：
+//!
+//! ```sh
+//! maturin develop --release
+//! ```
+//!
+//! ```python
+//! import ztr
+//!
+//! report = ztr.compress("./my_project", format="tar.gz", ignore=["target/", "*.log"])
+//! print(report.output_path, report.files_count)
+//! ```
+//!
+//! 错误以 `ztr.ZtrError` 异常抛出，异常信息包含完整的错误链。压缩期间会释放 GIL，
+//! 其他 Python 线程可以继续运行。
+
+use crate::compressor::Compressor;
+use crate::config::Config;
+use crate::format::Format;
+use crate::report::CompressionReport;
+use anyhow::Result;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::path::PathBuf;
+
+create_exception!(ztr, ZtrError, PyException, "ztr 操作失败时抛出的异常。");
+
+/// 一次压缩的结果，对应 [`CompressionReport`]。
+#[pyclass(name = "CompressionReport", module = "ztr", frozen, get_all)]
+#[derive(Debug)]
+pub struct PyCompressionReport {
+    /// 输出文件的路径 (`pathlib.Path`)
+    pub output_path: PathBuf,
+    /// 写入压缩包的文件数
+    pub files_count: usize,
+    /// 写入压缩包的文件总大小 (字节)
+    pub input_bytes: u64,
+    /// 压缩包大小 (字节)
+    pub output_bytes: u64,
+    /// 压缩耗时 (秒)
+    pub duration: f64,
+}
+
+#[pymethods]
+impl PyCompressionReport {
+    /// 压缩比 (输出大小 / 输入大小)，输入为空时为 0。
+    #[getter]
+    fn ratio(&self) -> f64 {
+        if self.input_bytes == 0 {
+            return 0.0;
+        }
+        self.output_bytes as f64 / self.input_bytes as f64
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CompressionReport(output_path={:?}, files_count={}, input_bytes={}, output_bytes={})",
+            self.output_path.display().to_string(),
+            self.files_count,
+            self.input_bytes,
+            self.output_bytes
+        )
+    }
+}
+
+impl From<CompressionReport> for PyCompressionReport {
+    fn from(report: CompressionReport) -> Self {
+        Self {
+            output_path: report.output_path,
+            files_count: report.files_count,
+            input_bytes: report.input_bytes,
+            output_bytes: report.output_bytes,
+            duration: report.duration.as_secs_f64(),
+        }
+    }
+}
+
+/// 压缩目录，行为与 `ztr compress` 相同（不执行钩子与上传）。
+///
+/// # 参数
+/// - `path`: 要压缩的目录。
+/// - `format`: 压缩格式（`"zip"`、`"tar.gz"`、`"tar.zst"`、`"7z"`），覆盖配置中的格式。
+/// - `ignore`: 忽略规则（Gitignore 语法），替换配置中的 `ignore`。
+/// - `output`: 压缩包的完整输出路径，默认按配置中的 `output_dir` 与 `output_name` 决定。
+/// - `config`: `ztr.toml` 配置文件的路径，默认使用默认配置。
+///
+/// # 返回
+/// `CompressionReport`；失败时抛出 `ZtrError`。
+#[pyfunction]
+#[pyo3(signature = (path, format=None, ignore=None, output=None, config=None))]
+fn compress(
+    py: Python<'_>,
+    path: PathBuf,
+    format: Option<String>,
+    ignore: Option<Vec<String>>,
+    output: Option<PathBuf>,
+    config: Option<PathBuf>,
+) -> PyResult<PyCompressionReport> {
+    py.detach(|| run_compress(path, format, ignore, output, config))
+        .map(PyCompressionReport::from)
+        .map_err(|e| ZtrError::new_err(format!("{:#}", e)))
+}
+
+fn run_compress(
+    path: PathBuf,
+    format: Option<String>,
+    ignore: Option<Vec<String>>,
+    output: Option<PathBuf>,
+    config: Option<PathBuf>,
+) -> Result<CompressionReport> {
+    let config = match config {
+        Some(config_path) => Config::load(config_path)?,
+        None => Config::default(),
+    };
+    let mut compressor = Compressor::new(path).config(config);
+    if let Some(format) = format {
+        compressor = compressor.format(format.parse::<Format>()?);
+    }
+    if let Some(ignore) = ignore {
+        compressor = compressor.ignore(ignore);
+    }
+    if let Some(output) = output {
+        compressor = compressor.output(output);
+    }
+    compressor.run()
+}
+
+/// Python 模块 `ztr`。
+#[pymodule]
+#[pyo3(name = "ztr")]
+fn ztr_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(compress, m)?)?;
+    m.add_class::<PyCompressionReport>()?;
+    m.add("ZtrError", m.py().get_type::<ZtrError>())?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_python_compress() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "hello")?;
+        std::fs::write(dir.path().join("debug.log"), "noise")?;
+        let out = dir.path().join("out.tar.gz");
+
+        Python::initialize();
+        Python::attach(|py| -> PyResult<()> {
+            let module = PyModule::new(py, "ztr")?;
+            ztr_module(&module)?;
+            let locals = PyDict::new(py);
+            locals.set_item("ztr", module)?;
+            locals.set_item("base", dir.path())?;
+            locals.set_item("out", &out)?;
+            py.run(
+                cr#"
+report = ztr.compress(base, format="tar.gz", ignore=["*.log"], output=out)
+assert report.files_count == 1, report
+assert report.output_path == out
+assert report.input_bytes == 5
+
+try:
+    ztr.compress(base, format="rar")
+    raise AssertionError("expected ZtrError")
+except ztr.ZtrError as e:
+    assert "rar" in str(e)
+"#,
+                None,
+                Some(&locals),
+            )
+        })?;
+
+        let mut names = Vec::new();
+        crate::reader::for_each_entry(&out, |name, _| {
+            names.push(name.to_string());
+            Ok(())
+        })?;
+        assert_eq!(names, ["a.txt"]);
+        Ok(())
+    }
+}
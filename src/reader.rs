@@ -0,0 +1,644 @@
+use crate::extractor;
+use crate::format::Format;
+use crate::guard;
+use crate::split;
+use anyhow::{Context, Result};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// 依次读取压缩包中的每个文件条目（目录与符号链接条目会被跳过）。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，格式由文件内容判断，无法识别时根据扩展名判断；可以是分卷压缩包。
+/// - `f`: 对每个条目调用的闭包，参数为条目名称（使用 `/` 分隔）与条目内容的读取器。
+///
+/// # 返回
+/// `Result<()>`: 压缩包无法读取或闭包返回错误时返回错误信息。
+pub fn for_each_entry<F>(archive_path: &Path, f: F) -> Result<()>
+where
+    F: FnMut(&str, &mut dyn Read) -> Result<()>,
+{
+    let (reader, format) = split::open_archive(archive_path)?;
+    for_each_entry_in(reader, format, f)
+}
+
+/// 压缩包条目的类型。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// 普通文件
+    File,
+    /// 目录
+    Directory,
+    /// 符号链接，目标记录在 [`EntryInfo::link_target`] 中
+    Symlink,
+}
+
+/// 压缩包中一个条目的信息。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// 条目名称 (使用 `/` 分隔)
+    pub name: String,
+    /// 条目类型
+    pub kind: EntryKind,
+    /// 解压后的大小 (字节)
+    pub size: u64,
+    /// 修改时间 (Unix 时间戳)，压缩包中没有记录时为 `None`
+    pub mtime: Option<u64>,
+    /// Unix 权限位，压缩包中没有记录时为 `None`
+    pub mode: Option<u32>,
+    /// 所有者的 uid 与 gid，只有 TAR 记录
+    pub owner: Option<(u32, u32)>,
+    /// 符号链接的目标，其他类型的条目为 `None`；[`ArchiveReader::list`] 不读取内容，同样为 `None`
+    pub link_target: Option<String>,
+}
+
+/// 依次读取压缩包数据中的每个文件条目（目录与符号链接条目会被跳过）。
+///
+/// 数据格式错误时返回错误而不会 panic，可以安全地用于不可信的输入。
+///
+/// # 参数
+/// - `reader`: 压缩包数据。
+/// - `format`: 压缩包格式。
+/// - `f`: 对每个条目调用的闭包，参数为条目名称（使用 `/` 分隔）与条目内容的读取器。
+///
+/// # 返回
+/// `Result<()>`: 压缩包无法读取或闭包返回错误时返回错误信息。
+pub fn for_each_entry_in<R, F>(reader: R, format: Format, mut f: F) -> Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&str, &mut dyn Read) -> Result<()>,
+{
+    for_each_entry_info_in(reader, format, |info, entry| f(&info.name, entry))
+}
+
+/// 与 [`for_each_entry_in`] 相同，但向闭包提供条目的大小、修改时间与权限。
+///
+/// # 参数
+/// - `reader`: 压缩包数据。
+/// - `format`: 压缩包格式。
+/// - `f`: 对每个条目调用的闭包，参数为条目信息与条目内容的读取器。
+///
+/// # 返回
+/// `Result<()>`: 压缩包无法读取或闭包返回错误时返回错误信息。
+pub fn for_each_entry_info_in<R, F>(reader: R, format: Format, mut f: F) -> Result<()>
+where
+    R: Read + Seek,
+    F: FnMut(&EntryInfo, &mut dyn Read) -> Result<()>,
+{
+    open_reader(reader, format)?.read_entries(&mut f)
+}
+
+/// 逐个读取压缩包中的文件条目，与写入接口 [`ArchiveWriter`](crate::archive_writer::ArchiveWriter) 相对应。
+///
+/// 解压、校验、列出、转换、比较压缩包与计算条目摘要都通过该接口读取，与具体格式无关。
+/// 读取会消耗读取器，需要再次读取时重新打开压缩包。
+pub trait ArchiveReader {
+    /// 依次将每个文件、目录与符号链接条目的信息与内容读取器交给 `f`，`f` 返回错误时停止。
+    ///
+    /// 符号链接的目标已读入 [`EntryInfo::link_target`]，交给 `f` 的内容为空；
+    /// 硬链接、设备文件等特殊条目会被跳过。
+    fn read_all_entries(
+        self: Box<Self>,
+        f: &mut dyn FnMut(&EntryInfo, &mut dyn Read) -> Result<()>,
+    ) -> Result<()>;
+
+    /// 与 [`read_all_entries`](Self::read_all_entries) 相同，但只交出文件条目（目录与符号链接条目会被跳过）。
+    fn read_entries(
+        self: Box<Self>,
+        f: &mut dyn FnMut(&EntryInfo, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        self.read_all_entries(&mut |info, entry| match info.kind {
+            EntryKind::File => f(info, entry),
+            EntryKind::Directory | EntryKind::Symlink => Ok(()),
+        })
+    }
+
+    /// 列出所有文件条目的信息，不使用其内容。
+    fn list(self: Box<Self>) -> Result<Vec<EntryInfo>> {
+        let mut entries = Vec::new();
+        self.read_entries(&mut |info, _| {
+            entries.push(info.clone());
+            Ok(())
+        })?;
+        Ok(entries)
+    }
+}
+
+/// 按格式创建读取压缩包数据的 [`ArchiveReader`]。
+///
+/// 数据格式错误时返回错误而不会 panic，可以安全地用于不可信的输入。
+///
+/// # 参数
+/// - `reader`: 压缩包数据。
+/// - `format`: 压缩包格式。
+///
+/// # 返回
+/// `Result<Box<dyn ArchiveReader>>`: 无法读取压缩包的目录或头部时返回错误。
+pub fn open_reader<'a, R: Read + Seek + 'a>(
+    reader: R,
+    format: Format,
+) -> Result<Box<dyn ArchiveReader + 'a>> {
+    guard::catch_panic(&format!("读取{}", format), || {
+        Ok(match format {
+            Format::Zip => Box::new(ZipArchiveReader::new(reader)?) as Box<dyn ArchiveReader>,
+            Format::TarGz | Format::TarZst => {
+                Box::new(TarArchiveReader::new(format.tar_decoder(reader)?))
+            }
+            Format::SevenZ => Box::new(SevenZArchiveReader::new(reader)?),
+        })
+    })
+}
+
+/// 打开压缩包文件（可以是分卷压缩包），格式由文件内容判断，无法识别时根据扩展名判断。
+pub fn open_archive_reader(archive_path: &Path) -> Result<Box<dyn ArchiveReader>> {
+    let (reader, format) = split::open_archive(archive_path)?;
+    open_reader(reader, format)
+}
+
+/// ZIP 压缩包的读取器。
+pub struct ZipArchiveReader<R> {
+    archive: zip::ZipArchive<R>,
+}
+
+impl<R: Read + Seek> ZipArchiveReader<R> {
+    /// 读取 ZIP 的中央目录。
+    pub fn new(reader: R) -> Result<Self> {
+        let archive = guard::catch_panic("读取zip", || {
+            zip::ZipArchive::new(reader).context("读取ZIP目录失败")
+        })?;
+        Ok(Self { archive })
+    }
+}
+
+fn zip_entry_info<R: Read>(entry: &zip::read::ZipFile<'_, R>) -> EntryInfo {
+    let kind = if entry.is_dir() {
+        EntryKind::Directory
+    } else if entry.is_symlink() {
+        EntryKind::Symlink
+    } else {
+        EntryKind::File
+    };
+    EntryInfo {
+        name: entry.name().to_string(),
+        kind,
+        size: entry.size(),
+        mtime: entry
+            .last_modified()
+            .and_then(extractor::zip_datetime_to_system_time)
+            .and_then(unix_secs),
+        mode: entry.unix_mode(),
+        owner: None,
+        link_target: None,
+    }
+}
+
+impl<R: Read + Seek> ArchiveReader for ZipArchiveReader<R> {
+    fn read_all_entries(
+        mut self: Box<Self>,
+        f: &mut dyn FnMut(&EntryInfo, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        guard::catch_panic("读取zip", || {
+            for i in 0..self.archive.len() {
+                let mut entry = self.archive.by_index(i).context("读取ZIP条目失败")?;
+                let mut info = zip_entry_info(&entry);
+                match info.kind {
+                    EntryKind::Symlink => {
+                        read_link_target(&mut info, &mut entry)?;
+                        f(&info, &mut std::io::empty())?;
+                    }
+                    EntryKind::File | EntryKind::Directory => f(&info, &mut entry)?,
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// 只读取中央目录，不解压条目内容。
+    fn list(mut self: Box<Self>) -> Result<Vec<EntryInfo>> {
+        guard::catch_panic("读取zip", || {
+            let mut entries = Vec::new();
+            for i in 0..self.archive.len() {
+                let entry = self.archive.by_index_raw(i).context("读取ZIP条目失败")?;
+                let info = zip_entry_info(&entry);
+                if info.kind == EntryKind::File {
+                    entries.push(info);
+                }
+            }
+            Ok(entries)
+        })
+    }
+}
+
+/// TAR 数据流的读取器，`R` 为已解压（如 gzip、zstd 解码后）的 TAR 数据。
+pub struct TarArchiveReader<R: Read> {
+    archive: tar::Archive<R>,
+}
+
+impl<R: Read> TarArchiveReader<R> {
+    /// 读取 TAR 数据流。
+    pub fn new(reader: R) -> Self {
+        Self {
+            archive: tar::Archive::new(reader),
+        }
+    }
+}
+
+impl<R: Read> ArchiveReader for TarArchiveReader<R> {
+    /// 读完所有条目后继续读完剩余的数据，解压数据流的校验（gzip 尾部的 CRC、zstd 帧的校验和）因此也会被检查。
+    fn read_all_entries(
+        self: Box<Self>,
+        f: &mut dyn FnMut(&EntryInfo, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        use tar::EntryType;
+
+        let Self { mut archive } = *self;
+        guard::catch_panic("读取tar", || {
+            for entry in archive.entries().context("读取TAR条目失败")? {
+                let mut entry = entry.context("读取TAR条目失败")?;
+                let header = entry.header();
+                let kind = match header.entry_type() {
+                    EntryType::Regular | EntryType::Continuous => EntryKind::File,
+                    EntryType::Directory => EntryKind::Directory,
+                    EntryType::Symlink => EntryKind::Symlink,
+                    _ => continue,
+                };
+                let name = entry.path()?.to_string_lossy().replace("\\", "/");
+                let link_target = match kind {
+                    EntryKind::Symlink => Some(
+                        entry
+                            .link_name()?
+                            .with_context(|| format!("符号链接缺少目标: {}", name))?
+                            .to_string_lossy()
+                            .replace("\\", "/"),
+                    ),
+                    EntryKind::File | EntryKind::Directory => None,
+                };
+                let header = entry.header();
+                let info = EntryInfo {
+                    name,
+                    kind,
+                    size: header.size()?,
+                    mtime: header.mtime().ok(),
+                    mode: header.mode().ok(),
+                    owner: header
+                        .uid()
+                        .ok()
+                        .zip(header.gid().ok())
+                        .and_then(|(uid, gid)| {
+                            Some((u32::try_from(uid).ok()?, u32::try_from(gid).ok()?))
+                        }),
+                    link_target,
+                };
+                f(&info, &mut entry)?;
+            }
+            std::io::copy(&mut archive.into_inner(), &mut std::io::sink())
+                .context("读取压缩数据流的结尾失败")?;
+            Ok(())
+        })
+    }
+}
+
+/// 7Z 压缩包的读取器。
+pub struct SevenZArchiveReader<R: Read + Seek> {
+    archive: sevenz_rust::SevenZReader<R>,
+}
+
+impl<R: Read + Seek> SevenZArchiveReader<R> {
+    /// 读取 7Z 的头部（不支持加密的压缩包）。
+    pub fn new(mut reader: R) -> Result<Self> {
+        use sevenz_rust::{Password, SevenZReader};
+
+        let archive = guard::catch_panic("读取7z", || {
+            let len = reader.seek(SeekFrom::End(0))?;
+            reader.rewind()?;
+            SevenZReader::new(reader, len, Password::empty()).context("读取7Z头失败")
+        })?;
+        Ok(Self { archive })
+    }
+}
+
+impl<R: Read + Seek> ArchiveReader for SevenZArchiveReader<R> {
+    fn read_all_entries(
+        mut self: Box<Self>,
+        f: &mut dyn FnMut(&EntryInfo, &mut dyn Read) -> Result<()>,
+    ) -> Result<()> {
+        guard::catch_panic("读取7z", || {
+            let mut error = None;
+            self.archive
+                .for_each_entries(|entry, reader| {
+                    // 返回 false 只会结束当前的数据块，之后的数据块仍会回调
+                    if error.is_some() {
+                        return Ok(false);
+                    }
+                    let mut info = sevenz_entry_info(entry);
+                    let result = match info.kind {
+                        EntryKind::Symlink => read_link_target(&mut info, reader)
+                            .and_then(|()| f(&info, &mut std::io::empty())),
+                        EntryKind::File | EntryKind::Directory => f(&info, reader),
+                    };
+                    match result {
+                        Ok(()) => Ok(true),
+                        Err(e) => {
+                            error = Some(e);
+                            Ok(false)
+                        }
+                    }
+                })
+                .context("读取7Z条目失败")?;
+            match error {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// 只读取头部中的条目列表，不解压内容。
+    fn list(self: Box<Self>) -> Result<Vec<EntryInfo>> {
+        Ok(self
+            .archive
+            .archive()
+            .files
+            .iter()
+            .map(sevenz_entry_info)
+            .filter(|info| info.kind == EntryKind::File)
+            .collect())
+    }
+}
+
+fn sevenz_entry_info(entry: &sevenz_rust::SevenZArchiveEntry) -> EntryInfo {
+    /// Unix 文件类型掩码 (S_IFMT) 与符号链接类型 (S_IFLNK)。
+    const UNIX_TYPE_MASK: u32 = 0o170000;
+    const UNIX_SYMLINK: u32 = 0o120000;
+
+    let mode = extractor::sevenz_unix_mode(entry);
+    let kind = if entry.is_directory() {
+        EntryKind::Directory
+    } else if mode.is_some_and(|m| m & UNIX_TYPE_MASK == UNIX_SYMLINK) {
+        EntryKind::Symlink
+    } else {
+        EntryKind::File
+    };
+    EntryInfo {
+        name: entry.name().to_string(),
+        kind,
+        size: entry.size(),
+        mtime: entry
+            .has_last_modified_date
+            .then(|| entry.last_modified_date().to_raw())
+            .and_then(extractor::filetime_to_system_time)
+            .and_then(unix_secs),
+        mode,
+        owner: None,
+        link_target: None,
+    }
+}
+
+/// 读取以条目内容保存的符号链接目标（ZIP 与 7Z 的约定）。
+fn read_link_target(info: &mut EntryInfo, reader: &mut dyn Read) -> Result<()> {
+    let mut target = String::new();
+    reader
+        .read_to_string(&mut target)
+        .with_context(|| format!("读取符号链接目标失败: {}", info.name))?;
+    info.link_target = Some(target);
+    Ok(())
+}
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor;
+    use crate::config::Config;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_for_each_entry() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::create_dir(dir.path().join("sub"))?;
+            std::fs::write(dir.path().join("sub/a.txt"), "hello")?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+            let archive = compressor::compress_directory(
+                &config,
+                dir.path(),
+                vec![dir.path().join("sub/a.txt")],
+                &NoopReporter,
+            )?
+            .output_path;
+
+            let mut entries = Vec::new();
+            for_each_entry(&archive, |name, reader| {
+                let mut content = String::new();
+                reader.read_to_string(&mut content)?;
+                entries.push((name.to_string(), content));
+                Ok(())
+            })?;
+            assert_eq!(
+                entries,
+                vec![("sub/a.txt".to_string(), "hello".to_string())],
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_reader() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::create_dir(dir.path().join("sub"))?;
+            std::fs::write(dir.path().join("a.txt"), "hello")?;
+            std::fs::write(dir.path().join("sub/b.txt"), "hi")?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+            let archive = compressor::compress_directory(
+                &config,
+                dir.path(),
+                vec![dir.path().join("a.txt"), dir.path().join("sub/b.txt")],
+                &NoopReporter,
+            )?
+            .output_path;
+
+            let listed = open_archive_reader(&archive)?.list()?;
+            let names: Vec<_> = listed.iter().map(|e| (e.name.as_str(), e.size)).collect();
+            assert_eq!(names, [("a.txt", 5), ("sub/b.txt", 2)], "{}", format);
+            // 所有格式都记录修改时间与权限
+            assert!(
+                listed.iter().all(|e| e.mtime.is_some() && e.mode.is_some()),
+                "{}",
+                format
+            );
+
+            // 与格式无关地读取内容
+            let reader: Box<dyn ArchiveReader> =
+                open_reader(std::fs::File::open(&archive)?, format)?;
+            let mut contents = Vec::new();
+            reader.read_entries(&mut |info, entry| {
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                contents.push(format!("{}={}", info.name, content));
+                Ok(())
+            })?;
+            assert_eq!(contents, ["a.txt=hello", "sub/b.txt=hi"], "{}", format);
+
+            // 闭包的错误会结束读取
+            let mut seen = 0;
+            let result = open_archive_reader(&archive)?.read_entries(&mut |_, _| {
+                seen += 1;
+                anyhow::bail!("stop")
+            });
+            assert!(result.is_err());
+            assert_eq!(seen, 1, "{}", format);
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_all_entries() -> Result<()> {
+        use crate::collector::SymlinkPolicy;
+
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let src = dir.path().join("src");
+            std::fs::create_dir(&src)?;
+            std::fs::write(src.join("a.txt"), "hello")?;
+            std::os::unix::fs::symlink("a.txt", src.join("link.txt"))?;
+            let config = Config {
+                format,
+                symlinks: Some(SymlinkPolicy::Preserve),
+                ..Default::default()
+            };
+            let archive = dir.path().join(format!("out.{}", format.extension()));
+            compressor::compress_files(
+                &config,
+                &src,
+                vec![src.join("a.txt"), src.join("link.txt")],
+                &archive,
+                &NoopReporter,
+            )?;
+
+            let mut entries = Vec::new();
+            open_archive_reader(&archive)?.read_all_entries(&mut |info, entry| {
+                let mut content = String::new();
+                entry.read_to_string(&mut content)?;
+                entries.push((
+                    info.name.clone(),
+                    info.kind,
+                    info.link_target.clone(),
+                    content,
+                ));
+                Ok(())
+            })?;
+            assert_eq!(
+                entries,
+                [
+                    (
+                        "a.txt".to_string(),
+                        EntryKind::File,
+                        None,
+                        "hello".to_string()
+                    ),
+                    (
+                        "link.txt".to_string(),
+                        EntryKind::Symlink,
+                        Some("a.txt".to_string()),
+                        String::new()
+                    ),
+                ],
+                "{}",
+                format
+            );
+
+            // 只读取文件条目的接口跳过符号链接
+            let listed = open_archive_reader(&archive)?.list()?;
+            assert_eq!(listed.len(), 1, "{}", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_renamed_archive() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::write(dir.path().join("a.txt"), "hello")?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+            let archive = compressor::compress_directory(
+                &config,
+                dir.path(),
+                vec![dir.path().join("a.txt")],
+                &NoopReporter,
+            )?
+            .output_path;
+            // 下载流水线常见的无扩展名文件，格式由魔数识别
+            let renamed = dir.path().join("download");
+            std::fs::rename(&archive, &renamed)?;
+
+            let mut names = Vec::new();
+            for_each_entry(&renamed, |name, _| {
+                names.push(name.to_string());
+                Ok(())
+            })?;
+            assert_eq!(names, vec!["a.txt"], "{}", format);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_archives_do_not_panic() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            std::fs::write(dir.path().join("a.txt"), "hello ".repeat(100))?;
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+            let archive = compressor::compress_directory(
+                &config,
+                dir.path(),
+                vec![dir.path().join("a.txt")],
+                &NoopReporter,
+            )?
+            .output_path;
+            let data = std::fs::read(&archive)?;
+
+            // 截断与逐字节破坏的数据只能得到错误（或碰巧仍然有效），不能 panic
+            let mut inputs: Vec<Vec<u8>> = (0..data.len())
+                .step_by(7)
+                .map(|len| data[..len].to_vec())
+                .collect();
+            for i in (0..data.len()).step_by(3) {
+                let mut corrupted = data.clone();
+                corrupted[i] ^= 0xff;
+                inputs.push(corrupted);
+            }
+            for input in inputs {
+                let _ = for_each_entry_in(std::io::Cursor::new(input), format, |_, reader| {
+                    std::io::copy(reader, &mut std::io::sink())?;
+                    Ok(())
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use crate::checksum::ChecksumAlgorithm;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// 一次压缩的统计结果。
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionReport {
+    /// 输出文件的路径
+    pub output_path: PathBuf,
+    /// 写入压缩包的文件数
+    pub files_count: usize,
+    /// 写入压缩包的文件总大小 (字节)
+    pub input_bytes: u64,
+    /// 压缩包大小 (字节)
+    pub output_bytes: u64,
+    /// 压缩耗时
+    pub duration: Duration,
+    /// 被跳过的文件（例如以前的压缩包、参考压缩包中未变化的文件）
+    pub skipped: Vec<PathBuf>,
+    /// 已写入附属文件的压缩包摘要
+    pub checksums: Vec<(ChecksumAlgorithm, String)>,
+    /// 分离签名文件的路径，未配置签名时为 `None`
+    pub signature: Option<PathBuf>,
+}
+
+impl CompressionReport {
+    /// 压缩比 (输出大小 / 输入大小)。输入为空时返回 0。
+    pub fn ratio(&self) -> f64 {
+        if self.input_bytes == 0 {
+            return 0.0;
+        }
+        self.output_bytes as f64 / self.input_bytes as f64
+    }
+}
+
+/// 一行式的统计摘要，供命令行、日志等展示。库本身从不打印该摘要。
+impl fmt::Display for CompressionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "共 {} 个文件, {} -> {} 字节 (压缩比 {:.1}%), 跳过 {} 个文件, 耗时 {:.2}s",
+            self.files_count,
+            self.input_bytes,
+            self.output_bytes,
+            self.ratio() * 100.0,
+            self.skipped.len(),
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+/// 以 bytes/KB/MB 为单位格式化文件大小。
+pub fn human_size(size: u64) -> String {
+    if size > 1024 * 1024 {
+        format!("{:.2} MB", size as f64 / (1024.0 * 1024.0))
+    } else if size > 1024 {
+        format!("{:.2} KB", size as f64 / 1024.0)
+    } else {
+        format!("{} bytes", size)
+    }
+}
+
+/// 单个阶段（遍历、过滤、哈希、压缩、上传等）的耗时统计。
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseTiming {
+    /// 阶段名称，例如 "walk"、"filter"、"compress"
+    pub name: String,
+    /// 阶段耗时
+    pub duration: Duration,
+    /// 阶段处理的文件数
+    pub files: usize,
+    /// 阶段处理的字节数，为 0 时不计算吞吐量
+    pub bytes: u64,
+}
+
+impl PhaseTiming {
+    /// 有效吞吐量 (MB/s)。字节数为 0 或耗时为 0 时返回 `None`。
+    pub fn throughput_mb_s(&self) -> Option<f64> {
+        let secs = self.duration.as_secs_f64();
+        if self.bytes == 0 || secs == 0.0 {
+            return None;
+        }
+        Some(self.bytes as f64 / (1024.0 * 1024.0) / secs)
+    }
+}
+
+/// 一次运行中各阶段的耗时统计，按记录顺序保存。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhaseTimings {
+    phases: Vec<PhaseTiming>,
+}
+
+impl PhaseTimings {
+    /// 创建空的统计。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一个已完成的阶段。
+    pub fn record(&mut self, name: &str, duration: Duration, files: usize, bytes: u64) {
+        self.phases.push(PhaseTiming {
+            name: name.to_string(),
+            duration,
+            files,
+            bytes,
+        });
+    }
+
+    /// 执行闭包并将其耗时记录为一个阶段。
+    ///
+    /// 闭包返回 `(结果, 文件数, 字节数)`。
+    pub fn time<T, F>(&mut self, name: &str, f: F) -> T
+    where
+        F: FnOnce() -> (T, usize, u64),
+    {
+        let start = Instant::now();
+        let (result, files, bytes) = f();
+        self.record(name, start.elapsed(), files, bytes);
+        result
+    }
+
+    /// 所有已记录的阶段。
+    pub fn phases(&self) -> &[PhaseTiming] {
+        &self.phases
+    }
+
+    /// 按名称查找阶段。
+    pub fn get(&self, name: &str) -> Option<&PhaseTiming> {
+        self.phases.iter().find(|p| p.name == name)
+    }
+
+    /// 所有阶段的总耗时。
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|p| p.duration).sum()
+    }
+}
+
+impl fmt::Display for PhaseTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for phase in &self.phases {
+            write!(
+                f,
+                "{:<10} {:>9.3}s {:>8} 个文件",
+                phase.name,
+                phase.duration.as_secs_f64(),
+                phase.files
+            )?;
+            if let Some(speed) = phase.throughput_mb_s() {
+                write!(f, " {:>10.2} MB/s", speed)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{:<10} {:>9.3}s", "total", self.total().as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_ratio() {
+        let report = CompressionReport {
+            output_path: PathBuf::from("out.zip"),
+            files_count: 2,
+            input_bytes: 1000,
+            output_bytes: 250,
+            duration: Duration::from_secs(1),
+            skipped: Vec::new(),
+            checksums: Vec::new(),
+            signature: None,
+        };
+        assert_eq!(report.ratio(), 0.25);
+        assert_eq!(
+            report.to_string(),
+            "共 2 个文件, 1000 -> 250 字节 (压缩比 25.0%), 跳过 0 个文件, 耗时 1.00s"
+        );
+        assert_eq!(human_size(250), "250 bytes");
+        assert_eq!(human_size(3 * 1024 * 1024), "3.00 MB");
+        assert_eq!(
+            CompressionReport {
+                input_bytes: 0,
+                ..report
+            }
+            .ratio(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_throughput() {
+        let phase = PhaseTiming {
+            name: "compress".to_string(),
+            duration: Duration::from_secs(2),
+            files: 1,
+            bytes: 4 * 1024 * 1024,
+        };
+        assert_eq!(phase.throughput_mb_s(), Some(2.0));
+
+        let empty = PhaseTiming { bytes: 0, ..phase };
+        assert_eq!(empty.throughput_mb_s(), None);
+    }
+
+    #[test]
+    fn test_record_and_display() {
+        let mut timings = PhaseTimings::new();
+        let value = timings.time("walk", || (42, 3, 0));
+        timings.record("compress", Duration::from_millis(500), 3, 1024 * 1024);
+
+        assert_eq!(value, 42);
+        assert_eq!(timings.phases().len(), 2);
+        assert_eq!(timings.get("walk").map(|p| p.files), Some(3));
+        assert!(timings.total() >= Duration::from_millis(500));
+
+        let text = timings.to_string();
+        assert!(text.contains("walk"));
+        assert!(text.contains("2.00 MB/s"));
+        assert!(text.lines().last().unwrap().starts_with("total"));
+    }
+}
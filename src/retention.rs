@@ -0,0 +1,506 @@
+use crate::attributes;
+use crate::checksum::{self, ChecksumAlgorithm};
+use crate::compressor;
+use crate::config::Config;
+use crate::format::Format;
+use crate::listing;
+use crate::signing::{self, SignTool};
+use crate::split;
+use crate::template::{self, OutputNaming};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 自动清理旧压缩包的保留策略，如 `retention = { keep_last = 5, keep_days = 30 }`。
+///
+/// 两条规则同时设置时，满足任一规则的压缩包都会被保留；被固定的压缩包总是保留，也不计入 `keep_last`。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// 保留最新的 N 个压缩包
+    pub keep_last: Option<usize>,
+    /// 保留最近 N 天内生成的压缩包
+    pub keep_days: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// 是否设置了任何保留规则。
+    pub fn is_set(&self) -> bool {
+        self.keep_last.is_some() || self.keep_days.is_some()
+    }
+}
+
+/// 输出目录中一个由 ztr 生成的压缩包。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveFile {
+    /// 压缩包路径
+    pub path: PathBuf,
+    /// 修改时间
+    pub modified: SystemTime,
+    /// 是否已被固定
+    pub pinned: bool,
+}
+
+/// 清理计划。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanPlan {
+    /// 保留的压缩包（包括被固定的），按从新到旧排列
+    pub keep: Vec<ArchiveFile>,
+    /// 要删除的压缩包，按从新到旧排列
+    pub delete: Vec<ArchiveFile>,
+}
+
+/// 由 `output_name` 模板得到的压缩包名称模式，用于识别输出目录中由 ztr 生成的压缩包。
+///
+/// 每个占位符只匹配其取值的形状：`{date}` 匹配 `YYYY-MM-DD`，`{time}` 匹配六位数字，
+/// `{timestamp}` 匹配数字，`{git_hash}` 匹配十六进制串或 `nogit`；`{dirname}`、`{hostname}` 取实际的值，
+/// 未知的 `{...}` 按原样匹配。也匹配 `on_conflict = "rename"` 追加的 `-1`、`-2`……后缀。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputNamePattern {
+    segments: Vec<Segment>,
+}
+
+/// 名称模式的一段。
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// 原样匹配的文本
+    Literal(String),
+    /// `YYYY-MM-DD`
+    Date,
+    /// `HHMMSS`
+    Time,
+    /// 一个或多个数字
+    Digits,
+    /// 一个或多个十六进制字符，或 `nogit`
+    GitHash,
+}
+
+impl OutputNamePattern {
+    /// 根据模板创建。
+    ///
+    /// # 参数
+    /// - `template`: `output_name` 模板，`None` 表示使用基础目录的名称。
+    /// - `base_dir`: 基础目录，决定 `{dirname}` 的值。
+    pub fn new(template: Option<&str>, base_dir: &Path) -> Self {
+        // 占位符先展开为 `\0`，再按 `\0` 切分出其间的文本，与输出名使用同样的解析规则
+        const MARK: char = '\0';
+        let naming = OutputNaming::new(template, base_dir);
+        let mut shapes = Vec::new();
+        let expanded = template::expand(template.unwrap_or("{dirname}"), |key| {
+            let shape = match key {
+                "date" => Segment::Date,
+                "time" => Segment::Time,
+                "timestamp" => Segment::Digits,
+                "git_hash" => Segment::GitHash,
+                _ => return naming.var(key),
+            };
+            shapes.push(shape);
+            Some(MARK.to_string())
+        });
+        let mut segments = Vec::new();
+        let mut shapes = shapes.into_iter();
+        for (i, text) in expanded.split(MARK).enumerate() {
+            if i > 0 {
+                segments.extend(shapes.next());
+            }
+            if !text.is_empty() {
+                segments.push(Segment::Literal(text.to_string()));
+            }
+        }
+        Self { segments }
+    }
+
+    /// 判断压缩包的文件名（不含扩展名）是否符合模式。
+    pub fn matches(&self, stem: &str) -> bool {
+        if match_segments(&self.segments, stem) {
+            return true;
+        }
+        stem.rsplit_once('-').is_some_and(|(head, n)| {
+            !n.is_empty()
+                && n.bytes().all(|b| b.is_ascii_digit())
+                && match_segments(&self.segments, head)
+        })
+    }
+}
+
+/// 回溯匹配：`segments` 是否恰好匹配整个 `s`。
+fn match_segments(segments: &[Segment], s: &str) -> bool {
+    let Some((first, rest)) = segments.split_first() else {
+        return s.is_empty();
+    };
+    let has_shape = |shape: &[u8]| {
+        s.len() >= shape.len()
+            && s.bytes().zip(shape).all(|(b, &c)| match c {
+                b'd' => b.is_ascii_digit(),
+                _ => b == c,
+            })
+    };
+    // 由满足 `accept` 的前缀字符组成的所有非空长度
+    let runs = |accept: fn(&u8) -> bool| 1..=s.bytes().take_while(accept).count();
+    match first {
+        Segment::Literal(text) => s
+            .strip_prefix(text.as_str())
+            .is_some_and(|s| match_segments(rest, s)),
+        Segment::Date => has_shape(b"dddd-dd-dd") && match_segments(rest, &s[10..]),
+        Segment::Time => has_shape(b"dddddd") && match_segments(rest, &s[6..]),
+        Segment::Digits => runs(u8::is_ascii_digit).any(|n| match_segments(rest, &s[n..])),
+        Segment::GitHash => {
+            s.strip_prefix("nogit")
+                .is_some_and(|s| match_segments(rest, s))
+                || runs(u8::is_ascii_hexdigit).any(|n| match_segments(rest, &s[n..]))
+        }
+    }
+}
+
+/// 查找输出目录中名称符合 `output_name` 模板的压缩包，按修改时间从新到旧排列。
+///
+/// # 参数
+/// - `dir`: 输出目录。
+/// - `pattern`: 由 `output_name` 模板得到的名称模式。
+pub fn find_archives(dir: &Path, pattern: &OutputNamePattern) -> Result<Vec<ArchiveFile>> {
+    let mut archives = Vec::new();
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("读取输出目录失败: {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("读取输出目录失败: {}", dir.display()))?;
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        // 分卷压缩包以第一个分卷代表
+        let archive_name = split::archive_of_first_volume(Path::new(&file_name))
+            .map(|archive| archive.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.clone());
+        let is_archive = Format::ALL.iter().any(|format| {
+            archive_name
+                .strip_suffix(&format!(".{}", format.extension()))
+                .is_some_and(|stem| pattern.matches(stem))
+        });
+        if !is_archive || !path.is_file() {
+            continue;
+        }
+        let modified = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .with_context(|| format!("读取修改时间失败: {}", path.display()))?;
+        archives.push(ArchiveFile {
+            pinned: is_pinned(&path),
+            path,
+            modified,
+        });
+    }
+    archives.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| b.path.cmp(&a.path))
+    });
+    Ok(archives)
+}
+
+/// 根据保留策略决定哪些压缩包需要删除。
+///
+/// # 参数
+/// - `archives`: 按从新到旧排列的压缩包。
+/// - `policy`: 保留策略。
+/// - `now`: 当前时间，用于计算 `keep_days`。
+pub fn plan(archives: Vec<ArchiveFile>, policy: &RetentionPolicy, now: SystemTime) -> CleanPlan {
+    let cutoff = policy
+        .keep_days
+        .and_then(|days| now.checked_sub(Duration::from_secs(days * 86_400)));
+    let mut plan = CleanPlan::default();
+    let mut kept_unpinned = 0;
+    for archive in archives {
+        if archive.pinned {
+            plan.keep.push(archive);
+            continue;
+        }
+        let within_last = policy.keep_last.is_some_and(|n| kept_unpinned < n);
+        let within_days = cutoff.is_some_and(|cutoff| archive.modified >= cutoff);
+        if within_last || within_days {
+            kept_unpinned += 1;
+            plan.keep.push(archive);
+        } else {
+            plan.delete.push(archive);
+        }
+    }
+    plan
+}
+
+/// 按配置查找输出目录中的压缩包，并根据保留策略生成清理计划。
+///
+/// # 参数
+/// - `config`: 压缩配置，决定输出目录和 `output_name` 模板。
+/// - `base_dir`: 基础目录。
+/// - `policy`: 保留策略。
+pub fn plan_for_config(
+    config: &Config,
+    base_dir: &Path,
+    policy: &RetentionPolicy,
+) -> Result<CleanPlan> {
+    let output_path = compressor::output_path(config, base_dir);
+    let dir = output_path.parent().unwrap_or(base_dir);
+    if !dir.is_dir() {
+        return Ok(CleanPlan::default());
+    }
+    let pattern = OutputNamePattern::new(config.output_name.as_deref(), base_dir);
+    let archives = find_archives(dir, &pattern)?;
+    Ok(plan(archives, policy, SystemTime::now()))
+}
+
+/// 删除压缩包及其附属文件（清单 `.list`、属性 `.attrs`/`.acl`、校验和 `.sha256`/`.blake3`、签名 `.minisig`/`.sig`）。被固定的压缩包会被拒绝删除。
+///
+/// `archive_path` 是第一个分卷 (`.001`) 时删除所有分卷。
+pub fn remove_archive(archive_path: &Path) -> Result<()> {
+    if is_pinned(archive_path) {
+        anyhow::bail!("拒绝删除已固定的压缩包: {}", archive_path.display());
+    }
+    std::fs::remove_file(archive_path)
+        .with_context(|| format!("删除压缩包失败: {}", archive_path.display()))?;
+    // 分卷压缩包：删除其余分卷，附属文件以合并后的压缩包命名
+    let archive = split::archive_of_first_volume(archive_path);
+    if let Some(archive) = &archive {
+        for volume in (2..)
+            .map(|n| split::volume_path(archive, n))
+            .take_while(|volume| volume.is_file())
+        {
+            std::fs::remove_file(&volume)
+                .with_context(|| format!("删除分卷失败: {}", volume.display()))?;
+        }
+    }
+    let archive_path = archive.as_deref().unwrap_or(archive_path);
+    let checksums = ChecksumAlgorithm::ALL.map(|alg| checksum::sidecar_path(archive_path, alg));
+    for sidecar in [
+        listing::listing_path(archive_path),
+        attributes::sidecar_path(archive_path),
+        attributes::acl_sidecar_path(archive_path),
+    ]
+    .into_iter()
+    .chain(checksums)
+    .chain(SignTool::ALL.map(|tool| signing::signature_path(archive_path, tool)))
+    {
+        if sidecar.is_file() {
+            std::fs::remove_file(&sidecar)
+                .with_context(|| format!("删除附属文件失败: {}", sidecar.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// 返回固定标记文件的路径 (`<压缩包>.pin`)。
+///
+/// 存在该文件的压缩包被视为受保护的归档（例如每月的完整备份），自动清理时不会被删除。
+pub fn pin_path(archive_path: &Path) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(".pin");
+    PathBuf::from(name)
+}
+
+/// 判断压缩包是否已被固定。
+pub fn is_pinned(archive_path: &Path) -> bool {
+    pin_path(archive_path).is_file()
+}
+
+/// 固定压缩包，使其不会被自动清理。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径。
+/// - `reason`: 固定的原因 (可选)，写入标记文件中供人查看。
+///
+/// # 返回
+/// `Result<PathBuf>`: 标记文件的路径；压缩包不存在或无法写入标记文件时返回错误。
+pub fn pin(archive_path: &Path, reason: Option<&str>) -> Result<PathBuf> {
+    if !archive_path.is_file() {
+        anyhow::bail!("压缩包不存在: {}", archive_path.display());
+    }
+    let path = pin_path(archive_path);
+    let mut content = reason.unwrap_or_default().trim().to_string();
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    std::fs::write(&path, content)
+        .with_context(|| format!("写入固定标记失败: {}", path.display()))?;
+    Ok(path)
+}
+
+/// 取消固定压缩包。
+///
+/// # 返回
+/// `Result<bool>`: 压缩包原本是否被固定。
+pub fn unpin(archive_path: &Path) -> Result<bool> {
+    let path = pin_path(archive_path);
+    if !path.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_file(&path).with_context(|| format!("删除固定标记失败: {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_output_name_pattern() {
+        let base_dir = Path::new("/srv/project");
+        let matches = |template: &str, stem: &str| {
+            OutputNamePattern::new(Some(template), base_dir).matches(stem)
+        };
+        assert!(matches("backup-{date}-{time}", "backup-2026-10-17-020000"));
+        assert!(matches("backup-{date}", "backup-2026-10-17-1"));
+        assert!(!matches("backup-{date}-{time}", "backup-2026-10-17-0200"));
+        assert!(matches("project", "project"));
+        assert!(matches("project", "project-2"));
+        assert!(!matches("project", "project-old"));
+        assert!(!matches("project", "other"));
+        assert!(matches("[v]-{date}", "[v]-2026-10-17"));
+        assert!(!matches("[v]-{date}", "[v]-2026"));
+        assert!(!matches("backup-{date}", "restore-2026-10-17"));
+        assert!(matches("bk-{timestamp}", "bk-1792195200"));
+        assert!(!matches("bk-{timestamp}", "bk-notes"));
+        assert!(!matches("{date}", "project"));
+        assert!(matches("{timestamp}{git_hash}", "17921952003f2a9c1"));
+        assert!(matches("v-{git_hash}", "v-nogit"));
+        assert!(!matches("v-{git_hash}", "v-main"));
+        assert!(matches("{dirname}-{date}", "project-2026-10-17"));
+        assert!(!matches("{dirname}-{date}", "other-2026-10-17"));
+        assert!(matches("{unknown}-{date}", "{unknown}-2026-10-17"));
+        // 未配置模板时只匹配基础目录的名称
+        let default = OutputNamePattern::new(None, base_dir);
+        assert!(default.matches("project"));
+        assert!(!default.matches("{dirname}"));
+    }
+
+    #[test]
+    fn test_plan() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * 86_400);
+        let archive = |name: &str, days_ago: u64, pinned: bool| ArchiveFile {
+            path: PathBuf::from(name),
+            modified: now - Duration::from_secs(days_ago * 86_400),
+            pinned,
+        };
+        let archives = vec![
+            archive("a", 0, false),
+            archive("b", 1, true),
+            archive("c", 2, false),
+            archive("d", 10, false),
+            archive("e", 40, true),
+            archive("f", 50, false),
+        ];
+        let names = |files: &[ArchiveFile]| -> Vec<String> {
+            files.iter().map(|f| f.path.display().to_string()).collect()
+        };
+
+        let keep_last = RetentionPolicy {
+            keep_last: Some(2),
+            keep_days: None,
+        };
+        let result = plan(archives.clone(), &keep_last, now);
+        assert_eq!(names(&result.keep), ["a", "b", "c", "e"]);
+        assert_eq!(names(&result.delete), ["d", "f"]);
+
+        let both = RetentionPolicy {
+            keep_last: Some(1),
+            keep_days: Some(30),
+        };
+        let result = plan(archives.clone(), &both, now);
+        assert_eq!(names(&result.delete), ["f"]);
+
+        let nothing = plan(archives, &RetentionPolicy::default(), now);
+        assert_eq!(names(&nothing.keep), ["b", "e"]);
+    }
+
+    #[test]
+    fn test_find_and_remove_archives() -> Result<()> {
+        let dir = tempdir()?;
+        let old = dir.path().join("backup-2026-10-01.tar.gz");
+        let new = dir.path().join("backup-2026-10-02.zip");
+        std::fs::write(&old, "old")?;
+        std::fs::write(&new, "new")?;
+        std::fs::write(listing::listing_path(&old), "list")?;
+        std::fs::write(dir.path().join("backup-notes.txt"), "not an archive")?;
+        std::fs::write(dir.path().join("other.zip"), "other")?;
+        std::fs::File::options()
+            .write(true)
+            .open(&old)?
+            .set_modified(SystemTime::now() - Duration::from_secs(3_600))?;
+
+        let pattern = OutputNamePattern::new(Some("backup-{date}"), dir.path());
+        let archives = find_archives(dir.path(), &pattern)?;
+        let paths: Vec<&PathBuf> = archives.iter().map(|a| &a.path).collect();
+        assert_eq!(paths, [&new, &old]);
+
+        remove_archive(&old)?;
+        assert!(!old.exists());
+        assert!(!listing::listing_path(&old).exists());
+
+        pin(&new, None)?;
+        assert!(remove_archive(&new).is_err());
+        assert!(new.exists());
+
+        // 分卷压缩包以第一个分卷代表，删除时删除所有分卷和附属文件
+        let split = dir.path().join("backup-2026-09-30.7z");
+        let volumes = [split::volume_path(&split, 1), split::volume_path(&split, 2)];
+        for volume in &volumes {
+            std::fs::write(volume, "part")?;
+        }
+        std::fs::write(listing::listing_path(&split), "list")?;
+        let archives = find_archives(dir.path(), &pattern)?;
+        assert!(archives.iter().any(|a| a.path == volumes[0]));
+        assert!(!archives.iter().any(|a| a.path == volumes[1]));
+        remove_archive(&volumes[0])?;
+        assert!(!volumes[1].exists());
+        assert!(!listing::listing_path(&split).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_keeps_hand_named_archives() -> Result<()> {
+        let dir = tempdir()?;
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("bk-{timestamp}".to_string()),
+            ..Default::default()
+        };
+        let older = dir.path().join("bk-1792195200.zip");
+        let newer = dir.path().join("bk-1792195300.zip");
+        let notes = dir.path().join("bk-notes.zip");
+        for path in [&older, &newer, &notes] {
+            std::fs::write(path, "archive")?;
+        }
+        for (path, age) in [(&older, 7_200), (&notes, 3_600)] {
+            std::fs::File::options()
+                .write(true)
+                .open(path)?
+                .set_modified(SystemTime::now() - Duration::from_secs(age))?;
+        }
+
+        let policy = RetentionPolicy {
+            keep_last: Some(1),
+            keep_days: None,
+        };
+        let plan = plan_for_config(&config, dir.path(), &policy)?;
+        let paths =
+            |files: &[ArchiveFile]| files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&plan.keep), [newer]);
+        assert_eq!(paths(&plan.delete), [older]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_and_unpin() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("full-2026-10.tar.gz");
+        assert!(pin(&archive, None).is_err());
+
+        std::fs::write(&archive, "archive")?;
+        assert!(!is_pinned(&archive));
+        let marker = pin(&archive, Some("monthly full"))?;
+        assert_eq!(marker, dir.path().join("full-2026-10.tar.gz.pin"));
+        assert!(is_pinned(&archive));
+        assert_eq!(std::fs::read_to_string(&marker)?, "monthly full\n");
+
+        assert!(unpin(&archive)?);
+        assert!(!is_pinned(&archive));
+        assert!(!unpin(&archive)?);
+        Ok(())
+    }
+}
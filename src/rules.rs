@@ -0,0 +1,241 @@
+use anyhow::{Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// ZIP 条目的压缩方法。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZipMethod {
+    /// 只存储，不压缩
+    Stored,
+    /// Deflate (默认)
+    #[default]
+    #[serde(alias = "deflated")]
+    Deflate,
+    /// bzip2，需要 `zip-bzip2` 特性
+    Bzip2,
+    /// Zstandard，需要 `zip-zstd` 特性
+    Zstd,
+}
+
+impl ZipMethod {
+    /// 当前构建是否支持写入该压缩方法。
+    pub fn is_available(self) -> bool {
+        match self {
+            ZipMethod::Stored | ZipMethod::Deflate => true,
+            ZipMethod::Bzip2 => cfg!(feature = "zip-bzip2"),
+            ZipMethod::Zstd => cfg!(feature = "zip-zstd"),
+        }
+    }
+
+    /// 检查当前构建是否支持该压缩方法，不支持时返回说明所需特性的错误。
+    pub fn check_available(self) -> Result<()> {
+        if !self.is_available() {
+            anyhow::bail!(
+                "zip 压缩方法 {} 不可用: ztr 构建时未启用 zip-{} 特性",
+                self,
+                self
+            );
+        }
+        Ok(())
+    }
+
+    /// 该方法接受的压缩级别范围。
+    fn level_range(self) -> (u32, u32) {
+        match self {
+            ZipMethod::Stored => (0, 0),
+            ZipMethod::Deflate => (1, 9),
+            ZipMethod::Bzip2 => (1, 9),
+            ZipMethod::Zstd => (1, 22),
+        }
+    }
+
+    /// 将压缩级别限制在该方法接受的范围内，`stored` 不使用级别。
+    pub fn clamp_level(self, level: u32) -> Option<i64> {
+        let (min, max) = self.level_range();
+        (self != ZipMethod::Stored).then(|| i64::from(level.clamp(min, max)))
+    }
+
+    /// 对应的 `zip` crate 压缩方法，调用前应通过 [`ZipMethod::check_available`] 检查。
+    pub fn to_zip(self) -> zip::CompressionMethod {
+        match self {
+            ZipMethod::Stored => zip::CompressionMethod::Stored,
+            ZipMethod::Deflate => zip::CompressionMethod::Deflated,
+            #[cfg(feature = "zip-bzip2")]
+            ZipMethod::Bzip2 => zip::CompressionMethod::Bzip2,
+            #[cfg(feature = "zip-zstd")]
+            ZipMethod::Zstd => zip::CompressionMethod::Zstd,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("zip 压缩方法 {} 未启用", self),
+        }
+    }
+}
+
+impl fmt::Display for ZipMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZipMethod::Stored => write!(f, "stored"),
+            ZipMethod::Deflate => write!(f, "deflate"),
+            ZipMethod::Bzip2 => write!(f, "bzip2"),
+            ZipMethod::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+/// 一条按文件类型调整压缩参数的规则，即 `[rules]` 中的一项。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionRule {
+    /// 压缩级别 0–9：ZIP 中为所用压缩方法的级别 (超出方法的范围时取最接近的有效值)，7Z 中为 LZMA2 预设
+    pub level: Option<u32>,
+    /// ZIP 中使用的压缩方法；`"stored"` 在 7Z 中对应最快的 LZMA2 预设
+    pub method: Option<ZipMethod>,
+}
+
+impl CompressionRule {
+    /// 最高的压缩级别。
+    pub const MAX_LEVEL: u32 = 9;
+
+    /// 将另一条规则合并到当前规则上，`other` 中的值优先。
+    fn merge(&mut self, other: &CompressionRule) {
+        if other.level.is_some() {
+            self.level = other.level;
+        }
+        if other.method.is_some() {
+            self.method = other.method;
+        }
+    }
+}
+
+/// 按文件类型查找压缩参数。
+///
+/// 模式使用 Gitignore 语法，不含 `/` 的模式 (如 `*.log`) 匹配任意层级的文件。
+/// 当多个模式匹配同一条目时，按模式的字典序依次合并，后者覆盖前者。
+#[derive(Debug, Default)]
+pub struct CompressionRules {
+    rules: Vec<(Gitignore, CompressionRule)>,
+}
+
+impl CompressionRules {
+    /// 从配置中的 `模式 -> 规则` 映射创建规则。
+    ///
+    /// # 参数
+    /// - `map`: 模式到规则的映射。
+    /// - `base_dir`: 模式所相对的目录。
+    ///
+    /// # 返回
+    /// `Result<Self>`: 模式无效或压缩级别超出范围时返回错误信息。
+    pub fn new(map: &BTreeMap<String, CompressionRule>, base_dir: &Path) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (pattern, rule) in map {
+            if let Some(level) = rule.level
+                && level > CompressionRule::MAX_LEVEL
+            {
+                anyhow::bail!(
+                    "规则 {} 的压缩级别 {} 超出范围 0-{}",
+                    pattern,
+                    level,
+                    CompressionRule::MAX_LEVEL
+                );
+            }
+            if let Some(method) = rule.method {
+                method
+                    .check_available()
+                    .with_context(|| format!("无效的压缩规则: {}", pattern))?;
+            }
+            let mut builder = GitignoreBuilder::new(base_dir);
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("无效的压缩规则模式: {}", pattern))?;
+            let matcher = builder
+                .build()
+                .with_context(|| format!("无效的压缩规则模式: {}", pattern))?;
+            rules.push((matcher, rule.clone()));
+        }
+        Ok(Self { rules })
+    }
+
+    /// 查找给定相对路径（使用 `/` 分隔）的压缩参数，没有匹配的规则时各项为 `None`。
+    pub fn lookup(&self, relative_path: &str) -> CompressionRule {
+        let mut rule = CompressionRule::default();
+        for (matcher, matched_rule) in &self.rules {
+            if matcher
+                .matched_path_or_any_parents(relative_path, false)
+                .is_ignore()
+            {
+                rule.merge(matched_rule);
+            }
+        }
+        rule
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_merges_matching_rules() -> Result<()> {
+        let config: crate::config::Config = toml::from_str(
+            r#"
+            format = "zip"
+
+            [rules]
+            "*.log" = { level = 9 }
+            "*.bin" = { method = "stored" }
+            "logs/" = { method = "deflate", level = 1 }
+            "#,
+        )?;
+        let rules = CompressionRules::new(&config.rules.unwrap(), Path::new("."))?;
+
+        assert_eq!(
+            rules.lookup("app.log"),
+            CompressionRule {
+                level: Some(9),
+                method: None
+            }
+        );
+        assert_eq!(
+            rules.lookup("data/model.bin").method,
+            Some(ZipMethod::Stored)
+        );
+        // "logs/" 排在 "*.log" 之后，覆盖其级别
+        assert_eq!(
+            rules.lookup("logs/today.log"),
+            CompressionRule {
+                level: Some(1),
+                method: Some(ZipMethod::Deflate)
+            }
+        );
+        assert_eq!(rules.lookup("src/main.rs"), CompressionRule::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_method_levels() {
+        assert_eq!(ZipMethod::Stored.clamp_level(9), None);
+        assert_eq!(ZipMethod::Deflate.clamp_level(9), Some(9));
+        assert_eq!(ZipMethod::Deflate.clamp_level(0), Some(1));
+        assert_eq!(ZipMethod::Bzip2.clamp_level(0), Some(1));
+        assert_eq!(ZipMethod::Zstd.clamp_level(0), Some(1));
+        assert!(ZipMethod::Deflate.check_available().is_ok());
+        assert_eq!(
+            ZipMethod::Zstd.check_available().is_ok(),
+            cfg!(feature = "zip-zstd")
+        );
+    }
+
+    #[test]
+    fn test_invalid_level() {
+        let map = BTreeMap::from([(
+            "*.log".to_string(),
+            CompressionRule {
+                level: Some(12),
+                method: None,
+            },
+        )]);
+        assert!(CompressionRules::new(&map, Path::new(".")).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::template;
+use anyhow::{Context, Result};
+use std::str::FromStr;
+
+/// 最多向后查找的天数，超过时认为表达式永远不会触发（如 `0 0 30 2 *`）。
+const MAX_SEARCH_DAYS: i64 = 366 * 8;
+
+/// 五段式 cron 表达式 `分 时 日 月 周`，如 `0 2 * * *` 表示每天 02:00。
+///
+/// 每一段支持 `*`、数字、范围 `1-5`、列表 `1,3,5` 和步长 `*/15`、`0-30/10`；
+/// 周的取值为 0-7（0 和 7 都表示星期日）。日和周都不是 `*` 时，满足任一即可（与 cron 相同）。
+/// 也支持 `@hourly`、`@daily`、`@weekly`、`@monthly`、`@yearly` 简写。时间按本地时区计算。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    /// 日一段是否不是 `*`
+    dom_restricted: bool,
+    /// 周一段是否不是 `*`
+    dow_restricted: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields.as_slice() else {
+            anyhow::bail!(
+                "cron 表达式应包含 5 段 (分 时 日 月 周)，实际为 {} 段: {}",
+                fields.len(),
+                expr
+            );
+        };
+        let context = |name: &str| format!("cron 表达式 {} 中的{}无效", expr, name);
+        let days_of_week = parse_field(dow, 0, 7).with_context(|| context("周"))?;
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59).with_context(|| context("分钟"))?,
+            hours: parse_field(hour, 0, 23).with_context(|| context("小时"))? as u32,
+            days_of_month: parse_field(dom, 1, 31).with_context(|| context("日"))? as u32,
+            months: parse_field(month, 1, 12).with_context(|| context("月"))? as u16,
+            // 7 与 0 都表示星期日
+            days_of_week: ((days_of_week | (days_of_week >> 7)) & 0x7f) as u8,
+            dom_restricted: *dom != "*",
+            dow_restricted: *dow != "*",
+        })
+    }
+}
+
+/// 解析一段 cron 字段，返回取值的位掩码。
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .with_context(|| format!("无效的步长: {}", step))?;
+                if step == 0 {
+                    anyhow::bail!("步长不能为 0");
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // `5/10` 表示从 5 开始每 10 个
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start > end {
+            anyhow::bail!("范围的起点大于终点: {}", range);
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32> {
+    let number: u32 = value
+        .parse()
+        .with_context(|| format!("无效的数值: {}", value))?;
+    if !(min..=max).contains(&number) {
+        anyhow::bail!("{} 超出范围 {}-{}", number, min, max);
+    }
+    Ok(number)
+}
+
+impl CronSchedule {
+    /// 解析 cron 表达式。
+    pub fn parse(expr: &str) -> Result<Self> {
+        expr.parse()
+    }
+
+    /// 返回严格晚于 `timestamp` 的下一次触发时间。
+    ///
+    /// # 参数
+    /// - `timestamp`: Unix 时间戳 (秒)。
+    /// - `utc_offset`: 本地时间相对 UTC 的偏移 (秒)。
+    ///
+    /// # 返回
+    /// `Option<u64>`: 下一次触发的 Unix 时间戳；表达式永远不会触发时为 `None`。
+    pub fn next_after(&self, timestamp: u64, utc_offset: i64) -> Option<u64> {
+        // 从下一分钟开始
+        let start = (timestamp as i64 + utc_offset).div_euclid(60) * 60 + 60;
+        let start_day = start.div_euclid(86_400);
+        let start_minute = start.rem_euclid(86_400) / 60;
+        for day in start_day..start_day + MAX_SEARCH_DAYS {
+            if !self.matches_day(day) {
+                continue;
+            }
+            let first_minute = if day == start_day { start_minute } else { 0 };
+            for minute_of_day in first_minute..24 * 60 {
+                let (hour, minute) = (minute_of_day / 60, minute_of_day % 60);
+                if self.hours & (1 << hour) != 0 && self.minutes & (1 << minute) != 0 {
+                    let local = day * 86_400 + minute_of_day * 60;
+                    return u64::try_from(local - utc_offset).ok();
+                }
+            }
+        }
+        None
+    }
+
+    /// 按本地时区返回严格晚于 `timestamp` 的下一次触发时间。
+    pub fn next_after_local(&self, timestamp: u64) -> Option<u64> {
+        self.next_after(timestamp, template::local_utc_offset(timestamp))
+    }
+
+    fn matches_day(&self, day: i64) -> bool {
+        let (_, month, dom) = template::civil_from_days(day);
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        // 1970-01-01 是星期四
+        let dow = (day + 4).rem_euclid(7);
+        let dom_match = self.days_of_month & (1 << dom) != 0;
+        let dow_match = self.days_of_week & (1 << dow) != 0;
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom_match || dow_match,
+            (true, false) => dom_match,
+            (false, true) => dow_match,
+            (false, false) => true,
+        }
+    }
+}
+
+/// 以本地时区将 Unix 时间戳格式化为 `YYYY-MM-DD HH:MM:SS`，用于运行日志。
+pub fn format_local_time(timestamp: u64) -> String {
+    let local = timestamp as i64 + template::local_utc_offset(timestamp);
+    let (year, month, day) = template::civil_from_days(local.div_euclid(86_400));
+    let rem = local.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2023-11-14 22:13:20 UTC，星期二
+    const NOW: u64 = 1_700_000_000;
+
+    fn next(expr: &str) -> u64 {
+        CronSchedule::parse(expr)
+            .unwrap()
+            .next_after(NOW, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 每分钟
+        assert_eq!(next("* * * * *"), 1_700_000_040);
+        // 每天 02:00 -> 2023-11-15 02:00
+        assert_eq!(next("0 2 * * *"), 1_700_013_600);
+        assert_eq!(next("@daily"), 1_700_006_400);
+        // 每 15 分钟 -> 22:15
+        assert_eq!(next("*/15 * * * *"), 1_700_000_100);
+        // 星期六 (11-18) 与 星期日写作 7 (11-19)
+        assert_eq!(next("0 0 * * 6"), 1_700_265_600);
+        assert_eq!(next("0 0 * * 7"), 1_700_352_000);
+        // 日和周都指定时满足任一: 15 日 (星期三) 早于星期五
+        assert_eq!(next("0 0 15 * 5"), 1_700_006_400);
+        // 下一个 2 月 29 日: 2024-02-29
+        assert_eq!(next("0 0 29 2 *"), 1_709_164_800);
+        // 时区: UTC+8 的本地时间已是 11-15 06:13，下一次 07:00 本地即 23:00 UTC
+        let schedule = CronSchedule::parse("0 7 * * *").unwrap();
+        assert_eq!(schedule.next_after(NOW, 8 * 3_600), Some(1_700_002_800));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(CronSchedule::parse("0 2 * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 * 13 *").is_err());
+        assert!(CronSchedule::parse("a * * * *").is_err());
+        assert!(
+            CronSchedule::parse("0 0 30 2 *")
+                .unwrap()
+                .next_after(NOW, 0)
+                .is_none()
+        );
+        assert!(CronSchedule::parse("0,30 9-17/2 1-15 */3 1-5").is_ok());
+    }
+}
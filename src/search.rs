@@ -0,0 +1,257 @@
+//! 在压缩包中查找条目：按 Gitignore 语法的模式匹配条目名称，可选地在条目内容中逐行查找文本。
+//!
+//! 条目内容以流的方式读取，不会解压到磁盘；只按名称查找时只读取条目信息。
+
+use crate::ignore_rules;
+use crate::reader::{self, EntryInfo};
+use anyhow::{Context, Result};
+use ignore::gitignore::Gitignore;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// 匹配行在结果中保留的最大字符数。
+const MAX_LINE_CHARS: usize = 200;
+
+/// 查找条件。
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// 条目名称的模式 (Gitignore 语法，如 `*.sql`、`db/`)，为空时匹配所有条目
+    pub patterns: Vec<String>,
+    /// 条目内容中要查找的文本，为 `None` 时只按名称查找
+    pub contains: Option<String>,
+    /// 查找内容时忽略大小写 (只对 ASCII 字母生效)
+    pub ignore_case: bool,
+}
+
+/// 内容中匹配的一行。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchedLine {
+    /// 行号，从 1 开始
+    pub number: u64,
+    /// 行的内容，过长时被截断
+    pub text: String,
+}
+
+/// 一个匹配的条目。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// 条目信息
+    pub entry: EntryInfo,
+    /// 内容中匹配的行，只按名称查找时为空
+    pub lines: Vec<MatchedLine>,
+    /// 条目内容是否为二进制 (包含 NUL 字节)，二进制条目只报告是否匹配，不记录匹配行
+    pub binary: bool,
+}
+
+/// 在压缩包中查找条目。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，格式按文件内容判断，支持分卷。
+/// - `options`: 查找条件。
+///
+/// # 返回
+/// `Result<Vec<SearchMatch>>`: 按条目在压缩包中的顺序排列的匹配结果；模式无效或压缩包无法读取时返回错误。
+pub fn find_entries(archive_path: &Path, options: &SearchOptions) -> Result<Vec<SearchMatch>> {
+    let matcher = name_matcher(&options.patterns)?;
+    let reader = reader::open_archive_reader(archive_path)?;
+    let Some(needle) = options.contains.as_deref() else {
+        return Ok(reader
+            .list()?
+            .into_iter()
+            .filter(|entry| name_matches(matcher.as_ref(), &entry.name))
+            .map(|entry| SearchMatch {
+                entry,
+                lines: Vec::new(),
+                binary: false,
+            })
+            .collect());
+    };
+    anyhow::ensure!(!needle.is_empty(), "要查找的文本不能为空");
+    let needle = fold_case(needle.as_bytes(), options.ignore_case);
+    let mut matches = Vec::new();
+    reader.read_entries(&mut |info, entry| {
+        if name_matches(matcher.as_ref(), &info.name)
+            && let Some((lines, binary)) = search_content(entry, &needle, options.ignore_case)
+                .with_context(|| format!("读取条目失败: {}", info.name))?
+        {
+            matches.push(SearchMatch {
+                entry: info.clone(),
+                lines,
+                binary,
+            });
+        }
+        Ok(())
+    })?;
+    Ok(matches)
+}
+
+fn name_matcher(patterns: &[String]) -> Result<Option<Gitignore>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    ignore_rules::pattern_matcher(patterns, Path::new(""), "查找模式").map(Some)
+}
+
+fn name_matches(matcher: Option<&Gitignore>, name: &str) -> bool {
+    let Some(matcher) = matcher else {
+        return true;
+    };
+    let path = Path::new(name.trim_start_matches("./"));
+    !path.has_root() && matcher.matched_path_or_any_parents(path, false).is_ignore()
+}
+
+/// 逐行查找内容，返回匹配的行与内容是否为二进制；没有匹配时返回 `None`。
+fn search_content(
+    entry: &mut dyn Read,
+    needle: &[u8],
+    ignore_case: bool,
+) -> Result<Option<(Vec<MatchedLine>, bool)>> {
+    let mut reader = BufReader::new(entry);
+    let mut lines = Vec::new();
+    let mut binary = false;
+    let mut line = Vec::new();
+    let mut number = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        number += 1;
+        binary |= line.contains(&0);
+        if !contains(&fold_case(&line, ignore_case), needle) {
+            continue;
+        }
+        if binary {
+            // 二进制内容只需知道是否匹配，读取剩余内容以便继续读取后面的条目
+            std::io::copy(&mut reader, &mut std::io::sink())?;
+            return Ok(Some((Vec::new(), true)));
+        }
+        let text = String::from_utf8_lossy(&line);
+        lines.push(MatchedLine {
+            number,
+            text: text
+                .trim_end_matches(['\r', '\n'])
+                .chars()
+                .take(MAX_LINE_CHARS)
+                .collect(),
+        });
+    }
+    Ok((!lines.is_empty()).then_some((lines, false)))
+}
+
+fn fold_case(bytes: &[u8], ignore_case: bool) -> Vec<u8> {
+    if ignore_case {
+        bytes.to_ascii_lowercase()
+    } else {
+        bytes.to_vec()
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor;
+    use crate::config::Config;
+    use crate::format::Format;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_entries() -> Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("db"))?;
+        std::fs::write(
+            src.join("db/schema.sql"),
+            "-- schema\nCREATE TABLE users (id INT);\ncreate table posts (id INT);\n",
+        )?;
+        std::fs::write(src.join("db/seed.sql"), "INSERT INTO users VALUES (1);\n")?;
+        std::fs::write(src.join("notes.txt"), "CREATE TABLE in a note\n")?;
+        std::fs::write(src.join("dump.bin"), b"\0\x01CREATE TABLE\n\x02")?;
+        let files = vec![
+            src.join("db/schema.sql"),
+            src.join("db/seed.sql"),
+            src.join("dump.bin"),
+            src.join("notes.txt"),
+        ];
+        for format in Format::ALL {
+            let output = dir.path().join(format!("out.{}", format.extension()));
+            let config = Config {
+                format,
+                ..Default::default()
+            };
+            compressor::compress_files(&config, &src, files.clone(), &output, &NoopReporter)?;
+
+            let names = |matches: Vec<SearchMatch>| -> Vec<String> {
+                let mut names: Vec<_> = matches.into_iter().map(|m| m.entry.name).collect();
+                names.sort();
+                names
+            };
+            let by_name = find_entries(
+                &output,
+                &SearchOptions {
+                    patterns: vec!["*.sql".to_string()],
+                    ..Default::default()
+                },
+            )?;
+            assert_eq!(
+                names(by_name),
+                ["db/schema.sql", "db/seed.sql"],
+                "{}",
+                format
+            );
+
+            let by_content = find_entries(
+                &output,
+                &SearchOptions {
+                    patterns: vec!["*.sql".to_string()],
+                    contains: Some("CREATE TABLE".to_string()),
+                    ..Default::default()
+                },
+            )?;
+            assert_eq!(by_content.len(), 1, "{}", format);
+            assert_eq!(by_content[0].entry.name, "db/schema.sql");
+            assert_eq!(
+                by_content[0].lines,
+                [MatchedLine {
+                    number: 2,
+                    text: "CREATE TABLE users (id INT);".to_string()
+                }]
+            );
+
+            let everywhere = find_entries(
+                &output,
+                &SearchOptions {
+                    contains: Some("create table".to_string()),
+                    ignore_case: true,
+                    ..Default::default()
+                },
+            )?;
+            assert_eq!(
+                names(everywhere.clone()),
+                ["db/schema.sql", "dump.bin", "notes.txt"],
+                "{}",
+                format
+            );
+            let binary = everywhere
+                .iter()
+                .find(|m| m.entry.name == "dump.bin")
+                .unwrap();
+            assert!(binary.binary && binary.lines.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(contains(b"abc", b"bc"));
+        assert!(!contains(b"ab", b"abc"));
+        assert_eq!(fold_case(b"AbC", true), b"abc");
+    }
+}
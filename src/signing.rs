@@ -0,0 +1,322 @@
+use crate::split;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// 生成分离签名所用的外部工具。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignTool {
+    /// minisign，签名文件为 `<压缩包>.minisig`
+    Minisign,
+    /// GnuPG，签名文件为 `<压缩包>.sig`
+    Gpg,
+}
+
+impl SignTool {
+    /// 所有支持的工具。
+    pub const ALL: [SignTool; 2] = [SignTool::Minisign, SignTool::Gpg];
+
+    /// 签名文件的扩展名（不含点）。
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SignTool::Minisign => "minisig",
+            SignTool::Gpg => "sig",
+        }
+    }
+
+    /// 可执行文件的名称。
+    fn program(&self) -> &'static str {
+        match self {
+            SignTool::Minisign => "minisign",
+            SignTool::Gpg => "gpg",
+        }
+    }
+}
+
+impl fmt::Display for SignTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+/// 压缩包的签名配置，即配置中的 `sign = { tool = "minisign", key = "..." }`。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignConfig {
+    /// 签名工具: "minisign" 或 "gpg"
+    pub tool: SignTool,
+    /// 签名所用的密钥 (可选)：minisign 为私钥文件路径，gpg 为密钥 ID 或邮箱；
+    /// 默认使用工具自身的默认密钥
+    pub key: Option<String>,
+}
+
+/// 返回压缩包签名文件的路径 (`<压缩包>.minisig`、`<压缩包>.sig`)。
+///
+/// 分卷压缩包的第一个分卷 (`.001`) 对应合并后压缩包的签名文件。
+pub fn signature_path(archive_path: &Path, tool: SignTool) -> PathBuf {
+    let archive_path =
+        split::archive_of_first_volume(archive_path).unwrap_or_else(|| archive_path.to_path_buf());
+    let mut name = archive_path.into_os_string();
+    name.push(format!(".{}", tool.extension()));
+    PathBuf::from(name)
+}
+
+/// 构造为压缩包生成分离签名的命令。
+pub fn sign_command(config: &SignConfig, archive_path: &Path) -> Command {
+    let signature = signature_path(archive_path, config.tool);
+    let mut cmd = Command::new(config.tool.program());
+    match config.tool {
+        SignTool::Minisign => {
+            cmd.arg("-S");
+            if let Some(key) = &config.key {
+                cmd.arg("-s").arg(key);
+            }
+            cmd.arg("-m").arg(archive_path).arg("-x").arg(signature);
+        }
+        SignTool::Gpg => {
+            gpg_sign_args(&mut cmd, config, &signature);
+            cmd.arg(archive_path);
+        }
+    }
+    cmd
+}
+
+/// 构造从标准输入读取数据并生成分离签名的命令，签名文件与 `archive_path` 对应。
+///
+/// 用于分卷压缩包：拼接后的分卷通过标准输入传入。只有 gpg 支持，minisign 只能为文件签名。
+pub fn sign_stdin_command(config: &SignConfig, archive_path: &Path) -> Result<Command> {
+    if config.tool != SignTool::Gpg {
+        anyhow::bail!(
+            "{} 只能为完整的文件签名，分卷压缩包请使用 gpg 签名",
+            config.tool
+        );
+    }
+    let mut cmd = Command::new(config.tool.program());
+    gpg_sign_args(&mut cmd, config, &signature_path(archive_path, config.tool));
+    Ok(cmd)
+}
+
+fn gpg_sign_args(cmd: &mut Command, config: &SignConfig, signature: &Path) {
+    cmd.args(["--yes", "--detach-sign"]);
+    if let Some(key) = &config.key {
+        cmd.arg("--local-user").arg(key);
+    }
+    cmd.arg("--output").arg(signature);
+}
+
+/// 构造校验压缩包分离签名的命令。
+///
+/// # 参数
+/// - `tool`: 签名工具。
+/// - `archive_path`: 压缩包路径。
+/// - `public_key`: minisign 的公钥文件路径，默认为当前目录下的 `minisign.pub`；gpg 使用密钥环，忽略此参数。
+pub fn verify_command(tool: SignTool, archive_path: &Path, public_key: Option<&Path>) -> Command {
+    let signature = signature_path(archive_path, tool);
+    let mut cmd = Command::new(tool.program());
+    match tool {
+        SignTool::Minisign => {
+            cmd.arg("-V");
+            if let Some(public_key) = public_key {
+                cmd.arg("-p").arg(public_key);
+            }
+            cmd.arg("-m").arg(archive_path).arg("-x").arg(signature);
+        }
+        SignTool::Gpg => {
+            cmd.arg("--verify").arg(signature).arg(archive_path);
+        }
+    }
+    cmd
+}
+
+fn run(mut cmd: Command, action: &str, tool: SignTool) -> Result<()> {
+    let status = cmd
+        .status()
+        .with_context(|| format!("{}失败: 无法执行 {}，请确认已安装", action, tool))?;
+    if !status.success() {
+        anyhow::bail!("{}失败: {} 以非零状态退出 ({})", action, tool, status);
+    }
+    Ok(())
+}
+
+/// 使用配置的工具为压缩包生成分离签名。
+///
+/// # 参数
+/// - `config`: 签名配置。
+/// - `archive_path`: 要签名的压缩包；分卷压缩包按拼接后的数据签名 (只支持 gpg)。
+///
+/// # 返回
+/// `Result<PathBuf>`: 签名文件的路径；工具无法执行或签名失败时返回错误。
+pub fn sign_archive(config: &SignConfig, archive_path: &Path) -> Result<PathBuf> {
+    match split::find_volumes(archive_path) {
+        Some(volumes) => sign_volumes(config, archive_path, &volumes)?,
+        None => run(sign_command(config, archive_path), "签名", config.tool)?,
+    }
+    Ok(signature_path(archive_path, config.tool))
+}
+
+/// 将拼接后的分卷通过标准输入交给签名工具，不需要先合并出完整的压缩包。
+fn sign_volumes(config: &SignConfig, archive_path: &Path, volumes: &[PathBuf]) -> Result<()> {
+    let mut reader = split::VolumeReader::open(volumes)?;
+    let mut child = sign_stdin_command(config, archive_path)?
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("签名失败: 无法执行 {}，请确认已安装", config.tool))?;
+    // 写完后关闭标准输入，签名工具才会结束
+    let copied = match child.stdin.take() {
+        Some(mut stdin) => io::copy(&mut reader, &mut stdin).map(|_| ()),
+        None => Ok(()),
+    };
+    let status = child
+        .wait()
+        .with_context(|| format!("签名失败: 等待 {} 结束失败", config.tool))?;
+    if !status.success() {
+        anyhow::bail!("签名失败: {} 以非零状态退出 ({})", config.tool, status);
+    }
+    copied.with_context(|| format!("签名失败: 无法将分卷写入 {}", config.tool))
+}
+
+/// 根据压缩包旁的签名文件 (`.minisig` 或 `.sig`) 校验压缩包的签名。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径。
+/// - `public_key`: minisign 的公钥文件路径 (可选)。
+///
+/// # 返回
+/// `Result<SignTool>`: 校验通过时返回所用的工具；找不到签名文件、压缩包已被切分或签名无效时返回错误。
+pub fn verify_signature(archive_path: &Path, public_key: Option<&Path>) -> Result<SignTool> {
+    let tool = SignTool::ALL
+        .into_iter()
+        .find(|&tool| signature_path(archive_path, tool).is_file())
+        .with_context(|| {
+            format!(
+                "找不到签名文件: {}",
+                signature_path(archive_path, SignTool::Minisign).display()
+            )
+        })?;
+    if split::find_volumes(archive_path).is_some() {
+        let archive = split::archive_of_first_volume(archive_path)
+            .unwrap_or_else(|| archive_path.to_path_buf());
+        anyhow::bail!(
+            "分卷压缩包需要先合并再校验签名，如 `cat {0}.* > {0}`",
+            archive.display()
+        );
+    }
+    run(
+        verify_command(tool, archive_path, public_key),
+        "校验签名",
+        tool,
+    )?;
+    Ok(tool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn args(cmd: &Command) -> Vec<&OsStr> {
+        cmd.get_args().collect()
+    }
+
+    #[test]
+    fn test_signature_path() {
+        assert_eq!(
+            signature_path(Path::new("out/app.zip"), SignTool::Minisign),
+            Path::new("out/app.zip.minisig")
+        );
+        assert_eq!(
+            signature_path(Path::new("out/app.zip.001"), SignTool::Gpg),
+            Path::new("out/app.zip.sig")
+        );
+    }
+
+    #[test]
+    fn test_commands() -> Result<()> {
+        let archive = Path::new("app.tar.gz");
+        let minisign = SignConfig {
+            tool: SignTool::Minisign,
+            key: Some("release.key".to_string()),
+        };
+        let cmd = sign_command(&minisign, archive);
+        assert_eq!(cmd.get_program(), "minisign");
+        assert_eq!(
+            args(&cmd),
+            [
+                "-S",
+                "-s",
+                "release.key",
+                "-m",
+                "app.tar.gz",
+                "-x",
+                "app.tar.gz.minisig"
+            ]
+        );
+
+        let gpg = SignConfig {
+            tool: SignTool::Gpg,
+            key: None,
+        };
+        assert_eq!(
+            args(&sign_command(&gpg, archive)),
+            [
+                "--yes",
+                "--detach-sign",
+                "--output",
+                "app.tar.gz.sig",
+                "app.tar.gz"
+            ]
+        );
+
+        let cmd = verify_command(SignTool::Minisign, archive, Some(Path::new("release.pub")));
+        assert_eq!(
+            args(&cmd),
+            [
+                "-V",
+                "-p",
+                "release.pub",
+                "-m",
+                "app.tar.gz",
+                "-x",
+                "app.tar.gz.minisig"
+            ]
+        );
+        let cmd = sign_stdin_command(
+            &SignConfig {
+                tool: SignTool::Gpg,
+                key: Some("ops@example.com".to_string()),
+            },
+            Path::new("app.tar.gz.001"),
+        )?;
+        assert_eq!(
+            args(&cmd),
+            [
+                "--yes",
+                "--detach-sign",
+                "--local-user",
+                "ops@example.com",
+                "--output",
+                "app.tar.gz.sig"
+            ]
+        );
+        assert!(sign_stdin_command(&minisign, archive).is_err());
+
+        assert_eq!(
+            args(&verify_command(SignTool::Gpg, archive, None)),
+            ["--verify", "app.tar.gz.sig", "app.tar.gz"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_without_signature() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join("app.zip");
+        std::fs::write(&archive, "data")?;
+        let err = verify_signature(&archive, None).unwrap_err();
+        assert!(err.to_string().contains("找不到签名文件"));
+        Ok(())
+    }
+}
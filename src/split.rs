@@ -0,0 +1,431 @@
+use crate::format::Format;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// 分卷序号的位数，如 `.001`。
+const VOLUME_DIGITS: usize = 3;
+
+/// 返回压缩包的第 `n` 个分卷 (从 1 开始) 的路径，如 `name.tar.gz.001`。
+pub fn volume_path(archive_path: &Path, n: usize) -> PathBuf {
+    let mut name = archive_path.as_os_str().to_os_string();
+    name.push(format!(".{:0width$}", n, width = VOLUME_DIGITS));
+    PathBuf::from(name)
+}
+
+/// 判断文件名是否为压缩包 `archive_name` 的分卷，即 `<archive_name>.<序号>`。
+pub fn is_volume_of(file_name: &str, archive_name: &str) -> bool {
+    file_name
+        .strip_prefix(archive_name)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .is_some_and(|n| n.len() >= VOLUME_DIGITS && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// 如果路径是第一个分卷 (`.001`)，返回去掉序号后的压缩包路径。
+pub fn archive_of_first_volume(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let archive_name = name.strip_suffix(&format!(".{:0width$}", 1, width = VOLUME_DIGITS))?;
+    (!archive_name.is_empty()).then(|| path.with_file_name(archive_name))
+}
+
+/// 列出压缩包的所有分卷。
+///
+/// `path` 可以是第一个分卷 (`name.zip.001`)，也可以是不存在、但存在分卷的压缩包路径 (`name.zip`)。
+///
+/// # 返回
+/// `Option<Vec<PathBuf>>`: 按序号排列的连续分卷；`path` 不是分卷压缩包时返回 `None`。
+pub fn find_volumes(path: &Path) -> Option<Vec<PathBuf>> {
+    let archive = match archive_of_first_volume(path) {
+        Some(archive) => archive,
+        None if !path.exists() => path.to_path_buf(),
+        None => return None,
+    };
+    let volumes: Vec<PathBuf> = (1..)
+        .map(|n| volume_path(&archive, n))
+        .take_while(|volume| volume.is_file())
+        .collect();
+    (!volumes.is_empty()).then_some(volumes)
+}
+
+/// 将已有的压缩包按 `volume_size` 字节切分为分卷 `.001`、`.002`……，并删除原文件。
+///
+/// 分卷只是按字节切分，依次拼接即可得到原压缩包（与 7-Zip 的分卷相同，也可以用 `cat` 合并）。
+/// 同名的旧分卷中多余的部分会被删除。切分过程中原压缩包与分卷同时存在；压缩时请使用
+/// [`VolumeWriter`] 直接写入分卷。
+///
+/// # 参数
+/// - `archive_path`: 要切分的压缩包。
+/// - `volume_size`: 每个分卷的最大字节数，必须大于 0。
+///
+/// # 返回
+/// `Result<Vec<PathBuf>>`: 生成的分卷路径。
+pub fn split_archive(archive_path: &Path, volume_size: u64) -> Result<Vec<PathBuf>> {
+    if volume_size == 0 {
+        anyhow::bail!("分卷大小必须大于 0");
+    }
+    let file = File::open(archive_path)
+        .with_context(|| format!("打开压缩包失败: {}", archive_path.display()))?;
+    let total = file
+        .metadata()
+        .with_context(|| format!("读取压缩包信息失败: {}", archive_path.display()))?
+        .len();
+    let mut reader = BufReader::new(file);
+    let count = total.div_ceil(volume_size).max(1);
+    let mut volumes = Vec::new();
+    for n in 1..=count as usize {
+        let volume = volume_path(archive_path, n);
+        let mut writer = BufWriter::new(
+            File::create(&volume).with_context(|| format!("创建分卷失败: {}", volume.display()))?,
+        );
+        io::copy(&mut (&mut reader).take(volume_size), &mut writer)
+            .and_then(|_| writer.flush())
+            .with_context(|| format!("写入分卷失败: {}", volume.display()))?;
+        volumes.push(volume);
+    }
+    drop(reader);
+
+    // 删除上一次运行留下的、序号更大的分卷
+    for stale in (count as usize + 1..)
+        .map(|n| volume_path(archive_path, n))
+        .take_while(|volume| volume.is_file())
+    {
+        std::fs::remove_file(&stale)
+            .with_context(|| format!("删除旧分卷失败: {}", stale.display()))?;
+    }
+    std::fs::remove_file(archive_path)
+        .with_context(|| format!("删除原压缩包失败: {}", archive_path.display()))?;
+    Ok(volumes)
+}
+
+/// 删除压缩包的所有分卷 (从 `.001` 起连续的分卷)，用于清理写了一半的分卷压缩包。
+pub fn remove_volumes(archive_path: &Path) -> Result<()> {
+    for volume in (1..)
+        .map(|n| volume_path(archive_path, n))
+        .take_while(|volume| volume.is_file())
+    {
+        std::fs::remove_file(&volume)
+            .with_context(|| format!("删除分卷失败: {}", volume.display()))?;
+    }
+    Ok(())
+}
+
+/// 将数据直接写入分卷 `.001`、`.002`……的写入器，每个分卷最多 `volume_size` 字节，
+/// 写满后切换到下一个分卷，不会生成完整的压缩包。
+///
+/// 支持定位到已写入的位置后覆盖，ZIP 与 7z 写入完成后回写头部时需要。
+/// 每次写入都会定位到对应分卷中的位置，请在外层使用 [`BufWriter`] 合并小的写入。
+pub struct VolumeWriter {
+    archive_path: PathBuf,
+    volume_size: u64,
+    /// 已创建的分卷
+    volumes: Vec<File>,
+    /// 当前位置
+    pos: u64,
+    /// 已写入的总长度
+    len: u64,
+    /// 各分卷文件自身的读写位置，等于 `pos` 时不需要再定位
+    file_pos: Option<u64>,
+}
+
+impl VolumeWriter {
+    /// 创建第一个分卷。已存在的同名分卷会被覆盖。
+    ///
+    /// # 参数
+    /// - `archive_path`: 压缩包路径，分卷为 `<压缩包>.001`、`.002`……，压缩包本身不会被创建。
+    /// - `volume_size`: 每个分卷的最大字节数，必须大于 0。
+    pub fn create(archive_path: &Path, volume_size: u64) -> Result<Self> {
+        if volume_size == 0 {
+            anyhow::bail!("分卷大小必须大于 0");
+        }
+        let mut writer = Self {
+            archive_path: archive_path.to_path_buf(),
+            volume_size,
+            volumes: Vec::new(),
+            pos: 0,
+            len: 0,
+            file_pos: None,
+        };
+        writer
+            .open_volume()
+            .with_context(|| format!("创建分卷失败: {}", volume_path(archive_path, 1).display()))?;
+        Ok(writer)
+    }
+
+    fn open_volume(&mut self) -> io::Result<()> {
+        let path = volume_path(&self.archive_path, self.volumes.len() + 1);
+        self.volumes.push(File::create(path)?);
+        Ok(())
+    }
+
+    /// 完成写入：删除上一次运行留下的、序号更大的分卷，返回所有分卷的路径。
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        for file in &mut self.volumes {
+            file.flush()?;
+        }
+        let count = self.volumes.len();
+        drop(self.volumes);
+        for stale in (count + 1..)
+            .map(|n| volume_path(&self.archive_path, n))
+            .take_while(|volume| volume.is_file())
+        {
+            std::fs::remove_file(&stale)
+                .with_context(|| format!("删除旧分卷失败: {}", stale.display()))?;
+        }
+        Ok((1..=count)
+            .map(|n| volume_path(&self.archive_path, n))
+            .collect())
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let index = (self.pos / self.volume_size) as usize;
+        let offset = self.pos % self.volume_size;
+        while self.volumes.len() <= index {
+            self.open_volume()?;
+        }
+        let file = &mut self.volumes[index];
+        if self.file_pos != Some(self.pos) {
+            file.seek(SeekFrom::Start(offset))?;
+        }
+        let max = ((self.volume_size - offset) as usize).min(buf.len());
+        let n = file.write(&buf[..max])?;
+        self.pos += n as u64;
+        self.len = self.len.max(self.pos);
+        // 写满一个分卷后下一次写入位于另一个文件中
+        self.file_pos = (offset + (n as u64) < self.volume_size).then_some(self.pos);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for VolumeWriter {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let target = match from {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无效的定位位置"))?;
+        if target != self.pos {
+            self.pos = target;
+            self.file_pos = None;
+        }
+        Ok(target)
+    }
+}
+
+/// 将多个分卷按顺序拼接成一个可读、可定位的数据流。单个文件同样适用。
+pub struct VolumeReader {
+    /// 各分卷及其起始偏移
+    volumes: Vec<(File, u64)>,
+    /// 总长度
+    len: u64,
+    /// 当前位置
+    pos: u64,
+    /// 当前分卷的下标
+    current: usize,
+}
+
+impl VolumeReader {
+    /// 按顺序打开所有分卷。
+    pub fn open(paths: &[PathBuf]) -> Result<Self> {
+        let mut volumes = Vec::with_capacity(paths.len());
+        let mut offset = 0;
+        for path in paths {
+            let file =
+                File::open(path).with_context(|| format!("打开分卷失败: {}", path.display()))?;
+            let len = file
+                .metadata()
+                .with_context(|| format!("读取分卷信息失败: {}", path.display()))?
+                .len();
+            volumes.push((file, offset));
+            offset += len;
+        }
+        Ok(Self {
+            volumes,
+            len: offset,
+            pos: 0,
+            current: 0,
+        })
+    }
+
+    /// 总长度 (字节)。
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// 是否没有任何数据。
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn volume_end(&self, index: usize) -> u64 {
+        self.volumes
+            .get(index + 1)
+            .map_or(self.len, |(_, offset)| *offset)
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current < self.volumes.len() {
+            let end = self.volume_end(self.current);
+            if self.pos < end {
+                let (file, offset) = &mut self.volumes[self.current];
+                file.seek(SeekFrom::Start(self.pos - *offset))?;
+                let max = ((end - self.pos) as usize).min(buf.len());
+                let n = file.read(&mut buf[..max])?;
+                if n == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "分卷在读取过程中被截断",
+                    ));
+                }
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            self.current += 1;
+        }
+        Ok(0)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, from: SeekFrom) -> io::Result<u64> {
+        let target = match from {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        }
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "无效的定位位置"))?;
+        self.pos = target;
+        // 定位到包含该位置的分卷 (超出末尾时为最后一个分卷之后)
+        self.current = self
+            .volumes
+            .iter()
+            .rposition(|(_, offset)| *offset <= target)
+            .unwrap_or(0);
+        Ok(target)
+    }
+}
+
+/// 打开压缩包用于读取，并识别其格式。分卷压缩包会被透明地拼接。
+///
+/// # 参数
+/// - `path`: 压缩包路径，也可以是第一个分卷 (`.001`) 或已被切分的压缩包路径。
+///
+/// # 返回
+/// `Result<(VolumeReader, Format)>`: 压缩包数据与格式。
+pub fn open_archive(path: &Path) -> Result<(VolumeReader, Format)> {
+    match find_volumes(path) {
+        Some(volumes) => {
+            let format = Format::detect(&volumes[0]).or_else(|e| {
+                archive_of_first_volume(&volumes[0])
+                    .and_then(|archive| Format::from_path(&archive))
+                    .ok_or(e)
+            })?;
+            Ok((VolumeReader::open(&volumes)?, format))
+        }
+        None => {
+            let format = Format::detect(path)?;
+            Ok((VolumeReader::open(&[path.to_path_buf()])?, format))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_volume_names() {
+        let archive = Path::new("out/app.tar.gz");
+        assert_eq!(volume_path(archive, 2), Path::new("out/app.tar.gz.002"));
+        assert_eq!(
+            archive_of_first_volume(Path::new("out/app.tar.gz.001")),
+            Some(PathBuf::from("out/app.tar.gz"))
+        );
+        assert_eq!(archive_of_first_volume(Path::new("app.tar.gz.002")), None);
+        assert!(is_volume_of("app.zip.001", "app.zip"));
+        assert!(is_volume_of("app.zip.1234", "app.zip"));
+        assert!(!is_volume_of("app.zip.list", "app.zip"));
+        assert!(!is_volume_of("app.zip.01", "app.zip"));
+    }
+
+    #[test]
+    fn test_split_and_read_volumes() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("data.bin");
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        std::fs::write(&archive, &data)?;
+        // 上一次运行留下的多余分卷
+        std::fs::write(volume_path(&archive, 5), "stale")?;
+
+        let volumes = split_archive(&archive, 300)?;
+        assert_eq!(volumes.len(), 4);
+        assert!(!archive.exists());
+        assert!(!volume_path(&archive, 5).exists());
+        assert_eq!(std::fs::metadata(&volumes[3])?.len(), 100);
+        assert_eq!(find_volumes(&archive), Some(volumes.clone()));
+        assert_eq!(find_volumes(&volumes[0]), Some(volumes.clone()));
+
+        let mut reader = VolumeReader::open(&volumes)?;
+        let mut joined = Vec::new();
+        reader.read_to_end(&mut joined)?;
+        assert_eq!(joined, data);
+
+        // 跨分卷定位
+        let mut buf = [0u8; 10];
+        reader.seek(SeekFrom::Start(295))?;
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf, data[295..305]);
+        reader.seek(SeekFrom::End(-10))?;
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf, data[990..]);
+        assert_eq!(reader.read(&mut buf)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_volume_writer() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = dir.path().join("data.bin");
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        std::fs::write(volume_path(&archive, 5), "stale")?;
+
+        let mut writer = VolumeWriter::create(&archive, 300)?;
+        for chunk in data.chunks(70) {
+            writer.write_all(chunk)?;
+        }
+        // 回写跨越分卷边界的头部
+        writer.seek(SeekFrom::Start(295))?;
+        writer.write_all(b"HEADER0123")?;
+        assert_eq!(writer.seek(SeekFrom::End(0))?, 1000);
+        let volumes = writer.finish()?;
+
+        assert!(!archive.exists());
+        assert!(!volume_path(&archive, 5).exists());
+        assert_eq!(volumes.len(), 4);
+        assert_eq!(std::fs::metadata(&volumes[0])?.len(), 300);
+        assert_eq!(std::fs::metadata(&volumes[3])?.len(), 100);
+        let mut expected = data.clone();
+        expected[295..305].copy_from_slice(b"HEADER0123");
+        let mut joined = Vec::new();
+        VolumeReader::open(&volumes)?.read_to_end(&mut joined)?;
+        assert_eq!(joined, expected);
+
+        remove_volumes(&archive)?;
+        assert!(find_volumes(&archive).is_none());
+        assert!(VolumeWriter::create(&archive, 0).is_err());
+        Ok(())
+    }
+}
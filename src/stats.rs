@@ -0,0 +1,294 @@
+//! 压缩包的统计报告：按扩展名汇总的大小与压缩比，以及最大的若干个条目。
+//!
+//! 每个条目的压缩后大小按读取该条目时解码器消耗的压缩数据计算：zip 中每个条目单独压缩，tar.gz 的解码器只消耗解码所需的数据，
+//! 结果接近精确；tar.zst 按数据块 (最大 128 KB) 解码，7z 的解码器会预先读取数据，单个条目的值只是近似，
+//! 条目越大、数量越多，按扩展名的合计越可靠。
+
+use crate::format::Format;
+use crate::reader::{self, ArchiveReader, EntryInfo, TarArchiveReader};
+use crate::report::human_size;
+use crate::split;
+use anyhow::{Context, Result};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::rc::Rc;
+
+/// 默认列出的最大条目数。
+pub const DEFAULT_TOP: usize = 10;
+
+/// 没有扩展名的条目在报告中的名称。
+const NO_EXTENSION: &str = "(无扩展名)";
+
+/// 同一扩展名的条目统计。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionStats {
+    /// 扩展名 (小写，不含 `.`)，没有扩展名时为空字符串
+    pub extension: String,
+    /// 条目数
+    pub files: usize,
+    /// 原始大小 (字节)
+    pub bytes: u64,
+    /// 压缩后的大小 (字节，估算值)
+    pub compressed_bytes: u64,
+}
+
+impl ExtensionStats {
+    /// 压缩比 (压缩后大小 / 原始大小)。原始大小为 0 时返回 0。
+    pub fn ratio(&self) -> f64 {
+        if self.bytes == 0 {
+            return 0.0;
+        }
+        self.compressed_bytes as f64 / self.bytes as f64
+    }
+}
+
+/// 一个压缩包的统计报告。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveStats {
+    /// 压缩包格式
+    pub format: Format,
+    /// 压缩包大小 (字节，分卷压缩包为所有分卷之和)
+    pub archive_bytes: u64,
+    /// 文件条目数
+    pub files: usize,
+    /// 所有文件的原始大小 (字节)
+    pub bytes: u64,
+    /// 按原始大小从大到小排列的扩展名统计
+    pub extensions: Vec<ExtensionStats>,
+    /// 原始大小最大的条目，从大到小排列
+    pub largest: Vec<EntryInfo>,
+}
+
+impl ArchiveStats {
+    /// 整个压缩包的压缩比 (压缩包大小 / 原始大小)。原始大小为 0 时返回 0。
+    pub fn ratio(&self) -> f64 {
+        if self.bytes == 0 {
+            return 0.0;
+        }
+        self.archive_bytes as f64 / self.bytes as f64
+    }
+}
+
+/// 多行的统计表格，供命令行展示。
+impl fmt::Display for ArchiveStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "共 {} 个文件, {} -> {} (压缩比 {:.1}%, {})",
+            self.files,
+            human_size(self.bytes),
+            human_size(self.archive_bytes),
+            self.ratio() * 100.0,
+            self.format
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<14} {:>8} {:>12} {:>12} {:>8}",
+            "扩展名", "文件数", "原始大小", "压缩后", "压缩比"
+        )?;
+        for ext in &self.extensions {
+            let name = if ext.extension.is_empty() {
+                NO_EXTENSION
+            } else {
+                &ext.extension
+            };
+            writeln!(
+                f,
+                "{:<14} {:>8} {:>12} {:>12} {:>7.1}%",
+                name,
+                ext.files,
+                human_size(ext.bytes),
+                human_size(ext.compressed_bytes),
+                ext.ratio() * 100.0
+            )?;
+        }
+        if !self.largest.is_empty() {
+            writeln!(f)?;
+            write!(f, "最大的 {} 个文件:", self.largest.len())?;
+            for entry in &self.largest {
+                write!(f, "\n{:>12}  {}", human_size(entry.size), entry.name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 读取压缩包并生成统计报告。
+///
+/// 需要解压所有条目的内容（不写入磁盘）以估算每个条目的压缩后大小。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，格式按文件内容判断，支持分卷。
+/// - `top`: 列出的最大条目数。
+///
+/// # 返回
+/// `Result<ArchiveStats>`: 压缩包无法读取时返回错误。
+pub fn archive_stats(archive_path: &Path, top: usize) -> Result<ArchiveStats> {
+    let (mut volumes, format) = split::open_archive(archive_path)?;
+    let archive_bytes = volumes.len();
+    volumes.rewind()?;
+    let consumed = Rc::new(Cell::new(0));
+    let counting = CountingReader {
+        inner: BufReader::new(volumes),
+        count: consumed.clone(),
+    };
+    // gzip 解码器直接从带缓冲的读取器中消耗数据，不会额外预读
+    let reader: Box<dyn ArchiveReader> = match format {
+        Format::TarGz => Box::new(TarArchiveReader::new(flate2::bufread::GzDecoder::new(
+            counting,
+        ))),
+        _ => reader::open_reader(counting, format)?,
+    };
+
+    // 目录与头部在打开时已读取，不计入任何条目
+    let mut last = consumed.get();
+    let mut extensions: HashMap<String, ExtensionStats> = HashMap::new();
+    let mut entries = Vec::new();
+    reader.read_entries(&mut |info, entry| {
+        io::copy(entry, &mut io::sink()).with_context(|| format!("读取条目失败: {}", info.name))?;
+        let compressed = consumed.get() - last;
+        last = consumed.get();
+        let extension = extension_of(&info.name);
+        let stats = extensions
+            .entry(extension.clone())
+            .or_insert_with(|| ExtensionStats {
+                extension,
+                files: 0,
+                bytes: 0,
+                compressed_bytes: 0,
+            });
+        stats.files += 1;
+        stats.bytes += info.size;
+        stats.compressed_bytes += compressed;
+        entries.push(info.clone());
+        Ok(())
+    })?;
+
+    let mut extensions: Vec<ExtensionStats> = extensions.into_values().collect();
+    extensions.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    let files = entries.len();
+    let bytes = entries.iter().map(|entry| entry.size).sum();
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    entries.truncate(top);
+    Ok(ArchiveStats {
+        format,
+        archive_bytes,
+        files,
+        bytes,
+        extensions,
+        largest: entries,
+    })
+}
+
+/// 条目名称的扩展名 (小写)，没有扩展名时为空字符串。
+fn extension_of(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 记录已读取（或通过 [`BufRead::consume`] 消耗）字节数的读取器。
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.count.set(self.count.get() + amount as u64);
+        self.inner.consume(amount);
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor;
+    use crate::config::Config;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_archive_stats() -> Result<()> {
+        let dir = tempdir()?;
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("assets"))?;
+        std::fs::write(src.join("assets/big.LOG"), "log line\n".repeat(2000))?;
+        std::fs::write(src.join("a.log"), "x".repeat(100))?;
+        std::fs::write(src.join("main.rs"), "fn main() {}\n")?;
+        std::fs::write(src.join("Makefile"), "all:\n")?;
+        let files = vec![
+            src.join("a.log"),
+            src.join("assets/big.LOG"),
+            src.join("main.rs"),
+            src.join("Makefile"),
+        ];
+        for format in Format::ALL {
+            let output = dir.path().join(format!("out.{}", format.extension()));
+            let config = Config {
+                format,
+                ..Default::default()
+            };
+            compressor::compress_files(&config, &src, files.clone(), &output, &NoopReporter)?;
+
+            let stats = archive_stats(&output, 2)?;
+            assert_eq!(stats.format, format);
+            assert_eq!(stats.files, 4);
+            assert_eq!(stats.bytes, 18000 + 100 + 13 + 5);
+            assert_eq!(stats.archive_bytes, std::fs::metadata(&output)?.len());
+            let names: Vec<_> = stats.largest.iter().map(|e| e.name.as_str()).collect();
+            assert_eq!(names, ["assets/big.LOG", "a.log"], "{}", format);
+
+            let exts: Vec<_> = stats
+                .extensions
+                .iter()
+                .map(|e| (e.extension.as_str(), e.files))
+                .collect();
+            assert_eq!(exts, [("log", 2), ("rs", 1), ("", 1)], "{}", format);
+            let log = &stats.extensions[0];
+            assert!(log.compressed_bytes < log.bytes / 10, "{}", format);
+            let compressed: u64 = stats.extensions.iter().map(|e| e.compressed_bytes).sum();
+            assert!(compressed <= stats.archive_bytes, "{}", format);
+
+            let text = stats.to_string();
+            assert!(text.contains(NO_EXTENSION));
+            assert!(text.contains("最大的 2 个文件:"));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extension_of() {
+        assert_eq!(extension_of("a/b.TXT"), "txt");
+        assert_eq!(extension_of("archive.tar.gz"), "gz");
+        assert_eq!(extension_of(".bashrc"), "");
+        assert_eq!(extension_of("Makefile"), "");
+    }
+}
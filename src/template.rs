@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 展开模板中的 `{name}` 占位符。
+///
+/// `lookup` 返回 `None` 的占位符（未知的变量）以及未闭合的 `{` 按原样保留。
+///
+/// # 参数
+/// - `template`: 模板字符串，如 `backup-{date}-{git_hash}`。
+/// - `lookup`: 根据变量名返回其值。
+///
+/// # 返回
+/// `String`: 展开后的字符串。
+pub fn expand<F>(template: &str, mut lookup: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let key = &after[..end];
+        match lookup(key) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 根据基础目录和 `output_name` 模板确定压缩包的输出名称。
+///
+/// 未设置模板时使用基础目录的名称；设置了模板时展开其中的占位符（见 [`OutputNaming::var`]）。
+/// 时间在构造时取一次，同一个模板中的 `{date}`、`{time}`、`{timestamp}` 总是一致；
+/// `{git_hash}` 和 `{hostname}` 只在模板用到时才会查询。
+#[derive(Debug, Clone)]
+pub struct OutputNaming<'a> {
+    /// `output_name` 模板
+    template: Option<&'a str>,
+    /// 基础目录，`{dirname}` 为其名称，`{git_hash}` 在其中查询
+    dir: PathBuf,
+    /// Unix 时间戳 (秒)
+    timestamp: u64,
+    /// 本地时间相对 UTC 的偏移 (秒)
+    utc_offset: i64,
+}
+
+impl<'a> OutputNaming<'a> {
+    /// 以当前时间创建。
+    ///
+    /// # 参数
+    /// - `template`: `output_name` 模板，`None` 表示使用基础目录的名称。
+    /// - `base_dir`: 要压缩的基础目录，相对路径（如 `.`、`../other`）会先解析为实际路径。
+    pub fn new(template: Option<&'a str>, base_dir: &Path) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            template,
+            dir: base_dir
+                .canonicalize()
+                .unwrap_or_else(|_| base_dir.to_path_buf()),
+            timestamp,
+            utc_offset: local_utc_offset(timestamp),
+        }
+    }
+
+    /// 输出名称（不含扩展名）。
+    pub fn name(&self) -> String {
+        match self.template {
+            Some(template) => expand(template, |key| self.var(key)),
+            None => dir_name(&self.dir),
+        }
+    }
+
+    /// 返回变量的值，未知变量返回 `None`。
+    ///
+    /// 支持的变量: `dirname`、`date` (`YYYY-MM-DD`)、`time` (`HHMMSS`)、`timestamp`、
+    /// `git_hash` (不在 git 仓库中时为 `nogit`)、`hostname` (需要 `native` 特性)。日期和时间使用本地时区。
+    pub fn var(&self, key: &str) -> Option<String> {
+        let local = self.timestamp as i64 + self.utc_offset;
+        let value = match key {
+            "dirname" => dir_name(&self.dir),
+            "date" => {
+                let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            }
+            "time" => {
+                let rem = local.rem_euclid(86_400);
+                format!("{:02}{:02}{:02}", rem / 3_600, rem % 3_600 / 60, rem % 60)
+            }
+            "timestamp" => self.timestamp.to_string(),
+            "git_hash" => git_short_hash(&self.dir).unwrap_or_else(|| "nogit".to_string()),
+            #[cfg(feature = "native")]
+            "hostname" => hostname::get()
+                .map(|h| h.to_string_lossy().to_string())
+                .unwrap_or_else(|_| "unknown".to_string()),
+            _ => return None,
+        };
+        // 变量值中的路径分隔符会让输出文件落到别的目录
+        Some(value.replace(['/', '\\'], "_"))
+    }
+}
+
+/// 返回目录名，无法确定时 (如根目录) 返回 `archive`。
+pub fn dir_name(dir: &Path) -> String {
+    dir.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("archive")
+        .to_string()
+}
+
+/// 1970-01-01 起的天数到公历日期 (Howard Hinnant 的 civil_from_days 算法)。
+///
+/// # 返回
+/// `(i64, u32, u32)`: 年、月、日。
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+/// 公历日期到 1970-01-01 起的天数，[`civil_from_days`] 的逆运算 (Howard Hinnant 的 days_from_civil 算法)。
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// 查询 `dir` 所在 git 仓库 HEAD 的短哈希。
+fn git_short_hash(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()?;
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !hash.is_empty()).then_some(hash)
+}
+
+/// 本地时区在给定时刻相对 UTC 的偏移 (秒)。
+#[cfg(unix)]
+pub(crate) fn local_utc_offset(timestamp: u64) -> i64 {
+    let time = timestamp as libc::time_t;
+    // SAFETY: localtime_r 只写入我们提供的 tm 结构体
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            0
+        } else {
+            tm.tm_gmtoff as i64
+        }
+    }
+}
+
+/// 非 unix 平台上使用 UTC。
+#[cfg(not(unix))]
+pub(crate) fn local_utc_offset(_timestamp: u64) -> i64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let lookup = |key: &str| (key == "x").then(|| "1".to_string());
+        assert_eq!(expand("a-{x}-{x}", lookup), "a-1-1");
+        assert_eq!(expand("{unknown}-{x}", lookup), "{unknown}-1");
+        assert_eq!(expand("open-{x", lookup), "open-{x");
+        assert_eq!(expand("plain", lookup), "plain");
+    }
+
+    #[test]
+    fn test_output_name_vars() {
+        let dir = tempfile::tempdir().unwrap();
+        let vars = OutputNaming {
+            template: Some("backup-{date}-{time}"),
+            dir: dir.path().to_path_buf(),
+            timestamp: 1_700_000_000,
+            utc_offset: 0,
+        };
+        assert_eq!(vars.name(), "backup-2023-11-14-221320");
+        assert_eq!(vars.var("date").as_deref(), Some("2023-11-14"));
+        assert_eq!(vars.var("time").as_deref(), Some("221320"));
+        assert_eq!(vars.var("timestamp").as_deref(), Some("1700000000"));
+        assert_eq!(vars.var("git_hash").as_deref(), Some("nogit"));
+        assert_eq!(vars.var("dirname"), Some(dir_name(dir.path())));
+        assert_eq!(
+            vars.var("hostname").is_some_and(|h| !h.is_empty()),
+            cfg!(feature = "native")
+        );
+        assert_eq!(vars.var("nope"), None);
+
+        let east = OutputNaming {
+            utc_offset: 8 * 3_600,
+            ..vars
+        };
+        assert_eq!(east.var("date").as_deref(), Some("2023-11-15"));
+        assert_eq!(east.var("time").as_deref(), Some("061320"));
+    }
+
+    #[test]
+    fn test_default_name_from_base_dir() -> std::io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let project = dir.path().join("other-project");
+        std::fs::create_dir_all(project.join("sub"))?;
+        assert_eq!(OutputNaming::new(None, &project).name(), "other-project");
+        // `..` 等相对路径按实际目录命名
+        assert_eq!(
+            OutputNaming::new(None, &project.join("sub/..")).name(),
+            "other-project"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        for days in [-1, 0, 19_782, 730_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use crate::archive_manifest;
+use crate::archive_writer::{ArchiveOutput, ArchiveWriter, EntryHeader, FormatRegistry};
+use crate::collector::Mount;
+use crate::compressor::{self, EntryOptions};
+use crate::config::Config;
+use crate::format::Format;
+use crate::progress::ProgressReporter;
+use crate::report::CompressionReport;
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Instant, UNIX_EPOCH};
+
+/// 一次更新中各类文件的数量。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateCounts {
+    /// 新增的文件
+    pub added: usize,
+    /// 内容发生变化、重新压缩的文件
+    pub updated: usize,
+    /// 未变化、直接从原压缩包复制的文件
+    pub unchanged: usize,
+    /// 已删除、从压缩包中移除的文件
+    pub removed: usize,
+}
+
+/// 更新已有的压缩包：未变化的条目从原压缩包中直接复制，只压缩新增或修改的文件。
+///
+/// 结果与完整压缩相同的文件集合（已删除的文件会从压缩包中移除），新增与修改的文件排在最后。
+/// ZIP 条目按大小与 CRC-32 判断是否变化，并且不解压、不重新压缩地复制；
+/// TAR.GZ 与 TAR.ZST 条目按大小与修改时间判断，仍需解压并重新压缩整个数据流，
+/// 但未变化的文件不会再从磁盘读取。7Z 压缩包不支持更新。
+/// 压缩包不存在时生成完整压缩包。新的压缩包先写入临时文件，成功后才替换原压缩包。
+///
+/// # 参数
+/// - `config`: 压缩配置，不能同时启用 `incremental`、`split_size` 或 `sample_bytes`。
+/// - `base_dir`: 基础目录。
+/// - `files`: 过滤后的全部文件路径列表。
+/// - `output_path`: 要更新的压缩包路径。
+/// - `reporter`: 进度与日志回调。
+///
+/// # 返回
+/// `Result<CompressionReport>`: 更新后的压缩包的报告。
+pub fn compress_update(
+    config: &Config,
+    base_dir: &Path,
+    files: Vec<PathBuf>,
+    output_path: &Path,
+    reporter: &dyn ProgressReporter,
+) -> Result<CompressionReport> {
+    if config.incremental.unwrap_or(false) {
+        anyhow::bail!("更新模式不能与增量压缩 (incremental) 同时使用");
+    }
+    if config.split_size.is_some() {
+        anyhow::bail!("更新模式不支持分卷压缩包 (split_size)");
+    }
+    if config.sample_bytes.is_some() {
+        anyhow::bail!("更新模式不能与采样模式同时使用");
+    }
+    if !output_path.exists() {
+        reporter.on_message(&format!(
+            "压缩包 {} 不存在，生成完整压缩包",
+            output_path.display()
+        ));
+        return compressor::compress_files(config, base_dir, files, output_path, reporter);
+    }
+    let existing = Format::detect(output_path)?;
+    if existing != config.format {
+        anyhow::bail!(
+            "已有压缩包的格式 {} 与配置的格式 {} 不同: {}",
+            existing,
+            config.format,
+            output_path.display()
+        );
+    }
+    if existing == Format::SevenZ {
+        anyhow::bail!("7Z 压缩包不支持更新，请去掉 --update 重新压缩");
+    }
+
+    let start = Instant::now();
+    reporter.on_message(&format!("正在更新压缩包: {}", output_path.display()));
+    let candidates = files.clone();
+    let mut files = compressor::exclude_archives(config, base_dir, output_path, files);
+    let fixed_mtime = config
+        .reproducible
+        .unwrap_or(false)
+        .then(compressor::source_date_epoch);
+    if fixed_mtime.is_some() {
+        files.sort();
+    }
+    compressor::check_entries(config, base_dir, &files, reporter)?;
+    let options = compressor::entry_options(config, base_dir, &files, None, fixed_mtime)?;
+
+    let kept: HashSet<&PathBuf> = files.iter().collect();
+    let mut report = CompressionReport {
+        output_path: output_path.to_path_buf(),
+        files_count: files.len(),
+        input_bytes: files
+            .iter()
+            .filter_map(|f| f.metadata().ok())
+            .map(|m| m.len())
+            .sum(),
+        output_bytes: 0,
+        duration: Default::default(),
+        skipped: candidates
+            .into_iter()
+            .filter(|f| !kept.contains(f))
+            .collect(),
+        checksums: Vec::new(),
+        signature: None,
+    };
+
+    // 更新完成前原压缩包与临时文件同时存在
+    compressor::check_disk_space(
+        config,
+        output_path,
+        report.files_count,
+        report.input_bytes,
+        reporter,
+    )?;
+
+    let temp_path = temp_path(output_path);
+    reporter.on_start(files.len(), report.input_bytes);
+    let result = match existing {
+        Format::Zip => update_zip(
+            output_path,
+            &temp_path,
+            &files,
+            base_dir,
+            &options,
+            reporter,
+        ),
+        _ => update_tar(
+            existing,
+            output_path,
+            &temp_path,
+            &files,
+            base_dir,
+            &options,
+            reporter,
+        ),
+    };
+    reporter.on_done();
+    let counts = match result.and_then(|counts| {
+        std::fs::rename(&temp_path, output_path)
+            .with_context(|| format!("替换压缩包失败: {}", output_path.display()))?;
+        Ok(counts)
+    }) {
+        Ok(counts) => counts,
+        Err(e) => {
+            // 原压缩包保持不变
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+    reporter.on_message(&format!(
+        "新增 {} 个, 更新 {} 个, 未变 {} 个, 删除 {} 个文件",
+        counts.added, counts.updated, counts.unchanged, counts.removed
+    ));
+
+    compressor::finish_archive(config, base_dir, &files, &mut report, reporter)?;
+    report.duration = start.elapsed();
+    Ok(report)
+}
+
+/// 更新时写入新压缩包的临时文件 `<压缩包>.ztr-update`。
+fn temp_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.as_os_str().to_os_string();
+    name.push(".ztr-update");
+    PathBuf::from(name)
+}
+
+fn open(path: &Path) -> Result<File> {
+    File::open(path).with_context(|| format!("打开压缩包失败: {}", path.display()))
+}
+
+fn create(path: &Path) -> Result<File> {
+    File::create(path).with_context(|| format!("创建压缩包失败: {}", path.display()))
+}
+
+/// 当前文件按条目名称的索引，以及记录已处理条目的状态。
+struct Pending<'a> {
+    files: HashMap<String, &'a Path>,
+    previous: HashSet<String>,
+    counts: UpdateCounts,
+}
+
+impl<'a> Pending<'a> {
+    fn new(files: &'a [PathBuf], base_dir: &Path, mounts: &[Mount]) -> Result<Self> {
+        let files = files
+            .iter()
+            .map(|f| Ok((compressor::entry_name(base_dir, mounts, f)?, f.as_path())))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            files,
+            previous: HashSet::new(),
+            counts: UpdateCounts::default(),
+        })
+    }
+
+    /// 记录原压缩包中的一个条目，返回对应的当前文件；文件已删除或条目已处理过时返回 `None`。
+    fn visit(&mut self, name: &str) -> Option<&'a Path> {
+        if !self.previous.insert(name.to_string()) {
+            return None;
+        }
+        let file = self.files.get(name).copied();
+        if file.is_none() {
+            self.counts.removed += 1;
+        }
+        file
+    }
+
+    /// 条目未变化并已被复制。
+    fn keep(&mut self, name: &str) {
+        self.files.remove(name);
+        self.counts.unchanged += 1;
+    }
+
+    /// 文件是否需要从磁盘压缩 (没有从原压缩包复制)，需要时计入新增或修改的数量。
+    fn take(&mut self, base_dir: &Path, mounts: &[Mount], file: &Path) -> Result<bool> {
+        let name = compressor::entry_name(base_dir, mounts, file)?;
+        if !self.files.contains_key(&name) {
+            return Ok(false);
+        }
+        if self.previous.contains(&name) {
+            self.counts.updated += 1;
+        } else {
+            self.counts.added += 1;
+        }
+        Ok(true)
+    }
+}
+
+/// 新的压缩包与原压缩包格式相同，写入器从格式注册表中查找。
+fn create_writer<'a>(
+    format: Format,
+    path: &Path,
+    options: &EntryOptions,
+) -> Result<Box<dyn ArchiveWriter + 'a>> {
+    FormatRegistry::default().create(
+        format.extension(),
+        ArchiveOutput::seekable(BufWriter::new(create(path)?)),
+        options,
+    )
+}
+
+/// 未从原压缩包复制的文件从磁盘压缩，排在最后，然后写入内嵌清单并完成压缩包。
+fn finish_update(
+    mut writer: Box<dyn ArchiveWriter + '_>,
+    mut pending: Pending,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<UpdateCounts> {
+    for file in files {
+        if pending.take(base_dir, &options.mounts, file)? {
+            compressor::append_file(writer.as_mut(), file, base_dir, options, reporter)?;
+        }
+    }
+    compressor::append_manifest(writer.as_mut(), options)?;
+    writer.finish()?;
+    Ok(pending.counts)
+}
+
+fn update_zip(
+    old_path: &Path,
+    new_path: &Path,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<UpdateCounts> {
+    let mut archive =
+        zip::ZipArchive::new(BufReader::new(open(old_path)?)).context("读取ZIP目录失败")?;
+    let mut writer = create_writer(Format::Zip, new_path, options)?;
+    let mut pending = Pending::new(files, base_dir, &options.mounts)?;
+
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i).context("读取ZIP条目失败")?;
+        let name = entry.name().to_string();
+        if entry.is_dir() || name == archive_manifest::ENTRY_NAME {
+            continue;
+        }
+        let Some(file) = pending.visit(&name) else {
+            continue;
+        };
+        if zip_entry_unchanged(&entry, &name, file, options)? {
+            reporter.on_file_start(&name);
+            // 写入器不支持原样复制时，该文件随后从磁盘重新压缩
+            if writer.copy_zip_entry(entry)? {
+                pending.keep(&name);
+            }
+        }
+    }
+    finish_update(writer, pending, files, base_dir, options, reporter)
+}
+
+/// ZIP 条目与文件的大小、CRC-32 与注释是否都相同。
+fn zip_entry_unchanged<R: Read>(
+    entry: &zip::read::ZipFile<'_, R>,
+    name: &str,
+    file: &Path,
+    options: &EntryOptions,
+) -> Result<bool> {
+    if compressor::is_preserved_link(file, options.symlinks) {
+        return Ok(false);
+    }
+    let metadata =
+        std::fs::metadata(file).with_context(|| format!("读取文件信息失败: {}", file.display()))?;
+    let comment = options
+        .metadata
+        .lookup(options.rule_path(name))
+        .comment
+        .unwrap_or_default();
+    if metadata.len() != entry.size()
+        || entry.comment() != comment
+        || entry.compression() != options.zip_method(name)
+    {
+        return Ok(false);
+    }
+    let mut hasher = crc32fast::Hasher::new();
+    let mut reader = open(file)?;
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let n = reader
+            .read(&mut buffer)
+            .with_context(|| format!("读取文件失败: {}", file.display()))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize() == entry.crc32())
+}
+
+fn update_tar(
+    format: Format,
+    old_path: &Path,
+    new_path: &Path,
+    files: &[PathBuf],
+    base_dir: &Path,
+    options: &EntryOptions,
+    reporter: &dyn ProgressReporter,
+) -> Result<UpdateCounts> {
+    let mut archive = tar::Archive::new(format.tar_decoder(BufReader::new(open(old_path)?))?);
+    let mut writer = create_writer(format, new_path, options)?;
+    let mut pending = Pending::new(files, base_dir, &options.mounts)?;
+
+    for entry in archive.entries().context("读取TAR条目失败")? {
+        let mut entry = entry.context("读取TAR条目失败")?;
+        let entry_type = entry.header().entry_type();
+        if !entry_type.is_file() && !entry_type.is_symlink() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().replace("\\", "/");
+        if name == archive_manifest::ENTRY_NAME {
+            continue;
+        }
+        let Some(file) = pending.visit(&name) else {
+            continue;
+        };
+        if entry_type.is_file() && tar_entry_unchanged(entry.header(), file, options)? {
+            reporter.on_file_start(&name);
+            let old = entry.header();
+            let header = EntryHeader {
+                mtime: old.mtime()?,
+                mode: old.mode()?,
+                uid: old.uid()?,
+                gid: old.gid()?,
+                metadata: options.metadata.lookup(options.rule_path(&name)),
+                ..EntryHeader::new(name.as_str(), old.size()?)
+            };
+            writer
+                .start_entry(&header)
+                .and_then(|_| Ok(io::copy(&mut entry, &mut writer)?))
+                .with_context(|| format!("复制TAR条目失败: {}", name))?;
+            pending.keep(&name);
+        }
+    }
+    finish_update(writer, pending, files, base_dir, options, reporter)
+}
+
+/// TAR 条目与文件的大小与修改时间是否都相同。
+///
+/// 可复现模式下条目的修改时间是固定值，无法判断文件是否变化，所有文件都会被重新压缩。
+fn tar_entry_unchanged(header: &tar::Header, file: &Path, options: &EntryOptions) -> Result<bool> {
+    if options.fixed_mtime.is_some() || compressor::is_preserved_link(file, options.symlinks) {
+        return Ok(false);
+    }
+    let metadata =
+        std::fs::metadata(file).with_context(|| format!("读取文件信息失败: {}", file.display()))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs());
+    Ok(header.size()? == metadata.len() && header.mtime().ok() == mtime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::NoopReporter;
+    use crate::reader;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    /// 记录进度消息的报告器。
+    #[derive(Default)]
+    struct Messages(Mutex<Vec<String>>);
+
+    impl ProgressReporter for Messages {
+        fn on_message(&self, message: &str) {
+            self.0.lock().unwrap().push(message.to_string());
+        }
+    }
+
+    fn contents(archive: &Path) -> Result<Vec<(String, String)>> {
+        let mut entries = Vec::new();
+        reader::for_each_entry(archive, |name, entry| {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            entries.push((name.to_string(), content));
+            Ok(())
+        })?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    fn set_mtime(path: &Path, secs: u64) -> Result<()> {
+        File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(UNIX_EPOCH + std::time::Duration::from_secs(secs))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_archive() -> Result<()> {
+        for format in [Format::Zip, Format::TarGz, Format::TarZst] {
+            let root = tempdir()?;
+            let src = root.path().join("src");
+            std::fs::create_dir_all(src.join("sub"))?;
+            for (name, content) in [
+                ("keep.txt", "same"),
+                ("sub/edit.txt", "old"),
+                ("gone.txt", "bye"),
+            ] {
+                std::fs::write(src.join(name), content)?;
+                set_mtime(&src.join(name), 1_700_000_000)?;
+            }
+            let config = Config {
+                format,
+                output_name: Some("out".to_string()),
+                ..Default::default()
+            };
+            let output = root.path().join(format!("out.{}", format.extension()));
+            let files = |dir: &Path| crate::collector::collect_all_files(dir, Default::default());
+
+            // 压缩包不存在时生成完整压缩包
+            compress_update(&config, &src, files(&src)?, &output, &NoopReporter)?;
+            assert_eq!(contents(&output)?.len(), 3);
+
+            std::fs::write(src.join("sub/edit.txt"), "new")?;
+            std::fs::remove_file(src.join("gone.txt"))?;
+            std::fs::write(src.join("added.txt"), "hi")?;
+            let messages = Messages::default();
+            let report = compress_update(&config, &src, files(&src)?, &output, &messages)?;
+            assert_eq!(report.files_count, 3);
+            assert_eq!(report.output_bytes, std::fs::metadata(&output)?.len());
+            assert!(!temp_path(&output).exists());
+            assert!(
+                messages
+                    .0
+                    .lock()
+                    .unwrap()
+                    .contains(&"新增 1 个, 更新 1 个, 未变 1 个, 删除 1 个文件".to_string()),
+                "{}",
+                format
+            );
+            assert_eq!(
+                contents(&output)?,
+                [
+                    ("added.txt".to_string(), "hi".to_string()),
+                    ("keep.txt".to_string(), "same".to_string()),
+                    ("sub/edit.txt".to_string(), "new".to_string()),
+                ],
+                "{}",
+                format
+            );
+            assert!(crate::verifier::verify_archive(&output)?.is_ok());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_rejects_unsupported() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "a")?;
+        let files = vec![dir.path().join("a.txt")];
+        let output = dir.path().join("out.7z");
+        let config = Config {
+            format: Format::SevenZ,
+            ..Default::default()
+        };
+        compressor::compress_files(&config, dir.path(), files.clone(), &output, &NoopReporter)?;
+        assert!(
+            compress_update(&config, dir.path(), files.clone(), &output, &NoopReporter).is_err()
+        );
+
+        // 格式与已有压缩包不同
+        let zip_config = Config {
+            format: Format::Zip,
+            ..Default::default()
+        };
+        let renamed = dir.path().join("out.zip");
+        std::fs::rename(&output, &renamed)?;
+        assert!(compress_update(&zip_config, dir.path(), files, &renamed, &NoopReporter).is_err());
+        Ok(())
+    }
+}
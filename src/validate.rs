@@ -0,0 +1,491 @@
+use crate::report::human_size;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// ZIP 在不使用 ZIP64 扩展时最多能容纳的条目数。
+pub const ZIP_MAX_ENTRIES_WITHOUT_ZIP64: usize = u16::MAX as usize;
+
+/// 发现条目名称冲突时的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// 终止压缩并报告所有冲突
+    Error,
+    /// 报告冲突后继续压缩 (默认)
+    #[default]
+    Warn,
+    /// 不做检查
+    Ignore,
+}
+
+/// 输出目录所在磁盘的可用空间不足时的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskSpacePolicy {
+    /// 在写入之前终止压缩 (默认)
+    #[default]
+    Error,
+    /// 警告后继续压缩
+    Warn,
+    /// 不做检查
+    Ignore,
+}
+
+/// 估算压缩包大小时每个条目的头部开销上限 (字节)：TAR 的头部、长路径的 PAX 头与填充，ZIP 的本地头与中央目录记录。
+const ENTRY_OVERHEAD: u64 = 2048;
+
+/// 估算压缩包大小时整个压缩包的固定开销 (字节)。
+const ARCHIVE_OVERHEAD: u64 = 64 * 1024;
+
+/// 输出文件已存在时的处理方式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// 覆盖已有文件
+    Overwrite,
+    /// 终止压缩，保留已有文件 (默认)
+    #[default]
+    Error,
+    /// 在文件名后追加 `-1`、`-2`……直到找到未被占用的名称
+    Rename,
+}
+
+/// 根据输出文件冲突策略确定实际的输出路径。
+///
+/// # 参数
+/// - `output_path`: 期望的输出路径。
+/// - `extension`: 压缩格式的扩展名（如 `tar.gz`），重命名时追加的序号位于其之前。
+/// - `policy`: 冲突策略。
+///
+/// # 返回
+/// `Result<PathBuf>`: 实际的输出路径；策略为 `error` 且文件已存在时返回错误。
+pub fn resolve_output_conflict(
+    output_path: &Path,
+    extension: &str,
+    policy: ConflictPolicy,
+) -> Result<PathBuf> {
+    resolve_output_conflict_with(output_path, extension, policy, Path::exists)
+}
+
+/// 与 [`resolve_output_conflict`] 相同，但由 `exists` 判断输出路径是否已被占用，
+/// 例如分卷压缩时检查第一个分卷。
+pub fn resolve_output_conflict_with(
+    output_path: &Path,
+    extension: &str,
+    policy: ConflictPolicy,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<PathBuf> {
+    if !exists(output_path) {
+        return Ok(output_path.to_path_buf());
+    }
+    match policy {
+        ConflictPolicy::Overwrite => Ok(output_path.to_path_buf()),
+        ConflictPolicy::Error => anyhow::bail!(
+            "输出文件已存在: {}（使用 --force 或配置 on_conflict = \"overwrite\" 覆盖，on_conflict = \"rename\" 自动重命名）",
+            output_path.display()
+        ),
+        ConflictPolicy::Rename => {
+            let file_name = output_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let suffix = format!(".{}", extension);
+            let stem = file_name.strip_suffix(&suffix).unwrap_or(&file_name);
+            let suffix = if stem.len() == file_name.len() {
+                ""
+            } else {
+                suffix.as_str()
+            };
+            (1..)
+                .map(|n| output_path.with_file_name(format!("{}-{}{}", stem, n, suffix)))
+                .find(|candidate| !exists(candidate))
+                .ok_or_else(|| anyhow::anyhow!("找不到可用的输出文件名: {}", output_path.display()))
+        }
+    }
+}
+
+/// 两个条目名称之间的冲突。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameCollision {
+    /// 先出现的条目名称
+    pub first: String,
+    /// 与之冲突的条目名称
+    pub second: String,
+}
+
+impl NameCollision {
+    /// 两个名称是否完全相同（而不仅是大小写不同）。
+    pub fn is_duplicate(&self) -> bool {
+        self.first == self.second
+    }
+
+    /// 一个条目是否与另一个条目的上级目录同名（不区分大小写），例如 `p/BIN` 与 `p/bin/app`。
+    pub fn is_file_dir_clash(&self) -> bool {
+        let (first, second) = (self.first.to_lowercase(), self.second.to_lowercase());
+        let is_parent = |dir: &str, path: &str| {
+            path.strip_prefix(dir)
+                .is_some_and(|rest| rest.starts_with('/'))
+        };
+        is_parent(&first, &second) || is_parent(&second, &first)
+    }
+}
+
+impl fmt::Display for NameCollision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_duplicate() {
+            write!(f, "重复的条目名称: {}", self.first)
+        } else if self.is_file_dir_clash() {
+            write!(
+                f,
+                "文件与目录同名: {} 与 {}（解压时无法同时创建）",
+                self.first, self.second
+            )
+        } else {
+            write!(
+                f,
+                "条目名称仅大小写不同: {} 与 {}（在 Windows/macOS 上解压时会互相覆盖）",
+                self.first, self.second
+            )
+        }
+    }
+}
+
+/// 查找重复的条目名称、仅大小写不同的条目名称，以及与其他条目的上级目录同名（不区分大小写）的文件。
+///
+/// 在大小写不敏感的文件系统（Windows、macOS 默认）上，这样的条目解压后会互相覆盖，结果不可预测；
+/// 文件 `p/BIN` 与 `p/bin/app` 则在任何文件系统上都无法同时解压。
+///
+/// # 参数
+/// - `names`: 文件条目名称（使用 `/` 分隔）。
+///
+/// # 返回
+/// `Vec<NameCollision>`: 所有冲突，按第二个名称出现的顺序排列。
+pub fn find_collisions<'a, I>(names: I) -> Vec<NameCollision>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    // 小写的路径 -> (最先占用该路径的条目, 该路径是否为目录)
+    let mut seen: HashMap<String, (&str, bool)> = HashMap::new();
+    let mut collisions = Vec::new();
+    for name in names {
+        let key = name.to_lowercase();
+        let dirs = key.match_indices('/').map(|(i, _)| &key[..i]);
+        let mut clash = None;
+        for dir in dirs {
+            match seen.get(dir) {
+                Some(&(first, false)) => clash = clash.or(Some(first)),
+                Some(&(_, true)) => {}
+                None => {
+                    seen.insert(dir.to_string(), (name, true));
+                }
+            }
+        }
+        match seen.get(&key) {
+            Some(&(first, _)) => clash = clash.or(Some(first)),
+            None => {
+                seen.insert(key, (name, false));
+            }
+        }
+        if let Some(first) = clash {
+            collisions.push(NameCollision {
+                first: first.to_string(),
+                second: name.to_string(),
+            });
+        }
+    }
+    collisions
+}
+
+/// 一次调用中计划执行的一次压缩（例如一个 profile 或 target）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRun {
+    /// 名称，用于诊断信息
+    pub name: String,
+    /// 要压缩的基础目录
+    pub base_dir: PathBuf,
+    /// 压缩包的输出路径
+    pub output_path: PathBuf,
+}
+
+/// 在开始压缩前检查同一次调用中的多次压缩是否互相冲突。
+///
+/// 两次压缩写入同一个输出文件时，后执行的会覆盖先执行的结果；一次压缩的基础目录与另一次相同或包含另一次的
+/// 基础目录时，同一批文件会被打包两次，而且先生成的压缩包可能被后一次打包进去。发现任一情况时列出所有冲突并返回错误。
+///
+/// # 参数
+/// - `runs`: 计划执行的压缩，按执行顺序排列。
+///
+/// # 返回
+/// `Result<()>`: 没有冲突时返回 `Ok(())`。
+pub fn check_planned_runs(runs: &[PlannedRun]) -> Result<()> {
+    let normalized: Vec<(PathBuf, PathBuf)> = runs
+        .iter()
+        .map(|run| (normalize(&run.base_dir), normalize(&run.output_path)))
+        .collect();
+    let mut problems = Vec::new();
+    for (i, a) in runs.iter().enumerate() {
+        for (j, b) in runs.iter().enumerate().skip(i + 1) {
+            let (a_base, a_output) = &normalized[i];
+            let (b_base, b_output) = &normalized[j];
+            if a_output == b_output {
+                problems.push(format!(
+                    "{} 与 {} 写入同一个输出文件: {}",
+                    a.name,
+                    b.name,
+                    b.output_path.display()
+                ));
+            }
+            if a_base.starts_with(b_base) || b_base.starts_with(a_base) {
+                problems.push(format!(
+                    "{} ({}) 与 {} ({}) 的源目录重叠",
+                    a.name,
+                    a.base_dir.display(),
+                    b.name,
+                    b.base_dir.display()
+                ));
+            }
+        }
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("计划执行的压缩互相冲突:\n  {}", problems.join("\n  "));
+    }
+    Ok(())
+}
+
+/// 估算压缩包在最坏情况下的大小：内容完全无法压缩 (原样存储，每 1 KB 最多增加 1 字节的块头)，
+/// 再加上每个条目与整个压缩包的头部开销。
+///
+/// # 参数
+/// - `files`: 条目数。
+/// - `input_bytes`: 所有文件的总大小 (字节)。
+pub fn worst_case_output_size(files: usize, input_bytes: u64) -> u64 {
+    input_bytes
+        .saturating_add(input_bytes / 1024)
+        .saturating_add((files as u64).saturating_mul(ENTRY_OVERHEAD))
+        .saturating_add(ARCHIVE_OVERHEAD)
+}
+
+/// 路径所在文件系统中当前用户可用的空间 (字节)。路径不存在时查询最近的已存在的上级目录。
+///
+/// # 返回
+/// `Option<u64>`: 无法查询（或不是 unix 平台）时返回 `None`。
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = path
+        .ancestors()
+        .find(|dir| !dir.as_os_str().is_empty() && dir.exists());
+    let existing = existing.unwrap_or(Path::new("."));
+    let c_path = std::ffi::CString::new(existing.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs 只写入我们提供的结构体，c_path 在调用期间有效
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+    }
+}
+
+/// 非 unix 平台上不检查可用空间。
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+/// 在写入之前检查输出路径所在磁盘的可用空间是否足够。
+///
+/// # 参数
+/// - `output_path`: 输出文件路径，可以还不存在。
+/// - `required`: 需要的空间 (字节)，通常为 [`worst_case_output_size`]。
+/// - `policy`: 空间不足时的处理方式。
+///
+/// # 返回
+/// `Result<Option<String>>`: 空间不足且策略为 `warn` 时返回警告信息；策略为 `error` 时返回错误；
+/// 空间足够、策略为 `ignore` 或无法查询可用空间时返回 `Ok(None)`。
+pub fn check_disk_space(
+    output_path: &Path,
+    required: u64,
+    policy: DiskSpacePolicy,
+) -> Result<Option<String>> {
+    if policy == DiskSpacePolicy::Ignore {
+        return Ok(None);
+    }
+    let Some(available) = available_space(output_path) else {
+        return Ok(None);
+    };
+    if available >= required {
+        return Ok(None);
+    }
+    let message = format!(
+        "输出目录所在磁盘的可用空间不足: 压缩包最多需要 {}，可用 {} ({})",
+        human_size(required),
+        human_size(available),
+        output_path.display()
+    );
+    match policy {
+        DiskSpacePolicy::Error => anyhow::bail!(
+            "{}。内容可压缩时实际需要的空间会小得多，可设置 on_low_disk_space = \"warn\" 继续压缩",
+            message
+        ),
+        _ => Ok(Some(message)),
+    }
+}
+
+/// 尽量将路径解析为实际路径（输出文件可能还不存在，只解析其所在目录）。
+fn normalize(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => parent
+            .canonicalize()
+            .map(|parent| parent.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_disk_space() -> Result<()> {
+        assert_eq!(worst_case_output_size(0, 0), ARCHIVE_OVERHEAD);
+        assert_eq!(
+            worst_case_output_size(2, 1024 * 1024),
+            1024 * 1024 + 1024 + 2 * ENTRY_OVERHEAD + ARCHIVE_OVERHEAD
+        );
+        assert_eq!(worst_case_output_size(1, u64::MAX), u64::MAX);
+
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("missing/dir/out.zip");
+        let Some(available) = available_space(&output) else {
+            return Ok(());
+        };
+        assert!(available > 0);
+        assert_eq!(check_disk_space(&output, 1, DiskSpacePolicy::Error)?, None);
+
+        let err = check_disk_space(&output, u64::MAX, DiskSpacePolicy::Error).unwrap_err();
+        assert!(err.to_string().contains("可用空间不足"));
+        assert!(
+            check_disk_space(&output, u64::MAX, DiskSpacePolicy::Warn)?
+                .is_some_and(|warning| warning.contains("可用空间不足"))
+        );
+        assert_eq!(
+            check_disk_space(&output, u64::MAX, DiskSpacePolicy::Ignore)?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_output_conflict() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let output = dir.path().join("backup.tar.gz");
+        assert_eq!(
+            resolve_output_conflict(&output, "tar.gz", ConflictPolicy::Error)?,
+            output
+        );
+
+        std::fs::write(&output, "old")?;
+        std::fs::write(dir.path().join("backup-1.tar.gz"), "old")?;
+        assert!(resolve_output_conflict(&output, "tar.gz", ConflictPolicy::Error).is_err());
+        assert_eq!(
+            resolve_output_conflict(&output, "tar.gz", ConflictPolicy::Overwrite)?,
+            output
+        );
+        assert_eq!(
+            resolve_output_conflict(&output, "tar.gz", ConflictPolicy::Rename)?,
+            dir.path().join("backup-2.tar.gz")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_collisions() {
+        let names = ["a.txt", "b/c.txt", "A.TXT", "b/c.txt", "d.txt"];
+        let collisions = find_collisions(names);
+        assert_eq!(
+            collisions,
+            vec![
+                NameCollision {
+                    first: "a.txt".to_string(),
+                    second: "A.TXT".to_string(),
+                },
+                NameCollision {
+                    first: "b/c.txt".to_string(),
+                    second: "b/c.txt".to_string(),
+                },
+            ]
+        );
+        assert!(!collisions[0].is_duplicate());
+        assert!(collisions[1].is_duplicate());
+        assert!(collisions[1].to_string().contains("重复"));
+    }
+
+    #[test]
+    fn test_find_file_dir_collisions() {
+        // 文件在前或目录在前都会被发现，上级目录的大小写不同也一样
+        let names = ["p/BIN", "p/bin/app", "q/lib/a.so", "Q/LIB", "q/Lib/b.so"];
+        let collisions = find_collisions(names);
+        assert_eq!(
+            collisions,
+            vec![
+                NameCollision {
+                    first: "p/BIN".to_string(),
+                    second: "p/bin/app".to_string(),
+                },
+                NameCollision {
+                    first: "q/lib/a.so".to_string(),
+                    second: "Q/LIB".to_string(),
+                },
+            ]
+        );
+        assert!(collisions.iter().all(NameCollision::is_file_dir_clash));
+        assert!(collisions[0].to_string().contains("文件与目录同名"));
+        // 只是上级目录的大小写不同时，解压后合并到同一个目录
+        assert!(find_collisions(["a/b", "A/c"]).is_empty());
+    }
+
+    #[test]
+    fn test_check_planned_runs() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for sub in ["app", "docs", "app/assets"] {
+            std::fs::create_dir_all(dir.path().join(sub))?;
+        }
+        let run = |name: &str, base: &str, output: &str| PlannedRun {
+            name: name.to_string(),
+            base_dir: dir.path().join(base),
+            output_path: dir.path().join(output),
+        };
+
+        check_planned_runs(&[
+            run("app", "app", "app.zip"),
+            run("docs", "docs", "docs.zip"),
+        ])?;
+
+        // 相对路径写法不同，但指向同一个输出文件
+        let error = check_planned_runs(&[
+            run("app", "app", "out.zip"),
+            run("docs", "docs", "docs/../out.zip"),
+        ])
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("app 与 docs 写入同一个输出文件"));
+
+        let error = check_planned_runs(&[
+            run("app", "app", "app.zip"),
+            run("assets", "app/assets", "assets.zip"),
+        ])
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("源目录重叠"));
+        Ok(())
+    }
+}
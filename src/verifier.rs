@@ -0,0 +1,168 @@
+use crate::format::Format;
+use crate::reader::{self, ArchiveReader, TarArchiveReader};
+use crate::split;
+use anyhow::Result;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+/// 单个损坏条目的信息。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryError {
+    /// 条目名称
+    pub name: String,
+    /// 错误描述
+    pub message: String,
+}
+
+/// 压缩包完整性校验的结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// 校验通过的条目数
+    pub entries: usize,
+    /// 解压出的总字节数
+    pub bytes: u64,
+    /// 损坏的条目
+    pub errors: Vec<EntryError>,
+}
+
+impl VerifyReport {
+    /// 压缩包是否完好。
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn record(&mut self, name: &str, result: std::io::Result<u64>) {
+        match result {
+            Ok(bytes) => {
+                self.entries += 1;
+                self.bytes += bytes;
+            }
+            Err(e) => self.errors.push(EntryError {
+                name: name.to_string(),
+                message: e.to_string(),
+            }),
+        }
+    }
+}
+
+/// 校验压缩包的完整性：遍历所有条目并解压到空设备，检查 CRC 等校验信息。
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径，格式由文件内容判断，无法识别时根据扩展名判断；可以是分卷压缩包。
+///
+/// # 返回
+/// `Result<VerifyReport>`: 校验结果。压缩包无法打开（例如目录结构损坏）时返回错误。
+pub fn verify_archive(archive_path: &Path) -> Result<VerifyReport> {
+    let (file, format) = split::open_archive(archive_path)?;
+    verify_entries(reader::open_reader(file, format)?, format)
+}
+
+/// 校验 ZIP 数据。
+pub fn verify_zip<R: Read + Seek>(reader: R) -> Result<VerifyReport> {
+    verify_entries(reader::open_reader(reader, Format::Zip)?, Format::Zip)
+}
+
+/// 校验 TAR.GZ 数据，读完所有条目后还会检查 gzip 尾部的 CRC。
+///
+/// TAR 是顺序格式，一旦某个条目损坏，后续条目通常也无法读取，此时除了损坏的条目，还会记录一个数据流错误。
+pub fn verify_tar_gz<R: Read>(reader: R) -> Result<VerifyReport> {
+    let decoder = Format::TarGz.tar_decoder(reader)?;
+    verify_entries(Box::new(TarArchiveReader::new(decoder)), Format::TarGz)
+}
+
+/// 校验 TAR.ZST 数据，与 [`verify_tar_gz`] 相同，在最后检查 zstd 帧的校验和。
+pub fn verify_tar_zst<R: Read>(reader: R) -> Result<VerifyReport> {
+    let decoder = Format::TarZst.tar_decoder(reader)?;
+    verify_entries(Box::new(TarArchiveReader::new(decoder)), Format::TarZst)
+}
+
+/// 校验 7Z 数据。
+pub fn verify_7z<R: Read + Seek>(reader: R) -> Result<VerifyReport> {
+    verify_entries(reader::open_reader(reader, Format::SevenZ)?, Format::SevenZ)
+}
+
+/// 将每个文件条目解压到空设备。
+///
+/// 单个条目的错误记录在该条目名下并继续读取；读取器本身的错误（条目头或数据流损坏）
+/// 记录为 `<格式>`，例如 `<tar.gz>`。
+fn verify_entries(reader: Box<dyn ArchiveReader + '_>, format: Format) -> Result<VerifyReport> {
+    let mut report = VerifyReport::default();
+    let result = reader.read_entries(&mut |info, entry| {
+        report.record(&info.name, std::io::copy(entry, &mut std::io::sink()));
+        Ok(())
+    });
+    if let Err(e) = result {
+        report.errors.push(EntryError {
+            name: format!("<{}>", format),
+            message: format!("{:#}", e),
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor;
+    use crate::config::Config;
+    use crate::progress::NoopReporter;
+    use tempfile::tempdir;
+
+    fn make_archive(format: Format, dir: &Path) -> Result<std::path::PathBuf> {
+        std::fs::write(dir.join("a.txt"), "hello hello hello hello")?;
+        std::fs::write(dir.join("b.txt"), "world")?;
+        let config = Config {
+            format,
+            output_name: Some("out".to_string()),
+            ..Default::default()
+        };
+        let files = vec![dir.join("a.txt"), dir.join("b.txt")];
+        Ok(compressor::compress_directory(&config, dir, files, &NoopReporter)?.output_path)
+    }
+
+    #[test]
+    fn test_verify_valid_archives() -> Result<()> {
+        for format in Format::ALL {
+            let dir = tempdir()?;
+            let archive = make_archive(format, dir.path())?;
+            let report = verify_archive(&archive)?;
+            assert!(report.is_ok(), "{}: {:?}", format, report.errors);
+            assert_eq!(report.entries, 2);
+            assert_eq!(report.bytes, 28);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_corrupted_zip() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = make_archive(Format::Zip, dir.path())?;
+
+        // 破坏第一个条目的数据区
+        let mut data = std::fs::read(&archive)?;
+        let name_len = u16::from_le_bytes([data[26], data[27]]) as usize;
+        let data_start = 30 + name_len;
+        data[data_start] ^= 0xff;
+        std::fs::write(&archive, data)?;
+
+        let report = verify_archive(&archive)?;
+        assert!(!report.is_ok());
+        assert_eq!(report.errors[0].name, "a.txt");
+        assert_eq!(report.entries, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_truncated_tar_gz() -> Result<()> {
+        let dir = tempdir()?;
+        let archive = make_archive(Format::TarGz, dir.path())?;
+        let data = std::fs::read(&archive)?;
+        std::fs::write(&archive, &data[..data.len() - 4])?;
+
+        let report = verify_archive(&archive)?;
+        assert!(!report.is_ok());
+        assert_eq!(report.entries, 2);
+        assert_eq!(report.errors[0].name, "<tar.gz>");
+        Ok(())
+    }
+}
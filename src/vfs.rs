@@ -0,0 +1,248 @@
+//! 虚拟文件系统：对不在磁盘上的文件（例如浏览器或 Node 中的内存文件表）应用忽略规则并生成压缩包。
+//!
+//! 这里的函数不访问真实的文件系统，关闭默认特性后可以在 `wasm32-unknown-unknown` 上使用：
+//!
+//! ```toml
+//! [dependencies]
+//! ztr = { version = "*", default-features = false }
+//! ```
+//!
+//! # 示例
+//!
+//! ```
+//! use std::io::Cursor;
+//! use ztr_lib::config::Config;
+//! use ztr_lib::vfs::{self, MemoryFs};
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let mut fs = MemoryFs::new();
+//!     fs.insert("src/main.rs", "fn main() {}");
+//!     fs.insert("target/debug/app", vec![0u8; 16]);
+//!
+//!     let config = Config {
+//!         ignore: Some(vec!["target/".to_string()]),
+//!         ..Default::default()
+//!     };
+//!     assert_eq!(vfs::filter_files(&fs, &config)?, ["src/main.rs"]);
+//!     let archive = vfs::compress_fs(&fs, &config, Cursor::new(Vec::new()))?.into_inner();
+//!     assert!(!archive.is_empty());
+//!     Ok(())
+//! }
+//! ```
+
+use crate::compressor;
+use crate::config::Config;
+use crate::ignore_rules::{self, IgnoreRules, ZTRIGNORE_FILE_NAME};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+/// 一组可以列出与读取的文件，路径为使用 `/` 分隔的相对路径。
+pub trait FileSystem {
+    /// 列出所有文件（不含目录）的路径。
+    fn files(&self) -> Result<Vec<String>>;
+
+    /// 打开文件读取其内容。
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>>;
+}
+
+/// 内存中的文件表，路径按字典序排列。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryFs {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemoryFs {
+    /// 创建空的文件表。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加或替换文件，路径开头的 `./` 与 `/` 会被去掉。
+    pub fn insert<P: Into<String>, C: Into<Vec<u8>>>(&mut self, path: P, content: C) {
+        let path = path.into();
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.files.insert(path.to_string(), content.into());
+    }
+
+    /// 文件的内容，文件不存在时返回 `None`。
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.files.get(path).map(Vec::as_slice)
+    }
+}
+
+impl<P: Into<String>, C: Into<Vec<u8>>> FromIterator<(P, C)> for MemoryFs {
+    fn from_iter<T: IntoIterator<Item = (P, C)>>(iter: T) -> Self {
+        let mut fs = Self::new();
+        for (path, content) in iter {
+            fs.insert(path, content);
+        }
+        fs
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn files(&self) -> Result<Vec<String>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + '_>> {
+        let content = self
+            .get(path)
+            .with_context(|| format!("文件不存在: {}", path))?;
+        Ok(Box::new(content))
+    }
+}
+
+/// 按配置中的忽略规则过滤文件系统中的文件。
+///
+/// 使用配置中的 `ignore`、`ignore_file` 与默认规则，文件系统根目录中的 `.ztrignore`
+/// (`ztrignore = false` 时不读取) 以及 `include`；`.ztrignore` 本身与其他文件一样参与过滤。
+/// `sources`、`gitignore` 等需要遍历真实目录的配置项不生效。
+///
+/// # 参数
+/// - `fs`: 文件系统。
+/// - `config`: 压缩配置。
+///
+/// # 返回
+/// `Result<Vec<String>>`: 保留的文件路径；规则无效或无法读取 `.ztrignore` 时返回错误。
+pub fn filter_files(fs: &dyn FileSystem, config: &Config) -> Result<Vec<String>> {
+    let files = fs.files()?;
+    let mut rules = config.get_ignore_rules();
+    if config.ztrignore.unwrap_or(true) && files.iter().any(|f| f == ZTRIGNORE_FILE_NAME) {
+        let mut content = String::new();
+        fs.open(ZTRIGNORE_FILE_NAME)?
+            .read_to_string(&mut content)
+            .with_context(|| format!("无法读取忽略文件: {}", ZTRIGNORE_FILE_NAME))?;
+        rules.extend(ignore_rules::ignore_file_lines(&content).map(str::to_string));
+    }
+    let mut ignore_rules = IgnoreRules::new(&rules, Path::new(""))?;
+    if let Some(include) = &config.include {
+        ignore_rules.set_include(include)?;
+    }
+    Ok(files
+        .into_iter()
+        .filter(|file| !ignore_rules.should_ignore(Path::new(file), false))
+        .collect())
+}
+
+/// 按配置过滤文件系统中的文件，并以配置中的格式压缩写入 `out`。
+///
+/// 条目的修改时间为当前时间（`wasm32-unknown-unknown` 上没有系统时钟，为 0），可复现模式下为
+/// `SOURCE_DATE_EPOCH`；权限为 644。
+///
+/// # 参数
+/// - `fs`: 文件系统。
+/// - `config`: 压缩配置，使用其中的 `format` 与忽略规则（见 [`filter_files`]）。
+/// - `out`: 压缩包的写入目标，例如 `Cursor<Vec<u8>>`。
+///
+/// # 返回
+/// `Result<W>`: 写入完成后的 `out`。
+pub fn compress_fs<W: Write + Seek>(fs: &dyn FileSystem, config: &Config, out: W) -> Result<W> {
+    let files = filter_files(fs, config)?;
+    let mut entries = Vec::with_capacity(files.len());
+    for file in files {
+        let reader = fs.open(&file)?;
+        entries.push((file, reader));
+    }
+    compressor::compress_entries_at(config.format, entries, out, archive_mtime(config))
+}
+
+/// 条目的修改时间。`wasm32-unknown-unknown` 上读取系统时钟会 panic，使用 0。
+fn archive_mtime(config: &Config) -> u64 {
+    if config.reproducible.unwrap_or(false) {
+        return compressor::source_date_epoch();
+    }
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        return 0;
+    }
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::Format;
+    use std::io::Cursor;
+
+    fn project() -> MemoryFs {
+        [
+            ("src/main.rs", "fn main() {}"),
+            ("src/lib.rs", "pub fn f() {}"),
+            ("target/debug/app", "binary"),
+            ("debug.log", "noise"),
+            ("./README.md", "# demo"),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_filter_files() -> Result<()> {
+        let mut fs = project();
+        let config = Config {
+            ignore: Some(vec!["*.log".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            filter_files(&fs, &config)?,
+            ["README.md", "src/lib.rs", "src/main.rs", "target/debug/app"]
+        );
+
+        fs.insert(".ztrignore", "target/\n!debug.log\n");
+        assert_eq!(
+            filter_files(&fs, &config)?,
+            [
+                ".ztrignore",
+                "README.md",
+                "debug.log",
+                "src/lib.rs",
+                "src/main.rs"
+            ]
+        );
+
+        let config = Config {
+            include: Some(vec!["src/".to_string()]),
+            ..config
+        };
+        assert_eq!(filter_files(&fs, &config)?, ["src/lib.rs", "src/main.rs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_fs() -> Result<()> {
+        let fs = project();
+        for format in Format::ALL {
+            let config = Config {
+                format,
+                ignore: Some(vec!["target/".to_string(), "*.log".to_string()]),
+                reproducible: Some(true),
+                ..Default::default()
+            };
+            let data = compress_fs(&fs, &config, Cursor::new(Vec::new()))?.into_inner();
+
+            let mut read = Vec::new();
+            crate::reader::for_each_entry_in(Cursor::new(data), format, |name, r| {
+                let mut content = String::new();
+                r.read_to_string(&mut content)?;
+                read.push((name.to_string(), content));
+                Ok(())
+            })?;
+            assert_eq!(
+                read,
+                [
+                    ("README.md".to_string(), "# demo".to_string()),
+                    ("src/lib.rs".to_string(), "pub fn f() {}".to_string()),
+                    ("src/main.rs".to_string(), "fn main() {}".to_string()),
+                ],
+                "{}",
+                format
+            );
+        }
+        Ok(())
+    }
+}
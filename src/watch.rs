@@ -0,0 +1,224 @@
+use crate::compressor;
+use crate::config::Config;
+use crate::format::Format;
+use crate::ignore_rules::IgnoreRules;
+use crate::retention::OutputNamePattern;
+use ::notify::{EventKind, RecursiveMode, Watcher};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// 没有文件事件时检查取消标志的间隔。
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 判断文件变化是否需要重新压缩。
+///
+/// 被忽略规则排除的路径、输出目录中的文件，以及 ztr 自己生成的压缩包和附属文件
+/// （名称符合 `output_name` 模板，如 `<名称>.zip`、`<名称>.zip.list`）都不会触发压缩。
+pub struct ChangeFilter {
+    rules: IgnoreRules,
+    base_dir: PathBuf,
+    /// 位于基础目录内部的单独输出目录
+    output_dir: Option<PathBuf>,
+    /// 由输出名模板得到的名称模式
+    name_pattern: OutputNamePattern,
+}
+
+impl ChangeFilter {
+    /// 根据配置创建。
+    pub fn new(config: &Config, base_dir: &Path) -> Result<Self> {
+        let base_dir = base_dir
+            .canonicalize()
+            .with_context(|| format!("要监视的目录不存在: {}", base_dir.display()))?;
+        let output_path = compressor::output_path(config, &base_dir);
+        let output_dir = output_path
+            .parent()
+            .and_then(|dir| dir.canonicalize().ok())
+            .filter(|dir| *dir != base_dir);
+        let name_pattern = OutputNamePattern::new(config.output_name.as_deref(), &base_dir);
+        Ok(Self {
+            rules: IgnoreRules::from_config(config, &base_dir)?,
+            base_dir,
+            output_dir,
+            name_pattern,
+        })
+    }
+
+    /// 判断路径的变化是否需要重新压缩。
+    pub fn is_relevant(&self, path: &Path) -> bool {
+        if !path.starts_with(&self.base_dir) {
+            return false;
+        }
+        if self
+            .output_dir
+            .as_ref()
+            .is_some_and(|dir| path.starts_with(dir))
+        {
+            return false;
+        }
+        if self.is_output_artifact(path) {
+            return false;
+        }
+        // 已删除的路径无法判断是否为目录，按文件处理
+        !self.rules.should_ignore(path, path.is_dir())
+    }
+
+    /// 文件名形如 `<输出名>.<压缩包扩展名>[...]`、`<输出名>.incr-...` 或 `<输出名>.manifest` 时，
+    /// 视为压缩包或其附属文件。
+    fn is_output_artifact(&self, path: &Path) -> bool {
+        let Some(file_name) = path.file_name().map(|n| n.to_string_lossy()) else {
+            return false;
+        };
+        file_name.match_indices('.').any(|(i, _)| {
+            let rest = &file_name[i..];
+            let is_artifact_suffix = rest == ".manifest"
+                || rest.starts_with(".incr-")
+                || Format::ALL
+                    .iter()
+                    .any(|format| rest.starts_with(&format!(".{}", format.extension())));
+            is_artifact_suffix && self.name_pattern.matches(&file_name[..i])
+        })
+    }
+}
+
+/// 监视基础目录，文件变化停止 `debounce` 时长后调用 `on_change`。
+///
+/// 只有 [`ChangeFilter`] 认为相关的变化才会触发；`on_change` 返回错误时停止监视并返回该错误。
+/// `cancel` 被置位后返回 `Ok(())`。
+///
+/// # 参数
+/// - `config`: 压缩配置，用于忽略规则和输出名。
+/// - `base_dir`: 要监视的目录。
+/// - `debounce`: 最后一次变化之后等待的时长，期间的变化合并为一次。
+/// - `cancel`: 取消标志。
+/// - `on_change`: 回调，参数为发生变化的路径。
+pub fn watch<F>(
+    config: &Config,
+    base_dir: &Path,
+    debounce: Duration,
+    cancel: &AtomicBool,
+    mut on_change: F,
+) -> Result<()>
+where
+    F: FnMut(&[PathBuf]) -> Result<()>,
+{
+    let filter = ChangeFilter::new(config, base_dir)?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = ::notify::recommended_watcher(tx).context("创建文件监视器失败")?;
+    watcher
+        .watch(&filter.base_dir, RecursiveMode::Recursive)
+        .with_context(|| format!("监视目录失败: {}", filter.base_dir.display()))?;
+
+    let mut pending = BTreeSet::new();
+    let mut last_change = Instant::now();
+    while !cancel.load(Ordering::SeqCst) {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                // 压缩时读取文件也会产生访问事件
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                let relevant: Vec<PathBuf> = event
+                    .paths
+                    .into_iter()
+                    .filter(|path| filter.is_relevant(path))
+                    .collect();
+                if !relevant.is_empty() {
+                    pending.extend(relevant);
+                    last_change = Instant::now();
+                }
+            }
+            Ok(Err(e)) => return Err(anyhow::Error::new(e).context("文件监视出错")),
+            Err(RecvTimeoutError::Timeout) => {
+                if !pending.is_empty() && last_change.elapsed() >= debounce {
+                    let changed: Vec<PathBuf> = std::mem::take(&mut pending).into_iter().collect();
+                    on_change(&changed)?;
+                    // 丢弃回调期间产生的事件（例如写入的压缩包）
+                    while rx.try_recv().is_ok() {}
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("文件监视器已停止"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_change_filter() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().canonicalize()?;
+        std::fs::create_dir_all(base_dir.join("dist"))?;
+        std::fs::create_dir_all(base_dir.join("target"))?;
+        let config = Config {
+            format: Format::Zip,
+            output_name: Some("build-{time}".to_string()),
+            ignore: Some(vec!["target/".to_string(), "*.log".to_string()]),
+            ..Default::default()
+        };
+        let filter = ChangeFilter::new(&config, &base_dir)?;
+        assert!(filter.is_relevant(&base_dir.join("src/main.rs")));
+        assert!(!filter.is_relevant(&base_dir.join("debug.log")));
+        assert!(!filter.is_relevant(&base_dir.join("target/app")));
+        assert!(!filter.is_relevant(&base_dir.join("build-120000.zip")));
+        assert!(!filter.is_relevant(&base_dir.join("build-120000.zip.list")));
+        assert!(filter.is_relevant(&base_dir.join("build.rs")));
+        assert!(filter.is_relevant(&base_dir.join("build-notes.md")));
+        assert!(!filter.is_relevant(Path::new("/elsewhere/file")));
+
+        let separate_output = Config {
+            output_dir: Some(base_dir.join("dist").to_string_lossy().to_string()),
+            ..config
+        };
+        let filter = ChangeFilter::new(&separate_output, &base_dir)?;
+        assert!(!filter.is_relevant(&base_dir.join("dist/anything.txt")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_triggers_on_change() -> Result<()> {
+        let dir = tempdir()?;
+        let base_dir = dir.path().canonicalize()?;
+        let config = Config {
+            format: Format::Zip,
+            ignore: Some(vec!["*.log".to_string()]),
+            ..Default::default()
+        };
+        let cancel = std::sync::Arc::new(AtomicBool::new(false));
+        let writer = {
+            let base_dir = base_dir.clone();
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                // 等待监视器启动；超时后取消，避免测试挂起
+                std::thread::sleep(Duration::from_millis(300));
+                let _ = std::fs::write(base_dir.join("ignored.log"), "x");
+                let _ = std::fs::write(base_dir.join("a.txt"), "a");
+                std::thread::sleep(Duration::from_secs(10));
+                cancel.store(true, Ordering::SeqCst);
+            })
+        };
+        let mut seen = Vec::new();
+        let result = watch(
+            &config,
+            &base_dir,
+            Duration::from_millis(100),
+            &cancel,
+            |changed| {
+                seen.extend_from_slice(changed);
+                cancel.store(true, Ordering::SeqCst);
+                Ok(())
+            },
+        );
+        result?;
+        assert_eq!(seen, vec![base_dir.join("a.txt")]);
+        drop(writer);
+        Ok(())
+    }
+}